@@ -6,5 +6,6 @@ yarn install --dev
 ./build_js.sh
 popd
 
-cargo run --release generate-completions
+mkdir -p completions
+cargo run --release completions bash > completions/gitnotes.bash
 cargo deb
\ No newline at end of file
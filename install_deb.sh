@@ -1,5 +1,6 @@
 #!/bin/bash
 set -eo pipefail
-cargo run --release generate-completions
+mkdir -p completions
+cargo run --release completions bash > completions/gitnotes.bash
 cargo deb
 sudo dpkg -i target/debian/gitnotes_*_amd64.deb
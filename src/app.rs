@@ -1,10 +1,12 @@
 use std::cell::RefCell;
-use std::io::{IsTerminal, stdin};
+use std::collections::HashMap;
+use std::io::{BufRead, IsTerminal, stdin, stdout, Write};
 use std::ops::Deref;
 use std::path::{Path, PathBuf};
 use std::process::Stdio;
 use std::rc::Rc;
 
+use chrono::Local;
 use globset::Glob;
 
 use regex::Regex;
@@ -13,16 +15,22 @@ use thiserror::Error;
 use git2::{FetchOptions, PushOptions, RemoteCallbacks};
 
 use structopt::StructOpt;
+use structopt::clap::Shell;
 
-use crate::command::{Command, CommandInterpreter, CommandError, CommandResult};
+use crate::command::{Command, CommandInterpreter, CommandError, CommandResult, CommitHookFn, SnippetCheckResult};
 use crate::config::{Config, config_path, FileConfig};
-use crate::{editor, git_helpers, interactive, markdown, querying};
-use crate::helpers::{base_dir, get_or_insert_with, io_error, StdinExt};
-use crate::model::{NoteFileTree, NoteFileTreeCreateConfig, NoteMetadataStorage, NOTES_DIR};
-use crate::querying::{Finder, FindQuery, GitLog, ListDirectory, ListTree, print_list_directory_results, print_note_metadata_results, QueryingError, QueryingResult, RegexMatcher, Searcher, StringMatcher};
-use crate::web_editor::AccessMode;
+use crate::editor::EditorOutput;
+use crate::{command, editor, git_helpers, interactive, markdown, querying};
+use crate::clipboard::{Clipboard, SystemClipboard};
+use crate::helpers::{absolute_path, base_dir, confirm, get_or_insert_with, io_error, StdinExt, Timings};
+use crate::model::{AccessTimes, NoteAliases, NoteId, NoteFileTree, NoteFileTreeCreateConfig, NoteMetadata, NoteMetadataStorage, NOTE_CONTENT_EXT, NOTES_DIR};
+use crate::querying::{DateFormat, DuplicateFinder, Finder, FindQuery, GitLog, GitLogFilter, LinkGraph, ListDirectory, ListTree, ListTreeFilter, NoteChange, NoteHistoryEntry, OutputFormat, print_duplicate_results, print_list_directory_results, print_note_history, print_note_metadata_results, print_recently_accessed_results, print_replace_results, QueryingError, QueryingResult, RegexMatcher, ResourceSort, Searcher, SortBy, StatusEntry, TagMatcher, TagSort};
+use crate::snippets::{SnippetContext, SnippetRunnerManger};
+use crate::web_editor::{AccessMode, WebEditorInput};
 
 pub type RepositoryRef = Rc<RefCell<git2::Repository>>;
+/// Asks the user the given yes/no question, where None means that there is no terminal to ask on.
+pub type ConfirmFn = Box<dyn Fn(&str) -> std::io::Result<Option<bool>>>;
 
 pub struct App {
     config: Config,
@@ -31,7 +39,10 @@ pub struct App {
     note_metadata_storage: Option<NoteMetadataStorage>,
     auto_commit: bool,
     working_dir: Option<PathBuf>,
-    version: u64
+    timings: Timings,
+    version: u64,
+    result_count: Option<usize>,
+    confirm: ConfirmFn
 }
 
 impl App {
@@ -43,7 +54,7 @@ impl App {
         let repository = Rc::new(RefCell::new(open_repository(&config.repository)?));
 
         let notes_dir = config.repository.join(NOTES_DIR);
-        if !notes_dir.exists() {
+        if !repository.borrow().is_bare() && !notes_dir.exists() {
             std::fs::create_dir_all(notes_dir)?;
         }
 
@@ -55,14 +66,27 @@ impl App {
                 note_metadata_storage: None,
                 auto_commit: true,
                 working_dir: get_initial_working_dir(&config),
-                version: 0
+                timings: Timings::from_enabled(config.timings),
+                version: 0,
+                result_count: None,
+                confirm: Box::new(|question| {
+                    if stdin().is_terminal() {
+                        confirm(question).map(Some)
+                    } else {
+                        Ok(None)
+                    }
+                })
             }
         )
     }
 
     pub fn run(&mut self, input_command: InputCommand) -> AppResult<Option<InputCommand>> {
+        if self.config.read_only && input_command.is_mutating() {
+            return Err(AppError::ReadOnly);
+        }
+
         match input_command {
-            InputCommand::Initialize { .. } => {
+            InputCommand::Initialize { .. } | InputCommand::Clone { .. } => {
                 println!("Not supported in interactive mode.");
             }
             InputCommand::Switch { path } => {
@@ -74,7 +98,10 @@ impl App {
 
                 self.config.repository = repository_path.clone();
                 *self.repository.borrow_mut() = open_repository(&self.config.repository)?;
+                let commit_hook = self.command_interpreter.take_commit_hook();
                 self.command_interpreter = CommandInterpreter::new(self.config.clone(), self.repository.clone())?;
+                self.command_interpreter.set_commit_hook(commit_hook);
+                self.command_interpreter.set_timings(self.timings.clone());
 
                 let mut file_config = FileConfig::load(&config_path())?;
                 file_config.repository = repository_path;
@@ -84,10 +111,14 @@ impl App {
 
                 self.config.print();
             }
-            InputCommand::Config { only_repository, set } => {
-                if let Some(set) = set {
-                    let parts = set.split("=").collect::<Vec<_>>();
-                    if let &[key, value] = &parts[..] {
+            InputCommand::Config { only_repository, repository_path, set, list_keys } => {
+                if list_keys {
+                    let file_config = FileConfig::load(&config_path())?;
+                    for (key, value_type, value) in file_config.list_keys() {
+                        println!("{} ({}): {}", key, value_type, value.unwrap_or_else(|| "N/A".to_owned()));
+                    }
+                } else if let Some(set) = set {
+                    if let Some((key, value)) = set.split_once('=') {
                         let mut file_config = FileConfig::load(&config_path())?;
                         file_config.change(key, value).map_err(|err| AppError::Input(err))?;
                         file_config.save(&config_path())?;
@@ -102,6 +133,8 @@ impl App {
                 } else {
                     if only_repository {
                         println!("{}", self.config.repository.to_str().unwrap());
+                    } else if repository_path {
+                        println!("{}", absolute_path(&self.config.repository)?.to_str().unwrap());
                     } else {
                         self.config.print();
                     }
@@ -112,26 +145,46 @@ impl App {
                     Command::UpdateSymbolicLinks {}
                 ])?;
             }
-            InputCommand::Add { path, tags } => {
-                let path = self.get_path(path)?;
+            InputCommand::Add { path, tags, allow_empty, open } => {
+                let path = self.resolve_path(path)?;
 
                 if !self.config.allow_stdin || stdin().is_terminal() {
                     self.create_and_execute_commands(vec![
-                        Command::AddNote { path, tags }
+                        Command::AddNote { path, tags, allow_empty }
                     ])?;
                 } else {
                     let content = stdin().read_into_string()?;
-                    self.create_and_execute_commands(vec![
-                        Command::AddNoteWithContent { path, tags, content }
-                    ])?;
+                    self.add_note_with_content(path, tags, content, open)?;
                 }
             }
-            InputCommand::Edit { path, history, clear_tags, add_tags } => {
+            InputCommand::Edit { path, history, clear_tags, add_tags, create, force, strip_output } => {
                 let path = self.get_path(path)?;
 
+                if strip_output {
+                    let content = markdown::strip_output_blocks(&self.get_note_content(&path, None)?);
+                    self.create_and_execute_commands(vec![
+                        Command::EditNoteSetContent { path, clear_tags, add_tags, content, force }
+                    ])?;
+
+                    return Ok(None);
+                }
+
+                if create && self.note_metadata_storage()?.get(&path).is_none() {
+                    if !self.config.allow_stdin || stdin().is_terminal() {
+                        self.create_and_execute_commands(vec![
+                            Command::AddNote { path, tags: add_tags, allow_empty: false }
+                        ])?;
+                    } else {
+                        let content = stdin().read_into_string()?;
+                        self.add_note_with_content(path, add_tags, content, false)?;
+                    }
+
+                    return Ok(None);
+                }
+
                 if !self.config.allow_stdin || stdin().is_terminal() {
                     self.create_and_execute_commands(vec![
-                        Command::EditNoteContent { path, history, clear_tags, add_tags }
+                        Command::EditNoteContent { path, history, clear_tags, add_tags, force }
                     ])?;
                 } else {
                     if history.is_some() {
@@ -140,14 +193,22 @@ impl App {
 
                     let content = stdin().read_into_string()?;
                     self.create_and_execute_commands(vec![
-                        Command::EditNoteSetContent { path, clear_tags, add_tags, content }
+                        Command::EditNoteSetContent { path, clear_tags, add_tags, content, force }
                     ])?;
                 }
             }
-            InputCommand::Move { source, destination, force } => {
+            InputCommand::Move { source, destination, force, merge, ignore_lock, dry_run } => {
+                if dry_run {
+                    for (source, destination) in self.preview_move(source, destination, force)? {
+                        println!("{} -> {}", source.to_str().unwrap(), destination.to_str().unwrap());
+                    }
+
+                    return Ok(None);
+                }
+
                 let working_dir = self.working_dir()?;
                 let source = self.get_path(source)?;
-                let destination = self.get_path(destination)?;
+                let destination = self.resolve_path(destination)?;
 
                 self.note_metadata_storage()?;
 
@@ -155,7 +216,9 @@ impl App {
                     working_dir,
                     source,
                     destination,
-                    force
+                    force,
+                    merge,
+                    ignore_lock
                 )?);
 
                 if let Err(err) = result {
@@ -163,14 +226,19 @@ impl App {
                     return Err(err);
                 }
             }
-            InputCommand::Remove { path, recursive } => {
+            InputCommand::Copy { source, destination, force, ignore_lock } => {
                 let working_dir = self.working_dir()?;
-                let path = self.get_path(path)?;
+                let source = self.get_path(source)?;
+                let destination = self.resolve_path(destination)?;
+
+                self.note_metadata_storage()?;
 
-                let result = self.create_and_execute_commands(self.create_remove_commands(
+                let result = self.create_and_execute_commands(self.create_copy_commands(
                     working_dir,
-                    path,
-                    recursive
+                    source,
+                    destination,
+                    force,
+                    ignore_lock
                 )?);
 
                 if let Err(err) = result {
@@ -178,29 +246,208 @@ impl App {
                     return Err(err);
                 }
             }
-            InputCommand::Undo { commit } => {
+            InputCommand::MoveToDate { force, filter } => {
+                let working_dir = self.find_scope(&filter)?;
+                self.note_metadata_storage()?;
+                let commands = self.create_move_to_date_commands(&filter.query()?, working_dir.as_deref(), force)?;
+                if commands.is_empty() {
+                    println!("All matching notes are already placed under their created date.");
+                    return Ok(None);
+                }
+
+                let result = self.create_and_execute_commands(commands);
+                if let Err(err) = result {
+                    self.command_interpreter.reset()?;
+                    return Err(err);
+                }
+            }
+            InputCommand::Remove { path, recursive, dry_run, yes, force } => {
+                if dry_run {
+                    for path in self.preview_remove(path, recursive)? {
+                        println!("{}", path.to_str().unwrap());
+                    }
+
+                    return Ok(None);
+                }
+
+                let working_dir = self.working_dir()?;
+                let display_path = path.to_str().unwrap().to_owned();
+                let path = self.get_path(path)?;
+
+                let commands = self.create_remove_commands(working_dir, path, recursive, force)?;
+                if recursive && !yes {
+                    let question = format!("Remove {} notes under '{}'?", commands.len(), display_path);
+                    match (self.confirm)(&question)? {
+                        Some(true) => {}
+                        Some(false) => return Ok(None),
+                        None => return Err(AppError::Input(format!("{} Use --yes to remove without confirmation.", question)))
+                    }
+                }
+
+                let result = self.create_and_execute_commands(commands);
+
+                if let Err(err) = result {
+                    self.command_interpreter.reset()?;
+                    return Err(err);
+                }
+            }
+            InputCommand::Restore { id } => {
+                match id {
+                    Some(id) => {
+                        self.create_and_execute_commands(vec![
+                            Command::RestoreNote { id }
+                        ])?;
+                    }
+                    None => {
+                        let notes = self.trashed_notes()?;
+                        for note_metadata in &notes {
+                            println!("{}: {}", note_metadata.id, note_metadata.path.to_str().unwrap());
+                        }
+
+                        self.result_count = Some(notes.len());
+                    }
+                }
+            }
+            InputCommand::PruneEmpty { yes } => {
+                let finder = Finder::new(self.note_metadata_storage()?)?;
+                let empty_notes = finder.find_empty()?;
+                if empty_notes.is_empty() {
+                    println!("No empty notes found.");
+                    return Ok(None);
+                }
+
+                println!("Empty notes:");
+                for note_metadata in &empty_notes {
+                    println!("\t{}", note_metadata.info_text());
+                }
+
+                let commands = empty_notes
+                    .into_iter()
+                    .map(|note_metadata| Command::RemoveNote { path: note_metadata.path.clone(), force: false })
+                    .collect::<Vec<_>>();
+
+                if !yes {
+                    let question = format!("Remove {} empty note(s)?", commands.len());
+                    match (self.confirm)(&question)? {
+                        Some(true) => {}
+                        Some(false) => return Ok(None),
+                        None => return Err(AppError::Input(format!("{} Use --yes to remove without confirmation.", question)))
+                    }
+                }
+
+                let result = self.create_and_execute_commands(commands);
+                if let Err(err) = result {
+                    self.command_interpreter.reset()?;
+                    return Err(err);
+                }
+            }
+            InputCommand::Id {} => {
+                let id = self.note_metadata_storage()?
+                    .new_note_id()
+                    .ok_or_else(|| CommandError::InternalError("No free note id found".to_owned()))?;
+                println!("{}", id);
+            }
+            InputCommand::Undo { commit, last } => {
+                let commits = match (commit, last) {
+                    (Some(commit), None) => vec![commit],
+                    (None, Some(count)) => self.last_commits(count.unwrap_or(1))?,
+                    _ => {
+                        return Err(AppError::Input("Either a commit or --last must be given".to_owned()));
+                    }
+                };
+
+                self.create_and_execute_commands(
+                    commits
+                        .into_iter()
+                        .map(|commit| Command::UndoCommit { commit })
+                        .collect()
+                )?;
+            }
+            InputCommand::Squash { range, force } => {
+                let (from, to) = match range.split_once("..") {
+                    Some((from, to)) => (from.to_owned(), if to.is_empty() { "HEAD".to_owned() } else { to.to_owned() }),
+                    None => (range, "HEAD".to_owned())
+                };
+
                 self.create_and_execute_commands(vec![
-                    Command::UndoCommit { commit }
+                    Command::SquashCommits { from, to, force }
                 ])?;
             }
-            InputCommand::RunSnippet { path, save_output } => {
+            InputCommand::RunSnippet { path, save_output, check } => {
                 let path = self.get_path(path)?;
 
-                let mut commands = vec![
+                if check {
+                    let results = self.check_snippets(&path)?;
+                    for result in &results {
+                        if result.is_match() {
+                            println!("Snippet {} ({}): ok", result.index, result.language);
+                        } else {
+                            println!("Snippet {} ({}): mismatch", result.index, result.language);
+                            println!("Expected:\n{}", result.expected.trim_end());
+                            println!("Actual:\n{}", result.actual.trim_end());
+                        }
+                    }
+
+                    let mismatches = results.iter().filter(|result| !result.is_match()).count();
+                    if mismatches > 0 {
+                        return Err(CommandError::SnippetOutputMismatch(mismatches).into());
+                    }
+
+                    return Ok(None);
+                }
+
+                let commands = vec![
                     Command::RunSnippet { path, save_output }
                 ];
 
-                if save_output && self.auto_commit {
-                    commands.push(Command::Commit);
+                if save_output {
+                    self.create_and_execute_commands(commands)?;
+                } else {
+                    self.execute_commands(commands)?;
+                }
+            }
+            InputCommand::Replay { range } => {
+                let results = self.replay_snippets(&range)?;
+                for (commit_id, path, result) in &results {
+                    let short_commit_id = &commit_id.to_string()[..7];
+                    if result.is_match() {
+                        println!("{} - {}: snippet {} ({}): ok", short_commit_id, path.to_str().unwrap(), result.index, result.language);
+                    } else {
+                        println!("{} - {}: snippet {} ({}): mismatch", short_commit_id, path.to_str().unwrap(), result.index, result.language);
+                        println!("Expected:\n{}", result.expected.trim_end());
+                        println!("Actual:\n{}", result.actual.trim_end());
+                    }
                 }
 
-                self.execute_commands(commands)?;
+                let mismatches = results.iter().filter(|(_, _, result)| !result.is_match()).count();
+                if mismatches > 0 {
+                    return Err(CommandError::SnippetOutputMismatch(mismatches).into());
+                }
             }
             InputCommand::ConvertFile { path, destination } => {
                 let path = self.get_path(path)?;
                 let abs_content_path = self.get_note_content_path(&path)?;
                 markdown::convert(&abs_content_path, &destination)?;
             }
+            InputCommand::ConvertAll { destination, format, filter } => {
+                let results = self.convert_notes(&destination, &format, filter)?;
+
+                let mut failures = 0;
+                for (path, result) in &results {
+                    match result {
+                        Ok(destination) => println!("{} -> {}", path.to_str().unwrap(), destination.to_str().unwrap()),
+                        Err(err) => {
+                            eprintln!("Failed to convert {}: {}", path.to_str().unwrap(), err);
+                            failures += 1;
+                        }
+                    }
+                }
+
+                self.result_count = Some(results.len() - failures);
+                if failures > 0 {
+                    return Err(AppError::FailedToConvert(format!("{} of {} notes failed", failures, results.len())));
+                }
+            }
             InputCommand::Begin { } => {
                 self.auto_commit = false;
                 self.command_interpreter.new_commit()?;
@@ -209,6 +456,19 @@ impl App {
                 self.execute_commands(vec![Command::Commit])?;
                 self.auto_commit = true;
             }
+            InputCommand::Metadata { command } => {
+                match command {
+                    InputCommandMetadata::Dump { path, json } => {
+                        let path = self.get_path(path)?;
+                        println!("{}", self.dump_note_metadata(&path, json)?.trim_end());
+                    }
+                    InputCommandMetadata::Load { path } => {
+                        let path = self.get_path(path)?;
+                        let content = stdin().read_into_string()?;
+                        self.load_note_metadata(&path, &content)?;
+                    }
+                }
+            }
             InputCommand::Remote { command } => {
                 match command {
                     InputCommandRemote::List { .. } => {
@@ -233,74 +493,233 @@ impl App {
                     }
                 }
             }
-            InputCommand::Synchronize { branch, remote, no_pull, no_push } => {
+            InputCommand::RenameId { path, new_id } => {
+                let path = self.get_path(path)?;
+                self.create_and_execute_commands(vec![
+                    Command::ChangeNoteId { path, new_id }
+                ])?;
+            }
+            InputCommand::Pin { path } => {
+                let path = self.get_path(path)?;
+                self.create_and_execute_commands(vec![
+                    Command::SetNotePinned { path, pinned: true }
+                ])?;
+            }
+            InputCommand::Unpin { path } => {
+                let path = self.get_path(path)?;
+                self.create_and_execute_commands(vec![
+                    Command::SetNotePinned { path, pinned: false }
+                ])?;
+            }
+            InputCommand::Lock { path } => {
+                let path = self.get_path(path)?;
+                self.create_and_execute_commands(vec![
+                    Command::SetNoteLocked { path, locked: true }
+                ])?;
+            }
+            InputCommand::Unlock { path } => {
+                let path = self.get_path(path)?;
+                self.create_and_execute_commands(vec![
+                    Command::SetNoteLocked { path, locked: false }
+                ])?;
+            }
+            InputCommand::SetTags { path, tags } => {
+                let path = self.get_path(path)?;
+
+                let mut new_tags = Vec::new();
+                for tag in tags {
+                    let tag = tag.trim();
+                    if tag.is_empty() {
+                        return Err(AppError::Input("Tags cannot be empty".to_owned()));
+                    }
+
+                    if !new_tags.iter().any(|current_tag| current_tag == tag) {
+                        new_tags.push(tag.to_owned());
+                    }
+                }
+
+                self.create_and_execute_commands(vec![
+                    Command::SetNoteTags { path, tags: new_tags }
+                ])?;
+            }
+            InputCommand::Pinned {} => {
+                let date_format = self.config.date_format;
+                let results = self.pinned_notes()?;
+                print_note_metadata_results(&results, date_format);
+                self.result_count = Some(results.len());
+            }
+            InputCommand::Alias { command } => {
+                match command {
+                    InputCommandAlias::List { .. } => {
+                        for (alias, path) in self.list_aliases()? {
+                            println!("{}: {}", alias, path.to_str().unwrap());
+                        }
+                    }
+                    InputCommandAlias::Add { alias, path } => {
+                        self.add_alias(&alias, path)?;
+                        println!("Added alias '{}'.", alias);
+                    }
+                    InputCommandAlias::Remove { alias } => {
+                        self.remove_alias(&alias)?;
+                        println!("Removed alias '{}'.", alias);
+                    }
+                }
+            }
+            InputCommand::Synchronize { branch, remote, no_pull, no_push, abort_on_conflict } => {
                 let branch = branch.unwrap_or_else(|| self.config.sync_default_branch.clone());
                 let remote = remote.unwrap_or_else(|| self.config.sync_default_remote.clone());
                 let pull = !no_pull;
                 let push = !no_push;
 
-                let repository = self.repository.borrow();
-
-                let branch_ref = git_helpers::find_branch_ref(&repository, &branch)?;
-                let mut remote = repository.find_remote(&remote).map_err(|_| AppError::RemoteNotFound(remote.clone()))?;
-
                 if pull {
                     println!("Pulling from remote...");
 
-                    let mut fetch_options = FetchOptions::new();
-                    let mut callbacks = RemoteCallbacks::new();
-                    callbacks.credentials(git_helpers::create_ssh_credentials());
-                    fetch_options.remote_callbacks(callbacks);
+                    let changes = match self.pull(&remote, &branch, abort_on_conflict) {
+                        Ok(changes) => changes,
+                        Err(AppError::MergeConflicts(paths)) => {
+                            if abort_on_conflict {
+                                println!("Merge aborted, conflicts in:");
+                            } else {
+                                println!("Merge conflicts (resolve and commit) in:");
+                            }
+
+                            for path in &paths {
+                                println!("  {}", path.to_str().unwrap());
+                            }
 
-                    remote.fetch(&[&branch_ref], Some(&mut fetch_options), None)?;
-                    let fetch_head = repository.find_reference("FETCH_HEAD")?;
-                    let fetch_commit = repository.reference_to_annotated_commit(&fetch_head)?;
-                    git_helpers::merge(&repository, &branch, fetch_commit)?;
+                            return Err(AppError::MergeConflicts(paths));
+                        }
+                        Err(err) => {
+                            return Err(err);
+                        }
+                    };
+                    if changes.is_empty() {
+                        println!("No notes changed.");
+                    } else {
+                        println!("Changed notes:");
+                        for (change, path) in changes {
+                            println!("  {}: {}", change, path.to_str().unwrap());
+                        }
+                    }
                 }
 
                 if push {
                     println!("Pushing to remote...");
 
+                    let repository = self.repository.borrow();
+                    let branch_ref = git_helpers::find_branch_ref(&repository, &branch)?;
+                    let mut remote = repository.find_remote(&remote).map_err(|_| AppError::RemoteNotFound(remote.clone()))?;
+
                     let mut push_options = PushOptions::new();
                     let mut callbacks = RemoteCallbacks::new();
-                    callbacks.credentials(git_helpers::create_ssh_credentials());
+                    callbacks.credentials(self.create_sync_credentials(&remote)?);
                     push_options.remote_callbacks(callbacks);
 
                     remote.push(&[&branch_ref], Some(&mut push_options))?;
                 }
             }
             InputCommand::PrintContent { path, history, only_code, only_output } => {
+                if path == Path::new("-") {
+                    self.print_contents_from(&mut stdin().lock(), &mut stdout(), history, only_code, only_output)?;
+                    return Ok(None);
+                }
+
                 let path = self.get_path(path)?;
 
                 let content = self.get_note_content(&path, history)?;
                 let content = querying::extract_content(content, only_code, only_output)?;
                 print!("{}", content);
+
+                if self.config.track_access {
+                    self.record_access(&path)?;
+                }
+            }
+            InputCommand::Diff { path, from, to } => {
+                let path = self.get_path(path)?;
+                let diff = self.note_diff(&path, from, to)?;
+                querying::print_diff(&diff, &mut stdout(), stdout().is_terminal())?;
             }
-            InputCommand::Show { path, history, only_code, only_output } => {
+            InputCommand::Show { path, history, only_code, only_output, run, save } => {
                 let path = self.get_path(path)?;
 
-                let content = self.get_note_content(&path, history)?;
+                let content = self.get_show_content(&path, history, run, save)?;
                 let content = querying::extract_content(content, only_code, only_output)?;
                 editor::launch_with_content(&self.config, &content, Some(&path), AccessMode::Read)?;
+
+                if self.config.track_access {
+                    self.record_access(&path)?;
+                }
+            }
+            InputCommand::CopyToClipboard { path, only_code } => {
+                let path = self.get_path(path)?;
+                self.copy_to_clipboard(&path, only_code, &mut SystemClipboard, &mut stdout())?;
+
+                if self.config.track_access {
+                    self.record_access(&path)?;
+                }
+            }
+            InputCommand::Touch { path } => {
+                let path = self.get_path(path)?;
+                self.record_access(&path)?;
+            }
+            InputCommand::Recent { count, accessed } => {
+                let date_format = self.config.date_format;
+                if accessed {
+                    let access_times = AccessTimes::load(self.repository.borrow().deref())?;
+                    let results = querying::recently_accessed(self.note_metadata_storage()?, &access_times, count);
+                    print_recently_accessed_results(&results, date_format);
+                } else {
+                    let results = querying::recently_updated(self.note_metadata_storage()?, count);
+                    print_note_metadata_results(&results, date_format);
+                }
             }
-            InputCommand::ListDirectory { query } => {
+            InputCommand::Tags { sort, format } => {
+                let results = querying::tag_counts(self.note_metadata_storage()?, sort);
+                match format {
+                    OutputFormat::Text => querying::print_tag_counts(&results),
+                    OutputFormat::Json => println!("{}", querying::tag_counts_json(&results))
+                }
+                self.result_count = Some(results.len());
+            }
+            InputCommand::SizeReport { top } => {
+                let results = querying::note_sizes(self.note_metadata_storage()?, top)?;
+                querying::print_note_sizes(&results);
+                self.result_count = Some(results.len());
+            }
+            InputCommand::ListDirectory { query, with_size, pinned_first, format } => {
                 let query = query.unwrap_or_else(|| Path::new("").to_owned());
-                let query = self.get_path(query)?;
+                let query = self.resolve_path(query)?;
 
+                let date_format = self.config.date_format;
                 let list_directory = ListDirectory::new(self.note_metadata_storage()?)?;
-                let results = list_directory.list(&query)?;
-                print_list_directory_results(&results)?
+                let mut results = list_directory.list(&query, with_size)?;
+                if pinned_first {
+                    results.sort_by_key(|entry| !entry.note_metadata.map(|note_metadata| note_metadata.pinned).unwrap_or(false));
+                }
+
+                match format {
+                    OutputFormat::Text => print_list_directory_results(&results, date_format)?,
+                    OutputFormat::Json => println!("{}", querying::list_directory_results_json(&results))
+                }
             }
-            InputCommand::Tree { prefix, using_date, using_tags, } => {
+            InputCommand::Tree { prefix, using_date, using_tags, files_only, dirs_only, with_size } => {
                 let prefix = prefix.unwrap_or_else(|| Path::new("").to_owned());
-                let prefix = self.get_path(prefix)?;
+                let prefix = self.resolve_path(prefix)?;
 
                 let mut create_config = NoteFileTreeCreateConfig::default();
                 create_config.using_date = using_date;
                 create_config.using_tags = using_tags;
 
+                let filter = if files_only {
+                    ListTreeFilter::OnlyFiles
+                } else if dirs_only {
+                    ListTreeFilter::OnlyDirs
+                } else {
+                    ListTreeFilter::All
+                };
+
                 let list_tree = ListTree::new(self.note_metadata_storage()?, create_config)?;
-                list_tree.list(&prefix);
+                list_tree.list(&prefix, filter, with_size)?;
             }
             InputCommand::OpenNotesInFileExplorer {} => {
                 self.create_and_execute_commands(vec![
@@ -309,33 +728,97 @@ impl App {
 
                 open::that(&self.config.repository)?;
             }
-            InputCommand::Finder { interactive, command } => {
-                let finder = Finder::new(self.note_metadata_storage()?)?;
-                let results = finder.find(&command.query())?;
-                print_note_metadata_results(&results);
+            InputCommand::Finder { interactive, sort, reverse, format, limit, then, command } => {
+                let working_dir = self.find_scope(&command)?;
+                let date_format = self.config.date_format;
+                let note_metadata_storage = self.note_metadata_storage()?;
+                let finder = Finder::new(note_metadata_storage)?;
+                let mut results = finder.find(&command.query()?)?;
+                if let Some(working_dir) = working_dir {
+                    results.retain(|note_metadata| note_metadata.path.starts_with(&working_dir));
+                }
+                querying::sort_note_metadata_results(note_metadata_storage, &mut results, sort, reverse);
+                if let Some(limit) = limit {
+                    results.truncate(limit);
+                }
 
-                if let Some(command) = interactive {
-                    if let Some(next_command) = interactive::select_with_note_metadata(&command, &results)? {
-                        return Ok(Some(next_command));
-                    }
+                match format {
+                    OutputFormat::Text => print_note_metadata_results(&results, date_format),
+                    OutputFormat::Json => println!("{}", querying::note_metadata_results_json(&results))
+                }
+
+                let result_count = results.len();
+                let next_command = match interactive {
+                    Some(command) => interactive::select_with_note_metadata(&command, &results)?,
+                    None => None
+                };
+
+                if let Some(then) = then {
+                    let then_commands = results
+                        .iter()
+                        .map(|note_metadata| then_command(&then, &note_metadata.path))
+                        .collect::<AppResult<Vec<_>>>()?;
+                    self.run_in_single_commit(then_commands)?;
+                }
+
+                self.result_count = Some(result_count);
+                if next_command.is_some() {
+                    return Ok(next_command);
                 }
             }
-            InputCommand::SearchContent { mut query, case_sensitive, history, interactive } => {
+            InputCommand::SearchContent { mut query, case_sensitive, multiline, tree, include_resources, history, interactive, replace, dry_run, limit, json, after, before, context, files_with_matches, count } => {
                 if !case_sensitive {
-                    query = format!("(?i)({})", query);
+                    query = format!("(?i:{})", query);
+                }
+
+                if multiline {
+                    query = format!("(?m){}", query);
                 }
                 let query = Regex::new(&query)?;
 
+                let resources_dir = self.config.resources_dir();
                 self.note_metadata_storage()?;
-                let searcher = Searcher::new(self.note_metadata_storage_ref()?)?;
+                let mut searcher = Searcher::new(self.note_metadata_storage_ref()?)?;
+                searcher.set_grouped(tree);
+                searcher.set_limit(limit);
+                searcher.set_context(before.or(context).unwrap_or(0), after.or(context).unwrap_or(0));
+
+                if let Some(replacement) = replace {
+                    self.result_count = Some(self.replace_content(&query, &replacement, None, dry_run)?);
+                    return Ok(None);
+                }
 
-                if history.len() == 0 {
-                    let matches = searcher.search(&query)?;
-                    if let Some(command) = interactive {
-                        if let Some(next_command) = interactive::select_with_note_metadata(&command, &matches)? {
-                            return Ok(Some(next_command));
-                        }
-                    }
+                if json {
+                    let matches = searcher.search_matches(&query)?;
+                    println!("{}", querying::search_matches_json(&matches));
+                    self.result_count = Some(matches.len());
+                    return Ok(None);
+                }
+
+                let (result_count, next_command) = if history.is_empty() {
+                    let matches = if multiline {
+                        searcher.search_multiline(&query)?
+                    } else if files_with_matches || count {
+                        let results = searcher.search_counts(&query, !count)?;
+                        querying::print_search_counts(&results, count);
+                        results.into_iter().map(|(note_metadata, _)| note_metadata).collect()
+                    } else {
+                        searcher.search(&query)?
+                    };
+
+                    let resource_matches = if include_resources {
+                        searcher.set_limit(limit.map(|limit| limit.saturating_sub(matches.len())));
+                        searcher.search_resources(&query, &resources_dir)?
+                    } else {
+                        Vec::new()
+                    };
+
+                    let next_command = match interactive {
+                        Some(command) => interactive::select_with_note_metadata(&command, &matches)?,
+                        None => None
+                    };
+
+                    (matches.len() + resource_matches.len(), next_command)
                 } else {
                     let matches = searcher.search_historic(
                         self.repository.borrow().deref(),
@@ -344,22 +827,51 @@ impl App {
                         history.get(1).map(|x| x.as_str())
                     )?;
 
-                    if let Some(command) = interactive {
-                        let next_command = interactive::select(&command, matches.len(), |command_name: &str, index: usize| {
-                            format!("{} --history {} {}", command_name, matches[index].0, matches[index].1.path.to_str().unwrap())
-                        })?;
-
-                        if let Some(next_command) = next_command{
-                            return Ok(Some(next_command));
+                    let next_command = match interactive {
+                        Some(command) => {
+                            interactive::select(&command, matches.len(), |command_name: &str, index: usize| {
+                                format!("{} --history {} {}", command_name, matches[index].0, matches[index].1.path.to_str().unwrap())
+                            })?
                         }
-                    }
+                        None => None
+                    };
+
+                    (matches.len(), next_command)
+                };
+
+                self.result_count = Some(result_count);
+                if next_command.is_some() {
+                    return Ok(next_command);
+                }
+            }
+            InputCommand::Replace { query, replacement, path, dry_run } => {
+                let query = Regex::new(&query)?;
+                let path = path.map(|path| self.get_path(path)).transpose()?;
+                self.result_count = Some(self.replace_content(&query, &replacement, path, dry_run)?);
+            }
+            InputCommand::Duplicates { exact, similar, threshold } => {
+                if !(0.0..=1.0).contains(&threshold) {
+                    return Err(AppError::Input("The threshold must be between 0 and 1".to_owned()));
                 }
+
+                let duplicate_finder = DuplicateFinder::new(self.note_metadata_storage()?)?;
+                let groups = if similar && !exact {
+                    duplicate_finder.find_similar(threshold)?
+                } else {
+                    duplicate_finder.find_exact()?
+                };
+
+                print_duplicate_results(&groups);
             }
             InputCommand::Resource { command } => {
                 match command {
-                    InputCommandResource::List { query, print_absolute } => {
+                    InputCommandResource::List { query, print_absolute, long, sort } => {
                         let resources_dir = self.config.resources_dir();
-                        querying::list_resources(&resources_dir, query, print_absolute)?;
+                        querying::list_resources(&resources_dir, query, print_absolute, long, sort)?;
+                    }
+                    InputCommandResource::Tree { prefix } => {
+                        let resources_dir = self.config.resources_dir();
+                        querying::list_resources_tree(&resources_dir, prefix)?;
                     }
                     InputCommandResource::Add { path, destination } => {
                         self.create_and_execute_commands(vec![
@@ -383,24 +895,29 @@ impl App {
                             }
                         }
                     }
-                    InputCommandResource::Apply { command, resource } => {
-                        let full_path = self.config.resources_dir().join(resource).canonicalize()?;
-
-                        let mut result = std::process::Command::new(&command)
-                            .arg(full_path)
-                            .stdin(Stdio::inherit())
-                            .spawn()
-                            .map_err(|err| CommandError::SubProcess(err))?;
-                        result.wait().map_err(|err| CommandError::SubProcess(err))?;
+                    InputCommandResource::Apply { command, resource, all } => {
+                        let count = self.apply_resource_command(&command, resource, all)?;
+                        self.result_count = Some(count);
                     }
                 }
             }
-            InputCommand::Log { count } => {
+            InputCommand::Log { count, graph, author, grep } => {
                 let repository = self.repository.borrow();
-                let git_log = GitLog::new(repository.deref(), count)?;
-                git_log.print()?;
+                let git_log = GitLog::new(repository.deref(), count, GitLogFilter { author, grep })?;
+                git_log.print(self.config.date_format, graph)?;
+            }
+            InputCommand::Status {} => {
+                let entries = self.status()?;
+                querying::print_status(&entries);
+                self.result_count = Some(entries.len());
+            }
+            InputCommand::History { path } => {
+                let path = self.get_path(path)?;
+                let entries = self.note_history(&path)?;
+                print_note_history(&entries, self.config.date_format);
+                self.result_count = Some(entries.len());
             }
-            InputCommand::Info { path, only_file_system_path } => {
+            InputCommand::Info { path, only_file_system_path, resolve } => {
                 self.note_metadata_storage()?;
                 let note_metadata = self.note_metadata_storage_ref()?
                     .get(&path)
@@ -418,6 +935,20 @@ impl App {
                     println!("Tags: {}", note_metadata.tags.join(", "));
                     println!("Created: {}", note_metadata.created);
                     println!("Last updated: {}", note_metadata.last_updated);
+
+                    if resolve {
+                        let link_graph = LinkGraph::new(self.note_metadata_storage_ref()?)?;
+
+                        println!("Links to:");
+                        for linked_note_metadata in link_graph.outbound(&note_metadata.id) {
+                            println!("\t{}", linked_note_metadata.info_text());
+                        }
+
+                        println!("Linked from:");
+                        for linked_note_metadata in link_graph.inbound(&note_metadata.id) {
+                            println!("\t{}", linked_note_metadata.info_text());
+                        }
+                    }
                 } else {
                     println!("{}", file_system_path);
                 }
@@ -458,22 +989,32 @@ impl App {
             InputCommand::WebEditor { .. } => {
                 println!("Not supported in interactive mode.");
             }
+            InputCommand::Completions { .. } => {
+                println!("Not supported in interactive mode.");
+            }
         }
 
         Ok(None)
     }
 
-    pub fn run_until_completion(&mut self, input_command: InputCommand) -> AppResult<()> {
+    /// Runs the command (and any command chosen from it), returning the number of results for commands that search.
+    pub fn run_until_completion(&mut self, input_command: InputCommand) -> AppResult<Option<usize>> {
+        self.result_count = None;
+
         let mut next_input_command = Some(input_command);
         while let Some(input_command) = next_input_command {
             next_input_command = self.run(input_command)?;
         }
 
-        Ok(())
+        Ok(self.result_count)
     }
 
     pub fn execute_commands(&mut self, commands: Vec<Command>) -> AppResult<()> {
-        self.command_interpreter.execute(commands)?;
+        let start = self.timings.start();
+        let result = self.command_interpreter.execute(commands);
+        self.timings.record("Command execution", start);
+        result?;
+
         self.clear_cache();
         Ok(())
     }
@@ -490,10 +1031,60 @@ impl App {
         self.execute_commands(self.create_commands(commands))
     }
 
+    /// Runs the commands with their changes in a single commit (unless already inside begin/commit), where all changes are reset if any command fails.
+    fn run_in_single_commit(&mut self, input_commands: Vec<InputCommand>) -> AppResult<()> {
+        if !self.auto_commit {
+            for input_command in input_commands {
+                self.run_until_completion(input_command)?;
+            }
+
+            return Ok(());
+        }
+
+        self.auto_commit = false;
+        self.command_interpreter.new_commit()?;
+        let result = input_commands
+            .into_iter()
+            .try_for_each(|input_command| self.run_until_completion(input_command).map(|_| ()))
+            .and_then(|_| self.execute_commands(vec![Command::Commit]));
+        self.auto_commit = true;
+
+        if let Err(err) = result {
+            self.command_interpreter.reset()?;
+            self.clear_cache();
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    pub fn set_commit_hook(&mut self, commit_hook: Option<CommitHookFn>) {
+        self.command_interpreter.set_commit_hook(commit_hook);
+    }
+
+    pub fn set_confirm(&mut self, confirm: ConfirmFn) {
+        self.confirm = confirm;
+    }
+
+    pub fn set_timings(&mut self, timings: Timings) {
+        self.timings = timings.clone();
+        self.command_interpreter.set_timings(timings);
+    }
+
     pub fn note_metadata_storage(&mut self) -> std::io::Result<&NoteMetadataStorage> {
         get_or_insert_with(
             &mut self.note_metadata_storage,
-            || Ok(NoteMetadataStorage::from_dir_with_config(&self.config)?)
+            || {
+                let start = self.timings.start();
+                let repository = self.repository.borrow();
+                let note_metadata_storage = if repository.is_bare() {
+                    NoteMetadataStorage::from_git_tree(repository.deref(), self.config.base_dir.as_ref().map(|x| x.as_path()))?
+                } else {
+                    NoteMetadataStorage::from_dir_with_config(&self.config)?
+                };
+                self.timings.record("Metadata load", start);
+                Ok(note_metadata_storage)
+            }
         ).map(|x| &*x)
     }
 
@@ -501,12 +1092,118 @@ impl App {
         self.note_metadata_storage.as_ref().ok_or_else(|| io_error("note_metadata_storage not created"))
     }
 
-    fn create_move_commands(
+    /// Returns the (source, destination) pairs that a move would result in, without executing it.
+    pub fn preview_move(&mut self, source: PathBuf, destination: PathBuf, force: bool) -> AppResult<Vec<(PathBuf, PathBuf)>> {
+        let working_dir = self.working_dir()?;
+        let source = self.get_path(source)?;
+        let destination = self.resolve_path(destination)?;
+
+        self.note_metadata_storage()?;
+        let commands = self.create_move_commands(working_dir, source, destination, force, false, false)?;
+
+        Ok(
+            commands
+                .into_iter()
+                .flat_map(|command| {
+                    match command {
+                        Command::MoveNote { source, destination, .. } => Some((source, destination)),
+                        _ => None
+                    }
+                })
+                .collect()
+        )
+    }
+
+    /// Applies the command on the given resource (or each matching resource if all is set), returning the number of resources applied on.
+    pub fn apply_resource_command(&self, command: &str, resource: Option<PathBuf>, all: bool) -> AppResult<usize> {
+        let resources_dir = self.config.resources_dir();
+
+        let paths = if all {
+            let pattern = resource.as_ref().map(|resource| resource.to_str().unwrap());
+            querying::find_matching_resources(&resources_dir, pattern)?
+                .into_iter()
+                .map(|entry| entry.path)
+                .collect::<Vec<_>>()
+        } else {
+            let resource = resource.ok_or_else(|| AppError::Input("Resource not given".to_owned()))?;
+            vec![resources_dir.join(resource)]
+        };
+
+        for path in &paths {
+            let full_path = path.canonicalize()?;
+
+            let mut result = std::process::Command::new(command)
+                .arg(full_path)
+                .stdin(Stdio::inherit())
+                .spawn()
+                .map_err(|err| CommandError::SubProcess(err))?;
+            result.wait().map_err(|err| CommandError::SubProcess(err))?;
+        }
+
+        Ok(paths.len())
+    }
+
+    /// Returns the paths of the notes that a remove would result in, without executing it.
+    pub fn preview_remove(&mut self, path: PathBuf, recursive: bool) -> AppResult<Vec<PathBuf>> {
+        let working_dir = self.working_dir()?;
+        let path = self.get_path(path)?;
+
+        self.note_metadata_storage()?;
+        let commands = self.create_remove_commands(working_dir, path, recursive, false)?;
+
+        Ok(
+            commands
+                .into_iter()
+                .flat_map(|command| {
+                    match command {
+                        Command::RemoveNote { path, .. } => Some(path),
+                        _ => None
+                    }
+                })
+                .collect()
+        )
+    }
+
+    fn create_move_commands(
         &self,
         working_dir: PathBuf,
         source: PathBuf,
         destination: PathBuf,
-        force: bool
+        force: bool,
+        merge: bool,
+        ignore_lock: bool
+    ) -> QueryingResult<Vec<Command>> {
+        self.create_source_destination_commands(
+            working_dir,
+            source,
+            destination,
+            |source, destination| Command::MoveNote { source, destination, force, merge, ignore_lock }
+        )
+    }
+
+    fn create_copy_commands(
+        &self,
+        working_dir: PathBuf,
+        source: PathBuf,
+        destination: PathBuf,
+        force: bool,
+        ignore_lock: bool
+    ) -> QueryingResult<Vec<Command>> {
+        self.create_source_destination_commands(
+            working_dir,
+            source,
+            destination,
+            |source, destination| Command::CopyNote { source, destination, force, ignore_lock }
+        )
+    }
+
+    /// Creates a command for each note of the source (a note, folder or glob), where a folder destination keeps the file name.
+    fn create_source_destination_commands<F: Fn(PathBuf, PathBuf) -> Command>(
+        &self,
+        working_dir: PathBuf,
+        source: PathBuf,
+        destination: PathBuf,
+        create_command: F
     ) -> QueryingResult<Vec<Command>> {
         let note_file_tree = NoteFileTree::from_iter(self.note_metadata_storage_ref()?.notes());
 
@@ -518,11 +1215,7 @@ impl App {
                     note_file_tree.walk(|_, parent, name, tree, _| {
                         let path = parent.join(name);
                         if tree.is_leaf() {
-                            moves.push(Command::MoveNote {
-                                source: source.join(&path),
-                                destination: destination.join(&path),
-                                force
-                            });
+                            moves.push(create_command(source.join(&path), destination.join(&path)));
                         }
 
                         true
@@ -537,11 +1230,7 @@ impl App {
                 if destination_tree.is_tree() {
                     return Ok(
                         vec![
-                            Command::MoveNote {
-                                source: source.clone(),
-                                destination: destination.join(filename),
-                                force
-                            }
+                            create_command(source.clone(), destination.join(filename))
                         ]
                     );
                 }
@@ -549,7 +1238,7 @@ impl App {
 
             Ok(
                 vec![
-                    Command::MoveNote { source, destination, force }
+                    create_command(source, destination)
                 ]
             )
         };
@@ -569,11 +1258,51 @@ impl App {
         inner(source, destination)
     }
 
+    fn create_move_to_date_commands(&self, query: &FindQuery, working_dir: Option<&Path>, force: bool) -> AppResult<Vec<Command>> {
+        let finder = Finder::new(self.note_metadata_storage_ref()?)?;
+
+        let mut commands = Vec::new();
+        let mut sources = HashMap::new();
+        for note_metadata in finder.find(query)? {
+            if let Some(working_dir) = working_dir {
+                if !note_metadata.path.starts_with(working_dir) {
+                    continue;
+                }
+            }
+
+            if let Some(filename) = note_metadata.path.file_name() {
+                let destination = Path::new(&note_metadata.created.format("%Y/%m/%d").to_string()).join(filename);
+                if destination != note_metadata.path {
+                    // Moving both would make the second move fail (or overwrite the first with --force)
+                    if let Some(other_source) = sources.insert(destination.clone(), note_metadata.path.clone()) {
+                        return Err(AppError::Input(format!(
+                            "Both '{}' and '{}' would be moved to '{}'",
+                            other_source.to_str().unwrap(),
+                            note_metadata.path.to_str().unwrap(),
+                            destination.to_str().unwrap()
+                        )));
+                    }
+
+                    commands.push(Command::MoveNote {
+                        source: note_metadata.path.clone(),
+                        destination,
+                        force,
+                        merge: false,
+                        ignore_lock: false
+                    });
+                }
+            }
+        }
+
+        Ok(commands)
+    }
+
     fn create_remove_commands(
         &self,
         working_dir: PathBuf,
         path: PathBuf,
-        recursive: bool
+        recursive: bool,
+        force: bool
     ) -> QueryingResult<Vec<Command>> {
         let note_file_tree = NoteFileTree::from_iter(self.note_metadata_storage_ref()?.notes());
 
@@ -584,7 +1313,7 @@ impl App {
                     let mut removes = Vec::new();
                     note_file_tree.walk(|_, parent, name, tree, _| {
                         if tree.is_leaf() {
-                            removes.push(Command::RemoveNote { path: path.join(parent.join(name)) });
+                            removes.push(Command::RemoveNote { path: path.join(parent.join(name)), force });
                         }
 
                         true
@@ -596,7 +1325,7 @@ impl App {
 
             Ok(
                 vec![
-                    Command::RemoveNote { path }
+                    Command::RemoveNote { path, force }
                 ]
             )
         };
@@ -641,58 +1370,670 @@ impl App {
             }
         }
 
-        Ok(None)
-    }
+        Ok(None)
+    }
+
+    /// Adds a note with the given content, optionally opening it in the editor afterwards.
+    pub fn add_note_with_content(&mut self, path: PathBuf, tags: Vec<String>, content: String, open: bool) -> AppResult<()> {
+        let mut commands = vec![
+            Command::AddNoteWithContent { path: path.clone(), tags, content }
+        ];
+
+        if open {
+            commands.push(Command::EditNoteContent { path, history: None, clear_tags: false, add_tags: Vec::new(), force: false });
+        }
+
+        let result = self.create_and_execute_commands(commands);
+        if let Err(err) = result {
+            self.command_interpreter.reset()?;
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    pub fn check_snippets(&mut self, path: &Path) -> AppResult<Vec<SnippetCheckResult>> {
+        let content = self.get_note_content(path, None)?;
+        let snippet_runner_manager = SnippetRunnerManger::from_config(
+            self.config.snippet.as_ref()
+        ).map_err(|err| CommandError::Snippet(err))?;
+
+        let note_path = self.get_note_metadata_path(path)?;
+        let context = SnippetContext::new(&self.config.repository, &note_path);
+        Ok(command::check_snippets(&snippet_runner_manager, &content, Some(&context))?)
+    }
+
+    /// Runs the snippets of each note version changed in the commit range and compares with the output stored at that commit.
+    pub fn replay_snippets(&mut self, range: &str) -> AppResult<Vec<(git2::Oid, PathBuf, SnippetCheckResult)>> {
+        let (from, to) = match range.split_once("..") {
+            Some((from, to)) => (from, if to.is_empty() { "HEAD" } else { to }),
+            None => (range, "HEAD")
+        };
+
+        let snippet_runner_manager = SnippetRunnerManger::from_config(
+            self.config.snippet.as_ref()
+        ).map_err(|err| CommandError::Snippet(err))?;
+
+        let repository = self.repository.borrow();
+        let mut rev_walk = repository.revwalk()?;
+        rev_walk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::REVERSE)?;
+        rev_walk.push(repository.revparse_single(to)?.id())?;
+        rev_walk.hide(repository.revparse_single(from)?.id())?;
+
+        let mut results = Vec::new();
+        for commit_id in rev_walk {
+            let commit = repository.find_commit(commit_id?)?;
+            let parent_tree = match commit.parent(0) {
+                Ok(parent) => Some(parent.tree()?),
+                Err(_) => None
+            };
+
+            for historic_note in querying::historic_notes(&repository, &commit.tree()?)? {
+                let content_path = PathBuf::from(format!("{}/{}.{}", NOTES_DIR, historic_note.metadata.id, NOTE_CONTENT_EXT));
+                let parent_content_id = parent_tree.as_ref().and_then(|tree| tree.get_path(&content_path).ok()).map(|entry| entry.id());
+                if parent_content_id == Some(historic_note.content_id) {
+                    continue;
+                }
+
+                let context = SnippetContext::new(&self.config.repository, &historic_note.metadata.path);
+                for result in command::check_snippets(&snippet_runner_manager, &historic_note.content, Some(&context))? {
+                    results.push((commit.id(), historic_note.metadata.path.clone(), result));
+                }
+            }
+        }
+
+        Ok(results)
+    }
+
+    /// Replaces the matches of the query on each line of the notes under the given path (all notes if not given), committing the changes once.
+    /// If dry_run, the changes are only printed. Returns the number of changed notes.
+    pub fn replace_content(&mut self, query: &Regex, replacement: &str, path: Option<PathBuf>, dry_run: bool) -> AppResult<usize> {
+        self.note_metadata_storage()?;
+        let note_metadata_storage = self.note_metadata_storage_ref()?;
+        let searcher = Searcher::new(note_metadata_storage)?;
+
+        let results = if let Some(path) = path {
+            let mut notes = Vec::new();
+            if let Some(note_metadata) = note_metadata_storage.get(&path) {
+                notes.push(note_metadata);
+            } else {
+                let note_file_tree = NoteFileTree::from_iter(note_metadata_storage.notes())
+                    .ok_or_else(|| QueryingError::FailedToCreateNoteFileTree)?;
+                let tree = note_file_tree
+                    .find(&path)
+                    .ok_or_else(|| QueryingError::NoteNotFound(path.to_str().unwrap().to_owned()))?;
+
+                tree.walk(|_, parent, name, tree, _| {
+                    if tree.is_leaf() {
+                        notes.extend(note_metadata_storage.get(&path.join(parent.join(name))));
+                    }
+
+                    true
+                });
+            }
+
+            searcher.replace_in(notes.into_iter(), query, replacement)?
+        } else {
+            searcher.replace(query, replacement)?
+        };
+
+        let result_count = results.len();
+        if dry_run || results.is_empty() {
+            print_replace_results(&results);
+            return Ok(result_count);
+        }
+
+        let commands = results
+            .into_iter()
+            .map(|result| {
+                Command::EditNoteSetContent {
+                    path: result.note_metadata.path.clone(),
+                    clear_tags: false,
+                    add_tags: Vec::new(),
+                    content: result.content,
+                    force: false
+                }
+            })
+            .collect();
+
+        let result = self.create_and_execute_commands(commands);
+        if let Err(err) = result {
+            self.command_interpreter.reset()?;
+            return Err(err);
+        }
+
+        Ok(result_count)
+    }
+
+    /// Copies the content of the note to the clipboard. If no clipboard is available, the content is printed instead.
+    /// Returns true if the content was copied.
+    pub fn copy_to_clipboard(&mut self,
+                             path: &Path,
+                             only_code: bool,
+                             clipboard: &mut dyn Clipboard,
+                             out: &mut dyn Write) -> AppResult<bool> {
+        let content = self.get_note_content(path, None)?;
+        let content = querying::extract_content(content, only_code, false)?;
+
+        match clipboard.set_text(&content) {
+            Ok(()) => Ok(true),
+            Err(err) => {
+                eprintln!("Warning: clipboard not available ({}), printing the content instead.", err);
+                write!(out, "{}", content)?;
+                Ok(false)
+            }
+        }
+    }
+
+    /// Prints the content of each note whose path is given on a line of the input, preceded by a header with the path.
+    pub fn print_contents_from(&mut self,
+                               input: &mut dyn BufRead,
+                               out: &mut dyn Write,
+                               history: Option<String>,
+                               only_code: bool,
+                               only_output: bool) -> AppResult<()> {
+        let mut is_first = true;
+        for line in input.lines() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let path = self.get_path(PathBuf::from(line))?;
+            let content = self.get_note_content(&path, history.clone())?;
+            let content = querying::extract_content(content, only_code, only_output)?;
+
+            if !is_first {
+                writeln!(out)?;
+            }
+            is_first = false;
+
+            writeln!(out, "==> {} <==", path.to_str().unwrap())?;
+            write!(out, "{}", content)?;
+
+            if self.config.track_access {
+                self.record_access(&path)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the content to show for a note, where run indicates if the snippets should be run first (and save if the output is saved).
+    pub fn get_show_content(&mut self, path: &Path, history: Option<String>, run: bool, save: bool) -> AppResult<String> {
+        if run && save {
+            self.create_and_execute_commands(vec![
+                Command::RunSnippet { path: path.to_owned(), save_output: true }
+            ])?;
+            return Ok(self.get_note_content(path, None)?);
+        }
+
+        let content = self.get_note_content(path, history)?;
+        if run {
+            let snippet_runner_manager = SnippetRunnerManger::from_config(
+                self.config.snippet.as_ref()
+            ).map_err(|err| CommandError::Snippet(err))?;
+
+            let note_path = self.get_note_metadata_path(path)?;
+            let arena = markdown::storage();
+            let context = SnippetContext::new(&self.config.repository, &note_path);
+            let root = command::run_snippet(&snippet_runner_manager, &arena, &content, Some(&context), |_| {})?;
+            return Ok(markdown::ast_to_string(&root)?);
+        }
+
+        Ok(content)
+    }
+
+    fn get_note_content(&mut self, path: &Path, git_reference: Option<String>) -> QueryingResult<String> {
+        self.note_metadata_storage()?;
+        let repository = self.repository.borrow();
+        querying::get_note_content(
+            repository.deref(),
+            self.note_metadata_storage_ref()?,
+            path,
+            git_reference
+        )
+    }
+
+    /// Returns the unified diff of the content of a note between two git commits (current content if to is not given).
+    pub fn note_diff(&mut self, path: &Path, from: String, to: Option<String>) -> QueryingResult<String> {
+        let old_content = self.get_note_content(path, Some(from))?;
+        let new_content = self.get_note_content(path, to)?;
+        querying::create_note_diff(path, &old_content, &new_content)
+    }
+
+    fn record_access(&mut self, path: &Path) -> AppResult<()> {
+        let id = self.note_metadata_storage()?
+            .get(path)
+            .ok_or_else(|| QueryingError::NoteNotFound(path.to_str().unwrap().to_string()))?
+            .id;
+
+        let mut access_times = AccessTimes::load(self.repository.borrow().deref())?;
+        access_times.record(&id, Local::now());
+        access_times.save()?;
+        Ok(())
+    }
+
+    /// Returns the path of the note given by path or id (or the given path if the note does not currently exist, such as in history).
+    fn get_note_metadata_path(&mut self, path: &Path) -> QueryingResult<PathBuf> {
+        Ok(
+            self.note_metadata_storage()?
+                .get(path)
+                .map(|note_metadata| note_metadata.path.clone())
+                .unwrap_or_else(|| path.to_owned())
+        )
+    }
+
+    fn get_note_content_path(&mut self, path: &Path) -> QueryingResult<PathBuf> {
+        self.note_metadata_storage()?;
+        let id = self.note_metadata_storage()?
+            .get(path)
+            .ok_or_else(|| QueryingError::NoteNotFound(path.to_str().unwrap().to_string()))?
+            .id;
+
+        Ok(
+            NoteMetadataStorage::get_note_storage_path(
+                &self.config.repository,
+                &id
+            ).1
+        )
+    }
+
+    pub fn clear_cache(&mut self) {
+        self.note_metadata_storage = None;
+        self.version += 1;
+    }
+
+    pub fn has_changed(&self, version: &mut u64) -> bool {
+        if *version != self.version {
+            *version = self.version;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Returns the working directory that the matches of the find query are restricted to (if any).
+    fn find_scope(&mut self, finder: &InputCommandFinder) -> AppResult<Option<PathBuf>> {
+        if finder.is_here() {
+            Ok(Some(self.working_dir()?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Converts the notes matching the filter to the given format (in parallel), returning the destination or error of each note sorted by path.
+    pub fn convert_notes(&mut self, destination: &Path, format: &str, filter: InputCommandFinder) -> AppResult<Vec<(PathBuf, Result<PathBuf, String>)>> {
+        let working_dir = self.find_scope(&filter)?;
+        let query = filter.query()?;
+
+        self.note_metadata_storage()?;
+        let finder = Finder::new(self.note_metadata_storage_ref()?)?;
+        let mut conversions = finder.find(&query)?
+            .into_iter()
+            .filter(|note_metadata| working_dir.as_ref().map(|working_dir| note_metadata.path.starts_with(working_dir)).unwrap_or(true))
+            .map(|note_metadata| {
+                (
+                    note_metadata.path.clone(),
+                    NoteMetadataStorage::get_note_storage_path(&self.config.repository, &note_metadata.id).1,
+                    destination.join(note_metadata.path.with_extension(format))
+                )
+            })
+            .collect::<Vec<_>>();
+        conversions.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let convert = |source: &Path, destination: &Path| -> AppResult<()> {
+            if let Some(parent) = destination.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+
+            markdown::convert(source, destination)
+        };
+
+        let num_threads = std::thread::available_parallelism().map(|count| count.get()).unwrap_or(1);
+        let mut results = Vec::new();
+        for chunk in conversions.chunks(num_threads) {
+            std::thread::scope(|scope| {
+                let handles = chunk
+                    .iter()
+                    .map(|(_, source, destination)| scope.spawn(|| convert(source, destination)))
+                    .collect::<Vec<_>>();
+
+                for ((path, _, destination), handle) in chunk.iter().zip(handles) {
+                    let result = match handle.join() {
+                        Ok(result) => result.map(|_| destination.clone()).map_err(|err| err.to_string()),
+                        Err(_) => Err("conversion panicked".to_owned())
+                    };
+
+                    results.push((path.clone(), result));
+                }
+            });
+        }
+
+        Ok(results)
+    }
+
+    /// Returns the commits that changed the note, newest first.
+    pub fn note_history(&mut self, path: &Path) -> AppResult<Vec<NoteHistoryEntry>> {
+        let id = self.note_metadata_storage()?.get_id_result(path)?;
+        Ok(querying::note_history(&self.repository.borrow(), &id)?)
+    }
+
+    /// Returns the uncommitted changes of the repository.
+    pub fn status(&mut self) -> AppResult<Vec<StatusEntry>> {
+        self.note_metadata_storage()?;
+        let repository = self.repository.borrow();
+        Ok(querying::working_tree_status(repository.deref(), self.note_metadata_storage_ref()?)?)
+    }
+
+    /// Returns the notes in the trash sorted by path.
+    pub fn trashed_notes(&self) -> AppResult<Vec<NoteMetadata>> {
+        Ok(NoteMetadataStorage::trashed_notes(&self.config.repository)?)
+    }
+
+    /// Returns the pinned notes sorted by path.
+    pub fn pinned_notes(&mut self) -> AppResult<Vec<&NoteMetadata>> {
+        let mut results = self.note_metadata_storage()?.notes()
+            .filter(|note_metadata| note_metadata.pinned)
+            .collect::<Vec<_>>();
+        results.sort_by_key(|note_metadata| &note_metadata.path);
+        Ok(results)
+    }
+
+    /// Returns the aliases together with the current path of the note (aliases of removed notes are skipped).
+    pub fn list_aliases(&mut self) -> AppResult<Vec<(String, PathBuf)>> {
+        let note_aliases = NoteAliases::load(&self.repository.borrow())?;
+        let note_metadata_storage = self.note_metadata_storage()?;
+
+        Ok(
+            note_aliases.iter()
+                .filter_map(|(alias, id)| Some((alias.to_owned(), note_metadata_storage.get_by_id(&id)?.path.clone())))
+                .collect()
+        )
+    }
+
+    pub fn add_alias(&mut self, alias: &str, path: PathBuf) -> AppResult<()> {
+        if alias.is_empty() || alias.contains('/') {
+            return Err(AppError::InvalidPath(format!("Invalid alias '{}'", alias)));
+        }
+
+        let path = self.get_path(path)?;
+        let id = self.note_metadata_storage()?.get_id_result(&path)?;
+
+        let mut note_aliases = NoteAliases::load(&self.repository.borrow())?;
+        note_aliases.add(alias, &id);
+        note_aliases.save()?;
+        Ok(())
+    }
+
+    pub fn remove_alias(&mut self, alias: &str) -> AppResult<()> {
+        let mut note_aliases = NoteAliases::load(&self.repository.borrow())?;
+        if !note_aliases.remove(alias) {
+            return Err(AppError::AliasNotFound(alias.to_owned()));
+        }
+
+        note_aliases.save()?;
+        Ok(())
+    }
+
+    fn create_sync_credentials(&self, remote: &git2::Remote) -> AppResult<git_helpers::CredentialsCallback> {
+        Ok(
+            git_helpers::create_sync_credentials(
+                remote.url().unwrap_or(""),
+                &self.config.sync_token_env,
+                self.config.sync_username.clone()
+            )?
+        )
+    }
+
+    /// Returns the ids of the given number of most recent commits (following the first parent), newest first.
+    fn last_commits(&self, count: usize) -> AppResult<Vec<String>> {
+        if count == 0 {
+            return Err(AppError::Input("The number of commits must be at least 1".to_owned()));
+        }
+
+        let repository = self.repository.borrow();
+        let mut commit = repository.head()
+            .and_then(|head| head.peel_to_commit())
+            .map_err(|_| CommandError::FailedToUndo("there are no commits".to_owned()))?;
+
+        let mut commits = Vec::new();
+        loop {
+            if commit.parent_count() == 0 {
+                return Err(CommandError::FailedToUndo("the initial commit can not be undone".to_owned()).into());
+            }
+
+            commits.push(commit.id().to_string());
+            if commits.len() == count {
+                break;
+            }
+
+            commit = commit.parent(0)?;
+        }
+
+        Ok(commits)
+    }
+
+    /// Fetches the branch from the remote and merges it, returning the notes that were changed by the merge.
+    /// If the merge has conflicts, the conflicting notes are returned as an error.
+    pub fn pull(&mut self, remote: &str, branch: &str, abort_on_conflict: bool) -> AppResult<Vec<(NoteChange, PathBuf)>> {
+        self.note_metadata_storage()?;
+
+        let result = {
+            let repository = self.repository.borrow();
+
+            let branch_ref = git_helpers::find_branch_ref(&repository, branch)?;
+            let mut remote = repository.find_remote(remote).map_err(|_| AppError::RemoteNotFound(remote.to_owned()))?;
+
+            let old_tree = repository.head().ok().map(|head| head.peel_to_tree()).transpose()?;
+
+            let mut fetch_options = FetchOptions::new();
+            let mut callbacks = RemoteCallbacks::new();
+            callbacks.credentials(self.create_sync_credentials(&remote)?);
+            fetch_options.remote_callbacks(callbacks);
+
+            remote.fetch(&[&branch_ref], Some(&mut fetch_options), None)?;
+            let fetch_head = repository.find_reference("FETCH_HEAD")?;
+            let fetch_commit = repository.reference_to_annotated_commit(&fetch_head)?;
+            let conflicted_paths = git_helpers::merge(&repository, branch, fetch_commit, abort_on_conflict, self.config.commit_signing())?;
+
+            if conflicted_paths.is_empty() {
+                let new_tree = repository.head()?.peel_to_tree()?;
+                Ok(querying::note_changes(&repository, old_tree.as_ref(), &new_tree)?)
+            } else {
+                let note_metadata_storage = self.note_metadata_storage_ref()?;
+                let mut conflicted_notes = Vec::new();
+                for path in conflicted_paths {
+                    let path = querying::note_path_for_file(&path, note_metadata_storage).unwrap_or(path);
+                    if !conflicted_notes.contains(&path) {
+                        conflicted_notes.push(path);
+                    }
+                }
+
+                if abort_on_conflict {
+                    repository.cleanup_state()?;
+                }
+
+                Err(AppError::MergeConflicts(conflicted_notes))
+            }
+        };
+
+        self.clear_cache();
+        result
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn working_dir(&mut self) -> AppResult<PathBuf> {
+        self.resolve_path(Path::new("").to_owned())
+    }
+
+    pub fn set_working_dir(&mut self, working_dir: &Path) {
+        self.working_dir = Some(working_dir.to_owned());
+    }
+
+    pub fn dump_note_metadata(&mut self, path: &Path, json: bool) -> AppResult<String> {
+        let note_metadata = self.note_metadata_storage()?
+            .get(path)
+            .ok_or_else(|| QueryingError::NoteNotFound(path.to_str().unwrap().to_owned()))?;
+
+        if json {
+            serde_json::to_string_pretty(note_metadata).map_err(|err| AppError::InvalidMetadata(err.to_string()))
+        } else {
+            toml::to_string(note_metadata).map_err(|err| AppError::InvalidMetadata(err.to_string()))
+        }
+    }
+
+    /// Sets the metadata of the note from the given (TOML or JSON) content. The id can not be changed.
+    /// Only the changed fields are updated and nothing is committed if the metadata is unchanged.
+    pub fn load_note_metadata(&mut self, path: &Path, content: &str) -> AppResult<()> {
+        let new_metadata: NoteMetadata = if content.trim_start().starts_with('{') {
+            serde_json::from_str(content).map_err(|err| AppError::InvalidMetadata(err.to_string()))?
+        } else {
+            NoteMetadata::parse(content).map_err(|err| AppError::InvalidMetadata(err.to_string()))?
+        };
+
+        let note_metadata_storage = self.note_metadata_storage()?;
+        let note_metadata = note_metadata_storage
+            .get(path)
+            .ok_or_else(|| QueryingError::NoteNotFound(path.to_str().unwrap().to_owned()))?
+            .clone();
+
+        if new_metadata.id != note_metadata.id {
+            return Err(AppError::InvalidMetadata(format!("the id can not be changed (from {} to {})", note_metadata.id, new_metadata.id)));
+        }
+
+        let id_path = Path::new(&note_metadata.id.to_string()).to_owned();
+        let mut commands = Vec::new();
+
+        // Unlock before moving so that a note being unlocked can also be moved
+        if note_metadata.locked && !new_metadata.locked {
+            commands.push(Command::SetNoteLocked { path: id_path.clone(), locked: false });
+        }
+
+        if new_metadata.path != note_metadata.path {
+            if note_metadata_storage.contains_path(&new_metadata.path) {
+                return Err(AppError::InvalidMetadata(format!("a note already exists at '{}'", new_metadata.path.to_str().unwrap())));
+            }
+
+            commands.push(Command::MoveNote {
+                source: note_metadata.path.clone(),
+                destination: new_metadata.path.clone(),
+                force: false,
+                merge: false,
+                ignore_lock: false
+            });
+        }
+
+        if new_metadata.tags != note_metadata.tags
+            || new_metadata.created != note_metadata.created
+            || new_metadata.last_updated != note_metadata.last_updated {
+            commands.push(Command::SetNoteMetadata {
+                path: id_path.clone(),
+                tags: new_metadata.tags,
+                created: new_metadata.created,
+                last_updated: new_metadata.last_updated
+            });
+        }
+
+        if new_metadata.pinned != note_metadata.pinned {
+            commands.push(Command::SetNotePinned { path: id_path.clone(), pinned: new_metadata.pinned });
+        }
+
+        if !note_metadata.locked && new_metadata.locked {
+            commands.push(Command::SetNoteLocked { path: id_path, locked: true });
+        }
 
-    fn get_note_content(&mut self, path: &Path, git_reference: Option<String>) -> QueryingResult<String> {
-        self.note_metadata_storage()?;
-        let repository = self.repository.borrow();
-        querying::get_note_content(
-            repository.deref(),
-            self.note_metadata_storage_ref()?,
-            path,
-            git_reference
-        )
-    }
+        if commands.is_empty() {
+            return Ok(());
+        }
 
-    fn get_note_content_path(&mut self, path: &Path) -> QueryingResult<PathBuf> {
-        self.note_metadata_storage()?;
-        let id = self.note_metadata_storage()?
-            .get(path)
-            .ok_or_else(|| QueryingError::NoteNotFound(path.to_str().unwrap().to_string()))?
-            .id;
+        let result = self.create_and_execute_commands(commands);
+        if let Err(err) = result {
+            self.command_interpreter.reset()?;
+            return Err(err);
+        }
 
-        Ok(
-            NoteMetadataStorage::get_note_storage_path(
-                &self.config.repository,
-                &id
-            ).1
-        )
+        Ok(())
     }
 
-    pub fn clear_cache(&mut self) {
-        self.note_metadata_storage = None;
-        self.version += 1;
-    }
+    /// Edits the note given by its (logical) path with the web editor, where the edited content is saved (and committed) when the editor exits.
+    pub fn edit_with_web_editor<F: FnOnce(WebEditorInput) -> EditorOutput>(&mut self, path: PathBuf, launch: F) -> AppResult<()> {
+        let path = self.get_path(path)?;
+        let repository_path = self.config.repository.clone();
+        let note_metadata_storage = self.note_metadata_storage()?;
+        let note_metadata = note_metadata_storage
+            .get(&path)
+            .ok_or_else(|| QueryingError::NoteNotFound(path.to_str().unwrap().to_owned()))?
+            .clone();
+
+        if note_metadata.locked {
+            return Err(CommandError::NoteLocked(note_metadata.path).into());
+        }
 
-    pub fn has_changed(&self, version: &mut u64) -> bool {
-        if *version != self.version {
-            *version = self.version;
-            true
-        } else {
-            false
+        let content = note_metadata_storage.get_content(&note_metadata.path)?;
+        let temp_file = tempfile::Builder::new()
+            .suffix(&format!(".{}", NOTE_CONTENT_EXT))
+            .tempfile()?;
+        std::fs::write(temp_file.path(), &content)?;
+
+        let output = launch(
+            WebEditorInput {
+                path: temp_file.path().to_owned(),
+                display_path: Some(note_metadata.path.clone()),
+                repository_path: Some(repository_path)
+            }
+        );
+
+        let new_content = std::fs::read_to_string(temp_file.path())?;
+
+        let mut commands = Vec::new();
+        if new_content != content {
+            commands.push(Command::EditNoteSetContent {
+                path: note_metadata.path,
+                clear_tags: false,
+                add_tags: vec![],
+                content: new_content,
+                force: false
+            });
         }
-    }
 
-    pub fn working_dir(&mut self) -> AppResult<PathBuf> {
-        self.get_path(Path::new("").to_owned())
-    }
+        let result = self.command_interpreter
+            .add_resources_from_editor_output(output)
+            .map_err(|err| err.into())
+            .and_then(|_| self.create_and_execute_commands(commands));
+        if let Err(err) = result {
+            self.command_interpreter.reset()?;
+            return Err(err);
+        }
 
-    pub fn set_working_dir(&mut self, working_dir: &Path) {
-        self.working_dir = Some(working_dir.to_owned());
+        Ok(())
     }
 
+    /// Resolves the path of an existing note, where an alias is used if there is no note at the path.
     fn get_path(&mut self, path: PathBuf) -> AppResult<PathBuf> {
+        let resolved_path = self.resolve_path(path.clone())?;
+
+        // Aliases are only used when there is no note at the path
+        let note_metadata_storage = self.note_metadata_storage_ref()?;
+        if note_metadata_storage.get(&resolved_path).is_none() {
+            if let Some(alias) = path.to_str() {
+                if let Some(id) = NoteAliases::load(&self.repository.borrow())?.get(alias) {
+                    if note_metadata_storage.get_by_id(&id).is_some() {
+                        return Ok(Path::new(&id.to_string()).to_owned());
+                    }
+                }
+            }
+        }
+
+        Ok(resolved_path)
+    }
+
+    /// Resolves the path as is (relative to the working dir), such as the path of a new note or a destination.
+    fn resolve_path(&mut self, path: PathBuf) -> AppResult<PathBuf> {
         self.note_metadata_storage()?;
         self.note_metadata_storage_ref()?.resolve_path(
             self.working_dir.as_ref(),
@@ -707,6 +2048,21 @@ pub struct MainInputCommand {
     /// Don't use current directory as initial working dir
     #[structopt(long="no-working-dir")]
     pub use_non_working_dir: bool,
+    /// Sets the created date of added notes from leading YYYY/MM/DD parts of the path
+    #[structopt(long="created-from-path")]
+    pub created_from_path: bool,
+    /// Prints the elapsed time of the major phases (to stderr)
+    #[structopt(long)]
+    pub timings: bool,
+    /// How dates are shown in listings: default, rfc3339, unix or relative
+    #[structopt(long="date-format")]
+    pub date_format: Option<DateFormat>,
+    /// Disallows commands that change the repository
+    #[structopt(long="read-only")]
+    pub read_only: bool,
+    /// Don't add the 'snippet' tag to notes with code blocks when tags are added automatically
+    #[structopt(long="no-snippet-tag")]
+    pub no_snippet_tag: bool,
     #[structopt(subcommand)]
     pub command: Option<InputCommand>
 }
@@ -717,6 +2073,26 @@ impl MainInputCommand {
             config.use_working_dir = false;
         }
 
+        if self.created_from_path {
+            config.created_from_path = true;
+        }
+
+        if self.timings {
+            config.timings = true;
+        }
+
+        if let Some(date_format) = self.date_format {
+            config.date_format = date_format;
+        }
+
+        if self.read_only {
+            config.read_only = true;
+        }
+
+        if self.no_snippet_tag {
+            config.tags.snippet_tag = false;
+        }
+
         config
     }
 }
@@ -733,6 +2109,13 @@ pub enum InputCommand {
         #[structopt(long)]
         use_existing: bool
     },
+    /// Clones an existing notes repository and makes it the active repository.
+    Clone {
+        /// The URL of the repository
+        url: String,
+        /// The name of the repository (default is derived from the URL)
+        name: Option<String>
+    },
     /// Switches the active repository to the given one. If path is relative, then it is relative to $HOME/.gitnotes
     Switch {
         path: PathBuf
@@ -742,10 +2125,15 @@ pub enum InputCommand {
         /// Prints only the name of the repository.
         #[structopt(long="repo")]
         only_repository: bool,
-        /// Sets the given config value (format key=value).
-        /// Supported keys: repository, editor, base_dir, sync_default_branch, sync_default_remote
+        /// Prints the absolute path of the repository.
+        #[structopt(long="repo-path", conflicts_with="only-repository")]
+        repository_path: bool,
+        /// Sets the given config value (format key=value). See --list-keys for the supported keys.
         #[structopt(long)]
-        set: Option<String>
+        set: Option<String>,
+        /// Lists the keys that can be set, their type and current value.
+        #[structopt(long="list-keys")]
+        list_keys: bool
     },
     /// Updates the symbolic links
     UpdateSymbolicLinks {
@@ -757,7 +2145,13 @@ pub enum InputCommand {
         path: PathBuf,
         /// The tags of the note.
         #[structopt(long)]
-        tags: Vec<String>
+        tags: Vec<String>,
+        /// Adds the note even if the editor was closed without any content
+        #[structopt(long="allow-empty")]
+        allow_empty: bool,
+        /// Opens the note in the editor after it has been created from stdin
+        #[structopt(long)]
+        open: bool
     },
     /// Edit an existing note.
     Edit {
@@ -771,7 +2165,16 @@ pub enum InputCommand {
         clear_tags: bool,
         /// Adds tags. These are added after tags are cleared.
         #[structopt(long)]
-        add_tags: Vec<String>
+        add_tags: Vec<String>,
+        /// Creates the note (with the added tags) if it doesn't exist
+        #[structopt(long, conflicts_with="history")]
+        create: bool,
+        /// Edits the note even if it is locked
+        #[structopt(long, short)]
+        force: bool,
+        /// Removes the saved output of snippets instead of launching an editor
+        #[structopt(long="strip-output", conflicts_with_all=&["history", "create"])]
+        strip_output: bool
     },
     /// Moves a note to a new location
     #[structopt(name="mv")]
@@ -782,7 +2185,39 @@ pub enum InputCommand {
         destination: PathBuf,
         /// Deletes note if it exists at destination
         #[structopt(long, short)]
-        force: bool
+        force: bool,
+        /// Appends the content (and adds the tags) to the note if it exists at destination, removing the source
+        #[structopt(long, conflicts_with="force")]
+        merge: bool,
+        /// Allows moving locked notes, and deleting or merging into a locked note at the destination
+        #[structopt(long="ignore-lock")]
+        ignore_lock: bool,
+        /// Only prints the resolved source and destination of each note without moving
+        #[structopt(long="dry-run")]
+        dry_run: bool
+    },
+    /// Copies a note to a new location, where the copy gets a new id
+    #[structopt(name="cp", alias="copy")]
+    Copy {
+        /// The path of the note. Id also work.
+        source: PathBuf,
+        /// The absolute path of the destination.
+        destination: PathBuf,
+        /// Deletes note if it exists at destination
+        #[structopt(long, short)]
+        force: bool,
+        /// Allows deleting a locked note at the destination
+        #[structopt(long="ignore-lock")]
+        ignore_lock: bool
+    },
+    /// Moves the notes matching the filter to YYYY/MM/DD/<filename> based on their created date
+    #[structopt(name="move-to-date")]
+    MoveToDate {
+        /// Deletes existing notes at the destinations
+        #[structopt(long, short)]
+        force: bool,
+        #[structopt(subcommand)]
+        filter: InputCommandFinder
     },
     /// Removes a note
     #[structopt(name="rm")]
@@ -791,12 +2226,49 @@ pub enum InputCommand {
         path: PathBuf,
         /// Recursively removes all notes in path.
         #[structopt(long, short)]
-        recursive: bool
+        recursive: bool,
+        /// Only prints the resolved paths of the notes that would be removed without removing
+        #[structopt(long="dry-run")]
+        dry_run: bool,
+        /// Removes recursively without asking for confirmation
+        #[structopt(long, short)]
+        yes: bool,
+        /// Removes the notes even if they are locked
+        #[structopt(long, short)]
+        force: bool
+    },
+    /// Restores a note that has been moved to the trash (when soft_delete is enabled)
+    Restore {
+        /// The id of the trashed note. Leave empty to list the notes in the trash.
+        id: Option<NoteId>
+    },
+    /// Removes the notes whose content is empty or only whitespace
+    #[structopt(name="prune-empty")]
+    PruneEmpty {
+        /// Removes without asking for confirmation
+        #[structopt(long, short)]
+        yes: bool
+    },
+    /// Prints a new note id that is not used by any existing note
+    Id {
+
     },
     /// Undo the given commit
     Undo {
         /// The git commit to undo
-        commit: String
+        #[structopt(required_unless="last")]
+        commit: Option<String>,
+        /// Undo the most recent commit, or the given number of most recent commits (as a single commit)
+        #[structopt(long, conflicts_with="commit")]
+        last: Option<Option<usize>>
+    },
+    /// Squashes the commits in the given range (<from>..<to>) into a single commit. The end of the range must be HEAD.
+    Squash {
+        /// The commit range to squash. The from commit itself is not included. If only <from> is given, HEAD is used as end.
+        range: String,
+        /// Squashes even if the range contains merge commits
+        #[structopt(long)]
+        force: bool
     },
     /// Runs the code snippet contained in a note.
     #[structopt(name="run")]
@@ -805,7 +2277,16 @@ pub enum InputCommand {
         path: PathBuf,
         /// Saves the output of the snippet inside the note.
         #[structopt(long="save")]
-        save_output: bool
+        save_output: bool,
+        /// Compares the output of each snippet with its saved output block instead, failing on any mismatch.
+        #[structopt(long, conflicts_with="save")]
+        check: bool
+    },
+    /// Re-runs the snippets of the notes changed in a commit range and reports if the outputs match the ones stored at each commit (nothing is changed).
+    #[structopt(name="replay")]
+    Replay {
+        /// The commit range to replay. The from commit itself is not included. If only <from> is given, HEAD is used as end.
+        range: String
     },
     /// Converts the given note to a file (like pdf)
     #[structopt(name="convert")]
@@ -815,6 +2296,17 @@ pub enum InputCommand {
         /// The destination of  the path
         destination: PathBuf
     },
+    /// Converts the notes matching the filter to files (like pdf) in the destination directory
+    #[structopt(name="convert-all")]
+    ConvertAll {
+        /// The destination directory. The converted files are placed at the path of the note within it.
+        destination: PathBuf,
+        /// The format (file extension) to convert to
+        #[structopt(long, default_value="pdf")]
+        format: String,
+        #[structopt(subcommand)]
+        filter: InputCommandFinder
+    },
     /// Begins a commit. All subsequent operations are done within this commit (interactive mode only).
     Begin {
 
@@ -828,6 +2320,56 @@ pub enum InputCommand {
         #[structopt(subcommand)]
         command: InputCommandRemote
     },
+    /// Changes the id of a note to the given id (6 digits). Use an alias for a readable name.
+    #[structopt(name="rename-id")]
+    RenameId {
+        /// The path of the note. Id also work.
+        path: PathBuf,
+        /// The new id of the note
+        new_id: NoteId
+    },
+    /// Pins a note, which makes it show up in the pinned listing
+    Pin {
+        /// The path of the note. Id also work.
+        path: PathBuf
+    },
+    /// Unpins a note
+    Unpin {
+        /// The path of the note. Id also work.
+        path: PathBuf
+    },
+    /// Locks a note, which prevents it from being edited, moved or removed without --force
+    Lock {
+        /// The path of the note. Id also work.
+        path: PathBuf
+    },
+    /// Unlocks a note
+    Unlock {
+        /// The path of the note. Id also work.
+        path: PathBuf
+    },
+    /// Replaces the tags of a note
+    #[structopt(name="set-tags")]
+    SetTags {
+        /// The path of the note. Id also work.
+        path: PathBuf,
+        /// The new tags of the note.
+        tags: Vec<String>
+    },
+    /// Lists the pinned notes
+    Pinned {
+
+    },
+    /// Manages aliases, which can be used instead of the path of a note
+    Alias {
+        #[structopt(subcommand)]
+        command: InputCommandAlias
+    },
+    /// Dumps or loads the metadata of notes (for scripting)
+    Metadata {
+        #[structopt(subcommand)]
+        command: InputCommandMetadata
+    },
     /// Synchronizes the notes with a remote git instance
     #[structopt(name="sync")]
     Synchronize {
@@ -840,12 +2382,15 @@ pub enum InputCommand {
         no_pull: bool,
         /// Don't push when synchronizing
         #[structopt(long="no-push")]
-        no_push: bool
+        no_push: bool,
+        /// Leaves the working tree unchanged if the pull has merge conflicts (instead of writing conflict markers)
+        #[structopt(long="abort-on-conflict", conflicts_with="no-pull")]
+        abort_on_conflict: bool
     },
     /// Prints the content of a note.
     #[structopt(name="cat")]
     PrintContent {
-        /// The path of the note. Id also work.
+        /// The path of the note. Id also work. Use '-' to read the paths of notes from stdin (one per line).
         path: PathBuf,
         /// Prints the content at the given git commit
         #[structopt(long="history")]
@@ -857,6 +2402,15 @@ pub enum InputCommand {
         #[structopt(long="output")]
         only_output: bool
     },
+    /// Prints the changes of a note between two git commits.
+    Diff {
+        /// The path of the note. Id also work.
+        path: PathBuf,
+        /// The git commit to diff from
+        from: String,
+        /// The git commit to diff to. If not given, the current content is used.
+        to: Option<String>
+    },
     /// Shows the content of a note in an editor
     Show {
         /// The path of the note. Id also work.
@@ -869,13 +2423,67 @@ pub enum InputCommand {
         only_code: bool,
         /// Print only output content.
         #[structopt(long="output")]
-        only_output: bool
+        only_output: bool,
+        /// Runs the code snippets before showing the content.
+        #[structopt(long, conflicts_with="history")]
+        run: bool,
+        /// Saves the output of the run to the note.
+        #[structopt(long, requires="run")]
+        save: bool
+    },
+    /// Copies the content of a note to the clipboard.
+    #[structopt(name="copy-to-clipboard", alias="clip")]
+    CopyToClipboard {
+        /// The path of the note. Id also work.
+        path: PathBuf,
+        /// Copy only code content.
+        #[structopt(long="code")]
+        only_code: bool
+    },
+    /// Records that a note was accessed without changing it.
+    Touch {
+        /// The path of the note. Id also work.
+        path: PathBuf
+    },
+    /// Lists the most recently updated notes.
+    Recent {
+        /// The number of notes to show.
+        #[structopt(default_value="10")]
+        count: usize,
+        /// Lists the most recently accessed notes instead (see the track_access config key).
+        #[structopt(long)]
+        accessed: bool
+    },
+    /// Lists all tags together with the number of notes that have them.
+    Tags {
+        /// Sorts the tags by name or count (most used first).
+        #[structopt(long, default_value="name")]
+        sort: TagSort,
+        /// The output format: text or json.
+        #[structopt(long, default_value="text")]
+        format: OutputFormat
+    },
+    /// Lists the notes by the size of their content, largest first.
+    #[structopt(name="size-report")]
+    SizeReport {
+        /// Shows only the given number of largest notes.
+        #[structopt(long)]
+        top: Option<usize>
     },
     /// Lists note in a directory.
     #[structopt(name="ls")]
     ListDirectory {
         /// The directory to list.
-        query: Option<PathBuf>
+        query: Option<PathBuf>,
+        /// Shows the size of the notes (directories shows the total size)
+        #[structopt(long="size")]
+        with_size: bool,
+        /// Lists pinned notes before the other entries.
+        #[structopt(long="pinned-first")]
+        pinned_first: bool,
+        /// The output format: text or json.
+        #[structopt(long, default_value="text")]
+        format: OutputFormat
     },
     /// Lists note in a tree structure.
     Tree {
@@ -886,31 +2494,116 @@ pub enum InputCommand {
         using_date: bool,
         /// Uses tags as the path instead (file name is still used)
         #[structopt(long="tags", short="-t")]
-        using_tags: bool
+        using_tags: bool,
+        /// Only shows the notes (with their path relative to the listed directory)
+        #[structopt(long="files-only", conflicts_with="dirs-only")]
+        files_only: bool,
+        /// Only shows the directories
+        #[structopt(long="dirs-only")]
+        dirs_only: bool,
+        /// Shows the size of the notes (directories shows the total size)
+        #[structopt(long="size")]
+        with_size: bool
     },
     /// Searches for note based on properties.
     #[structopt(name="find")]
     Finder {
-        /// Creates an interactive prompt to choose which match to launch a new command with
+        /// Creates an interactive prompt to choose which match to launch a new command with (Enter). The hotkeys e (edit), c (cat) and d (rm) can also be used
         #[structopt(long, short)]
         interactive: Option<String>,
+        /// Sorts the notes by path, created, updated, title (first heading) or id.
+        #[structopt(long, default_value="path")]
+        sort: SortBy,
+        /// Reverses the sort order.
+        #[structopt(long)]
+        reverse: bool,
+        /// The output format: text or json.
+        #[structopt(long, default_value="text")]
+        format: OutputFormat,
+        /// Only includes the first given number of notes (after sorting).
+        #[structopt(long)]
+        limit: Option<usize>,
+        /// Runs the given command (e.g. 'mv {} archive/') for each note, where {} is replaced by the path of the note (or added last if not used)
+        #[structopt(long, conflicts_with="interactive")]
+        then: Option<String>,
         #[structopt(subcommand)]
         command: InputCommandFinder
     },
     /// Searches for note based on content.
     #[structopt(name="grep")]
     SearchContent {
-        /// The regex query.
+        /// The regex query. Matching is Unicode-aware (e.g. \w and case insensitivity), use (?-u) to only match ASCII.
         query: String,
         /// Indicates if the match is cans sensitive
         #[structopt(long="no-ignore-case")]
         case_sensitive: bool,
+        /// Matches against the whole content instead of line by line, allowing matches (e.g. \n) to span lines. ^ and $ still match at line boundaries.
+        #[structopt(long, conflicts_with_all=&["history", "replace"])]
+        multiline: bool,
+        /// Groups the matches by note, printing the note once followed by the matching lines
+        #[structopt(long, alias="context-tree", conflicts_with_all=&["multiline", "history", "replace"])]
+        tree: bool,
+        /// Also searches the text files among the resources (prefixed with 'resource:')
+        #[structopt(long="include-resources", conflicts_with_all=&["multiline", "tree", "history", "replace"])]
+        include_resources: bool,
         /// Search through git history (reverse) instead between the given references (inclusive)
         #[structopt(long)]
         history: Vec<String>,
-        /// Creates an interactive prompt to choose which match to launch a new command with
+        /// Creates an interactive prompt to choose which match to launch a new command with (Enter). The hotkeys e (edit), c (cat) and d (rm) can also be used
         #[structopt(long, short)]
-        interactive: Option<String>
+        interactive: Option<String>,
+        /// Replaces the matches in each line with the given replacement ($1 style references supported) and commits the changes
+        #[structopt(long, conflicts_with_all=&["history", "interactive"])]
+        replace: Option<String>,
+        /// Only prints the changes that --replace would make
+        #[structopt(long="dry-run", requires="replace")]
+        dry_run: bool,
+        /// Stops after the given number of matching lines
+        #[structopt(long, conflicts_with="replace")]
+        limit: Option<usize>,
+        /// Prints the matches as JSON objects (path, id, line_number, line, match_start and match_end), where the match is a byte range of the line
+        #[structopt(long, conflicts_with_all=&["multiline", "tree", "include-resources", "history", "interactive", "replace"])]
+        json: bool,
+        /// Prints the given number of lines after each matching line
+        #[structopt(long, short="A", conflicts_with_all=&["multiline", "replace", "json"])]
+        after: Option<usize>,
+        /// Prints the given number of lines before each matching line
+        #[structopt(long, short="B", conflicts_with_all=&["multiline", "replace", "json"])]
+        before: Option<usize>,
+        /// Prints the given number of lines before and after each matching line (overridden by --before and --after)
+        #[structopt(long, short="C", conflicts_with_all=&["multiline", "replace", "json"])]
+        context: Option<usize>,
+        /// Only prints the path of each matching note
+        #[structopt(long="files-with-matches", short="l", conflicts_with_all=&["multiline", "tree", "include-resources", "history", "replace", "limit", "json", "after", "before", "context", "count"])]
+        files_with_matches: bool,
+        /// Only prints the path of each matching note together with the number of matching lines
+        #[structopt(long, short="c", conflicts_with_all=&["multiline", "tree", "include-resources", "history", "replace", "limit", "json", "after", "before", "context"])]
+        count: bool
+    },
+    /// Replaces the matches of a regex on each line of the notes and commits the changes ($1 style references supported)
+    Replace {
+        /// The regex to replace
+        query: String,
+        /// The replacement
+        replacement: String,
+        /// Only replaces in the notes under this path (or the note itself)
+        #[structopt(long)]
+        path: Option<PathBuf>,
+        /// Only prints the changes without replacing
+        #[structopt(long="dry-run")]
+        dry_run: bool
+    },
+    /// Finds notes with duplicated content.
+    Duplicates {
+        /// Only group notes with identical content (default).
+        #[structopt(long, conflicts_with="similar")]
+        exact: bool,
+        /// Groups notes with similar content instead of identical content.
+        #[structopt(long)]
+        similar: bool,
+        /// The (word based) similarity threshold between 0 and 1 used with --similar.
+        #[structopt(long, default_value="0.8")]
+        threshold: f64
     },
     /// Manage resources
     Resource {
@@ -926,7 +2619,25 @@ pub enum InputCommand {
     Log {
         /// The number of commits to show. -1 for all.
         #[structopt(default_value="5")]
-        count: isize
+        count: isize,
+        /// Draws the commit graph next to the commits (can not be combined with filters).
+        #[structopt(long, conflicts_with_all=&["author", "grep"])]
+        graph: bool,
+        /// Shows only commits where the author name or email contains the given text.
+        #[structopt(long)]
+        author: Option<String>,
+        /// Shows only commits where the message matches the given regex.
+        #[structopt(long)]
+        grep: Option<Regex>
+    },
+    /// Shows the uncommitted (staged and unstaged) changes
+    Status {
+
+    },
+    /// Shows the commits that changed a note (newest first)
+    History {
+        /// The path of the note. Id also work.
+        path: PathBuf
     },
     /// Shows information about a note
     Info {
@@ -935,6 +2646,9 @@ pub enum InputCommand {
         /// Prints only the file system path.
         #[structopt(long="file-system")]
         only_file_system_path: bool,
+        /// Also prints the notes this note links to and the notes linking to it.
+        #[structopt(long)]
+        resolve: bool
     },
     /// Changes the working directory of the (virtual) file system (interactive mode only)
     #[structopt(name="cd")]
@@ -951,66 +2665,274 @@ pub enum InputCommand {
     WebEditor {
         /// The (file system) path to edit
         path: PathBuf,
+        /// The path is the path of a note (id also work) instead of a file system path, where the changes are committed when the editor is stopped
+        #[structopt(long)]
+        note: bool,
         /// The part to run the web server at (default: 9000)
         #[structopt(long, default_value="9000")]
         port: u16,
         /// Launches editor in read only mode
         #[structopt(long="read-only")]
-        is_read_only: bool
+        is_read_only: bool,
+        /// The code highlight theme to use
+        #[structopt(long)]
+        theme: Option<String>
+    },
+    /// Generates shell completions (to stdout by default)
+    Completions {
+        /// The shell to generate completions for (default: detected from $SHELL)
+        #[structopt(possible_values=&Shell::variants(), case_insensitive=true)]
+        shell: Option<Shell>,
+        /// Writes the completions to the conventional location of the shell instead
+        #[structopt(long)]
+        install: bool
     }
 }
 
 #[derive(Debug, StructOpt)]
 pub enum InputCommandFinder {
     /// Searches based on tags.
+    #[structopt(setting=structopt::clap::AppSettings::TrailingVarArg)]
     Tag {
-        /// The tags that the note must contain (AND).
-        tags: Vec<StringMatcher>
+        /// The tags that the note must contain (AND). Tags with wildcards (e.g. 'py*') are matched as globs. Use '--' before a literal 'and' or 'or'.
+        #[structopt(required=true)]
+        tags: Vec<String>
     },
     /// Searches based on name.
+    #[structopt(setting=structopt::clap::AppSettings::TrailingVarArg)]
     Name {
         /// Regex pattern.
-        name: RegexMatcher
+        name: RegexMatcher,
+        /// Only includes notes under the current working directory.
+        #[structopt(long)]
+        here: bool,
+        /// Includes notes regardless of the working directory (default). Overrides --here.
+        #[structopt(long)]
+        global: bool,
+        /// Further searches combined with 'and' or 'or' (e.g. 'and tag python'). Use '--' before a literal 'and' or 'or'.
+        clauses: Vec<String>
     },
     /// Searches based on id.
+    #[structopt(setting=structopt::clap::AppSettings::TrailingVarArg)]
     Id {
         /// Regex pattern.
-        id: RegexMatcher
+        id: RegexMatcher,
+        /// Further searches combined with 'and' or 'or' (e.g. 'and tag python'). Use '--' before a literal 'and' or 'or'.
+        clauses: Vec<String>
+    },
+    /// Searches based on content.
+    #[structopt(setting=structopt::clap::AppSettings::TrailingVarArg)]
+    Content {
+        /// Regex pattern.
+        pattern: RegexMatcher,
+        /// Further searches combined with 'and' or 'or' (e.g. 'and tag python'). Use '--' before a literal 'and' or 'or'.
+        clauses: Vec<String>
+    },
+    /// Searches using an expression such as 'tag:rust AND NOT tag:draft'.
+    Expr {
+        /// Searches (tag:, name:, id:, content:, created:, updated:) combined with AND, OR, NOT and parentheses.
+        #[structopt(required=true)]
+        expression: Vec<String>
     },
     /// Searches based on created date
+    #[structopt(setting=structopt::clap::AppSettings::TrailingVarArg)]
     Created {
         /// First element is year, then month, etc. All parts are optional.
-        parts: Vec<i32>
+        parts: Vec<String>
     },
     /// Searches based on updated date
+    #[structopt(setting=structopt::clap::AppSettings::TrailingVarArg)]
     Updated {
         /// First element is year, then month, etc. All parts are optional.
-        parts: Vec<i32>
+        parts: Vec<String>
+    }
+}
+
+impl InputCommand {
+    /// Indicates if the command changes the repository.
+    pub fn is_mutating(&self) -> bool {
+        match self {
+            InputCommand::Add { .. }
+            | InputCommand::Edit { .. }
+            | InputCommand::Copy { .. }
+            | InputCommand::MoveToDate { .. }
+            | InputCommand::PruneEmpty { .. }
+            | InputCommand::Undo { .. }
+            | InputCommand::Squash { .. }
+            | InputCommand::Commit { .. }
+            | InputCommand::RenameId { .. }
+            | InputCommand::Pin { .. }
+            | InputCommand::Unpin { .. }
+            | InputCommand::Lock { .. }
+            | InputCommand::Unlock { .. }
+            | InputCommand::SetTags { .. }
+            | InputCommand::ConvertAll { .. }
+            | InputCommand::Touch { .. }
+            | InputCommand::Synchronize { .. } => true,
+            InputCommand::Move { dry_run, .. } => !*dry_run,
+            InputCommand::Remove { dry_run, .. } => !*dry_run,
+            InputCommand::Restore { id } => id.is_some(),
+            InputCommand::RunSnippet { save_output, .. } => *save_output,
+            InputCommand::Show { save, .. } => *save,
+            InputCommand::SearchContent { replace, dry_run, .. } => replace.is_some() && !*dry_run,
+            InputCommand::Replace { dry_run, .. } => !*dry_run,
+            InputCommand::Metadata { command } => matches!(command, InputCommandMetadata::Load { .. }),
+            InputCommand::Remote { command } => !matches!(command, InputCommandRemote::List { .. }),
+            InputCommand::Alias { command } => !matches!(command, InputCommandAlias::List { .. }),
+            InputCommand::Resource { command } => matches!(command, InputCommandResource::Add { .. } | InputCommandResource::Remove { .. }),
+            _ => false
+        }
     }
 }
 
 impl InputCommandFinder {
-    pub fn query(self) -> FindQuery {
+    pub fn is_here(&self) -> bool {
+        match self {
+            InputCommandFinder::Name { here, global, .. } => *here && !*global,
+            _ => false
+        }
+    }
+
+    /// Creates the query, where further searches joined by 'and'/'or' are combined from left to right.
+    pub fn query(self) -> AppResult<FindQuery> {
+        let (mut query, clauses) = self.first_query()?;
+
+        let mut clauses = clauses.into_iter().peekable();
+        while let Some(operator) = clauses.next() {
+            if !is_find_operator(&operator) {
+                return Err(AppError::Input(format!("Expected 'and' or 'or' but got '{}'", operator)));
+            }
+
+            let mut clause = Vec::new();
+            while let Some(token) = clauses.next_if(|token| !is_find_operator(token)) {
+                if token == "--" {
+                    // Keeps the escape for the parsing of the clause
+                    clause.push(token);
+                    clause.extend(clauses.next());
+                } else if token.starts_with('-') && token.len() > 1 {
+                    return Err(AppError::Input(format!("Options such as '{}' must be given before the 'and'/'or' clauses", token)));
+                } else {
+                    clause.push(token);
+                }
+            }
+
+            let clause = InputCommandFinder::from_iter_safe(std::iter::once("find".to_owned()).chain(clause))
+                .map_err(|err| AppError::Input(err.message))?;
+            let (clause_query, remaining) = clause.first_query()?;
+            if let Some(token) = remaining.first() {
+                return Err(AppError::Input(format!("Unexpected '{}'", token)));
+            }
+
+            query = if operator == "and" {
+                FindQuery::And(vec![query, clause_query])
+            } else {
+                FindQuery::Or(vec![query, clause_query])
+            };
+        }
+
+        Ok(query)
+    }
+
+    /// Creates the query of this search, together with the remaining clauses.
+    fn first_query(self) -> AppResult<(FindQuery, Vec<String>)> {
         match self {
-            InputCommandFinder::Tag { tags } => {
-                FindQuery::Tags(tags)
+            InputCommandFinder::Tag { mut tags } => {
+                let clauses = split_off_find_clauses(&mut tags)?;
+                let tags = tags.iter()
+                    .map(|tag| tag.parse::<TagMatcher>().map_err(|err| AppError::Input(err.to_string())))
+                    .collect::<AppResult<Vec<_>>>()?;
+                Ok((FindQuery::Tags(tags), clauses))
+            }
+            InputCommandFinder::Name { name, clauses, .. } => {
+                Ok((FindQuery::Path(name), clauses))
+            }
+            InputCommandFinder::Id { id, clauses } => {
+                Ok((FindQuery::Id(id), clauses))
             }
-            InputCommandFinder::Name { name } => {
-                FindQuery::Path(name)
+            InputCommandFinder::Content { pattern, clauses } => {
+                Ok((FindQuery::Content(pattern), clauses))
             }
-            InputCommandFinder::Id { id } => {
-                FindQuery::Id(id)
+            InputCommandFinder::Expr { expression } => {
+                let query = expression.join(" ").parse::<FindQuery>().map_err(AppError::Input)?;
+                Ok((query, Vec::new()))
             }
-            InputCommandFinder::Created { parts } => {
-                FindQuery::Created(parts)
+            InputCommandFinder::Created { mut parts } => {
+                let clauses = split_off_find_clauses(&mut parts)?;
+                Ok((FindQuery::Created(parse_date_parts(&parts)?), clauses))
             }
-            InputCommandFinder::Updated { parts } => {
-                FindQuery::LastUpdated(parts)
+            InputCommandFinder::Updated { mut parts } => {
+                let clauses = split_off_find_clauses(&mut parts)?;
+                Ok((FindQuery::LastUpdated(parse_date_parts(&parts)?), clauses))
             }
         }
     }
 }
 
+/// Creates the command to run for a note in find --then, where {} is replaced by the path.
+fn then_command(command: &str, path: &Path) -> AppResult<InputCommand> {
+    let path = path.to_str().unwrap();
+    let mut words = shellwords::split(command).map_err(|err| AppError::Input(err.to_string()))?;
+    if words.iter().any(|word| word.contains("{}")) {
+        for word in &mut words {
+            *word = word.replace("{}", path);
+        }
+    } else {
+        words.push(path.to_owned());
+    }
+
+    InputCommand::from_iter_safe(std::iter::once("gitnotes".to_owned()).chain(words))
+        .map_err(|err| AppError::Input(err.message))
+}
+
+fn is_find_operator(token: &str) -> bool {
+    token == "and" || token == "or"
+}
+
+/// Splits off the tokens starting at the first 'and'/'or' operator, where '--' makes the next token a value.
+/// A leading operator is always a value, since a leading '--' is already removed when parsing the arguments.
+fn split_off_find_clauses(tokens: &mut Vec<String>) -> AppResult<Vec<String>> {
+    let mut values = Vec::new();
+    let mut remaining = std::mem::take(tokens).into_iter();
+    while let Some(token) = remaining.next() {
+        if token == "--" {
+            values.extend(remaining.next());
+        } else if is_find_operator(&token) && !values.is_empty() {
+            *tokens = values;
+            return Ok(std::iter::once(token).chain(remaining).collect());
+        } else if token.starts_with('-') && token.len() > 1 {
+            return Err(AppError::Input(format!("Unexpected option '{}' (use '--' before values starting with '-')", token)));
+        } else {
+            values.push(token);
+        }
+    }
+
+    *tokens = values;
+    Ok(Vec::new())
+}
+
+fn parse_date_parts(parts: &[String]) -> AppResult<Vec<i32>> {
+    parts.iter()
+        .map(|part| part.parse::<i32>().map_err(|_| AppError::Input(format!("Invalid date part '{}'", part))))
+        .collect()
+}
+
+#[derive(Debug, StructOpt)]
+pub enum InputCommandMetadata {
+    /// Prints the metadata of a note (TOML by default)
+    Dump {
+        /// The path of the note. Id also work.
+        path: PathBuf,
+        /// Prints the metadata as JSON instead
+        #[structopt(long)]
+        json: bool
+    },
+    /// Reads (TOML or JSON) metadata from stdin and sets it for a note. Changing the path moves the note.
+    Load {
+        /// The path of the note. Id also work.
+        path: PathBuf
+    }
+}
+
 #[derive(Debug, StructOpt)]
 pub enum InputCommandRemote {
     /// Lists the existing remotes
@@ -1031,6 +2953,26 @@ pub enum InputCommandRemote {
     }
 }
 
+#[derive(Debug, StructOpt)]
+pub enum InputCommandAlias {
+    /// Lists the existing aliases
+    List {
+
+    },
+    /// Adds a new alias (replacing any existing alias with the same name)
+    Add {
+        /// The name of the alias
+        alias: String,
+        /// The path of the note. Id also work.
+        path: PathBuf
+    },
+    /// Removes an existing alias
+    Remove {
+        /// The name of the alias
+        alias: String
+    }
+}
+
 #[derive(Debug, StructOpt)]
 pub enum InputCommandResource {
     /// Lists the resources
@@ -1039,7 +2981,18 @@ pub enum InputCommandResource {
         query: Option<PathBuf>,
         /// Prints the absolute path.
         #[structopt(long)]
-        print_absolute: bool
+        print_absolute: bool,
+        /// Also prints the size and (guessed) type of the resources.
+        #[structopt(long, short)]
+        long: bool,
+        /// Sorts the resources by name or size (largest first).
+        #[structopt(long, default_value="name")]
+        sort: ResourceSort
+    },
+    /// Lists the resources in a tree structure.
+    Tree {
+        /// The directory to list. Leave empty for all.
+        prefix: Option<PathBuf>
     },
     /// Adds a resource to the repository
     Add {
@@ -1061,8 +3014,12 @@ pub enum InputCommandResource {
     Apply {
         /// The command to apply. Path to resource is given as first argument
         command: String,
-        /// The resource to apply on
-        resource: PathBuf
+        /// The resource to apply on. When using --all, a glob pattern or prefix (leave empty for all resources)
+        #[structopt(required_unless="all")]
+        resource: Option<PathBuf>,
+        /// Applies the command on each resource matching the given glob pattern or prefix
+        #[structopt(long)]
+        all: bool
     },
 }
 
@@ -1093,6 +3050,17 @@ pub enum AppError {
 
     #[error("Remote '{0}' not found")]
     RemoteNotFound(String),
+    #[error("Merge conflicts in {} note(s)", .0.len())]
+    MergeConflicts(Vec<PathBuf>),
+
+    #[error("Alias '{0}' not found")]
+    AliasNotFound(String),
+
+    #[error("The repository is read only")]
+    ReadOnly,
+
+    #[error("Invalid metadata: {0}")]
+    InvalidMetadata(String),
 
     #[error("{0}")]
     Regex(regex::Error),
@@ -1134,6 +3102,84 @@ impl From<std::io::Error> for AppError {
     }
 }
 
+/// The process exit code for the result of running a command: 0 if it succeeded and (when searching) found something,
+/// 1 if a search found nothing and 2 for errors (same as grep).
+pub fn exit_code(result: &AppResult<Option<usize>>) -> i32 {
+    match result {
+        Ok(Some(0)) => 1,
+        Ok(_) => 0,
+        Err(_) => 2
+    }
+}
+
+/// Detects the shell from the value of $SHELL.
+pub fn detect_shell(shell_path: &str) -> Option<Shell> {
+    match Path::new(shell_path).file_name()?.to_str()? {
+        "bash" => Some(Shell::Bash),
+        "zsh" => Some(Shell::Zsh),
+        "fish" => Some(Shell::Fish),
+        "elvish" => Some(Shell::Elvish),
+        "pwsh" | "powershell" => Some(Shell::PowerShell),
+        _ => None
+    }
+}
+
+/// The conventional (user) location of the completion file for the shell.
+pub fn completions_install_path(shell: Shell, home_dir: &Path) -> AppResult<PathBuf> {
+    match shell {
+        Shell::Bash => Ok(home_dir.join(".local/share/bash-completion/completions/gitnotes")),
+        Shell::Zsh => Ok(home_dir.join(".zfunc/_gitnotes")),
+        Shell::Fish => Ok(home_dir.join(".config/fish/completions/gitnotes.fish")),
+        Shell::Elvish => Ok(home_dir.join(".config/elvish/lib/gitnotes.elv")),
+        Shell::PowerShell => Err(AppError::Input("Installing completions is not supported for PowerShell, use the stdout mode instead".to_owned()))
+    }
+}
+
+/// Writes the completions for the shell to its conventional location, returning the path.
+pub fn install_completions(shell: Shell, home_dir: &Path) -> AppResult<PathBuf> {
+    let path = completions_install_path(shell, home_dir)?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let mut file = std::fs::File::create(&path)?;
+    MainInputCommand::clap().gen_completions_to("gitnotes", shell, &mut file);
+    Ok(path)
+}
+
+/// Clones the repository at the given URL and makes it the active repository in the config.
+pub fn clone_repository(config_path: &Path, url: &str, repository_path: &Path) -> AppResult<()> {
+    if repository_path.exists() {
+        return Err(AppError::Input(format!("The path '{}' already exists", repository_path.to_str().unwrap())));
+    }
+
+    let mut file_config = FileConfig::load_or_new(config_path, repository_path)?;
+
+    let mut fetch_options = FetchOptions::new();
+    let mut callbacks = RemoteCallbacks::new();
+    callbacks.credentials(git_helpers::create_credentials());
+    fetch_options.remote_callbacks(callbacks);
+
+    git2::build::RepoBuilder::new()
+        .fetch_options(fetch_options)
+        .clone(url, repository_path)?;
+
+    file_config.repository = repository_path.to_owned();
+    file_config.save(config_path)?;
+    Ok(())
+}
+
+/// The default name of a cloned repository, which is the last part of the URL without the '.git' extension.
+pub fn clone_name(url: &str) -> Option<String> {
+    let name = url.trim_end_matches('/').rsplit(|c| c == '/' || c == ':').next()?;
+    let name = name.strip_suffix(".git").unwrap_or(name);
+    if name.is_empty() {
+        None
+    } else {
+        Some(name.to_owned())
+    }
+}
+
 fn open_repository(path: &Path) -> AppResult<git2::Repository> {
     git2::Repository::open(path).map_err(|err| AppError::FailedToOpenRepository(err))
 }
@@ -1,9 +1,14 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use crate::app::{App, AppError, InputCommand, InputCommandResource};
+use crate::app::{App, AppError, clone_name, clone_repository, completions_install_path, detect_shell, exit_code, install_completions, InputCommand, InputCommandAlias, InputCommandFinder, InputCommandResource, MainInputCommand};
+use crate::clipboard::Clipboard;
 use crate::command::{Command, CommandError, CommandInterpreter};
-use crate::config::{Config, FileConfig};
+use crate::config::{Config, FileConfig, SnippetFileConfig};
 use crate::editor::EditorOutput;
+use crate::helpers::Timings;
+use crate::model::{AccessTimes, NoteAliases, NoteId, NoteMetadata, NoteMetadataStorage};
+use crate::querying::{create_resource_tree_lines, DuplicateFinder, find_resources, LinkGraph, ListTreeLine, ListDirectory, note_sizes, NoteChange, OutputFormat, recently_accessed, RegexMatcher, ResourceSort, search_matches_json, Searcher, sort_note_metadata_results, SortBy, StatusEntry, StatusKind, tag_counts, TagSort};
+use crate::snippets::{BashSnippetRunnerConfig, SnippetError};
 
 #[test]
 fn test_add() {
@@ -70,12 +75,261 @@ print([x * x for x in xs])
     app.run(InputCommand::Add {
         path: note_path.to_path_buf(),
         tags: vec![],
+        allow_empty: false,
+        open: false
     }).unwrap();
     assert_eq!(note_content, app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
     assert_eq!(1, repository.reflog("HEAD").unwrap().len());
     assert_eq!(vec!["snippet".to_owned(), "python".to_owned()], app.note_metadata_storage().unwrap().get(note_path).unwrap().tags);
 }
 
+#[test]
+fn test_add_no_auto_tag_directive() {
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let mut config = create_test_config(&temp_repository_dir);
+    config.allow_stdin = false;
+    let repository = git2::Repository::init(&config.repository).unwrap();
+
+    let note_path = Path::new("2023/07/sample");
+    let note_content = r#"<!-- gitnotes: no-auto-tag -->
+Hello, World!
+
+``` python
+xs = list(range(0, 10))
+print([x * x for x in xs])
+```
+"#.to_string();
+
+    let note_content_clone = note_content.clone();
+    let mut app = App::with_custom(config, move |config, repository| {
+        CommandInterpreter::with_launch_editor(
+            config,
+            repository,
+            Box::new(move |_, path, _| {
+                std::fs::write(path, &note_content_clone).map_err(|err| CommandError::IO(err))?;
+                Ok(EditorOutput::default())
+            })
+        )
+    }).unwrap();
+
+    app.run(InputCommand::Add {
+        path: note_path.to_path_buf(),
+        tags: vec![],
+        allow_empty: false,
+        open: false
+    }).unwrap();
+    assert_eq!(note_content, app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
+    assert_eq!(1, repository.reflog("HEAD").unwrap().len());
+    assert_eq!(Vec::<String>::new(), app.note_metadata_storage().unwrap().get(note_path).unwrap().tags);
+}
+
+#[test]
+fn test_add_aborted_editor() {
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let mut config = create_test_config(&temp_repository_dir);
+    config.allow_stdin = false;
+    let notes_dir = config.repository.join(".notes");
+    let repository = git2::Repository::init(&config.repository).unwrap();
+
+    let note_path = Path::new("2023/07/sample");
+
+    let mut app = App::with_custom(config, move |config, repository| {
+        CommandInterpreter::with_launch_editor(
+            config,
+            repository,
+            Box::new(move |_, _, _| {
+                Ok(EditorOutput::default())
+            })
+        )
+    }).unwrap();
+
+    let result = app.run(InputCommand::Add {
+        path: note_path.to_path_buf(),
+        tags: vec![],
+        allow_empty: false,
+        open: false
+    });
+    assert!(matches!(result, Err(AppError::Command(CommandError::EmptyNote))));
+    assert_eq!(false, app.note_metadata_storage().unwrap().get_content(note_path).is_ok());
+    assert_eq!(0, std::fs::read_dir(&notes_dir).unwrap().count());
+    assert!(repository.head().is_err());
+
+    app.run(InputCommand::Add {
+        path: note_path.to_path_buf(),
+        tags: vec![],
+        allow_empty: true,
+        open: false
+    }).unwrap();
+    assert_eq!("", app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
+    assert_eq!(1, repository.reflog("HEAD").unwrap().len());
+}
+
+#[test]
+fn test_add_with_content_open() {
+    use std::cell::RefCell;
+    use std::path::PathBuf;
+    use std::rc::Rc;
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    let repository_dir = config.repository.clone();
+    let repository = git2::Repository::init(&config.repository).unwrap();
+
+    let note_path = Path::new("2023/07/sample");
+
+    let opened_paths = Rc::new(RefCell::new(Vec::<(PathBuf, PathBuf)>::new()));
+    let opened_paths_editor = opened_paths.clone();
+    let mut app = App::with_custom(config, move |config, repository| {
+        CommandInterpreter::with_launch_editor(
+            config,
+            repository,
+            Box::new(move |_, content_path, note_path| {
+                opened_paths_editor.borrow_mut().push((content_path.to_owned(), note_path.to_owned()));
+                Ok(EditorOutput::default())
+            })
+        )
+    }).unwrap();
+
+    app.add_note_with_content(note_path.to_path_buf(), vec![], "Hello, World!".to_owned(), false).unwrap();
+    assert_eq!(0, opened_paths.borrow().len());
+
+    let other_note_path = Path::new("2023/07/other");
+    app.add_note_with_content(other_note_path.to_path_buf(), vec![], "Test".to_owned(), true).unwrap();
+
+    let note_id = app.note_metadata_storage().unwrap().get_id(other_note_path).unwrap();
+    assert_eq!(
+        vec![(NoteMetadataStorage::get_note_storage_path(&repository_dir, &note_id).1, other_note_path.to_path_buf())],
+        opened_paths.borrow().clone()
+    );
+    assert_eq!("Test", app.note_metadata_storage().unwrap().get_content(other_note_path).unwrap());
+    assert_eq!(2, repository.reflog("HEAD").unwrap().len());
+}
+
+#[test]
+fn test_dump_and_load_metadata() {
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    let repository = git2::Repository::init(&config.repository).unwrap();
+
+    let note_path = Path::new("2023/07/sample");
+    let other_note_path = Path::new("2023/07/other");
+    let moved_note_path = Path::new("2023/08/sample");
+
+    let mut app = App::new(config).unwrap();
+
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent {
+            path: note_path.to_path_buf(),
+            tags: vec!["x".to_owned()],
+            content: "Hello, World!".to_owned()
+        },
+        Command::AddNoteWithContent {
+            path: other_note_path.to_path_buf(),
+            tags: vec![],
+            content: "Other".to_owned()
+        }
+    ]).unwrap();
+
+    let dumped = app.dump_note_metadata(note_path, false).unwrap();
+    let mut note_metadata = NoteMetadata::parse(&dumped).unwrap();
+    assert_eq!(vec!["x".to_owned()], note_metadata.tags);
+
+    note_metadata.tags = vec!["a".to_owned(), "b".to_owned()];
+    app.load_note_metadata(note_path, &toml::to_string(&note_metadata).unwrap()).unwrap();
+    assert_eq!(vec!["a".to_owned(), "b".to_owned()], app.note_metadata_storage().unwrap().get(note_path).unwrap().tags);
+    assert_eq!(2, repository.reflog("HEAD").unwrap().len());
+
+    let dumped = app.dump_note_metadata(note_path, true).unwrap();
+    let mut json_metadata: serde_json::Value = serde_json::from_str(&dumped).unwrap();
+    json_metadata["path"] = serde_json::Value::String(moved_note_path.to_str().unwrap().to_owned());
+    app.load_note_metadata(note_path, &json_metadata.to_string()).unwrap();
+    assert!(app.note_metadata_storage().unwrap().get(note_path).is_none());
+    assert_eq!("Hello, World!", app.note_metadata_storage().unwrap().get_content(moved_note_path).unwrap());
+    assert_eq!(vec!["a".to_owned(), "b".to_owned()], app.note_metadata_storage().unwrap().get(moved_note_path).unwrap().tags);
+
+    let mut note_metadata = NoteMetadata::parse(&app.dump_note_metadata(moved_note_path, false).unwrap()).unwrap();
+    note_metadata.path = other_note_path.to_path_buf();
+    let result = app.load_note_metadata(moved_note_path, &toml::to_string(&note_metadata).unwrap());
+    assert!(matches!(result, Err(AppError::InvalidMetadata(_))));
+
+    let mut note_metadata = NoteMetadata::parse(&app.dump_note_metadata(moved_note_path, false).unwrap()).unwrap();
+    note_metadata.id = app.note_metadata_storage().unwrap().get_id(other_note_path).unwrap();
+    let result = app.load_note_metadata(moved_note_path, &toml::to_string(&note_metadata).unwrap());
+    assert!(matches!(result, Err(AppError::InvalidMetadata(_))));
+    assert_eq!(3, repository.reflog("HEAD").unwrap().len());
+
+    let dumped = app.dump_note_metadata(moved_note_path, false).unwrap();
+    app.load_note_metadata(moved_note_path, &dumped).unwrap();
+    assert_eq!(3, repository.reflog("HEAD").unwrap().len());
+
+    let mut note_metadata = NoteMetadata::parse(&dumped).unwrap();
+    note_metadata.pinned = true;
+    note_metadata.locked = true;
+    app.load_note_metadata(moved_note_path, &toml::to_string(&note_metadata).unwrap()).unwrap();
+    assert!(app.note_metadata_storage().unwrap().get(moved_note_path).unwrap().pinned);
+    assert!(app.note_metadata_storage().unwrap().get(moved_note_path).unwrap().locked);
+    assert_eq!(4, repository.reflog("HEAD").unwrap().len());
+
+    note_metadata.locked = false;
+    note_metadata.path = note_path.to_path_buf();
+    app.load_note_metadata(moved_note_path, &toml::to_string(&note_metadata).unwrap()).unwrap();
+    assert!(!app.note_metadata_storage().unwrap().get(note_path).unwrap().locked);
+    assert!(app.note_metadata_storage().unwrap().get(note_path).unwrap().pinned);
+    assert_eq!(5, repository.reflog("HEAD").unwrap().len());
+}
+
+#[test]
+fn test_edit_with_web_editor() {
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    let repository_dir = config.repository.clone();
+    let repository = git2::Repository::init(&config.repository).unwrap();
+
+    let note_path = Path::new("2023/07/sample");
+
+    let mut app = App::new(config).unwrap();
+
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent {
+            path: note_path.to_path_buf(),
+            tags: vec![],
+            content: "Hello, World!".to_owned()
+        }
+    ]).unwrap();
+    let last_updated = app.note_metadata_storage().unwrap().get(note_path).unwrap().last_updated;
+
+    // Opening without saving leaves the note unchanged
+    app.edit_with_web_editor(note_path.to_path_buf(), |input| {
+        assert_eq!(Some(note_path.to_path_buf()), input.display_path);
+        assert_eq!(Some(repository_dir.clone()), input.repository_path);
+        assert_eq!("Hello, World!", std::fs::read_to_string(&input.path).unwrap());
+        EditorOutput::default()
+    }).unwrap();
+    assert_eq!(1, repository.reflog("HEAD").unwrap().len());
+
+    let note_id = app.note_metadata_storage().unwrap().get_id(note_path).unwrap();
+    app.edit_with_web_editor(Path::new(&note_id.to_string()).to_path_buf(), |input| {
+        std::fs::write(&input.path, "Hello, Earth!").unwrap();
+        EditorOutput::default()
+    }).unwrap();
+    assert_eq!("Hello, Earth!", app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
+    assert_ne!(last_updated, app.note_metadata_storage().unwrap().get(note_path).unwrap().last_updated);
+    assert_eq!(2, repository.reflog("HEAD").unwrap().len());
+    let relative_content_path = NoteMetadataStorage::get_note_storage_path(&repository_dir, &note_id).0;
+    assert_eq!(git2::Status::CURRENT, repository.status_file(&relative_content_path).unwrap());
+
+    assert!(app.edit_with_web_editor(Path::new("2023/07/other").to_path_buf(), |_| EditorOutput::default()).is_err());
+}
+
 #[test]
 fn test_add_with_working_dir() {
     use tempfile::TempDir;
@@ -112,12 +366,50 @@ print([x * x for x in xs])
     app.run(InputCommand::Add {
         path: Path::new("sample").to_owned(),
         tags: vec![],
+        allow_empty: false,
+        open: false
     }).unwrap();
     assert_eq!(note_content, app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
     assert_eq!(1, repository.reflog("HEAD").unwrap().len());
     assert_eq!(vec!["snippet".to_owned(), "python".to_owned()], app.note_metadata_storage().unwrap().get(note_path).unwrap().tags);
 }
 
+#[test]
+fn test_add_created_from_path() {
+    use chrono::{Datelike, Local, Timelike};
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let mut config = create_test_config(&temp_repository_dir);
+    config.created_from_path = true;
+    git2::Repository::init(&config.repository).unwrap();
+
+    let note_path = Path::new("2020/01/02/foo");
+    let note_path2 = Path::new("notes/foo");
+
+    let mut app = App::new(config).unwrap();
+
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent {
+            path: note_path.to_path_buf(),
+            tags: vec![],
+            content: "Hello, World!".to_owned()
+        },
+        Command::AddNoteWithContent {
+            path: note_path2.to_path_buf(),
+            tags: vec![],
+            content: "Hello, World!".to_owned()
+        }
+    ]).unwrap();
+
+    let created = app.note_metadata_storage().unwrap().get(note_path).unwrap().created;
+    assert_eq!((2020, 1, 2), (created.year(), created.month(), created.day()));
+    assert_eq!((0, 0, 0), (created.hour(), created.minute(), created.second()));
+
+    let created2 = app.note_metadata_storage().unwrap().get(note_path2).unwrap().created;
+    assert_eq!(Local::now().date_naive(), created2.date_naive());
+}
+
 #[test]
 fn test_run_snippet() {
     use tempfile::TempDir;
@@ -183,7 +475,7 @@ print([x * x for x in xs])
     assert_eq!(note_content, app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
     assert_eq!(1, repository.reflog("HEAD").unwrap().len());
 
-    app.run(InputCommand::RunSnippet { path: note_path.to_owned(), save_output: true }).unwrap();
+    app.run(InputCommand::RunSnippet { path: note_path.to_owned(), save_output: true, check: false }).unwrap();
     assert_eq!(note_content_output, app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
     assert_eq!(2, repository.reflog("HEAD").unwrap().len());
 
@@ -192,19 +484,20 @@ print([x * x for x in xs])
             path: note_path.to_path_buf(),
             clear_tags: false,
             add_tags: vec![],
-            content: note_content2.clone()
+            content: note_content2.clone(),
+            force: false
         }
     ])).unwrap();
     assert_eq!(note_content2, app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
     assert_eq!(3, repository.reflog("HEAD").unwrap().len());
 
-    app.run(InputCommand::RunSnippet { path: note_path.to_owned(), save_output: true }).unwrap();
+    app.run(InputCommand::RunSnippet { path: note_path.to_owned(), save_output: true, check: false }).unwrap();
     assert_eq!(note_content_output2, app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
     assert_eq!(4, repository.reflog("HEAD").unwrap().len());
 }
 
 #[test]
-fn test_move() {
+fn test_show_run() {
     use tempfile::TempDir;
 
     let temp_repository_dir = TempDir::new().unwrap();
@@ -212,12 +505,10 @@ fn test_move() {
     let repository = git2::Repository::init(&config.repository).unwrap();
 
     let note_path = Path::new("2023/07/sample");
-    let note_path2 = Path::new("2023/07/01/sample");
     let note_content = r#"Hello, World!
 
 ``` python
-import numpy as np
-print(np.square(np.arange(0, 10)))
+print(sum(range(0, 10)))
 ```
 "#.to_string();
 
@@ -226,274 +517,282 @@ print(np.square(np.arange(0, 10)))
     app.create_and_execute_commands(vec![
         Command::AddNoteWithContent {
             path: note_path.to_path_buf(),
-            tags: vec!["python".to_owned()],
+            tags: vec![],
             content: note_content.clone()
         }
     ]).unwrap();
+
+    let content = app.get_show_content(note_path, None, true, false).unwrap();
+    assert!(content.contains("``` output\n45\n```"));
     assert_eq!(note_content, app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
     assert_eq!(1, repository.reflog("HEAD").unwrap().len());
 
-    app.run(InputCommand::Move { source: note_path.to_owned(), destination: note_path2.to_owned(), force: false }).unwrap();
-    assert_eq!(false, app.note_metadata_storage().unwrap().get_content(note_path).is_ok());
-    assert_eq!(note_content, app.note_metadata_storage().unwrap().get_content(note_path2).unwrap());
+    let content = app.get_show_content(note_path, None, true, true).unwrap();
+    assert!(content.contains("``` output\n45\n```"));
+    assert_eq!(content, app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
     assert_eq!(2, repository.reflog("HEAD").unwrap().len());
 }
 
 #[test]
-fn test_move_to_existing1() {
+fn test_check_snippets() {
     use tempfile::TempDir;
 
     let temp_repository_dir = TempDir::new().unwrap();
     let config = create_test_config(&temp_repository_dir);
-    let repository = git2::Repository::init(&config.repository).unwrap();
+    git2::Repository::init(&config.repository).unwrap();
 
-    let note_path = Path::new("2023/07/sample");
-    let note_path2 = Path::new("2023/07/01/sample");
-    let note_content = "Hello, World #1".to_owned();
-    let note_content2 = "Hello, World #2".to_owned();
+    let matching_note_path = Path::new("2023/07/matching");
+    let mismatching_note_path = Path::new("2023/07/mismatching");
 
     let mut app = App::new(config).unwrap();
 
     app.create_and_execute_commands(vec![
         Command::AddNoteWithContent {
-            path: note_path.to_path_buf(),
-            tags: vec!["python".to_owned()],
-            content: note_content.clone()
+            path: matching_note_path.to_path_buf(),
+            tags: vec![],
+            content: "Hello\n\n``` python\nprint(sum(range(0, 10)))\n```\n\n``` output\n45\n```\n\n``` python\nprint(1)\n```\n".to_owned()
         },
         Command::AddNoteWithContent {
-            path: note_path2.to_path_buf(),
-            tags: vec!["python".to_owned()],
-            content: note_content2.clone()
+            path: mismatching_note_path.to_path_buf(),
+            tags: vec![],
+            content: "Hello\n\n``` python\nprint(sum(range(0, 10)))\n```\n\n``` output\n46\n```\n".to_owned()
         }
     ]).unwrap();
-    let note_id = app.note_metadata_storage().unwrap().get_id(note_path).unwrap();
-    let note_id2 = app.note_metadata_storage().unwrap().get_id(note_path2).unwrap();
-    assert_eq!(note_content, app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
-    assert_eq!(note_content2, app.note_metadata_storage().unwrap().get_content(note_path2).unwrap());
-    assert_eq!(1, repository.reflog("HEAD").unwrap().len());
 
-    let err = app.run(InputCommand::Move { source: note_path.to_owned(), destination: note_path2.to_owned(), force: false }).err().unwrap();
-    if let AppError::Command(CommandError::NoteExistsAtDestination(err_path)) = err {
-        assert_eq!(note_path2, err_path);
-        assert_eq!(note_id, app.note_metadata_storage().unwrap().get_id(note_path).unwrap());
-        assert_eq!(note_id2, app.note_metadata_storage().unwrap().get_id(note_path2).unwrap());
-    } else {
-        assert!(false, "Expected 'NoteAtDestination' error");
-    }
+    let results = app.check_snippets(matching_note_path).unwrap();
+    assert_eq!(1, results.len());
+    assert!(results[0].is_match());
+    app.run(InputCommand::RunSnippet { path: matching_note_path.to_owned(), save_output: false, check: true }).unwrap();
+
+    let results = app.check_snippets(mismatching_note_path).unwrap();
+    assert_eq!(1, results.len());
+    assert!(!results[0].is_match());
+    assert_eq!("45\n", results[0].actual);
+
+    let result = app.run(InputCommand::RunSnippet { path: mismatching_note_path.to_owned(), save_output: false, check: true });
+    assert!(matches!(result, Err(AppError::Command(CommandError::SnippetOutputMismatch(1)))));
+    assert!(app.note_metadata_storage().unwrap().get_content(mismatching_note_path).unwrap().contains("46"));
 }
 
 #[test]
-fn test_move_to_existing2() {
+fn test_run_snippet_arbitrary_runner() {
     use tempfile::TempDir;
 
     let temp_repository_dir = TempDir::new().unwrap();
-    let config = create_test_config(&temp_repository_dir);
-    let repository = git2::Repository::init(&config.repository).unwrap();
+    let mut config = create_test_config(&temp_repository_dir);
+    git2::Repository::init(&config.repository).unwrap();
 
     let note_path = Path::new("2023/07/sample");
-    let note_path2 = Path::new("2023/07/01/sample");
-    let note_content = "Hello, World #1".to_owned();
-    let note_content2 = "Hello, World #2".to_owned();
+    let note_content = "Hello\n\n``` run:cat input.txt\nHello, World!\n```\n".to_owned();
+
+    let mut app = App::new(config.clone()).unwrap();
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent { path: note_path.to_path_buf(), tags: vec![], content: note_content.clone() }
+    ]).unwrap();
 
+    let result = app.get_show_content(note_path, None, true, false);
+    assert!(matches!(result, Err(AppError::Command(CommandError::Snippet(SnippetError::ArbitraryRunnersNotAllowed)))));
+
+    config.snippet = Some(SnippetFileConfig { allow_arbitrary_runners: Some(true), ..Default::default() });
     let mut app = App::new(config).unwrap();
+    let content = app.get_show_content(note_path, None, true, false).unwrap();
+    assert!(content.contains("``` output\nHello, World!\n```"));
+}
+
+#[test]
+fn test_run_snippet_info_args() {
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    git2::Repository::init(&config.repository).unwrap();
 
+    let note_path = Path::new("2023/07/sample");
+    let note_content = "Hello\n\n``` rust --cfg snippet_flag\nfn main() {\n    #[cfg(snippet_flag)]\n    println!(\"Enabled\");\n}\n```\n\n``` python:python3 -B\nprint(1 + 2)\n```\n".to_owned();
+
+    let mut app = App::new(config).unwrap();
     app.create_and_execute_commands(vec![
-        Command::AddNoteWithContent {
-            path: note_path.to_path_buf(),
-            tags: vec!["python".to_owned()],
-            content: note_content.clone()
-        },
-        Command::AddNoteWithContent {
-            path: note_path2.to_path_buf(),
-            tags: vec!["python".to_owned()],
-            content: note_content2.clone()
-        }
+        Command::AddNoteWithContent { path: note_path.to_path_buf(), tags: vec![], content: note_content }
     ]).unwrap();
-    let note_id = app.note_metadata_storage().unwrap().get_id(note_path).unwrap();
-    assert_eq!(note_content, app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
-    assert_eq!(note_content2, app.note_metadata_storage().unwrap().get_content(note_path2).unwrap());
-    assert_eq!(1, repository.reflog("HEAD").unwrap().len());
 
-    app.run(InputCommand::Move { source: note_path.to_owned(), destination: note_path2.to_owned(), force: true }).unwrap();
-    assert_eq!(false, app.note_metadata_storage().unwrap().get_content(note_path).is_ok());
-    assert_eq!(note_content, app.note_metadata_storage().unwrap().get_content(note_path2).unwrap());
-    assert_eq!(note_id, app.note_metadata_storage().unwrap().get(note_path2).unwrap().id);
-    assert_eq!(2, repository.reflog("HEAD").unwrap().len());
+    let content = app.get_show_content(note_path, None, true, false).unwrap();
+    assert!(content.contains("``` output\nEnabled\n```"), "{}", content);
+    assert!(content.contains("``` output\n3\n```"), "{}", content);
 }
 
 #[test]
-fn test_move_dir1() {
+fn test_run_snippet_stdin() {
     use tempfile::TempDir;
 
     let temp_repository_dir = TempDir::new().unwrap();
     let config = create_test_config(&temp_repository_dir);
-    let repository = git2::Repository::init(&config.repository).unwrap();
+    git2::Repository::init(&config.repository).unwrap();
 
-    let note1_path = Path::new("2023/07/sample");
-    let note1_path2 = Path::new("2024/07/sample");
-    let note1_content = r#"Hello, World!
+    let note_path = Path::new("2023/07/sample");
+    let note_content = "Hello\n\n``` stdin\n1\n2\n```\n\n``` python\nprint(int(input()) + int(input()))\n```\n".to_owned();
 
-``` python
-import numpy as np
-print(np.square(np.arange(0, 10)))
-```
-"#.to_string();
+    let mut app = App::new(config).unwrap();
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent { path: note_path.to_path_buf(), tags: vec![], content: note_content }
+    ]).unwrap();
 
-    let note2_path = Path::new("2023/07/sample2");
-    let note2_path2 = Path::new("2024/07/sample2");
-    let note2_content = r#"Hello, My World!
+    let content = app.get_show_content(note_path, None, true, true).unwrap();
+    assert_eq!("Hello\n\n``` stdin\n1\n2\n```\n\n``` python\nprint(int(input()) + int(input()))\n```\n\n``` output\n3\n```\n", content);
+}
 
-``` python
-import numpy as np
-print(np.square(np.arange(0, 15)))
-```
-"#.to_string();
+#[test]
+fn test_run_snippet_save_output_changes_version() {
+    use tempfile::TempDir;
 
-    let mut app = App::new(config).unwrap();
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    git2::Repository::init(&config.repository).unwrap();
+
+    let note_path = Path::new("2023/07/sample");
+    let note_content = "Hello\n\n``` python\nprint(1 + 2)\n```\n".to_owned();
 
+    let mut app = App::new(config).unwrap();
     app.create_and_execute_commands(vec![
-        Command::AddNoteWithContent {
-            path: note1_path.to_path_buf(),
-            tags: vec!["python".to_owned()],
-            content: note1_content.clone()
-        },
-        Command::AddNoteWithContent {
-            path: note2_path.to_path_buf(),
-            tags: vec!["python".to_owned()],
-            content: note2_content.clone()
-        }
+        Command::AddNoteWithContent { path: note_path.to_path_buf(), tags: vec![], content: note_content }
     ]).unwrap();
-    assert_eq!(note1_content, app.note_metadata_storage().unwrap().get_content(note1_path).unwrap());
-    assert_eq!(note2_content, app.note_metadata_storage().unwrap().get_content(note2_path).unwrap());
-    assert_eq!(1, repository.reflog("HEAD").unwrap().len());
 
-    app.run(InputCommand::Move { source: Path::new("2023").to_path_buf(), destination: Path::new("2024").to_path_buf(), force: false }).unwrap();
-    assert_eq!(false, app.note_metadata_storage().unwrap().get_content(note1_path).is_ok());
-    assert_eq!(false, app.note_metadata_storage().unwrap().get_content(note2_path).is_ok());
-    assert_eq!(note1_content, app.note_metadata_storage().unwrap().get_content(note1_path2).unwrap());
-    assert_eq!(note2_content, app.note_metadata_storage().unwrap().get_content(note2_path2).unwrap());
-    assert_eq!(2, repository.reflog("HEAD").unwrap().len());
+    let mut version = 0;
+    assert!(app.has_changed(&mut version));
+    assert!(!app.has_changed(&mut version));
+
+    app.run(InputCommand::RunSnippet { path: note_path.to_owned(), save_output: true, check: false }).unwrap();
+    assert!(app.has_changed(&mut version));
+    assert!(app.note_metadata_storage().unwrap().get_content(note_path).unwrap().contains("``` output\n3\n```"));
 }
 
 #[test]
-fn test_move_dir2() {
+fn test_run_snippet_separate_stderr() {
     use tempfile::TempDir;
 
     let temp_repository_dir = TempDir::new().unwrap();
-    let config = create_test_config(&temp_repository_dir);
-    let repository = git2::Repository::init(&config.repository).unwrap();
+    let mut config = create_test_config(&temp_repository_dir);
+    git2::Repository::init(&config.repository).unwrap();
+    config.snippet = Some(SnippetFileConfig {
+        bash: Some(BashSnippetRunnerConfig {
+            executable: Path::new("bash").to_owned(),
+            timeout_seconds: None,
+            separate_stderr: Some(true)
+        }),
+        ..Default::default()
+    });
 
-    let note1_path = Path::new("2023/07/sample");
-    let note1_path2 = Path::new("2023/07/07/sample");
-    let note1_content = r#"Hello, World!
+    let note_path = Path::new("2023/07/sample");
+    let note_content = "Hello\n\n``` bash\necho out\necho err >&2\n```\n".to_owned();
 
-``` python
-import numpy as np
-print(np.square(np.arange(0, 10)))
-```
-"#.to_string();
+    let mut app = App::new(config).unwrap();
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent { path: note_path.to_path_buf(), tags: vec![], content: note_content }
+    ]).unwrap();
 
-    let note2_path = Path::new("2023/07/sample2");
-    let note2_path2 = Path::new("2023/07/07/sample2");
-    let note2_content = r#"Hello, My World!
+    let expected_content = "Hello\n\n``` bash\necho out\necho err >&2\n```\n\n``` output\nout\n```\n\n``` stderr\nerr\n```\n";
+    assert_eq!(expected_content, app.get_show_content(note_path, None, true, true).unwrap());
+    assert_eq!(expected_content, app.get_show_content(note_path, None, true, true).unwrap());
+}
 
-``` python
-import numpy as np
-print(np.square(np.arange(0, 15)))
-```
-"#.to_string();
+#[test]
+fn test_run_snippet_context() {
+    use tempfile::TempDir;
 
-    let mut app = App::new(config).unwrap();
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    git2::Repository::init(&config.repository).unwrap();
+
+    let note_path = Path::new("2023/07/sample");
+    let note_content = "Hello\n\n``` bash\necho $GITNOTES_NOTE_PATH\necho $GITNOTES_REPOSITORY\necho $GITNOTES_RESOURCES_DIR\n```\n".to_owned();
 
+    let mut app = App::new(config.clone()).unwrap();
     app.create_and_execute_commands(vec![
-        Command::AddNoteWithContent {
-            path: note1_path.to_path_buf(),
-            tags: vec!["python".to_owned()],
-            content: note1_content.clone()
-        },
-        Command::AddNoteWithContent {
-            path: note2_path.to_path_buf(),
-            tags: vec!["python".to_owned()],
-            content: note2_content.clone()
-        }
+        Command::AddNoteWithContent { path: note_path.to_path_buf(), tags: vec![], content: note_content }
     ]).unwrap();
-    assert_eq!(note1_content, app.note_metadata_storage().unwrap().get_content(note1_path).unwrap());
-    assert_eq!(note2_content, app.note_metadata_storage().unwrap().get_content(note2_path).unwrap());
-    assert_eq!(1, repository.reflog("HEAD").unwrap().len());
 
-    app.run(InputCommand::Move { source: Path::new("2023").to_path_buf(), destination: Path::new("2023/07").to_path_buf(), force: false }).unwrap();
-    assert_eq!(false, app.note_metadata_storage().unwrap().get_content(note1_path).is_ok());
-    assert_eq!(false, app.note_metadata_storage().unwrap().get_content(note2_path).is_ok());
-    assert_eq!(note1_content, app.note_metadata_storage().unwrap().get_content(note1_path2).unwrap());
-    assert_eq!(note2_content, app.note_metadata_storage().unwrap().get_content(note2_path2).unwrap());
-    assert_eq!(2, repository.reflog("HEAD").unwrap().len());
+    let content = app.get_show_content(note_path, None, true, false).unwrap();
+    let expected_output = format!(
+        "``` output\n{}\n{}\n{}\n```",
+        note_path.to_str().unwrap(),
+        config.repository.to_str().unwrap(),
+        config.repository.join("resources").to_str().unwrap()
+    );
+    assert!(content.contains(&expected_output));
+
+    // The path of the note is used when given by id
+    let id_path = PathBuf::from(app.note_metadata_storage().unwrap().get_id(note_path).unwrap().to_string());
+    let content = app.get_show_content(&id_path, None, true, false).unwrap();
+    assert!(content.contains(&expected_output));
+
+    let results = app.check_snippets(&id_path).unwrap();
+    assert!(results.is_empty());
+
+    let content = app.get_show_content(&id_path, None, true, true).unwrap();
+    assert!(content.contains(&expected_output));
+    assert!(app.check_snippets(&id_path).unwrap().iter().all(|result| result.is_match()));
 }
 
 #[test]
-fn test_move_dir_to_existing1() {
+fn test_replay_snippets() {
     use tempfile::TempDir;
 
     let temp_repository_dir = TempDir::new().unwrap();
     let config = create_test_config(&temp_repository_dir);
     let repository = git2::Repository::init(&config.repository).unwrap();
 
-    let note1_path = Path::new("2023/07/test1");
-    let note1_content = "Test1".to_owned();
-
-    let note2_path = Path::new("2023/07/test2");
-    let note2_content = "Test2".to_owned();
-
-    let note3_path = Path::new("2024/07/test2");
-    let note3_content = "Test3".to_owned();
+    let note_path = Path::new("2023/07/sample");
+    let other_note_path = Path::new("2023/07/other");
 
     let mut app = App::new(config).unwrap();
 
     app.create_and_execute_commands(vec![
         Command::AddNoteWithContent {
-            path: note1_path.to_path_buf(),
-            tags: vec![],
-            content: note1_content.clone()
-        },
-        Command::AddNoteWithContent {
-            path: note2_path.to_path_buf(),
+            path: other_note_path.to_path_buf(),
             tags: vec![],
-            content: note2_content.clone()
-        },
+            content: "Test".to_owned()
+        }
+    ]).unwrap();
+    let start_commit_id = repository.head().unwrap().peel_to_commit().unwrap().id();
+
+    app.create_and_execute_commands(vec![
         Command::AddNoteWithContent {
-            path: note3_path.to_path_buf(),
+            path: note_path.to_path_buf(),
             tags: vec![],
-            content: note3_content.clone()
+            content: "Hello\n\n``` python\nprint(sum(range(0, 10)))\n```\n\n``` output\n45\n```\n".to_owned()
         }
     ]).unwrap();
-    assert_eq!(note1_content, app.note_metadata_storage().unwrap().get_content(note1_path).unwrap());
-    assert_eq!(note2_content, app.note_metadata_storage().unwrap().get_content(note2_path).unwrap());
-    assert_eq!(note3_content, app.note_metadata_storage().unwrap().get_content(note3_path).unwrap());
-    assert_eq!(1, repository.reflog("HEAD").unwrap().len());
+    let first_commit_id = repository.head().unwrap().peel_to_commit().unwrap().id();
 
-    let note_id1 = app.note_metadata_storage().unwrap().get_id(note1_path).unwrap();
-    let note_id2 = app.note_metadata_storage().unwrap().get_id(note2_path).unwrap();
-    let note_id3 = app.note_metadata_storage().unwrap().get_id(note3_path).unwrap();
+    app.create_and_execute_commands(vec![
+        Command::EditNoteSetContent {
+            path: note_path.to_path_buf(),
+            clear_tags: false,
+            add_tags: vec![],
+            content: "Hello\n\n``` python\nprint(sum(range(0, 11)))\n```\n\n``` output\n45\n```\n".to_owned(),
+            force: false
+        }
+    ]).unwrap();
+    let second_commit_id = repository.head().unwrap().peel_to_commit().unwrap().id();
 
-    let err = app.run(InputCommand::Move { source: Path::new("2023").to_owned(), destination: Path::new("2024").to_owned(), force: false }).err().unwrap();
-    assert_eq!(1, repository.reflog("HEAD").unwrap().len());
-    if let AppError::Command(CommandError::NoteExistsAtDestination(err_path)) = err {
-        app.clear_cache();
+    let results = app.replay_snippets(&format!("{}..", start_commit_id)).unwrap();
+    assert_eq!(2, results.len());
 
-        assert_eq!(note3_path, err_path);
-        assert_eq!(note_id1, app.note_metadata_storage().unwrap().get_id(note1_path).unwrap());
-        assert_eq!(note_id2, app.note_metadata_storage().unwrap().get_id(note2_path).unwrap());
-        assert_eq!(note_id3, app.note_metadata_storage().unwrap().get_id(note3_path).unwrap());
+    assert_eq!(first_commit_id, results[0].0);
+    assert_eq!(note_path, results[0].1);
+    assert!(results[0].2.is_match());
 
-        assert_eq!(note1_content, app.note_metadata_storage().unwrap().get_content(note1_path).unwrap());
-        assert_eq!(note2_content, app.note_metadata_storage().unwrap().get_content(note2_path).unwrap());
-        assert_eq!(note3_content, app.note_metadata_storage().unwrap().get_content(note3_path).unwrap());
-    } else {
-        assert!(false, "Expected 'NoteAtDestination' error");
-    }
+    assert_eq!(second_commit_id, results[1].0);
+    assert_eq!(note_path, results[1].1);
+    assert!(!results[1].2.is_match());
+    assert_eq!("55\n", results[1].2.actual);
+
+    let result = app.run(InputCommand::Replay { range: format!("{}..{}", start_commit_id, second_commit_id) });
+    assert!(matches!(result, Err(AppError::Command(CommandError::SnippetOutputMismatch(1)))));
+    assert_eq!(second_commit_id, repository.head().unwrap().peel_to_commit().unwrap().id());
 }
 
 #[test]
-fn test_move_file_to_dir() {
+fn test_move() {
     use tempfile::TempDir;
 
     let temp_repository_dir = TempDir::new().unwrap();
@@ -517,133 +816,98 @@ print(np.square(np.arange(0, 10)))
             path: note_path.to_path_buf(),
             tags: vec!["python".to_owned()],
             content: note_content.clone()
-        },
-        Command::AddNoteWithContent {
-            path: Path::new("2023/07/01/sample2").to_owned(),
-            tags: vec!["python".to_owned()],
-            content: note_content.clone()
         }
     ]).unwrap();
     assert_eq!(note_content, app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
     assert_eq!(1, repository.reflog("HEAD").unwrap().len());
 
-    app.run(InputCommand::Move { source: note_path.to_owned(), destination: Path::new("2023/07/01").to_owned(), force: false }).unwrap();
+    app.run(InputCommand::Move { source: note_path.to_owned(), destination: note_path2.to_owned(), force: false, merge: false, ignore_lock: false, dry_run: false }).unwrap();
     assert_eq!(false, app.note_metadata_storage().unwrap().get_content(note_path).is_ok());
     assert_eq!(note_content, app.note_metadata_storage().unwrap().get_content(note_path2).unwrap());
     assert_eq!(2, repository.reflog("HEAD").unwrap().len());
 }
 
 #[test]
-fn test_move_glob1() {
+fn test_copy() {
+    use structopt::StructOpt;
     use tempfile::TempDir;
 
     let temp_repository_dir = TempDir::new().unwrap();
     let config = create_test_config(&temp_repository_dir);
     let repository = git2::Repository::init(&config.repository).unwrap();
 
-    let note1_path = Path::new("2023/07/sample1");
-    let note1_path2 = Path::new("2025/07/sample1");
-    let note1_content = r#"Hello, World!
+    let note_path = Path::new("2023/07/sample");
+    let note_path2 = Path::new("2023/07/01/sample");
+    let note_content = r#"Hello, World!
 
 ``` python
 import numpy as np
 print(np.square(np.arange(0, 10)))
 ```
-"#.to_string();
-
-    let note2_path = Path::new("2024/07/sample2");
-    let note2_path2 = Path::new("2025/07/sample2");
-    let note2_content = r#"Hello, My World!
-
-``` python
-import numpy as np
-print(np.square(np.arange(0, 15)))
-```
 "#.to_string();
 
     let mut app = App::new(config).unwrap();
 
     app.create_and_execute_commands(vec![
         Command::AddNoteWithContent {
-            path: note1_path.to_path_buf(),
-            tags: vec!["python".to_owned()],
-            content: note1_content.clone()
-        },
-        Command::AddNoteWithContent {
-            path: note2_path.to_path_buf(),
+            path: note_path.to_path_buf(),
             tags: vec!["python".to_owned()],
-            content: note2_content.clone()
+            content: note_content.clone()
         }
     ]).unwrap();
-    assert_eq!(note1_content, app.note_metadata_storage().unwrap().get_content(note1_path).unwrap());
-    assert_eq!(note2_content, app.note_metadata_storage().unwrap().get_content(note2_path).unwrap());
     assert_eq!(1, repository.reflog("HEAD").unwrap().len());
 
-    app.run(InputCommand::Move { source: Path::new("202*").to_path_buf(), destination: Path::new("2025").to_path_buf(), force: false }).unwrap();
-    assert_eq!(false, app.note_metadata_storage().unwrap().get_content(note1_path).is_ok());
-    assert_eq!(false, app.note_metadata_storage().unwrap().get_content(note2_path).is_ok());
-    assert_eq!(note1_content, app.note_metadata_storage().unwrap().get_content(note1_path2).unwrap());
-    assert_eq!(note2_content, app.note_metadata_storage().unwrap().get_content(note2_path2).unwrap());
+    app.run(InputCommand::Copy { source: note_path.to_owned(), destination: note_path2.to_owned(), force: false, ignore_lock: false }).unwrap();
+    assert_eq!(note_content, app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
+    assert_eq!(note_content, app.note_metadata_storage().unwrap().get_content(note_path2).unwrap());
     assert_eq!(2, repository.reflog("HEAD").unwrap().len());
+
+    let note_metadata = app.note_metadata_storage().unwrap().get(note_path).unwrap().clone();
+    let note_metadata2 = app.note_metadata_storage().unwrap().get(note_path2).unwrap().clone();
+    assert_ne!(note_metadata.id, note_metadata2.id);
+    assert_eq!(vec!["python".to_owned()], note_metadata2.tags);
+    assert!(note_metadata2.created >= note_metadata.created);
+
+    // Into a folder keeps the file name
+    app.run(InputCommand::Copy { source: note_path.to_owned(), destination: Path::new("2023/07/01").to_owned(), force: false, ignore_lock: false }).err().unwrap();
+    app.run(InputCommand::Copy { source: note_path.to_owned(), destination: Path::new("2023/07/01").to_owned(), force: true, ignore_lock: false }).unwrap();
+    assert_eq!(2, app.note_metadata_storage().unwrap().notes().count());
+    assert_ne!(note_metadata2.id, app.note_metadata_storage().unwrap().get(note_path2).unwrap().id);
+    assert_eq!(3, repository.reflog("HEAD").unwrap().len());
+
+    // Both cp and copy copies the note
+    for name in ["cp", "copy"] {
+        let input_command = InputCommand::from_iter_safe(["gitnotes", name, "2023/07/sample", "2023/07/02/sample"]).unwrap();
+        assert!(matches!(input_command, InputCommand::Copy { .. }), "{}", name);
+    }
 }
 
 #[test]
-fn test_move_glob2() {
+fn test_copy_untagged() {
     use tempfile::TempDir;
 
     let temp_repository_dir = TempDir::new().unwrap();
     let config = create_test_config(&temp_repository_dir);
-    let repository = git2::Repository::init(&config.repository).unwrap();
-
-    let note1_path = Path::new("2023/07/sample1");
-    let note1_path2 = Path::new("2023/08/sample1");
-    let note1_content = r#"Hello, World!
-
-``` python
-import numpy as np
-print(np.square(np.arange(0, 10)))
-```
-"#.to_string();
-
-    let note2_path = Path::new("2024/07/sample2");
-    let note2_path2 = Path::new("2024/07/sample2");
-    let note2_content = r#"Hello, My World!
-
-``` python
-import numpy as np
-print(np.square(np.arange(0, 15)))
-```
-"#.to_string();
+    git2::Repository::init(&config.repository).unwrap();
 
+    let note_path = Path::new("2023/07/sample");
     let mut app = App::new(config).unwrap();
 
     app.create_and_execute_commands(vec![
         Command::AddNoteWithContent {
-            path: note1_path.to_path_buf(),
-            tags: vec!["python".to_owned()],
-            content: note1_content.clone()
-        },
-        Command::AddNoteWithContent {
-            path: note2_path.to_path_buf(),
+            path: note_path.to_path_buf(),
             tags: vec!["python".to_owned()],
-            content: note2_content.clone()
+            content: "``` python\nprint(1)\n```".to_owned()
         }
     ]).unwrap();
-    assert_eq!(note1_content, app.note_metadata_storage().unwrap().get_content(note1_path).unwrap());
-    assert_eq!(note2_content, app.note_metadata_storage().unwrap().get_content(note2_path).unwrap());
-    assert_eq!(1, repository.reflog("HEAD").unwrap().len());
+    app.run(InputCommand::SetTags { path: note_path.to_owned(), tags: vec![] }).unwrap();
 
-    app.run(InputCommand::ChangeWorkingDirectory { path: Path::new("2023").to_owned() }).unwrap();
-    app.run(InputCommand::Move { source: Path::new("*").to_path_buf(), destination: Path::new("08").to_path_buf(), force: false }).unwrap();
-    assert_eq!(false, app.note_metadata_storage().unwrap().get_content(note1_path).is_ok());
-    assert_eq!(true, app.note_metadata_storage().unwrap().get_content(note2_path).is_ok());
-    assert_eq!(note1_content, app.note_metadata_storage().unwrap().get_content(note1_path2).unwrap());
-    assert_eq!(note2_content, app.note_metadata_storage().unwrap().get_content(note2_path2).unwrap());
-    assert_eq!(2, repository.reflog("HEAD").unwrap().len());
+    app.run(InputCommand::Copy { source: note_path.to_owned(), destination: Path::new("2023/07/copy").to_owned(), force: false, ignore_lock: false }).unwrap();
+    assert_eq!(Vec::<String>::new(), app.note_metadata_storage().unwrap().get(Path::new("2023/07/copy")).unwrap().tags);
 }
 
 #[test]
-fn test_remove() {
+fn test_move_to_existing1() {
     use tempfile::TempDir;
 
     let temp_repository_dir = TempDir::new().unwrap();
@@ -651,13 +915,9 @@ fn test_remove() {
     let repository = git2::Repository::init(&config.repository).unwrap();
 
     let note_path = Path::new("2023/07/sample");
-    let note_content = r#"Hello, World!
-
-``` python
-import numpy as np
-print(np.square(np.arange(0, 10)))
-```
-"#.to_string();
+    let note_path2 = Path::new("2023/07/01/sample");
+    let note_content = "Hello, World #1".to_owned();
+    let note_content2 = "Hello, World #2".to_owned();
 
     let mut app = App::new(config).unwrap();
 
@@ -666,66 +926,121 @@ print(np.square(np.arange(0, 10)))
             path: note_path.to_path_buf(),
             tags: vec!["python".to_owned()],
             content: note_content.clone()
+        },
+        Command::AddNoteWithContent {
+            path: note_path2.to_path_buf(),
+            tags: vec!["python".to_owned()],
+            content: note_content2.clone()
         }
     ]).unwrap();
+    let note_id = app.note_metadata_storage().unwrap().get_id(note_path).unwrap();
+    let note_id2 = app.note_metadata_storage().unwrap().get_id(note_path2).unwrap();
     assert_eq!(note_content, app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
+    assert_eq!(note_content2, app.note_metadata_storage().unwrap().get_content(note_path2).unwrap());
     assert_eq!(1, repository.reflog("HEAD").unwrap().len());
 
-    app.run(InputCommand::Remove { path: note_path.to_owned(), recursive: false }).unwrap();
-    assert_eq!(false, app.note_metadata_storage().unwrap().get(note_path).is_some());
+    let err = app.run(InputCommand::Move { source: note_path.to_owned(), destination: note_path2.to_owned(), force: false, merge: false, ignore_lock: false, dry_run: false }).err().unwrap();
+    if let AppError::Command(CommandError::NoteExistsAtDestination(err_path)) = err {
+        assert_eq!(note_path2, err_path);
+        assert_eq!(note_id, app.note_metadata_storage().unwrap().get_id(note_path).unwrap());
+        assert_eq!(note_id2, app.note_metadata_storage().unwrap().get_id(note_path2).unwrap());
+    } else {
+        assert!(false, "Expected 'NoteAtDestination' error");
+    }
+}
+
+#[test]
+fn test_move_to_existing2() {
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    let repository = git2::Repository::init(&config.repository).unwrap();
+
+    let note_path = Path::new("2023/07/sample");
+    let note_path2 = Path::new("2023/07/01/sample");
+    let note_content = "Hello, World #1".to_owned();
+    let note_content2 = "Hello, World #2".to_owned();
+
+    let mut app = App::new(config).unwrap();
+
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent {
+            path: note_path.to_path_buf(),
+            tags: vec!["python".to_owned()],
+            content: note_content.clone()
+        },
+        Command::AddNoteWithContent {
+            path: note_path2.to_path_buf(),
+            tags: vec!["python".to_owned()],
+            content: note_content2.clone()
+        }
+    ]).unwrap();
+    let note_id = app.note_metadata_storage().unwrap().get_id(note_path).unwrap();
+    assert_eq!(note_content, app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
+    assert_eq!(note_content2, app.note_metadata_storage().unwrap().get_content(note_path2).unwrap());
+    assert_eq!(1, repository.reflog("HEAD").unwrap().len());
+
+    app.run(InputCommand::Move { source: note_path.to_owned(), destination: note_path2.to_owned(), force: true, merge: false, ignore_lock: false, dry_run: false }).unwrap();
     assert_eq!(false, app.note_metadata_storage().unwrap().get_content(note_path).is_ok());
+    assert_eq!(note_content, app.note_metadata_storage().unwrap().get_content(note_path2).unwrap());
+    assert_eq!(note_id, app.note_metadata_storage().unwrap().get(note_path2).unwrap().id);
     assert_eq!(2, repository.reflog("HEAD").unwrap().len());
 }
 
 #[test]
-fn test_remove_recursive() {
+fn test_move_merge() {
     use tempfile::TempDir;
 
     let temp_repository_dir = TempDir::new().unwrap();
     let config = create_test_config(&temp_repository_dir);
     let repository = git2::Repository::init(&config.repository).unwrap();
 
-    let note1_path = Path::new("2023/07/test1");
-    let note1_content = "Test1".to_owned();
-
-    let note2_path = Path::new("2023/07/test2");
-    let note2_content = "Test2".to_owned();
+    let note_path = Path::new("2023/07/sample");
+    let note_path2 = Path::new("2023/07/01/sample");
 
     let mut app = App::new(config).unwrap();
 
     app.create_and_execute_commands(vec![
         Command::AddNoteWithContent {
-            path: note1_path.to_path_buf(),
-            tags: vec![],
-            content: note1_content.clone()
+            path: note_path.to_path_buf(),
+            tags: vec!["python".to_owned(), "numpy".to_owned()],
+            content: "Hello, World #1".to_owned()
         },
         Command::AddNoteWithContent {
-            path: note2_path.to_path_buf(),
-            tags: vec![],
-            content: note2_content.clone()
+            path: note_path2.to_path_buf(),
+            tags: vec!["python".to_owned()],
+            content: "Hello, World #2\n".to_owned()
         }
     ]).unwrap();
-    assert_eq!(note1_content, app.note_metadata_storage().unwrap().get_content(note1_path).unwrap());
-    assert_eq!(note2_content, app.note_metadata_storage().unwrap().get_content(note2_path).unwrap());
+    let note_id2 = app.note_metadata_storage().unwrap().get_id(note_path2).unwrap();
     assert_eq!(1, repository.reflog("HEAD").unwrap().len());
 
-    app.run(InputCommand::Remove { path: Path::new("2023").to_owned(), recursive: true }).unwrap();
-    assert_eq!(false, app.note_metadata_storage().unwrap().get(note1_path).is_some());
-    assert_eq!(false, app.note_metadata_storage().unwrap().get_content(note1_path).is_ok());
-    assert_eq!(false, app.note_metadata_storage().unwrap().get(note2_path).is_some());
-    assert_eq!(false, app.note_metadata_storage().unwrap().get_content(note2_path).is_ok());
+    app.run(InputCommand::Move { source: note_path.to_owned(), destination: note_path2.to_owned(), force: false, merge: true, ignore_lock: false, dry_run: false }).unwrap();
+    assert_eq!(false, app.note_metadata_storage().unwrap().contains_path(note_path));
+    assert_eq!("Hello, World #2\n\nHello, World #1", app.note_metadata_storage().unwrap().get_content(note_path2).unwrap());
+    assert_eq!(note_id2, app.note_metadata_storage().unwrap().get(note_path2).unwrap().id);
+    assert_eq!(vec!["python".to_owned(), "numpy".to_owned()], app.note_metadata_storage().unwrap().get(note_path2).unwrap().tags);
     assert_eq!(2, repository.reflog("HEAD").unwrap().len());
+
+    for destination in [note_path2.to_owned(), PathBuf::from(note_id2.to_string())] {
+        let result = app.run(InputCommand::Move { source: note_path2.to_owned(), destination, force: false, merge: true, ignore_lock: false, dry_run: false });
+        assert!(matches!(result, Err(AppError::Command(CommandError::SameSourceAndDestination(_)))), "{:?}", result.err());
+        assert_eq!("Hello, World #2\n\nHello, World #1", app.note_metadata_storage().unwrap().get_content(note_path2).unwrap());
+        assert_eq!(2, repository.reflog("HEAD").unwrap().len());
+    }
 }
 
 #[test]
-fn test_remove_glob1() {
+fn test_move_dir1() {
     use tempfile::TempDir;
 
     let temp_repository_dir = TempDir::new().unwrap();
     let config = create_test_config(&temp_repository_dir);
     let repository = git2::Repository::init(&config.repository).unwrap();
 
-    let note1_path = Path::new("2023/07/sample1");
+    let note1_path = Path::new("2023/07/sample");
+    let note1_path2 = Path::new("2024/07/sample");
     let note1_content = r#"Hello, World!
 
 ``` python
@@ -734,7 +1049,8 @@ print(np.square(np.arange(0, 10)))
 ```
 "#.to_string();
 
-    let note2_path = Path::new("2024/07/sample2");
+    let note2_path = Path::new("2023/07/sample2");
+    let note2_path2 = Path::new("2024/07/sample2");
     let note2_content = r#"Hello, My World!
 
 ``` python
@@ -761,21 +1077,24 @@ print(np.square(np.arange(0, 15)))
     assert_eq!(note2_content, app.note_metadata_storage().unwrap().get_content(note2_path).unwrap());
     assert_eq!(1, repository.reflog("HEAD").unwrap().len());
 
-    app.run(InputCommand::Remove { path: Path::new("202*").to_path_buf(), recursive: true }).unwrap();
+    app.run(InputCommand::Move { source: Path::new("2023").to_path_buf(), destination: Path::new("2024").to_path_buf(), force: false, merge: false, ignore_lock: false, dry_run: false }).unwrap();
     assert_eq!(false, app.note_metadata_storage().unwrap().get_content(note1_path).is_ok());
     assert_eq!(false, app.note_metadata_storage().unwrap().get_content(note2_path).is_ok());
+    assert_eq!(note1_content, app.note_metadata_storage().unwrap().get_content(note1_path2).unwrap());
+    assert_eq!(note2_content, app.note_metadata_storage().unwrap().get_content(note2_path2).unwrap());
     assert_eq!(2, repository.reflog("HEAD").unwrap().len());
 }
 
 #[test]
-fn test_remove_glob2() {
+fn test_move_dir2() {
     use tempfile::TempDir;
 
     let temp_repository_dir = TempDir::new().unwrap();
     let config = create_test_config(&temp_repository_dir);
     let repository = git2::Repository::init(&config.repository).unwrap();
 
-    let note1_path = Path::new("2023/07/sample1");
+    let note1_path = Path::new("2023/07/sample");
+    let note1_path2 = Path::new("2023/07/07/sample");
     let note1_content = r#"Hello, World!
 
 ``` python
@@ -784,7 +1103,8 @@ print(np.square(np.arange(0, 10)))
 ```
 "#.to_string();
 
-    let note2_path = Path::new("2024/07/sample2");
+    let note2_path = Path::new("2023/07/sample2");
+    let note2_path2 = Path::new("2023/07/07/sample2");
     let note2_content = r#"Hello, My World!
 
 ``` python
@@ -811,57 +1131,79 @@ print(np.square(np.arange(0, 15)))
     assert_eq!(note2_content, app.note_metadata_storage().unwrap().get_content(note2_path).unwrap());
     assert_eq!(1, repository.reflog("HEAD").unwrap().len());
 
-    app.run(InputCommand::ChangeWorkingDirectory { path: Path::new("2023").to_owned() }).unwrap();
-    app.run(InputCommand::Remove { path: Path::new("*").to_path_buf(), recursive: true }).unwrap();
+    app.run(InputCommand::Move { source: Path::new("2023").to_path_buf(), destination: Path::new("2023/07").to_path_buf(), force: false, merge: false, ignore_lock: false, dry_run: false }).unwrap();
     assert_eq!(false, app.note_metadata_storage().unwrap().get_content(note1_path).is_ok());
-    assert_eq!(true, app.note_metadata_storage().unwrap().get_content(note2_path).is_ok());
+    assert_eq!(false, app.note_metadata_storage().unwrap().get_content(note2_path).is_ok());
+    assert_eq!(note1_content, app.note_metadata_storage().unwrap().get_content(note1_path2).unwrap());
+    assert_eq!(note2_content, app.note_metadata_storage().unwrap().get_content(note2_path2).unwrap());
     assert_eq!(2, repository.reflog("HEAD").unwrap().len());
 }
 
 #[test]
-fn test_change_tags() {
+fn test_move_dir_to_existing1() {
     use tempfile::TempDir;
 
     let temp_repository_dir = TempDir::new().unwrap();
     let config = create_test_config(&temp_repository_dir);
     let repository = git2::Repository::init(&config.repository).unwrap();
 
-    let note_path = Path::new("2023/07/sample");
-    let note_content = r#"Hello, World!
+    let note1_path = Path::new("2023/07/test1");
+    let note1_content = "Test1".to_owned();
 
-``` python
-import numpy as np
-print(np.square(np.arange(0, 10)))
-```
-"#.to_string();
+    let note2_path = Path::new("2023/07/test2");
+    let note2_content = "Test2".to_owned();
+
+    let note3_path = Path::new("2024/07/test2");
+    let note3_content = "Test3".to_owned();
 
     let mut app = App::new(config).unwrap();
 
     app.create_and_execute_commands(vec![
         Command::AddNoteWithContent {
-            path: note_path.to_path_buf(),
-            tags: vec!["python".to_owned()],
-            content: note_content.clone()
+            path: note1_path.to_path_buf(),
+            tags: vec![],
+            content: note1_content.clone()
+        },
+        Command::AddNoteWithContent {
+            path: note2_path.to_path_buf(),
+            tags: vec![],
+            content: note2_content.clone()
+        },
+        Command::AddNoteWithContent {
+            path: note3_path.to_path_buf(),
+            tags: vec![],
+            content: note3_content.clone()
         }
     ]).unwrap();
-    assert_eq!(note_content, app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
+    assert_eq!(note1_content, app.note_metadata_storage().unwrap().get_content(note1_path).unwrap());
+    assert_eq!(note2_content, app.note_metadata_storage().unwrap().get_content(note2_path).unwrap());
+    assert_eq!(note3_content, app.note_metadata_storage().unwrap().get_content(note3_path).unwrap());
     assert_eq!(1, repository.reflog("HEAD").unwrap().len());
 
-    app.create_and_execute_commands(vec![
-        Command::EditNoteSetContent {
-            path: note_path.to_path_buf(),
-            clear_tags: false,
-            add_tags: vec!["snippet".to_owned()],
-            content: note_content.clone()
-        }
-    ]).unwrap();
-    assert_eq!(note_content, app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
-    assert_eq!(vec!["python".to_owned(), "snippet".to_owned()], app.note_metadata_storage().unwrap().get(note_path).unwrap().tags);
-    assert_eq!(2, repository.reflog("HEAD").unwrap().len());
+    let note_id1 = app.note_metadata_storage().unwrap().get_id(note1_path).unwrap();
+    let note_id2 = app.note_metadata_storage().unwrap().get_id(note2_path).unwrap();
+    let note_id3 = app.note_metadata_storage().unwrap().get_id(note3_path).unwrap();
+
+    let err = app.run(InputCommand::Move { source: Path::new("2023").to_owned(), destination: Path::new("2024").to_owned(), force: false, merge: false, ignore_lock: false, dry_run: false }).err().unwrap();
+    assert_eq!(1, repository.reflog("HEAD").unwrap().len());
+    if let AppError::Command(CommandError::NoteExistsAtDestination(err_path)) = err {
+        app.clear_cache();
+
+        assert_eq!(note3_path, err_path);
+        assert_eq!(note_id1, app.note_metadata_storage().unwrap().get_id(note1_path).unwrap());
+        assert_eq!(note_id2, app.note_metadata_storage().unwrap().get_id(note2_path).unwrap());
+        assert_eq!(note_id3, app.note_metadata_storage().unwrap().get_id(note3_path).unwrap());
+
+        assert_eq!(note1_content, app.note_metadata_storage().unwrap().get_content(note1_path).unwrap());
+        assert_eq!(note2_content, app.note_metadata_storage().unwrap().get_content(note2_path).unwrap());
+        assert_eq!(note3_content, app.note_metadata_storage().unwrap().get_content(note3_path).unwrap());
+    } else {
+        assert!(false, "Expected 'NoteAtDestination' error");
+    }
 }
 
 #[test]
-fn test_edit() {
+fn test_move_file_to_dir() {
     use tempfile::TempDir;
 
     let temp_repository_dir = TempDir::new().unwrap();
@@ -869,18 +1211,12 @@ fn test_edit() {
     let repository = git2::Repository::init(&config.repository).unwrap();
 
     let note_path = Path::new("2023/07/sample");
+    let note_path2 = Path::new("2023/07/01/sample");
     let note_content = r#"Hello, World!
 
 ``` python
-xs = list(range(0, 10))
-print([x * x for x in xs])
-```
-"#.to_string();
-    let note_content2 = r#"Hello, World!
-
-``` python
-xs = list(range(0, 15))
-print([x * x for x in xs])
+import numpy as np
+print(np.square(np.arange(0, 10)))
 ```
 "#.to_string();
 
@@ -889,47 +1225,49 @@ print([x * x for x in xs])
     app.create_and_execute_commands(vec![
         Command::AddNoteWithContent {
             path: note_path.to_path_buf(),
-            tags: vec![],
+            tags: vec!["python".to_owned()],
             content: note_content.clone()
         },
+        Command::AddNoteWithContent {
+            path: Path::new("2023/07/01/sample2").to_owned(),
+            tags: vec!["python".to_owned()],
+            content: note_content.clone()
+        }
     ]).unwrap();
     assert_eq!(note_content, app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
     assert_eq!(1, repository.reflog("HEAD").unwrap().len());
-    assert_eq!(vec!["snippet".to_owned(), "python".to_owned()], app.note_metadata_storage().unwrap().get(note_path).unwrap().tags);
 
-    app.create_and_execute_commands(vec![
-        Command::EditNoteSetContent {
-            path: note_path.to_path_buf(),
-            clear_tags: false,
-            add_tags: vec![],
-            content: note_content2.clone()
-        },
-    ]).unwrap();
-    assert_eq!(note_content2, app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
+    app.run(InputCommand::Move { source: note_path.to_owned(), destination: Path::new("2023/07/01").to_owned(), force: false, merge: false, ignore_lock: false, dry_run: false }).unwrap();
+    assert_eq!(false, app.note_metadata_storage().unwrap().get_content(note_path).is_ok());
+    assert_eq!(note_content, app.note_metadata_storage().unwrap().get_content(note_path2).unwrap());
     assert_eq!(2, repository.reflog("HEAD").unwrap().len());
 }
 
 #[test]
-fn test_edit_note_id() {
+fn test_move_glob1() {
     use tempfile::TempDir;
 
     let temp_repository_dir = TempDir::new().unwrap();
     let config = create_test_config(&temp_repository_dir);
     let repository = git2::Repository::init(&config.repository).unwrap();
 
-    let note_path = Path::new("2023/07/sample");
-    let note_content = r#"Hello, World!
+    let note1_path = Path::new("2023/07/sample1");
+    let note1_path2 = Path::new("2025/07/sample1");
+    let note1_content = r#"Hello, World!
 
 ``` python
-xs = list(range(0, 10))
-print([x * x for x in xs])
+import numpy as np
+print(np.square(np.arange(0, 10)))
 ```
 "#.to_string();
-    let note_content2 = r#"Hello, World!
+
+    let note2_path = Path::new("2024/07/sample2");
+    let note2_path2 = Path::new("2025/07/sample2");
+    let note2_content = r#"Hello, My World!
 
 ``` python
-xs = list(range(0, 15))
-print([x * x for x in xs])
+import numpy as np
+print(np.square(np.arange(0, 15)))
 ```
 "#.to_string();
 
@@ -937,256 +1275,3498 @@ print([x * x for x in xs])
 
     app.create_and_execute_commands(vec![
         Command::AddNoteWithContent {
-            path: note_path.to_path_buf(),
-            tags: vec![],
-            content: note_content.clone()
+            path: note1_path.to_path_buf(),
+            tags: vec!["python".to_owned()],
+            content: note1_content.clone()
         },
+        Command::AddNoteWithContent {
+            path: note2_path.to_path_buf(),
+            tags: vec!["python".to_owned()],
+            content: note2_content.clone()
+        }
     ]).unwrap();
-    assert_eq!(note_content, app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
+    assert_eq!(note1_content, app.note_metadata_storage().unwrap().get_content(note1_path).unwrap());
+    assert_eq!(note2_content, app.note_metadata_storage().unwrap().get_content(note2_path).unwrap());
     assert_eq!(1, repository.reflog("HEAD").unwrap().len());
-    assert_eq!(vec!["snippet".to_owned(), "python".to_owned()], app.note_metadata_storage().unwrap().get(note_path).unwrap().tags);
-
-    let note_id = app.note_metadata_storage().unwrap().get_id(note_path).unwrap();
 
-    app.create_and_execute_commands(vec![
-        Command::EditNoteSetContent {
-            path: Path::new(&note_id.to_string()).to_owned(),
-            clear_tags: false,
-            add_tags: vec![],
-            content: note_content2.clone()
-        },
-    ]).unwrap();
-    assert_eq!(note_content2, app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
+    app.run(InputCommand::Move { source: Path::new("202*").to_path_buf(), destination: Path::new("2025").to_path_buf(), force: false, merge: false, ignore_lock: false, dry_run: false }).unwrap();
+    assert_eq!(false, app.note_metadata_storage().unwrap().get_content(note1_path).is_ok());
+    assert_eq!(false, app.note_metadata_storage().unwrap().get_content(note2_path).is_ok());
+    assert_eq!(note1_content, app.note_metadata_storage().unwrap().get_content(note1_path2).unwrap());
+    assert_eq!(note2_content, app.note_metadata_storage().unwrap().get_content(note2_path2).unwrap());
     assert_eq!(2, repository.reflog("HEAD").unwrap().len());
 }
 
 #[test]
-fn test_edit_with_editor() {
+fn test_move_glob2() {
     use tempfile::TempDir;
 
     let temp_repository_dir = TempDir::new().unwrap();
-    let mut config = create_test_config(&temp_repository_dir);
-    config.allow_stdin = false;
+    let config = create_test_config(&temp_repository_dir);
     let repository = git2::Repository::init(&config.repository).unwrap();
 
-    let note_path = Path::new("2023/07/sample");
-    let note_content = r#"Hello, World!
+    let note1_path = Path::new("2023/07/sample1");
+    let note1_path2 = Path::new("2023/08/sample1");
+    let note1_content = r#"Hello, World!
 
 ``` python
-xs = list(range(0, 10))
-print([x * x for x in xs])
+import numpy as np
+print(np.square(np.arange(0, 10)))
 ```
 "#.to_string();
-    let note_content2 = r#"Hello, World!
+
+    let note2_path = Path::new("2024/07/sample2");
+    let note2_path2 = Path::new("2024/07/sample2");
+    let note2_content = r#"Hello, My World!
 
 ``` python
-xs = list(range(0, 15))
-print([x * x for x in xs])
+import numpy as np
+print(np.square(np.arange(0, 15)))
 ```
 "#.to_string();
 
-    let note_content2_clone = note_content2.clone();
-    let mut app = App::with_custom(config, move |config, repository| {
-        CommandInterpreter::with_launch_editor(
-            config,
-            repository,
-            Box::new(move |_, path, _| {
-                std::fs::write(path, &note_content2_clone).map_err(|err| CommandError::IO(err))?;
-                Ok(EditorOutput::default())
-            })
-        )
-    }).unwrap();
+    let mut app = App::new(config).unwrap();
 
     app.create_and_execute_commands(vec![
         Command::AddNoteWithContent {
-            path: note_path.to_path_buf(),
-            tags: vec![],
-            content: note_content.clone()
+            path: note1_path.to_path_buf(),
+            tags: vec!["python".to_owned()],
+            content: note1_content.clone()
         },
+        Command::AddNoteWithContent {
+            path: note2_path.to_path_buf(),
+            tags: vec!["python".to_owned()],
+            content: note2_content.clone()
+        }
     ]).unwrap();
-    assert_eq!(note_content, app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
+    assert_eq!(note1_content, app.note_metadata_storage().unwrap().get_content(note1_path).unwrap());
+    assert_eq!(note2_content, app.note_metadata_storage().unwrap().get_content(note2_path).unwrap());
     assert_eq!(1, repository.reflog("HEAD").unwrap().len());
-    assert_eq!(vec!["snippet".to_owned(), "python".to_owned()], app.note_metadata_storage().unwrap().get(note_path).unwrap().tags);
 
-    app.run(InputCommand::Edit {
-        path: note_path.to_owned(),
-        history: None,
-        clear_tags: false,
-        add_tags: vec![],
-    }).unwrap();
-    assert_eq!(note_content2, app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
+    app.run(InputCommand::ChangeWorkingDirectory { path: Path::new("2023").to_owned() }).unwrap();
+    app.run(InputCommand::Move { source: Path::new("*").to_path_buf(), destination: Path::new("08").to_path_buf(), force: false, merge: false, ignore_lock: false, dry_run: false }).unwrap();
+    assert_eq!(false, app.note_metadata_storage().unwrap().get_content(note1_path).is_ok());
+    assert_eq!(true, app.note_metadata_storage().unwrap().get_content(note2_path).is_ok());
+    assert_eq!(note1_content, app.note_metadata_storage().unwrap().get_content(note1_path2).unwrap());
+    assert_eq!(note2_content, app.note_metadata_storage().unwrap().get_content(note2_path2).unwrap());
     assert_eq!(2, repository.reflog("HEAD").unwrap().len());
 }
 
 #[test]
-fn test_edit_with_editor_and_history() {
+fn test_move_to_date() {
     use tempfile::TempDir;
 
     let temp_repository_dir = TempDir::new().unwrap();
-    let mut config = create_test_config(&temp_repository_dir);
-    config.allow_stdin = false;
+    let config = create_test_config(&temp_repository_dir);
     let repository = git2::Repository::init(&config.repository).unwrap();
 
-    let note_path = Path::new("2023/07/sample");
-    let note_content = r#"Hello, World!
-
-``` python
-xs = list(range(0, 10))
-print([x * x for x in xs])
-```
-"#.to_string();
-    let note_content2 = r#"Hello, World!
-
-``` python
-xs = list(range(0, 15))
-print([x * x for x in xs])
-```
-"#.to_string();
+    let note_path = Path::new("misc/sample");
+    let note_content = "Hello, World!".to_owned();
 
-    let mut app = App::with_custom(config, move |config, repository| {
-        CommandInterpreter::with_launch_editor(
-            config,
-            repository,
-            Box::new(move |_, _, _| {
-                Ok(EditorOutput::default())
-            })
-        )
-    }).unwrap();
+    let mut app = App::new(config).unwrap();
 
     app.create_and_execute_commands(vec![
         Command::AddNoteWithContent {
             path: note_path.to_path_buf(),
             tags: vec![],
             content: note_content.clone()
+        }
+    ]).unwrap();
+    let created = app.note_metadata_storage().unwrap().get(note_path).unwrap().created;
+    let expected_path = Path::new(&created.format("%Y/%m/%d").to_string()).join("sample");
+
+    app.run(InputCommand::MoveToDate { force: false, filter: InputCommandFinder::Name { name: RegexMatcher::new("sample"), here: false, global: false, clauses: vec![] } }).unwrap();
+    assert_eq!(false, app.note_metadata_storage().unwrap().get_content(note_path).is_ok());
+    assert_eq!(note_content, app.note_metadata_storage().unwrap().get_content(&expected_path).unwrap());
+    assert_eq!(2, repository.reflog("HEAD").unwrap().len());
+
+    // Notes with the same name and created date would end up at the same path
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent {
+            path: Path::new("misc/a/other").to_path_buf(),
+            tags: vec![],
+            content: "A".to_owned()
         },
+        Command::AddNoteWithContent {
+            path: Path::new("misc/b/other").to_path_buf(),
+            tags: vec![],
+            content: "B".to_owned()
+        }
     ]).unwrap();
-    assert_eq!(note_content, app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
-    assert_eq!(1, repository.reflog("HEAD").unwrap().len());
-    assert_eq!(vec!["snippet".to_owned(), "python".to_owned()], app.note_metadata_storage().unwrap().get(note_path).unwrap().tags);
+    let created = app.note_metadata_storage().unwrap().get(Path::new("misc/b/other")).unwrap().created;
+    app.create_and_execute_commands(vec![
+        Command::SetNoteMetadata {
+            path: Path::new("misc/a/other").to_path_buf(),
+            tags: vec![],
+            created,
+            last_updated: created
+        }
+    ]).unwrap();
+
+    let result = app.run(InputCommand::MoveToDate { force: true, filter: InputCommandFinder::Name { name: RegexMatcher::new("other"), here: false, global: false, clauses: vec![] } });
+    assert!(matches!(result, Err(AppError::Input(_))));
+    assert_eq!("A", app.note_metadata_storage().unwrap().get_content(Path::new("misc/a/other")).unwrap());
+    assert_eq!("B", app.note_metadata_storage().unwrap().get_content(Path::new("misc/b/other")).unwrap());
+    assert_eq!(4, repository.reflog("HEAD").unwrap().len());
+}
+
+#[test]
+fn test_convert_all() {
+    use tempfile::TempDir;
+
+    if crate::helpers::where_is_binary(Path::new("pandoc")).is_none() {
+        return;
+    }
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let temp_destination_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    git2::Repository::init(&config.repository).unwrap();
+
+    let mut app = App::new(config).unwrap();
 
     app.create_and_execute_commands(vec![
-        Command::EditNoteSetContent {
-            path: note_path.to_path_buf(),
-            clear_tags: false,
-            add_tags: vec![],
-            content: note_content2.clone()
+        Command::AddNoteWithContent {
+            path: Path::new("2023/07/sample1").to_path_buf(),
+            tags: vec!["export".to_owned()],
+            content: "# Test1".to_owned()
+        },
+        Command::AddNoteWithContent {
+            path: Path::new("2023/08/sample2").to_path_buf(),
+            tags: vec!["export".to_owned()],
+            content: "# Test2".to_owned()
         },
+        Command::AddNoteWithContent {
+            path: Path::new("2024/07/sample3").to_path_buf(),
+            tags: vec![],
+            content: "# Test3".to_owned()
+        }
     ]).unwrap();
-    assert_eq!(note_content2, app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
-    assert_eq!(2, repository.reflog("HEAD").unwrap().len());
 
-    app.run(InputCommand::Edit {
-        path: note_path.to_owned(),
-        history: Some("HEAD~1".to_owned()),
-        clear_tags: false,
-        add_tags: vec![],
+    app.run(InputCommand::ConvertAll {
+        destination: temp_destination_dir.path().to_owned(),
+        format: "html".to_owned(),
+        filter: InputCommandFinder::Tag { tags: vec!["export".to_owned()] }
     }).unwrap();
-    assert_eq!(note_content, app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
-    assert_eq!(3, repository.reflog("HEAD").unwrap().len());
+    assert_eq!(true, temp_destination_dir.path().join("2023/07/sample1.html").exists());
+    assert_eq!(true, temp_destination_dir.path().join("2023/08/sample2.html").exists());
+    assert_eq!(false, temp_destination_dir.path().join("2024/07/sample3.html").exists());
 }
 
 #[test]
-fn test_undo() {
+fn test_move_dry_run() {
     use tempfile::TempDir;
 
     let temp_repository_dir = TempDir::new().unwrap();
     let config = create_test_config(&temp_repository_dir);
     let repository = git2::Repository::init(&config.repository).unwrap();
 
-    let note_path = Path::new("2023/07/sample");
-    let note_content1 = "Test1".to_owned();
-    let note_content2 = "Test2".to_owned();
+    let note1_path = Path::new("2023/07/sample1");
+    let note2_path = Path::new("2023/08/sample2");
+    let note3_path = Path::new("2024/07/sample3");
 
     let mut app = App::new(config).unwrap();
 
     app.create_and_execute_commands(vec![
         Command::AddNoteWithContent {
-            path: note_path.to_path_buf(),
+            path: note1_path.to_path_buf(),
             tags: vec![],
-            content: note_content1.clone()
+            content: "Test1".to_owned()
         },
+        Command::AddNoteWithContent {
+            path: note2_path.to_path_buf(),
+            tags: vec![],
+            content: "Test2".to_owned()
+        },
+        Command::AddNoteWithContent {
+            path: note3_path.to_path_buf(),
+            tags: vec![],
+            content: "Test3".to_owned()
+        }
     ]).unwrap();
-    assert_eq!(note_content1, app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
     assert_eq!(1, repository.reflog("HEAD").unwrap().len());
 
-    app.create_and_execute_commands(vec![
-        Command::EditNoteSetContent {
-            path: note_path.to_path_buf(),
-            clear_tags: false,
-            add_tags: vec![],
-            content: note_content2.clone()
-        },
-    ]).unwrap();
-    assert_eq!(note_content2, app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
-    assert_eq!(2, repository.reflog("HEAD").unwrap().len());
-    let commit_id = repository.reflog("HEAD").unwrap().get(0).unwrap().id_new();
+    let moves = app.preview_move(Path::new("2023").to_owned(), Path::new("2025").to_owned(), false).unwrap();
+    assert_eq!(
+        vec![
+            (Path::new("2023/07/sample1").to_owned(), Path::new("2025/07/sample1").to_owned()),
+            (Path::new("2023/08/sample2").to_owned(), Path::new("2025/08/sample2").to_owned())
+        ],
+        moves
+    );
 
-    app.run(InputCommand::Undo { commit: commit_id.to_string() }).unwrap();
-    assert_eq!(note_content1, app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
-    assert_eq!(3, repository.reflog("HEAD").unwrap().len());
+    let moves = app.preview_move(Path::new("202*/07").to_owned(), Path::new("2025").to_owned(), false).unwrap();
+    assert_eq!(
+        vec![
+            (Path::new("2023/07/sample1").to_owned(), Path::new("2025/sample1").to_owned()),
+            (Path::new("2024/07/sample3").to_owned(), Path::new("2025/sample3").to_owned())
+        ],
+        moves
+    );
+
+    app.run(InputCommand::Move { source: Path::new("2023").to_path_buf(), destination: Path::new("2025").to_path_buf(), force: false, merge: false, ignore_lock: false, dry_run: true }).unwrap();
+    assert_eq!(true, app.note_metadata_storage().unwrap().get_content(note1_path).is_ok());
+    assert_eq!(true, app.note_metadata_storage().unwrap().get_content(note2_path).is_ok());
+    assert_eq!(1, repository.reflog("HEAD").unwrap().len());
 }
 
 #[test]
-fn test_add_resource() {
+fn test_remove_dry_run() {
     use tempfile::TempDir;
 
     let temp_repository_dir = TempDir::new().unwrap();
     let config = create_test_config(&temp_repository_dir);
     let repository = git2::Repository::init(&config.repository).unwrap();
-    let resources_dir = config.resources_dir();
+
+    let note1_path = Path::new("2023/07/sample1");
+    let note2_path = Path::new("2023/08/sample2");
+    let note3_path = Path::new("2024/07/sample3");
 
     let mut app = App::new(config).unwrap();
 
-    app.run_until_completion(
-        InputCommand::Resource {
-            command: InputCommandResource::Add {
-                path: Path::new("testdata/resource1.txt").to_owned(),
-                destination: Path::new("resource1.txt").to_owned(),
-            },
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent {
+            path: note1_path.to_path_buf(),
+            tags: vec![],
+            content: "Test1".to_owned()
+        },
+        Command::AddNoteWithContent {
+            path: note2_path.to_path_buf(),
+            tags: vec![],
+            content: "Test2".to_owned()
+        },
+        Command::AddNoteWithContent {
+            path: note3_path.to_path_buf(),
+            tags: vec![],
+            content: "Test3".to_owned()
         }
-    ).unwrap();
+    ]).unwrap();
+    assert_eq!(1, repository.reflog("HEAD").unwrap().len());
 
+    let removes = app.preview_remove(Path::new("2023").to_owned(), true).unwrap();
     assert_eq!(
-        std::fs::read_to_string(Path::new("testdata/resource1.txt").to_owned()).unwrap(),
-        std::fs::read_to_string(resources_dir.join("resource1.txt")).unwrap(),
+        vec![note1_path.to_owned(), note2_path.to_owned()],
+        removes
     );
+
+    let removes = app.preview_remove(Path::new("202*/07").to_owned(), true).unwrap();
+    assert_eq!(
+        vec![note1_path.to_owned(), note3_path.to_owned()],
+        removes
+    );
+
+    app.run(InputCommand::Remove { path: Path::new("2023").to_path_buf(), recursive: true, dry_run: true, yes: false, force: false }).unwrap();
+    assert_eq!(true, app.note_metadata_storage().unwrap().get_content(note1_path).is_ok());
+    assert_eq!(true, app.note_metadata_storage().unwrap().get_content(note2_path).is_ok());
     assert_eq!(1, repository.reflog("HEAD").unwrap().len());
 }
 
 #[test]
-fn test_remove_resource() {
+fn test_remove() {
     use tempfile::TempDir;
 
     let temp_repository_dir = TempDir::new().unwrap();
     let config = create_test_config(&temp_repository_dir);
     let repository = git2::Repository::init(&config.repository).unwrap();
-    let resources_dir = config.resources_dir();
+
+    let note_path = Path::new("2023/07/sample");
+    let note_content = r#"Hello, World!
+
+``` python
+import numpy as np
+print(np.square(np.arange(0, 10)))
+```
+"#.to_string();
 
     let mut app = App::new(config).unwrap();
 
-    app.run_until_completion(
-        InputCommand::Resource {
-            command: InputCommandResource::Add {
-                path: Path::new("testdata/resource1.txt").to_owned(),
-                destination: Path::new("resource1.txt").to_owned(),
-            },
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent {
+            path: note_path.to_path_buf(),
+            tags: vec!["python".to_owned()],
+            content: note_content.clone()
         }
-    ).unwrap();
+    ]).unwrap();
+    assert_eq!(note_content, app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
+    assert_eq!(1, repository.reflog("HEAD").unwrap().len());
 
-    app.run_until_completion(
-        InputCommand::Resource {
-            command: InputCommandResource::Remove {
-                path: Path::new("resource1.txt").to_owned(),
-            },
+    app.run(InputCommand::Remove { path: note_path.to_owned(), recursive: false, dry_run: false, yes: false, force: false }).unwrap();
+    assert_eq!(false, app.note_metadata_storage().unwrap().get(note_path).is_some());
+    assert_eq!(false, app.note_metadata_storage().unwrap().get_content(note_path).is_ok());
+    assert_eq!(2, repository.reflog("HEAD").unwrap().len());
+}
+
+#[test]
+fn test_soft_delete() {
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let mut config = create_test_config(&temp_repository_dir);
+    config.soft_delete = true;
+    let repository = git2::Repository::init(&config.repository).unwrap();
+
+    let note_path = Path::new("2023/07/sample");
+    let note_content = "Hello, World!".to_owned();
+
+    let mut app = App::new(config).unwrap();
+
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent {
+            path: note_path.to_path_buf(),
+            tags: vec![],
+            content: note_content.clone()
         }
-    ).unwrap();
+    ]).unwrap();
+    let id = app.note_metadata_storage().unwrap().get_id(note_path).unwrap();
 
-    assert!(!resources_dir.join("resource1.txt").exists());
+    app.run(InputCommand::Remove { path: note_path.to_owned(), recursive: false, dry_run: false, yes: false, force: false }).unwrap();
+    assert_eq!(false, app.note_metadata_storage().unwrap().get(note_path).is_some());
     assert_eq!(2, repository.reflog("HEAD").unwrap().len());
-}
 
-fn create_test_config(temp_repository_dir: &tempfile::TempDir) -> Config {
+    let trashed_notes = app.trashed_notes().unwrap();
+    assert_eq!(1, trashed_notes.len());
+    assert_eq!(id, trashed_notes[0].id);
+    assert_eq!(note_path, trashed_notes[0].path);
+    assert_eq!(None, app.note_metadata_storage().unwrap().new_note_id_with(|| id));
+
+    let head_tree = repository.head().unwrap().peel_to_tree().unwrap();
+    assert_eq!(true, head_tree.get_path(&Path::new(".trash").join(format!("{}.md", id))).is_ok());
+    assert_eq!(true, head_tree.get_path(&Path::new(".notes").join(format!("{}.md", id))).is_err());
+
+    app.run(InputCommand::Restore { id: Some(id) }).unwrap();
+    assert_eq!(note_content, app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
+    assert_eq!(0, app.trashed_notes().unwrap().len());
+    assert_eq!(3, repository.reflog("HEAD").unwrap().len());
+
+    assert!(app.run(InputCommand::Restore { id: Some(id) }).is_err());
+
+    // Never overwrites an existing note in the trash
+    let (_, abs_trash_content_path) = NoteMetadataStorage::get_trash_storage_path(&repository.workdir().unwrap(), &id);
+    std::fs::write(&abs_trash_content_path, "Other").unwrap();
+    let result = app.run(InputCommand::Remove { path: note_path.to_owned(), recursive: false, dry_run: false, yes: false, force: false });
+    assert!(matches!(result, Err(AppError::Command(CommandError::FailedToRemoveNote(_)))), "{:?}", result.err());
+    assert_eq!("Other", std::fs::read_to_string(&abs_trash_content_path).unwrap());
+    assert_eq!(note_content, app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
+}
+
+#[test]
+fn test_soft_delete_restore_existing_path() {
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let mut config = create_test_config(&temp_repository_dir);
+    config.soft_delete = true;
+    let repository = git2::Repository::init(&config.repository).unwrap();
+
+    let note_path = Path::new("2023/07/sample");
+
+    let mut app = App::new(config).unwrap();
+
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent {
+            path: note_path.to_path_buf(),
+            tags: vec![],
+            content: "Test1".to_owned()
+        }
+    ]).unwrap();
+    let id = app.note_metadata_storage().unwrap().get_id(note_path).unwrap();
+
+    app.run(InputCommand::Remove { path: note_path.to_owned(), recursive: false, dry_run: false, yes: false, force: false }).unwrap();
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent {
+            path: note_path.to_path_buf(),
+            tags: vec![],
+            content: "Test2".to_owned()
+        }
+    ]).unwrap();
+    assert_eq!(3, repository.reflog("HEAD").unwrap().len());
+
+    assert!(matches!(app.run(InputCommand::Restore { id: Some(id) }), Err(AppError::Command(CommandError::NoteAlreadyExists(_)))));
+    assert_eq!("Test2", app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
+    assert_eq!(1, app.trashed_notes().unwrap().len());
+    assert_eq!(3, repository.reflog("HEAD").unwrap().len());
+}
+
+#[test]
+fn test_remove_recursive() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    let repository = git2::Repository::init(&config.repository).unwrap();
+
+    let note1_path = Path::new("2023/07/test1");
+    let note1_content = "Test1".to_owned();
+
+    let note2_path = Path::new("2023/07/test2");
+    let note2_content = "Test2".to_owned();
+
+    let mut app = App::new(config).unwrap();
+
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent {
+            path: note1_path.to_path_buf(),
+            tags: vec![],
+            content: note1_content.clone()
+        },
+        Command::AddNoteWithContent {
+            path: note2_path.to_path_buf(),
+            tags: vec![],
+            content: note2_content.clone()
+        }
+    ]).unwrap();
+    assert_eq!(note1_content, app.note_metadata_storage().unwrap().get_content(note1_path).unwrap());
+    assert_eq!(note2_content, app.note_metadata_storage().unwrap().get_content(note2_path).unwrap());
+    assert_eq!(1, repository.reflog("HEAD").unwrap().len());
+
+    let remove = |yes: bool| InputCommand::Remove { path: Path::new("2023").to_owned(), recursive: true, dry_run: false, yes, force: false };
+
+    // Without a terminal, aborts unless --yes is given
+    app.set_confirm(Box::new(|_| Ok(None)));
+    let result = app.run(remove(false));
+    assert!(matches!(result, Err(AppError::Input(message)) if message.starts_with("Remove 2 notes under '2023'?")));
+    assert_eq!(true, app.note_metadata_storage().unwrap().get(note1_path).is_some());
+    assert_eq!(1, repository.reflog("HEAD").unwrap().len());
+
+    // Declined on the terminal
+    let questions = Rc::new(RefCell::new(Vec::new()));
+    let questions_clone = questions.clone();
+    app.set_confirm(Box::new(move |question| {
+        questions_clone.borrow_mut().push(question.to_owned());
+        Ok(Some(false))
+    }));
+    app.run(remove(false)).unwrap();
+    assert_eq!(vec!["Remove 2 notes under '2023'?".to_owned()], *questions.borrow());
+    assert_eq!(true, app.note_metadata_storage().unwrap().get(note1_path).is_some());
+    assert_eq!(1, repository.reflog("HEAD").unwrap().len());
+
+    // Accepted on the terminal
+    app.set_confirm(Box::new(|_| Ok(Some(true))));
+    app.run(remove(false)).unwrap();
+    assert_eq!(false, app.note_metadata_storage().unwrap().get(note1_path).is_some());
+    assert_eq!(2, repository.reflog("HEAD").unwrap().len());
+
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent { path: note1_path.to_path_buf(), tags: vec![], content: note1_content.clone() },
+        Command::AddNoteWithContent { path: note2_path.to_path_buf(), tags: vec![], content: note2_content.clone() }
+    ]).unwrap();
+    assert_eq!(3, repository.reflog("HEAD").unwrap().len());
+
+    // --yes never asks
+    app.set_confirm(Box::new(|_| panic!("Should not ask")));
+    app.run(remove(true)).unwrap();
+    assert_eq!(false, app.note_metadata_storage().unwrap().get(note1_path).is_some());
+    assert_eq!(false, app.note_metadata_storage().unwrap().get_content(note1_path).is_ok());
+    assert_eq!(false, app.note_metadata_storage().unwrap().get(note2_path).is_some());
+    assert_eq!(false, app.note_metadata_storage().unwrap().get_content(note2_path).is_ok());
+    assert_eq!(4, repository.reflog("HEAD").unwrap().len());
+}
+
+#[test]
+fn test_remove_glob1() {
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    let repository = git2::Repository::init(&config.repository).unwrap();
+
+    let note1_path = Path::new("2023/07/sample1");
+    let note1_content = r#"Hello, World!
+
+``` python
+import numpy as np
+print(np.square(np.arange(0, 10)))
+```
+"#.to_string();
+
+    let note2_path = Path::new("2024/07/sample2");
+    let note2_content = r#"Hello, My World!
+
+``` python
+import numpy as np
+print(np.square(np.arange(0, 15)))
+```
+"#.to_string();
+
+    let mut app = App::new(config).unwrap();
+
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent {
+            path: note1_path.to_path_buf(),
+            tags: vec!["python".to_owned()],
+            content: note1_content.clone()
+        },
+        Command::AddNoteWithContent {
+            path: note2_path.to_path_buf(),
+            tags: vec!["python".to_owned()],
+            content: note2_content.clone()
+        }
+    ]).unwrap();
+    assert_eq!(note1_content, app.note_metadata_storage().unwrap().get_content(note1_path).unwrap());
+    assert_eq!(note2_content, app.note_metadata_storage().unwrap().get_content(note2_path).unwrap());
+    assert_eq!(1, repository.reflog("HEAD").unwrap().len());
+
+    app.run(InputCommand::Remove { path: Path::new("202*").to_path_buf(), recursive: true, dry_run: false, yes: true, force: false }).unwrap();
+    assert_eq!(false, app.note_metadata_storage().unwrap().get_content(note1_path).is_ok());
+    assert_eq!(false, app.note_metadata_storage().unwrap().get_content(note2_path).is_ok());
+    assert_eq!(2, repository.reflog("HEAD").unwrap().len());
+}
+
+#[test]
+fn test_remove_glob2() {
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    let repository = git2::Repository::init(&config.repository).unwrap();
+
+    let note1_path = Path::new("2023/07/sample1");
+    let note1_content = r#"Hello, World!
+
+``` python
+import numpy as np
+print(np.square(np.arange(0, 10)))
+```
+"#.to_string();
+
+    let note2_path = Path::new("2024/07/sample2");
+    let note2_content = r#"Hello, My World!
+
+``` python
+import numpy as np
+print(np.square(np.arange(0, 15)))
+```
+"#.to_string();
+
+    let mut app = App::new(config).unwrap();
+
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent {
+            path: note1_path.to_path_buf(),
+            tags: vec!["python".to_owned()],
+            content: note1_content.clone()
+        },
+        Command::AddNoteWithContent {
+            path: note2_path.to_path_buf(),
+            tags: vec!["python".to_owned()],
+            content: note2_content.clone()
+        }
+    ]).unwrap();
+    assert_eq!(note1_content, app.note_metadata_storage().unwrap().get_content(note1_path).unwrap());
+    assert_eq!(note2_content, app.note_metadata_storage().unwrap().get_content(note2_path).unwrap());
+    assert_eq!(1, repository.reflog("HEAD").unwrap().len());
+
+    app.run(InputCommand::ChangeWorkingDirectory { path: Path::new("2023").to_owned() }).unwrap();
+    app.run(InputCommand::Remove { path: Path::new("*").to_path_buf(), recursive: true, dry_run: false, yes: true, force: false }).unwrap();
+    assert_eq!(false, app.note_metadata_storage().unwrap().get_content(note1_path).is_ok());
+    assert_eq!(true, app.note_metadata_storage().unwrap().get_content(note2_path).is_ok());
+    assert_eq!(2, repository.reflog("HEAD").unwrap().len());
+}
+
+#[test]
+fn test_change_tags() {
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    let repository = git2::Repository::init(&config.repository).unwrap();
+
+    let note_path = Path::new("2023/07/sample");
+    let note_content = r#"Hello, World!
+
+``` python
+import numpy as np
+print(np.square(np.arange(0, 10)))
+```
+"#.to_string();
+
+    let mut app = App::new(config).unwrap();
+
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent {
+            path: note_path.to_path_buf(),
+            tags: vec!["python".to_owned()],
+            content: note_content.clone()
+        }
+    ]).unwrap();
+    assert_eq!(note_content, app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
+    assert_eq!(1, repository.reflog("HEAD").unwrap().len());
+
+    app.create_and_execute_commands(vec![
+        Command::EditNoteSetContent {
+            path: note_path.to_path_buf(),
+            clear_tags: false,
+            add_tags: vec!["snippet".to_owned()],
+            content: note_content.clone(),
+            force: false
+        }
+    ]).unwrap();
+    assert_eq!(note_content, app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
+    assert_eq!(vec!["python".to_owned(), "snippet".to_owned()], app.note_metadata_storage().unwrap().get(note_path).unwrap().tags);
+    assert_eq!(2, repository.reflog("HEAD").unwrap().len());
+}
+
+#[test]
+fn test_edit() {
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    let repository = git2::Repository::init(&config.repository).unwrap();
+
+    let note_path = Path::new("2023/07/sample");
+    let note_content = r#"Hello, World!
+
+``` python
+xs = list(range(0, 10))
+print([x * x for x in xs])
+```
+"#.to_string();
+    let note_content2 = r#"Hello, World!
+
+``` python
+xs = list(range(0, 15))
+print([x * x for x in xs])
+```
+"#.to_string();
+
+    let mut app = App::new(config).unwrap();
+
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent {
+            path: note_path.to_path_buf(),
+            tags: vec![],
+            content: note_content.clone()
+        },
+    ]).unwrap();
+    assert_eq!(note_content, app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
+    assert_eq!(1, repository.reflog("HEAD").unwrap().len());
+    assert_eq!(vec!["snippet".to_owned(), "python".to_owned()], app.note_metadata_storage().unwrap().get(note_path).unwrap().tags);
+
+    app.create_and_execute_commands(vec![
+        Command::EditNoteSetContent {
+            path: note_path.to_path_buf(),
+            clear_tags: false,
+            add_tags: vec![],
+            content: note_content2.clone(),
+            force: false
+        },
+    ]).unwrap();
+    assert_eq!(note_content2, app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
+    assert_eq!(2, repository.reflog("HEAD").unwrap().len());
+}
+
+#[test]
+fn test_edit_note_id() {
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    let repository = git2::Repository::init(&config.repository).unwrap();
+
+    let note_path = Path::new("2023/07/sample");
+    let note_content = r#"Hello, World!
+
+``` python
+xs = list(range(0, 10))
+print([x * x for x in xs])
+```
+"#.to_string();
+    let note_content2 = r#"Hello, World!
+
+``` python
+xs = list(range(0, 15))
+print([x * x for x in xs])
+```
+"#.to_string();
+
+    let mut app = App::new(config).unwrap();
+
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent {
+            path: note_path.to_path_buf(),
+            tags: vec![],
+            content: note_content.clone()
+        },
+    ]).unwrap();
+    assert_eq!(note_content, app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
+    assert_eq!(1, repository.reflog("HEAD").unwrap().len());
+    assert_eq!(vec!["snippet".to_owned(), "python".to_owned()], app.note_metadata_storage().unwrap().get(note_path).unwrap().tags);
+
+    let note_id = app.note_metadata_storage().unwrap().get_id(note_path).unwrap();
+
+    app.create_and_execute_commands(vec![
+        Command::EditNoteSetContent {
+            path: Path::new(&note_id.to_string()).to_owned(),
+            clear_tags: false,
+            add_tags: vec![],
+            content: note_content2.clone(),
+            force: false
+        },
+    ]).unwrap();
+    assert_eq!(note_content2, app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
+    assert_eq!(2, repository.reflog("HEAD").unwrap().len());
+}
+
+#[test]
+fn test_edit_with_editor() {
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let mut config = create_test_config(&temp_repository_dir);
+    config.allow_stdin = false;
+    let repository = git2::Repository::init(&config.repository).unwrap();
+
+    let note_path = Path::new("2023/07/sample");
+    let note_content = r#"Hello, World!
+
+``` python
+xs = list(range(0, 10))
+print([x * x for x in xs])
+```
+"#.to_string();
+    let note_content2 = r#"Hello, World!
+
+``` python
+xs = list(range(0, 15))
+print([x * x for x in xs])
+```
+"#.to_string();
+
+    let note_content2_clone = note_content2.clone();
+    let mut app = App::with_custom(config, move |config, repository| {
+        CommandInterpreter::with_launch_editor(
+            config,
+            repository,
+            Box::new(move |_, path, _| {
+                std::fs::write(path, &note_content2_clone).map_err(|err| CommandError::IO(err))?;
+                Ok(EditorOutput::default())
+            })
+        )
+    }).unwrap();
+
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent {
+            path: note_path.to_path_buf(),
+            tags: vec![],
+            content: note_content.clone()
+        },
+    ]).unwrap();
+    assert_eq!(note_content, app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
+    assert_eq!(1, repository.reflog("HEAD").unwrap().len());
+    assert_eq!(vec!["snippet".to_owned(), "python".to_owned()], app.note_metadata_storage().unwrap().get(note_path).unwrap().tags);
+
+    app.run(InputCommand::Edit {
+        path: note_path.to_owned(),
+        history: None,
+        clear_tags: false,
+        add_tags: vec![],
+        create: false,
+        force: false,
+        strip_output: false
+    }).unwrap();
+    assert_eq!(note_content2, app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
+    assert_eq!(2, repository.reflog("HEAD").unwrap().len());
+}
+
+#[test]
+fn test_edit_with_editor_and_history() {
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let mut config = create_test_config(&temp_repository_dir);
+    config.allow_stdin = false;
+    let repository = git2::Repository::init(&config.repository).unwrap();
+
+    let note_path = Path::new("2023/07/sample");
+    let note_content = r#"Hello, World!
+
+``` python
+xs = list(range(0, 10))
+print([x * x for x in xs])
+```
+"#.to_string();
+    let note_content2 = r#"Hello, World!
+
+``` python
+xs = list(range(0, 15))
+print([x * x for x in xs])
+```
+"#.to_string();
+
+    let mut app = App::with_custom(config, move |config, repository| {
+        CommandInterpreter::with_launch_editor(
+            config,
+            repository,
+            Box::new(move |_, _, _| {
+                Ok(EditorOutput::default())
+            })
+        )
+    }).unwrap();
+
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent {
+            path: note_path.to_path_buf(),
+            tags: vec![],
+            content: note_content.clone()
+        },
+    ]).unwrap();
+    assert_eq!(note_content, app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
+    assert_eq!(1, repository.reflog("HEAD").unwrap().len());
+    assert_eq!(vec!["snippet".to_owned(), "python".to_owned()], app.note_metadata_storage().unwrap().get(note_path).unwrap().tags);
+
+    app.create_and_execute_commands(vec![
+        Command::EditNoteSetContent {
+            path: note_path.to_path_buf(),
+            clear_tags: false,
+            add_tags: vec![],
+            content: note_content2.clone(),
+            force: false
+        },
+    ]).unwrap();
+    assert_eq!(note_content2, app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
+    assert_eq!(2, repository.reflog("HEAD").unwrap().len());
+
+    app.run(InputCommand::Edit {
+        path: note_path.to_owned(),
+        history: Some("HEAD~1".to_owned()),
+        clear_tags: false,
+        add_tags: vec![],
+        create: false,
+        force: false,
+        strip_output: false
+    }).unwrap();
+    assert_eq!(note_content, app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
+    assert_eq!(3, repository.reflog("HEAD").unwrap().len());
+}
+
+#[test]
+fn test_edit_create() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let mut config = create_test_config(&temp_repository_dir);
+    config.allow_stdin = false;
+    let repository = git2::Repository::init(&config.repository).unwrap();
+
+    let note_path = Path::new("2023/07/sample");
+
+    let editor_content = Rc::new(RefCell::new("Hello, World!".to_owned()));
+    let editor_content_clone = editor_content.clone();
+    let mut app = App::with_custom(config, move |config, repository| {
+        CommandInterpreter::with_launch_editor(
+            config,
+            repository,
+            Box::new(move |_, path, _| {
+                std::fs::write(path, editor_content_clone.borrow().as_str()).map_err(|err| CommandError::IO(err))?;
+                Ok(EditorOutput::default())
+            })
+        )
+    }).unwrap();
+
+    // Creates
+    app.run(InputCommand::Edit {
+        path: note_path.to_owned(),
+        history: None,
+        clear_tags: false,
+        add_tags: vec!["scratch".to_owned()],
+        create: true,
+        force: false,
+        strip_output: false
+    }).unwrap();
+    assert_eq!("Hello, World!", app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
+    assert_eq!(vec!["scratch".to_owned()], app.note_metadata_storage().unwrap().get(note_path).unwrap().tags);
+    assert_eq!(1, repository.reflog("HEAD").unwrap().len());
+
+    // Edits
+    *editor_content.borrow_mut() = "Hello, New World!".to_owned();
+    app.run(InputCommand::Edit {
+        path: note_path.to_owned(),
+        history: None,
+        clear_tags: false,
+        add_tags: vec!["edited".to_owned()],
+        create: true,
+        force: false,
+        strip_output: false
+    }).unwrap();
+    assert_eq!("Hello, New World!", app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
+    assert_eq!(vec!["scratch".to_owned(), "edited".to_owned()], app.note_metadata_storage().unwrap().get(note_path).unwrap().tags);
+    assert_eq!(2, repository.reflog("HEAD").unwrap().len());
+
+    // Edits by id
+    let note_id = app.note_metadata_storage().unwrap().get_id(note_path).unwrap();
+    *editor_content.borrow_mut() = "Hello, Id!".to_owned();
+    app.run(InputCommand::Edit {
+        path: Path::new(&note_id.to_string()).to_owned(),
+        history: None,
+        clear_tags: false,
+        add_tags: vec![],
+        create: true,
+        force: false,
+        strip_output: false
+    }).unwrap();
+    assert_eq!("Hello, Id!", app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
+    assert_eq!(1, app.note_metadata_storage().unwrap().notes().count());
+    assert_eq!(3, repository.reflog("HEAD").unwrap().len());
+}
+
+#[test]
+fn test_prune_empty() {
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    let repository = git2::Repository::init(&config.repository).unwrap();
+
+    let empty_note_path = Path::new("2023/07/empty");
+    let whitespace_note_path = Path::new("2023/07/whitespace");
+    let note_path = Path::new("2023/07/sample");
+
+    let mut app = App::new(config).unwrap();
+
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent {
+            path: empty_note_path.to_path_buf(),
+            tags: vec![],
+            content: String::new()
+        },
+        Command::AddNoteWithContent {
+            path: whitespace_note_path.to_path_buf(),
+            tags: vec![],
+            content: " \n\t\n".to_owned()
+        },
+        Command::AddNoteWithContent {
+            path: note_path.to_path_buf(),
+            tags: vec![],
+            content: "Hello, World!".to_owned()
+        }
+    ]).unwrap();
+    assert_eq!(1, repository.reflog("HEAD").unwrap().len());
+
+    app.set_confirm(Box::new(|_| Ok(None)));
+    let result = app.run(InputCommand::PruneEmpty { yes: false });
+    assert!(matches!(result, Err(AppError::Input(_))));
+
+    app.set_confirm(Box::new(|_| Ok(Some(false))));
+    app.run(InputCommand::PruneEmpty { yes: false }).unwrap();
+    assert_eq!(true, app.note_metadata_storage().unwrap().get_content(empty_note_path).is_ok());
+    assert_eq!(1, repository.reflog("HEAD").unwrap().len());
+
+    app.run(InputCommand::PruneEmpty { yes: true }).unwrap();
+    assert_eq!(false, app.note_metadata_storage().unwrap().get_content(empty_note_path).is_ok());
+    assert_eq!(false, app.note_metadata_storage().unwrap().get_content(whitespace_note_path).is_ok());
+    assert_eq!("Hello, World!", app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
+    assert_eq!(2, repository.reflog("HEAD").unwrap().len());
+}
+
+#[test]
+fn test_find_sort() {
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let mut config = create_test_config(&temp_repository_dir);
+    config.created_from_path = true;
+    git2::Repository::init(&config.repository).unwrap();
+
+    let mut app = App::new(config).unwrap();
+
+    for (path, content) in [("0/a", "# Delta"), ("2023/07/01/b", "# Gamma"), ("2023/07/02/c", "No heading")] {
+        app.create_and_execute_commands(vec![
+            Command::AddNoteWithContent {
+                path: Path::new(path).to_path_buf(),
+                tags: vec![],
+                content: content.to_owned()
+            }
+        ]).unwrap();
+    }
+
+    app.create_and_execute_commands(vec![
+        Command::EditNoteSetContent {
+            path: Path::new("2023/07/01/b").to_path_buf(),
+            clear_tags: false,
+            add_tags: vec![],
+            content: "# Gamma\nUpdated".to_owned(),
+            force: false
+        }
+    ]).unwrap();
+
+    let note_metadata_storage = app.note_metadata_storage().unwrap();
+    let sorted = |sort_by: SortBy, reverse: bool| {
+        let mut results = note_metadata_storage.notes().collect::<Vec<_>>();
+        sort_note_metadata_results(note_metadata_storage, &mut results, sort_by, reverse);
+        results.iter().map(|note_metadata| note_metadata.path.to_str().unwrap().to_owned()).collect::<Vec<_>>()
+    };
+
+    assert_eq!(vec!["0/a", "2023/07/01/b", "2023/07/02/c"], sorted(SortBy::Path, false));
+    assert_eq!(vec!["2023/07/02/c", "2023/07/01/b", "0/a"], sorted(SortBy::Path, true));
+    assert_eq!(vec!["2023/07/01/b", "2023/07/02/c", "0/a"], sorted(SortBy::Created, false));
+    assert_eq!(vec!["0/a", "2023/07/02/c", "2023/07/01/b"], sorted(SortBy::Updated, false));
+    assert_eq!(vec!["2023/07/02/c", "0/a", "2023/07/01/b"], sorted(SortBy::Title, false));
+
+    let mut by_id = note_metadata_storage.notes().collect::<Vec<_>>();
+    by_id.sort_by_key(|note_metadata| note_metadata.id.to_string());
+    let by_id = by_id.iter().map(|note_metadata| note_metadata.path.to_str().unwrap().to_owned()).collect::<Vec<_>>();
+    assert_eq!(by_id, sorted(SortBy::Id, false));
+
+    app.run(InputCommand::Finder {
+        interactive: None,
+        sort: SortBy::Title,
+        reverse: true,
+        format: OutputFormat::Text,
+        limit: None,
+        then: None,
+        command: InputCommandFinder::Name { name: RegexMatcher::new(".*"), here: false, global: false, clauses: vec![] }
+    }).unwrap();
+}
+
+#[test]
+fn test_find_here() {
+    use structopt::StructOpt;
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    git2::Repository::init(&config.repository).unwrap();
+
+    let mut app = App::new(config).unwrap();
+
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent {
+            path: Path::new("2023/07/sample").to_path_buf(),
+            tags: vec![],
+            content: "Test1".to_owned()
+        },
+        Command::AddNoteWithContent {
+            path: Path::new("2024/01/sample").to_path_buf(),
+            tags: vec![],
+            content: "Test2".to_owned()
+        }
+    ]).unwrap();
+
+    app.set_working_dir(Path::new("2023"));
+
+    let find = |args: &[&str]| {
+        InputCommand::from_iter_safe(["gitnotes", "find", "name", "sample"].iter().chain(args.iter())).unwrap()
+    };
+
+    assert_eq!(Some(1), app.run_until_completion(find(&["--here"])).unwrap());
+    assert_eq!(Some(2), app.run_until_completion(find(&[])).unwrap());
+    assert_eq!(Some(2), app.run_until_completion(find(&["--here", "--global"])).unwrap());
+
+    app.set_working_dir(Path::new(""));
+    assert_eq!(Some(2), app.run_until_completion(find(&["--here"])).unwrap());
+}
+
+#[test]
+fn test_find_tag_glob() {
+    use structopt::StructOpt;
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    git2::Repository::init(&config.repository).unwrap();
+
+    let mut app = App::new(config).unwrap();
+
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent {
+            path: Path::new("2023/07/sample1").to_path_buf(),
+            tags: vec!["python".to_owned(), "snippet".to_owned()],
+            content: "Test1".to_owned()
+        },
+        Command::AddNoteWithContent {
+            path: Path::new("2023/07/sample2").to_path_buf(),
+            tags: vec!["pytest".to_owned()],
+            content: "Test2".to_owned()
+        },
+        Command::AddNoteWithContent {
+            path: Path::new("2023/07/sample3").to_path_buf(),
+            tags: vec!["rust".to_owned()],
+            content: "Test3".to_owned()
+        }
+    ]).unwrap();
+
+    let find = |args: &[&str]| {
+        InputCommand::from_iter_safe(["gitnotes", "find", "tag"].iter().chain(args.iter())).unwrap()
+    };
+
+    assert_eq!(Some(2), app.run_until_completion(find(&["py*"])).unwrap());
+    assert_eq!(Some(1), app.run_until_completion(find(&["py*", "snippet"])).unwrap());
+    assert_eq!(Some(1), app.run_until_completion(find(&["python"])).unwrap());
+    assert_eq!(Some(0), app.run_until_completion(find(&["py"])).unwrap());
+}
+
+#[test]
+fn test_find_clauses() {
+    use structopt::StructOpt;
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    git2::Repository::init(&config.repository).unwrap();
+
+    let mut app = App::new(config).unwrap();
+
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent {
+            path: Path::new("2023/07/sample").to_path_buf(),
+            tags: vec!["python".to_owned()],
+            content: "Test1".to_owned()
+        },
+        Command::AddNoteWithContent {
+            path: Path::new("2023/07/other").to_path_buf(),
+            tags: vec!["python".to_owned()],
+            content: "Test2".to_owned()
+        },
+        Command::AddNoteWithContent {
+            path: Path::new("2024/01/sample").to_path_buf(),
+            tags: vec!["rust".to_owned()],
+            content: "Test3".to_owned()
+        }
+    ]).unwrap();
+
+    let find = |args: &[&str]| {
+        InputCommand::from_iter_safe(["gitnotes", "find"].iter().chain(args.iter())).unwrap()
+    };
+
+    assert_eq!(Some(1), app.run_until_completion(find(&["tag", "python", "and", "name", "sample"])).unwrap());
+    assert_eq!(Some(1), app.run_until_completion(find(&["name", "sample", "and", "tag", "python"])).unwrap());
+    assert_eq!(Some(3), app.run_until_completion(find(&["tag", "python", "or", "name", "sample"])).unwrap());
+    assert_eq!(Some(2), app.run_until_completion(find(&["--limit", "2", "tag", "python", "or", "name", "sample"])).unwrap());
+    assert_eq!(Some(0), app.run_until_completion(find(&["tag", "python", "or", "tag", "rust", "and", "created", "2000"])).unwrap());
+    assert!(matches!(app.run_until_completion(find(&["name", "sample", "tag", "python"])), Err(AppError::Input(_))));
+    assert!(matches!(app.run_until_completion(find(&["tag", "python", "and", "wrong", "sample"])), Err(AppError::Input(_))));
+
+    assert_eq!(Some(2), app.run_until_completion(find(&["expr", "tag:python OR tag:go"])).unwrap());
+    assert_eq!(Some(1), app.run_until_completion(find(&["expr", "tag:python AND NOT name:other"])).unwrap());
+    assert_eq!(Some(2), app.run_until_completion(find(&["expr", "name:sample", "and", "(tag:rust", "or", "tag:python)"])).unwrap());
+    assert_eq!(Some(1), app.run_until_completion(find(&["expr", "NOT (tag:python)"])).unwrap());
+    assert!(matches!(app.run_until_completion(find(&["expr", "tag:python AND"])), Err(AppError::Input(_))));
+
+    // Literal 'and'/'or' tags are escaped with '--'
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent { path: Path::new("2024/01/logic").to_path_buf(), tags: vec!["and".to_owned(), "or".to_owned()], content: "Test4".to_owned() }
+    ]).unwrap();
+    assert_eq!(Some(1), app.run_until_completion(find(&["tag", "--", "and"])).unwrap());
+    assert_eq!(Some(1), app.run_until_completion(find(&["tag", "and", "--", "or"])).unwrap());
+    assert_eq!(Some(1), app.run_until_completion(find(&["name", "logic", "and", "tag", "--", "or", "--", "and"])).unwrap());
+    assert_eq!(Some(0), app.run_until_completion(find(&["tag", "python", "and", "tag", "--", "or"])).unwrap());
+    assert_eq!(Some(4), app.run_until_completion(find(&["tag", "python", "or", "tag", "--", "and", "or", "tag", "rust"])).unwrap());
+
+    // Options are not supported within the clauses
+    assert_eq!(Some(1), app.run_until_completion(find(&["name", "sample", "--here", "and", "tag", "python"])).unwrap());
+    assert!(matches!(app.run_until_completion(find(&["name", "sample", "and", "name", "other", "--here"])), Err(AppError::Input(_))));
+    assert!(matches!(app.run_until_completion(find(&["tag", "python", "and", "name", "other", "--global"])), Err(AppError::Input(_))));
+    assert!(matches!(app.run_until_completion(find(&["tag", "python", "--here"])), Err(AppError::Input(_))));
+}
+
+#[test]
+fn test_find_content() {
+    use structopt::StructOpt;
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    git2::Repository::init(&config.repository).unwrap();
+
+    let mut app = App::new(config).unwrap();
+
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent {
+            path: Path::new("2023/07/sample").to_path_buf(),
+            tags: vec!["python".to_owned()],
+            content: "Hello, World!\nprint(x)".to_owned()
+        },
+        Command::AddNoteWithContent {
+            path: Path::new("2023/07/other").to_path_buf(),
+            tags: vec!["rust".to_owned()],
+            content: "println!(\"Hello, World!\")".to_owned()
+        }
+    ]).unwrap();
+
+    let find = |args: &[&str]| {
+        InputCommand::from_iter_safe(["gitnotes", "find"].iter().chain(args.iter())).unwrap()
+    };
+
+    assert_eq!(Some(2), app.run_until_completion(find(&["content", "Hello"])).unwrap());
+    assert_eq!(Some(0), app.run_until_completion(find(&["content", "^print\\("])).unwrap());
+    assert_eq!(Some(1), app.run_until_completion(find(&["content", "(?m)^print\\("])).unwrap());
+    assert_eq!(Some(1), app.run_until_completion(find(&["content", "Hello", "and", "tag", "rust"])).unwrap());
+    assert_eq!(Some(0), app.run_until_completion(find(&["content", "Goodbye"])).unwrap());
+}
+
+#[test]
+fn test_normalize_content() {
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let mut config = create_test_config(&temp_repository_dir);
+    config.normalize_content = true;
+    git2::Repository::init(&config.repository).unwrap();
+
+    let note_path = Path::new("2023/07/sample");
+
+    let mut app = App::new(config).unwrap();
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent { path: note_path.to_path_buf(), tags: vec![], content: "\u{feff}# Hello\r\nWorld\r\n".to_owned() }
+    ]).unwrap();
+    assert_eq!("# Hello\nWorld\n", app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
+
+    app.create_and_execute_commands(vec![
+        Command::EditNoteSetContent { path: note_path.to_path_buf(), clear_tags: false, add_tags: vec![], content: "\u{feff}Updated\r\n".to_owned(), force: false }
+    ]).unwrap();
+    assert_eq!("Updated\n", app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
+}
+
+#[test]
+fn test_find_then() {
+    use structopt::StructOpt;
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    let repository = git2::Repository::init(&config.repository).unwrap();
+
+    let mut app = App::new(config).unwrap();
+
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent {
+            path: Path::new("2023/07/sample").to_path_buf(),
+            tags: vec!["draft".to_owned()],
+            content: "Test1".to_owned()
+        },
+        Command::AddNoteWithContent {
+            path: Path::new("2023/08/other").to_path_buf(),
+            tags: vec!["draft".to_owned()],
+            content: "Test2".to_owned()
+        },
+        Command::AddNoteWithContent {
+            path: Path::new("2023/08/done").to_path_buf(),
+            tags: vec![],
+            content: "Test3".to_owned()
+        },
+        Command::AddNoteWithContent {
+            path: Path::new("archive/old").to_path_buf(),
+            tags: vec![],
+            content: "Test4".to_owned()
+        }
+    ]).unwrap();
+
+    let find = |args: &[&str]| {
+        InputCommand::from_iter_safe(["gitnotes", "find"].iter().chain(args.iter())).unwrap()
+    };
+
+    assert_eq!(Some(2), app.run_until_completion(find(&["--then", "mv {} archive/", "tag", "draft"])).unwrap());
+    assert_eq!(2, repository.reflog("HEAD").unwrap().len());
+
+    let mut paths = app.note_metadata_storage().unwrap().notes().map(|note_metadata| note_metadata.path.to_str().unwrap().to_owned()).collect::<Vec<_>>();
+    paths.sort();
+    assert_eq!(vec!["2023/08/done", "archive/old", "archive/other", "archive/sample"], paths);
+
+    assert!(matches!(app.run_until_completion(find(&["--then", "wrong {}", "tag", "draft"])), Err(AppError::Input(_))));
+
+    // The second move fails, which resets the first one
+    let err = app.run_until_completion(find(&["--then", "mv {} 2023/09/moved", "name", "^archive/(other|sample)$"])).err().unwrap();
+    assert!(matches!(err, AppError::Command(_)), "{:?}", err);
+    assert_eq!(2, repository.reflog("HEAD").unwrap().len());
+
+    let mut paths = app.note_metadata_storage().unwrap().notes().map(|note_metadata| note_metadata.path.to_str().unwrap().to_owned()).collect::<Vec<_>>();
+    paths.sort();
+    assert_eq!(vec!["2023/08/done", "archive/old", "archive/other", "archive/sample"], paths);
+}
+
+#[test]
+fn test_signed_commit() {
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let temp_gpg_dir = TempDir::new().unwrap();
+    let gpg_program = temp_gpg_dir.path().join("fake-gpg");
+    std::fs::write(&gpg_program, "#!/bin/sh\ncat > /dev/null\nprintf -- '-----BEGIN PGP SIGNATURE-----\\nsigned by %s\\n-----END PGP SIGNATURE-----\\n' \"$3\"\n").unwrap();
+    std::fs::set_permissions(&gpg_program, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let mut config = create_test_config(&temp_repository_dir);
+    config.signing_key = Some("ABCDEF".to_owned());
+    config.gpg_program = gpg_program.to_str().unwrap().to_owned();
+    let repository = git2::Repository::init(&config.repository).unwrap();
+
+    let mut app = App::new(config).unwrap();
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent { path: Path::new("2023/07/sample").to_path_buf(), tags: vec![], content: "Test1".to_owned() }
+    ]).unwrap();
+    app.create_and_execute_commands(vec![
+        Command::EditNoteSetContent { path: Path::new("2023/07/sample").to_path_buf(), clear_tags: false, add_tags: vec![], content: "Test2".to_owned(), force: false }
+    ]).unwrap();
+    assert_eq!(2, repository.reflog("HEAD").unwrap().len());
+    assert_eq!("Test2", app.note_metadata_storage().unwrap().get_content(Path::new("2023/07/sample")).unwrap());
+
+    let head = repository.head().unwrap().peel_to_commit().unwrap();
+    assert_eq!(1, head.parent_count());
+    let (signature, _) = repository.extract_signature(&head.id(), None).unwrap();
+    assert_eq!("-----BEGIN PGP SIGNATURE-----\nsigned by ABCDEF\n-----END PGP SIGNATURE-----\n", signature.as_str().unwrap());
+}
+
+#[test]
+fn test_signed_commit_failure() {
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let mut config = create_test_config(&temp_repository_dir);
+    config.signing_key = Some("ABCDEF".to_owned());
+    config.gpg_program = "false".to_owned();
+    let repository = git2::Repository::init(&config.repository).unwrap();
+
+    let mut app = App::new(config).unwrap();
+    let err = app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent { path: Path::new("2023/07/sample").to_path_buf(), tags: vec![], content: "Test1".to_owned() }
+    ]).err().unwrap();
+    assert!(matches!(err, AppError::Command(CommandError::FailedToCommit(_))), "{:?}", err);
+    assert!(repository.head().is_err());
+}
+
+#[test]
+fn test_pull_signed_merge() {
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile::TempDir;
+
+    let temp_gpg_dir = TempDir::new().unwrap();
+    let gpg_program = temp_gpg_dir.path().join("fake-gpg");
+    std::fs::write(&gpg_program, "#!/bin/sh\ncat > /dev/null\nprintf -- '-----BEGIN PGP SIGNATURE-----\\nsigned by %s\\n-----END PGP SIGNATURE-----\\n' \"$3\"\n").unwrap();
+    std::fs::set_permissions(&gpg_program, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let mut config = create_test_config(&temp_repository_dir);
+    let repository = git2::Repository::init(&config.repository).unwrap();
+
+    let mut app = App::new(config.clone()).unwrap();
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent { path: Path::new("2023/07/first").to_path_buf(), tags: vec![], content: "First".to_owned() }
+    ]).unwrap();
+
+    let temp_remote_dir = TempDir::new().unwrap();
+    let remote_config = create_test_config(&temp_remote_dir);
+    git2::Repository::clone(temp_repository_dir.path().to_str().unwrap(), &remote_config.repository).unwrap();
+    let mut remote_app = App::new(remote_config).unwrap();
+    remote_app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent { path: Path::new("2023/07/remote").to_path_buf(), tags: vec![], content: "Remote".to_owned() }
+    ]).unwrap();
+
+    config.signing_key = Some("ABCDEF".to_owned());
+    config.gpg_program = gpg_program.to_str().unwrap().to_owned();
+    let mut app = App::new(config).unwrap();
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent { path: Path::new("2023/07/local").to_path_buf(), tags: vec![], content: "Local".to_owned() }
+    ]).unwrap();
+    let local_head_id = repository.head().unwrap().peel_to_commit().unwrap().id();
+
+    repository.remote("origin", temp_remote_dir.path().to_str().unwrap()).unwrap();
+    repository.config().unwrap().set_str("user.name", "Test").unwrap();
+    repository.config().unwrap().set_str("user.email", "test@test.com").unwrap();
+    let branch = repository.head().unwrap().shorthand().unwrap().to_owned();
+    app.pull("origin", &branch, false).unwrap();
+
+    let head = repository.head().unwrap().peel_to_commit().unwrap();
+    assert_eq!(2, head.parent_count());
+    assert_eq!(local_head_id, head.parent_id(0).unwrap());
+    let (signature, _) = repository.extract_signature(&head.id(), None).unwrap();
+    assert_eq!("-----BEGIN PGP SIGNATURE-----\nsigned by ABCDEF\n-----END PGP SIGNATURE-----\n", signature.as_str().unwrap());
+    assert_eq!("Remote", app.note_metadata_storage().unwrap().get_content(Path::new("2023/07/remote")).unwrap());
+}
+
+#[test]
+fn test_signed_commit_stale_head() {
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile::TempDir;
+    use crate::git_helpers;
+
+    let temp_gpg_dir = TempDir::new().unwrap();
+    let gpg_program = temp_gpg_dir.path().join("fake-gpg");
+    std::fs::write(&gpg_program, "#!/bin/sh\ncat > /dev/null\necho signature\n").unwrap();
+    std::fs::set_permissions(&gpg_program, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let repository = git2::Repository::init(temp_repository_dir.path()).unwrap();
+    let signature = git2::Signature::now("Test", "test@test.com").unwrap();
+    let tree = repository.find_tree(repository.index().unwrap().write_tree().unwrap()).unwrap();
+    let signing = || Some(git_helpers::CommitSigning { signing_key: "ABCDEF", gpg_program: gpg_program.to_str().unwrap() });
+
+    let first_id = git_helpers::create_commit(&repository, &signature, "First", &tree, &[], signing()).unwrap();
+    let first = repository.find_commit(first_id).unwrap();
+    let second_id = git_helpers::create_commit(&repository, &signature, "Second", &tree, &[&first], signing()).unwrap();
+    assert_eq!(second_id, repository.head().unwrap().target().unwrap());
+
+    // HEAD no longer points to the parent, so it must not be moved
+    assert!(git_helpers::create_commit(&repository, &signature, "Stale", &tree, &[&first], signing()).is_err());
+    assert_eq!(second_id, repository.head().unwrap().target().unwrap());
+    assert!(git_helpers::create_commit(&repository, &signature, "Initial", &tree, &[], signing()).is_err());
+}
+
+#[test]
+fn test_size_report() {
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    git2::Repository::init(&config.repository).unwrap();
+
+    let mut app = App::new(config).unwrap();
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent { path: Path::new("2023/07/small").to_path_buf(), tags: vec![], content: "a".repeat(10) },
+        Command::AddNoteWithContent { path: Path::new("2023/07/large").to_path_buf(), tags: vec![], content: "a".repeat(1000) },
+        Command::AddNoteWithContent { path: Path::new("2023/07/medium").to_path_buf(), tags: vec![], content: "a".repeat(100) },
+        Command::AddNoteWithContent { path: Path::new("2023/07/medium2").to_path_buf(), tags: vec![], content: "a".repeat(100) }
+    ]).unwrap();
+
+    let sizes = |top: Option<usize>, app: &mut App| {
+        note_sizes(app.note_metadata_storage().unwrap(), top)
+            .unwrap()
+            .into_iter()
+            .map(|(note_metadata, size)| (note_metadata.path.to_str().unwrap().to_owned(), size))
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        vec![
+            ("2023/07/large".to_owned(), 1000),
+            ("2023/07/medium".to_owned(), 100),
+            ("2023/07/medium2".to_owned(), 100),
+            ("2023/07/small".to_owned(), 10)
+        ],
+        sizes(None, &mut app)
+    );
+    assert_eq!(
+        vec![("2023/07/large".to_owned(), 1000), ("2023/07/medium".to_owned(), 100)],
+        sizes(Some(2), &mut app)
+    );
+    assert_eq!(Some(3), app.run_until_completion(InputCommand::SizeReport { top: Some(3) }).unwrap());
+}
+
+#[test]
+fn test_tag_counts() {
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    git2::Repository::init(&config.repository).unwrap();
+
+    let mut app = App::new(config).unwrap();
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent { path: Path::new("2023/07/note1").to_path_buf(), tags: vec!["python".to_owned(), "work".to_owned()], content: "Test1".to_owned() },
+        Command::AddNoteWithContent { path: Path::new("2023/07/note2").to_path_buf(), tags: vec!["Python".to_owned()], content: "Test2".to_owned() },
+        Command::AddNoteWithContent { path: Path::new("2023/07/note3").to_path_buf(), tags: vec!["work".to_owned()], content: "Test3".to_owned() }
+    ]).unwrap();
+
+    assert_eq!(
+        vec![("Python".to_owned(), 1), ("python".to_owned(), 1), ("work".to_owned(), 2)],
+        tag_counts(app.note_metadata_storage().unwrap(), TagSort::Name)
+    );
+    assert_eq!(
+        vec![("work".to_owned(), 2), ("Python".to_owned(), 1), ("python".to_owned(), 1)],
+        tag_counts(app.note_metadata_storage().unwrap(), TagSort::Count)
+    );
+    assert_eq!(Some(3), app.run_until_completion(InputCommand::Tags { sort: TagSort::Count, format: OutputFormat::Json }).unwrap());
+}
+
+#[test]
+fn test_status() {
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    git2::Repository::init(&config.repository).unwrap();
+
+    let mut app = App::new(config.clone()).unwrap();
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent { path: Path::new("2023/07/sample").to_path_buf(), tags: vec![], content: "Test1".to_owned() },
+        Command::AddNoteWithContent { path: Path::new("2023/07/removed").to_path_buf(), tags: vec![], content: "Test2".to_owned() }
+    ]).unwrap();
+    assert_eq!(Vec::<StatusEntry>::new(), app.status().unwrap());
+    let sample_id = app.note_metadata_storage().unwrap().get(Path::new("2023/07/sample")).unwrap().id;
+    let removed_id = app.note_metadata_storage().unwrap().get(Path::new("2023/07/removed")).unwrap().id;
+
+    // Staged (like within begin/commit)
+    app.execute_commands(vec![
+        Command::AddNoteWithContent { path: Path::new("2023/07/other").to_path_buf(), tags: vec![], content: "Test3".to_owned() },
+        Command::RemoveNote { path: Path::new("2023/07/removed").to_path_buf(), force: false }
+    ]).unwrap();
+
+    // Unstaged
+    std::fs::write(NoteMetadataStorage::get_note_storage_path(&config.repository, &sample_id).1, "Changed").unwrap();
+    std::fs::create_dir_all(config.resources_dir().join("images")).unwrap();
+    std::fs::write(config.resources_dir().join("images/test.png"), "image").unwrap();
+
+    let entries = app.status().unwrap();
+    let entry = |staged: bool, change: NoteChange, kind: StatusKind, path: &str| StatusEntry { staged, change, kind, path: PathBuf::from(path) };
+    assert_eq!(
+        vec![
+            entry(true, NoteChange::Added, StatusKind::Note, "2023/07/other"),
+            entry(true, NoteChange::Removed, StatusKind::Note, &format!("<{}>", removed_id)),
+            entry(false, NoteChange::Modified, StatusKind::Note, "2023/07/sample"),
+            entry(false, NoteChange::Added, StatusKind::Resource, "images/test.png")
+        ],
+        entries
+    );
+    assert_eq!(Some(4), app.run_until_completion(InputCommand::Status {}).unwrap());
+}
+
+#[test]
+fn test_note_history() {
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    git2::Repository::init(&config.repository).unwrap();
+
+    let note_path = Path::new("2023/07/sample");
+    let other_note_path = Path::new("2023/07/other");
+
+    let mut app = App::new(config).unwrap();
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent { path: note_path.to_path_buf(), tags: vec![], content: "Test1".to_owned() }
+    ]).unwrap();
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent { path: other_note_path.to_path_buf(), tags: vec![], content: "Other".to_owned() }
+    ]).unwrap();
+    app.create_and_execute_commands(vec![
+        Command::EditNoteSetContent { path: note_path.to_path_buf(), clear_tags: false, add_tags: vec![], content: "Test2".to_owned(), force: false }
+    ]).unwrap();
+    app.create_and_execute_commands(vec![
+        Command::EditNoteSetContent { path: note_path.to_path_buf(), clear_tags: false, add_tags: vec![], content: "Test3".to_owned(), force: false }
+    ]).unwrap();
+
+    let history = app.note_history(note_path).unwrap();
+    assert_eq!(3, history.len());
+    assert_eq!("Updated note '2023/07/sample'.", history[0].subject);
+    assert_eq!("Updated note '2023/07/sample'.", history[1].subject);
+    assert!(history[2].subject.starts_with("Added note '2023/07/sample'"));
+
+    let history = app.note_history(other_note_path).unwrap();
+    assert_eq!(1, history.len());
+    assert_eq!(Some(1), app.run_until_completion(InputCommand::History { path: other_note_path.to_path_buf() }).unwrap());
+}
+
+#[test]
+fn test_note_diff() {
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    git2::Repository::init(&config.repository).unwrap();
+
+    let note_path = Path::new("2023/07/sample");
+
+    let mut app = App::new(config).unwrap();
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent { path: note_path.to_path_buf(), tags: vec![], content: "Line1\nLine2\n".to_owned() }
+    ]).unwrap();
+    app.create_and_execute_commands(vec![
+        Command::EditNoteSetContent { path: note_path.to_path_buf(), clear_tags: false, add_tags: vec![], content: "Line1\nLine3\n".to_owned(), force: false }
+    ]).unwrap();
+
+    let diff = app.note_diff(note_path, "HEAD~1".to_owned(), Some("HEAD".to_owned())).unwrap();
+    assert!(diff.contains("@@ -1,2 +1,2 @@\n Line1\n-Line2\n+Line3\n"), "{}", diff);
+
+    app.execute_commands(vec![
+        Command::EditNoteSetContent { path: note_path.to_path_buf(), clear_tags: false, add_tags: vec![], content: "Line1\nLine3\nLine4\n".to_owned(), force: false }
+    ]).unwrap();
+    let diff = app.note_diff(note_path, "HEAD".to_owned(), None).unwrap();
+    assert!(diff.contains("+Line4\n"), "{}", diff);
+    assert!(!diff.contains("-Line"), "{}", diff);
+
+    assert_eq!("", app.note_diff(note_path, "HEAD~1".to_owned(), Some("HEAD~1".to_owned())).unwrap());
+}
+
+#[test]
+fn test_pin() {
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    let repository = git2::Repository::init(&config.repository).unwrap();
+
+    let note_path = Path::new("2023/07/sample");
+    let other_note_path = Path::new("2023/07/other");
+
+    let mut app = App::new(config.clone()).unwrap();
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent { path: note_path.to_path_buf(), tags: vec![], content: "Test1".to_owned() },
+        Command::AddNoteWithContent { path: other_note_path.to_path_buf(), tags: vec![], content: "Test2".to_owned() }
+    ]).unwrap();
+    assert!(app.pinned_notes().unwrap().is_empty());
+
+    app.run(InputCommand::Pin { path: note_path.to_path_buf() }).unwrap();
+    assert_eq!(vec![note_path], app.pinned_notes().unwrap().iter().map(|note_metadata| note_metadata.path.as_path()).collect::<Vec<_>>());
+    assert_eq!(Some(1), app.run_until_completion(InputCommand::Pinned {}).unwrap());
+
+    // Saved in the committed metadata
+    let mut app = App::new(config).unwrap();
+    assert_eq!(1, app.pinned_notes().unwrap().len());
+    let head_commit = repository.head().unwrap().peel_to_commit().unwrap();
+    assert!(head_commit.message().unwrap().contains("Pinned note '2023/07/sample'."));
+
+    app.run(InputCommand::Unpin { path: note_path.to_path_buf() }).unwrap();
+    assert!(app.pinned_notes().unwrap().is_empty());
+}
+
+#[test]
+fn test_lock() {
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    let repository = git2::Repository::init(&config.repository).unwrap();
+
+    let note_path = Path::new("2023/07/sample");
+    let other_note_path = Path::new("2023/07/other");
+
+    let mut app = App::new(config).unwrap();
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent { path: note_path.to_path_buf(), tags: vec![], content: "Test1".to_owned() },
+        Command::AddNoteWithContent { path: other_note_path.to_path_buf(), tags: vec![], content: "Other".to_owned() }
+    ]).unwrap();
+
+    app.run(InputCommand::Lock { path: note_path.to_path_buf() }).unwrap();
+    assert_eq!(true, app.note_metadata_storage().unwrap().get(note_path).unwrap().locked);
+    assert_eq!(2, repository.reflog("HEAD").unwrap().len());
+    let head_commit = repository.head().unwrap().peel_to_commit().unwrap();
+    assert!(head_commit.message().unwrap().contains("Locked note '2023/07/sample'."));
+
+    let result = app.create_and_execute_commands(vec![
+        Command::EditNoteSetContent { path: note_path.to_path_buf(), clear_tags: false, add_tags: vec![], content: "Test2".to_owned(), force: false }
+    ]);
+    assert!(matches!(result, Err(AppError::Command(CommandError::NoteLocked(_)))));
+
+    let result = app.run(InputCommand::Move {
+        source: note_path.to_path_buf(),
+        destination: Path::new("2023/07/moved").to_path_buf(),
+        force: false,
+        merge: false,
+        ignore_lock: false,
+        dry_run: false
+    });
+    assert!(matches!(result, Err(AppError::Command(CommandError::NoteLocked(_)))));
+
+    // Overwriting a locked note at the destination is not allowed with only --force
+    let result = app.run(InputCommand::Move {
+        source: other_note_path.to_path_buf(),
+        destination: note_path.to_path_buf(),
+        force: true,
+        merge: false,
+        ignore_lock: false,
+        dry_run: false
+    });
+    assert!(matches!(result, Err(AppError::Command(CommandError::NoteLocked(_)))));
+
+    let result = app.run(InputCommand::Copy { source: other_note_path.to_path_buf(), destination: note_path.to_path_buf(), force: true, ignore_lock: false });
+    assert!(matches!(result, Err(AppError::Command(CommandError::NoteLocked(_)))));
+    assert_eq!(true, app.note_metadata_storage().unwrap().get(note_path).unwrap().locked);
+
+    let result = app.run(InputCommand::Remove { path: note_path.to_owned(), recursive: false, dry_run: false, yes: false, force: false });
+    assert!(matches!(result, Err(AppError::Command(CommandError::NoteLocked(_)))));
+    assert_eq!("Test1", app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
+    assert_eq!(2, repository.reflog("HEAD").unwrap().len());
+
+    app.create_and_execute_commands(vec![
+        Command::EditNoteSetContent { path: note_path.to_path_buf(), clear_tags: false, add_tags: vec![], content: "Test2".to_owned(), force: true }
+    ]).unwrap();
+    assert_eq!("Test2", app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
+    assert_eq!(3, repository.reflog("HEAD").unwrap().len());
+
+    app.run(InputCommand::Unlock { path: note_path.to_path_buf() }).unwrap();
+    assert_eq!(false, app.note_metadata_storage().unwrap().get(note_path).unwrap().locked);
+
+    app.run(InputCommand::Remove { path: note_path.to_owned(), recursive: false, dry_run: false, yes: false, force: false }).unwrap();
+    assert_eq!(false, app.note_metadata_storage().unwrap().get(note_path).is_some());
+
+    app.run(InputCommand::Lock { path: other_note_path.to_path_buf() }).unwrap();
+    app.run(InputCommand::Move {
+        source: other_note_path.to_path_buf(),
+        destination: Path::new("2023/07/moved").to_path_buf(),
+        force: false,
+        merge: false,
+        ignore_lock: true,
+        dry_run: false
+    }).unwrap();
+    assert_eq!("Other", app.note_metadata_storage().unwrap().get_content(Path::new("2023/07/moved")).unwrap());
+}
+
+#[test]
+fn test_edit_strip_output() {
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    let repository = git2::Repository::init(&config.repository).unwrap();
+
+    let note_path = Path::new("2023/07/sample");
+    let note_content = r#"Hello, World!
+
+``` python
+print("Hello")
+```
+
+``` output
+Hello
+```
+
+Some *text*.
+"#.to_string();
+
+    let mut app = App::new(config).unwrap();
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent { path: note_path.to_path_buf(), tags: vec![], content: note_content }
+    ]).unwrap();
+
+    app.run(InputCommand::Edit {
+        path: note_path.to_owned(),
+        history: None,
+        clear_tags: false,
+        add_tags: vec![],
+        create: false,
+        force: false,
+        strip_output: true
+    }).unwrap();
+    assert_eq!(
+        "Hello, World!\n\n``` python\nprint(\"Hello\")\n```\n\nSome *text*.\n",
+        app.note_metadata_storage().unwrap().get_content(note_path).unwrap()
+    );
+    assert_eq!(2, repository.reflog("HEAD").unwrap().len());
+}
+
+struct MockClipboard {
+    available: bool,
+    text: Option<String>
+}
+
+impl Clipboard for MockClipboard {
+    fn set_text(&mut self, text: &str) -> Result<(), String> {
+        if self.available {
+            self.text = Some(text.to_owned());
+            Ok(())
+        } else {
+            Err("no clipboard".to_owned())
+        }
+    }
+}
+
+#[test]
+fn test_copy_to_clipboard() {
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    git2::Repository::init(&config.repository).unwrap();
+
+    let note_path = Path::new("2023/07/sample");
+    let note_content = r#"Hello, World!
+
+``` python
+print("Hello")
+```
+"#.to_string();
+
+    let mut app = App::new(config).unwrap();
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent { path: note_path.to_path_buf(), tags: vec![], content: note_content.clone() }
+    ]).unwrap();
+
+    let mut clipboard = MockClipboard { available: true, text: None };
+    let mut output = Vec::new();
+    assert_eq!(true, app.copy_to_clipboard(note_path, false, &mut clipboard, &mut output).unwrap());
+    assert_eq!(Some(note_content.clone()), clipboard.text);
+    assert!(output.is_empty());
+
+    assert_eq!(true, app.copy_to_clipboard(note_path, true, &mut clipboard, &mut output).unwrap());
+    assert_eq!(Some("print(\"Hello\")\n".to_owned()), clipboard.text);
+    assert!(output.is_empty());
+
+    let mut clipboard = MockClipboard { available: false, text: None };
+    assert_eq!(false, app.copy_to_clipboard(note_path, true, &mut clipboard, &mut output).unwrap());
+    assert_eq!(None, clipboard.text);
+    assert_eq!("print(\"Hello\")\n", String::from_utf8(output).unwrap());
+}
+
+#[test]
+fn test_set_tags() {
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    let repository = git2::Repository::init(&config.repository).unwrap();
+
+    let note_path = Path::new("2023/07/sample");
+
+    let mut app = App::new(config).unwrap();
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent { path: note_path.to_path_buf(), tags: vec!["python".to_owned(), "x".to_owned()], content: "Test".to_owned() }
+    ]).unwrap();
+
+    app.run(InputCommand::SetTags {
+        path: note_path.to_path_buf(),
+        tags: vec!["rust".to_owned(), " async ".to_owned(), "rust".to_owned()]
+    }).unwrap();
+    assert_eq!(vec!["rust".to_owned(), "async".to_owned()], app.note_metadata_storage().unwrap().get(note_path).unwrap().tags);
+
+    let head_commit = repository.head().unwrap().peel_to_commit().unwrap();
+    assert!(head_commit.message().unwrap().contains("Set tags of note '2023/07/sample' to: rust, async."));
+
+    let result = app.run(InputCommand::SetTags { path: note_path.to_path_buf(), tags: vec!["a".to_owned(), "  ".to_owned()] });
+    assert!(matches!(result, Err(AppError::Input(_))));
+    assert_eq!(vec!["rust".to_owned(), "async".to_owned()], app.note_metadata_storage().unwrap().get(note_path).unwrap().tags);
+}
+
+#[test]
+fn test_read_only() {
+    use structopt::StructOpt;
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let mut config = create_test_config(&temp_repository_dir);
+    git2::Repository::init(&config.repository).unwrap();
+
+    let mut app = App::new(config.clone()).unwrap();
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent {
+            path: Path::new("2023/07/sample").to_path_buf(),
+            tags: vec![],
+            content: "Test".to_owned()
+        }
+    ]).unwrap();
+
+    config.read_only = true;
+    let mut app = App::new(config).unwrap();
+
+    let command = |args: &[&str]| {
+        InputCommand::from_iter_safe(["gitnotes"].iter().chain(args.iter())).unwrap()
+    };
+
+    assert!(matches!(app.run(command(&["add", "2023/07/other"])), Err(AppError::ReadOnly)));
+    assert!(matches!(app.run(command(&["mv", "2023/07/sample", "2023/07/other"])), Err(AppError::ReadOnly)));
+    assert!(matches!(app.run(command(&["rm", "2023/07/sample"])), Err(AppError::ReadOnly)));
+    assert!(matches!(app.run(command(&["touch", "2023/07/sample"])), Err(AppError::ReadOnly)));
+    assert!(matches!(app.run(command(&["convert-all", "output", "name", "sample"])), Err(AppError::ReadOnly)));
+    assert!(app.run(command(&["ls"])).is_ok());
+    assert!(app.run(command(&["mv", "2023/07/sample", "2023/07/other", "--dry-run"])).is_ok());
+    assert_eq!("Test", app.get_show_content(Path::new("2023/07/sample"), None, false, false).unwrap());
+}
+
+#[test]
+fn test_exit_code() {
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    git2::Repository::init(&config.repository).unwrap();
+
+    let mut app = App::new(config).unwrap();
+
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent {
+            path: Path::new("2023/07/sample").to_path_buf(),
+            tags: vec![],
+            content: "Hello, World!".to_owned()
+        }
+    ]).unwrap();
+
+    let find = |name: &str| InputCommand::Finder {
+        interactive: None,
+        sort: SortBy::Path,
+        reverse: false,
+        format: OutputFormat::Text,
+        limit: None,
+        then: None,
+        command: InputCommandFinder::Name { name: RegexMatcher::new(name), here: false, global: false, clauses: vec![] }
+    };
+
+    let grep = |query: &str| InputCommand::SearchContent {
+        query: query.to_owned(),
+        case_sensitive: false,
+        multiline: false,
+        tree: false,
+        include_resources: false,
+        history: vec![],
+        interactive: None,
+        replace: None,
+        dry_run: false,
+        limit: None,
+        json: false,
+        after: None,
+        before: None,
+        context: None,
+        files_with_matches: false,
+        count: false
+    };
+
+    assert_eq!(0, exit_code(&app.run_until_completion(find("sample"))));
+    assert_eq!(1, exit_code(&app.run_until_completion(find("other"))));
+    assert_eq!(0, exit_code(&app.run_until_completion(grep("world"))));
+    assert_eq!(1, exit_code(&app.run_until_completion(grep("other"))));
+    assert_eq!(0, exit_code(&app.run_until_completion(InputCommand::Log { count: 1, graph: false, author: None, grep: None })));
+    assert_eq!(2, exit_code(&app.run_until_completion(InputCommand::PrintContent {
+        path: Path::new("2023/07/other").to_path_buf(),
+        history: None,
+        only_code: false,
+        only_output: false
+    })));
+}
+
+#[test]
+fn test_log_graph_with_filters() {
+    use structopt::StructOpt;
+
+    let log = |args: &[&str]| InputCommand::from_iter_safe(["gitnotes", "log"].iter().chain(args.iter()));
+
+    assert!(log(&["--graph"]).is_ok());
+    assert!(log(&["--author", "test"]).is_ok());
+    assert!(log(&["--graph", "--author", "test"]).is_err());
+    assert!(log(&["--graph", "--grep", "note"]).is_err());
+}
+
+#[test]
+fn test_install_completions() {
+    use structopt::clap::Shell;
+    use tempfile::TempDir;
+
+    let home_dir = TempDir::new().unwrap();
+    let home_path = home_dir.path();
+
+    assert!(matches!(detect_shell("/bin/bash"), Some(Shell::Bash)));
+    assert!(matches!(detect_shell("/usr/bin/zsh"), Some(Shell::Zsh)));
+    assert!(matches!(detect_shell("/usr/local/bin/fish"), Some(Shell::Fish)));
+    assert!(detect_shell("/bin/sh").is_none());
+
+    assert_eq!(home_path.join(".local/share/bash-completion/completions/gitnotes"), completions_install_path(Shell::Bash, home_path).unwrap());
+    assert_eq!(home_path.join(".zfunc/_gitnotes"), completions_install_path(Shell::Zsh, home_path).unwrap());
+    assert_eq!(home_path.join(".config/fish/completions/gitnotes.fish"), completions_install_path(Shell::Fish, home_path).unwrap());
+    assert!(completions_install_path(Shell::PowerShell, home_path).is_err());
+
+    let path = install_completions(Shell::Bash, home_path).unwrap();
+    assert_eq!(completions_install_path(Shell::Bash, home_path).unwrap(), path);
+    assert!(std::fs::read_to_string(path).unwrap().contains("gitnotes"));
+}
+
+#[test]
+fn test_track_access() {
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let mut config = create_test_config(&temp_repository_dir);
+    config.track_access = true;
+    let repository = git2::Repository::init(&config.repository).unwrap();
+
+    let note_path = Path::new("2023/07/sample");
+    let other_note_path = Path::new("2023/07/other");
+
+    let mut app = App::new(config).unwrap();
+
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent {
+            path: note_path.to_path_buf(),
+            tags: vec![],
+            content: "Test1".to_owned()
+        },
+        Command::AddNoteWithContent {
+            path: other_note_path.to_path_buf(),
+            tags: vec![],
+            content: "Test2".to_owned()
+        }
+    ]).unwrap();
+    let head_commit_id = repository.head().unwrap().peel_to_commit().unwrap().id();
+
+    let note_id = app.note_metadata_storage().unwrap().get_id(note_path).unwrap();
+    let other_note_id = app.note_metadata_storage().unwrap().get_id(other_note_path).unwrap();
+    assert_eq!(None, AccessTimes::load(&repository).unwrap().get(&note_id));
+
+    app.run(InputCommand::PrintContent { path: note_path.to_path_buf(), history: None, only_code: false, only_output: false }).unwrap();
+    let first_access = AccessTimes::load(&repository).unwrap().get(&note_id);
+    assert!(first_access.is_some());
+    assert_eq!(None, AccessTimes::load(&repository).unwrap().get(&other_note_id));
+
+    app.run(InputCommand::Touch { path: note_path.to_path_buf() }).unwrap();
+    assert!(AccessTimes::load(&repository).unwrap().get(&note_id) >= first_access);
+
+    assert_eq!(head_commit_id, repository.head().unwrap().peel_to_commit().unwrap().id());
+
+    std::thread::sleep(std::time::Duration::from_millis(10));
+    app.run(InputCommand::Touch { path: other_note_path.to_path_buf() }).unwrap();
+    app.run(InputCommand::Recent { count: 10, accessed: true }).unwrap();
+
+    let access_times = AccessTimes::load(&repository).unwrap();
+    let note_metadata_storage = app.note_metadata_storage().unwrap();
+    let recent_paths = |count: usize| {
+        recently_accessed(note_metadata_storage, &access_times, count)
+            .into_iter()
+            .map(|(note_metadata, _)| note_metadata.path.clone())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(vec![other_note_path.to_path_buf(), note_path.to_path_buf()], recent_paths(10));
+    assert_eq!(vec![other_note_path.to_path_buf()], recent_paths(1));
+}
+
+#[test]
+fn test_aliases() {
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    git2::Repository::init(&config.repository).unwrap();
+
+    let note_path = Path::new("2023/07/sample");
+
+    let mut app = App::new(config).unwrap();
+
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent {
+            path: note_path.to_path_buf(),
+            tags: vec![],
+            content: "Test1".to_owned()
+        }
+    ]).unwrap();
+
+    app.run(InputCommand::Alias { command: InputCommandAlias::Add { alias: "sample".to_owned(), path: note_path.to_path_buf() } }).unwrap();
+    assert_eq!(vec![("sample".to_owned(), note_path.to_path_buf())], app.list_aliases().unwrap());
+    let cat = |path: &str| InputCommand::PrintContent { path: Path::new(path).to_path_buf(), history: None, only_code: false, only_output: false };
+    app.run(cat("sample")).unwrap();
+    app.run(InputCommand::Alias { command: InputCommandAlias::List {} }).unwrap();
+
+    app.run(InputCommand::Alias { command: InputCommandAlias::Remove { alias: "sample".to_owned() } }).unwrap();
+    assert_eq!(Vec::<(String, PathBuf)>::new(), app.list_aliases().unwrap());
+    assert!(app.run(cat("sample")).is_err());
+
+    let result = app.run(InputCommand::Alias { command: InputCommandAlias::Remove { alias: "sample".to_owned() } });
+    assert!(matches!(result, Err(AppError::AliasNotFound(_))));
+
+    // Destinations are not resolved as aliases
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent { path: Path::new("2023/07/other").to_path_buf(), tags: vec![], content: "Test2".to_owned() },
+        Command::AddNoteWithContent { path: Path::new("2023/07/third").to_path_buf(), tags: vec![], content: "Test3".to_owned() }
+    ]).unwrap();
+    app.run(InputCommand::Alias { command: InputCommandAlias::Add { alias: "sample".to_owned(), path: note_path.to_path_buf() } }).unwrap();
+
+    app.run(InputCommand::Copy { source: Path::new("2023/07/other").to_owned(), destination: Path::new("sample").to_owned(), force: true, ignore_lock: false }).unwrap();
+    assert_eq!("Test1", app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
+    assert_eq!("Test2", app.note_metadata_storage().unwrap().get_content(Path::new("sample")).unwrap());
+
+    app.run(InputCommand::Move { source: Path::new("2023/07/third").to_owned(), destination: Path::new("sample").to_owned(), force: true, merge: false, ignore_lock: false, dry_run: false }).unwrap();
+    assert_eq!("Test1", app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
+    assert_eq!("Test3", app.note_metadata_storage().unwrap().get_content(Path::new("sample")).unwrap());
+    assert_eq!(vec![("sample".to_owned(), note_path.to_path_buf())], app.list_aliases().unwrap());
+}
+
+#[test]
+fn test_print_contents_from() {
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    git2::Repository::init(&config.repository).unwrap();
+
+    let mut app = App::new(config).unwrap();
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent { path: Path::new("2023/07/sample").to_path_buf(), tags: vec![], content: "Test1\n".to_owned() },
+        Command::AddNoteWithContent { path: Path::new("2023/07/other").to_path_buf(), tags: vec![], content: "Test2\n".to_owned() }
+    ]).unwrap();
+
+    let mut output = Vec::new();
+    app.print_contents_from(&mut "2023/07/sample\n\n2023/07/other\n".as_bytes(), &mut output, None, false, false).unwrap();
+    assert_eq!(
+        "==> 2023/07/sample <==\nTest1\n\n==> 2023/07/other <==\nTest2\n",
+        String::from_utf8(output).unwrap()
+    );
+
+    let mut output = Vec::new();
+    let result = app.print_contents_from(&mut "2023/07/sample\n2023/07/missing\n".as_bytes(), &mut output, None, false, false);
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_rename_id() {
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    let repository = git2::Repository::init(&config.repository).unwrap();
+
+    let note_path = Path::new("2023/07/sample");
+    let other_note_path = Path::new("2023/07/other");
+
+    let mut app = App::new(config).unwrap();
+
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent {
+            path: note_path.to_path_buf(),
+            tags: vec![],
+            content: "Test1".to_owned()
+        },
+        Command::AddNoteWithContent {
+            path: other_note_path.to_path_buf(),
+            tags: vec![],
+            content: "Test2".to_owned()
+        }
+    ]).unwrap();
+
+    let old_id = app.note_metadata_storage().unwrap().get_id(note_path).unwrap();
+    let other_id = app.note_metadata_storage().unwrap().get_id(other_note_path).unwrap();
+    let new_id = app.note_metadata_storage().unwrap().new_note_id().unwrap();
+    app.run(InputCommand::Alias { command: InputCommandAlias::Add { alias: "sample".to_owned(), path: note_path.to_path_buf() } }).unwrap();
+
+    let result = app.run(InputCommand::RenameId { path: note_path.to_path_buf(), new_id: other_id });
+    assert!(matches!(result, Err(AppError::Command(CommandError::NoteIdAlreadyUsed(_)))));
+
+    app.run(InputCommand::RenameId { path: note_path.to_path_buf(), new_id }).unwrap();
+    assert_eq!(Some(new_id), app.note_metadata_storage().unwrap().get_id(note_path));
+    assert_eq!("Test1", app.note_metadata_storage().unwrap().get_content(Path::new(&new_id.to_string())).unwrap());
+    assert!(app.note_metadata_storage().unwrap().get_by_id(&old_id).is_none());
+    assert_eq!(vec![("sample".to_owned(), note_path.to_path_buf())], app.list_aliases().unwrap());
+
+    let cat = |path: &str| InputCommand::PrintContent { path: Path::new(path).to_path_buf(), history: None, only_code: false, only_output: false };
+    app.run(cat(&new_id.to_string())).unwrap();
+    assert!(app.run(cat(&old_id.to_string())).is_err());
+
+    let head_commit = repository.head().unwrap().peel_to_commit().unwrap();
+    assert!(head_commit.message().unwrap().contains(&format!("from {} to {}", old_id, new_id)));
+    assert!(repository.statuses(None).unwrap().iter().all(|status| !status.status().is_index_new() && !status.status().is_index_deleted()));
+
+    // Renaming to the current id does nothing
+    let head_commit_id = head_commit.id();
+    app.run(InputCommand::RenameId { path: note_path.to_path_buf(), new_id }).unwrap();
+    assert_eq!(Some(new_id), app.note_metadata_storage().unwrap().get_id(note_path));
+    assert_eq!(head_commit_id, repository.head().unwrap().peel_to_commit().unwrap().id());
+
+    // The alias is only changed when the commit is created
+    let newer_id = app.note_metadata_storage().unwrap().new_note_id().unwrap();
+    app.run(InputCommand::Begin {}).unwrap();
+    app.run(InputCommand::RenameId { path: note_path.to_path_buf(), new_id: newer_id }).unwrap();
+    assert_eq!(Some(new_id), NoteAliases::load(&repository).unwrap().get("sample"));
+    app.run(InputCommand::Commit {}).unwrap();
+    assert_eq!(Some(newer_id), NoteAliases::load(&repository).unwrap().get("sample"));
+    assert_eq!(vec![("sample".to_owned(), note_path.to_path_buf())], app.list_aliases().unwrap());
+}
+
+#[test]
+fn test_config_repository_path_without_repository() {
+    use structopt::StructOpt;
+    use tempfile::TempDir;
+
+    let temp_dir = TempDir::new().unwrap();
+    let config_path = temp_dir.path().join("config.toml");
+
+    for repository_path in [temp_dir.path().join("missing"), Path::new("~/.gitnotes-missing-test").to_path_buf()] {
+        FileConfig::new(&repository_path).save(&config_path).unwrap();
+        assert!(App::new(Config::load(&config_path).unwrap()).is_err());
+
+        let mut main_input_command = MainInputCommand::from_iter_safe(["gitnotes", "config", "--repo-path"]).unwrap();
+        let input_command = main_input_command.command.take().unwrap();
+        assert_eq!(None, crate::run(&config_path, input_command, main_input_command).unwrap());
+    }
+}
+
+#[test]
+fn test_clone_repository() {
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    git2::Repository::init(&config.repository).unwrap();
+
+    let note_path = Path::new("2023/07/sample");
+    let mut app = App::new(config).unwrap();
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent { path: note_path.to_path_buf(), tags: vec![], content: "Hello, World!".to_owned() }
+    ]).unwrap();
+
+    let temp_remote_dir = TempDir::new().unwrap();
+    let remote_path = temp_remote_dir.path().join("notes.git");
+    git2::build::RepoBuilder::new().bare(true).clone(temp_repository_dir.path().to_str().unwrap(), &remote_path).unwrap();
+
+    let temp_clone_dir = TempDir::new().unwrap();
+    let config_path = temp_clone_dir.path().join("config.toml");
+    let clone_path = temp_clone_dir.path().join(clone_name(remote_path.to_str().unwrap()).unwrap());
+    clone_repository(&config_path, remote_path.to_str().unwrap(), &clone_path).unwrap();
+
+    assert_eq!(temp_clone_dir.path().join("notes"), clone_path);
+    let file_config = FileConfig::load(&config_path).unwrap();
+    assert_eq!(clone_path, file_config.repository);
+
+    let mut config = Config::from_env(file_config);
+    config.use_working_dir = false;
+    let mut app = App::new(config).unwrap();
+    assert_eq!("Hello, World!", app.get_show_content(note_path, None, false, false).unwrap());
+
+    assert!(clone_repository(&config_path, remote_path.to_str().unwrap(), &clone_path).is_err());
+
+    // An invalid config is kept as is
+    let other_clone_path = temp_clone_dir.path().join("other");
+    std::fs::write(&config_path, "repository = ").unwrap();
+    assert!(clone_repository(&config_path, remote_path.to_str().unwrap(), &other_clone_path).is_err());
+    assert_eq!("repository = ", std::fs::read_to_string(&config_path).unwrap());
+    assert!(!other_clone_path.exists());
+}
+
+#[test]
+fn test_pull_reports_changes() {
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    let repository = git2::Repository::init(&config.repository).unwrap();
+
+    let mut app = App::new(config).unwrap();
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent {
+            path: Path::new("2023/07/local").to_path_buf(),
+            tags: vec![],
+            content: "Local".to_owned()
+        }
+    ]).unwrap();
+
+    let temp_remote_dir = TempDir::new().unwrap();
+    let remote_config = create_test_config(&temp_remote_dir);
+    git2::Repository::clone(temp_repository_dir.path().to_str().unwrap(), &remote_config.repository).unwrap();
+
+    let mut remote_app = App::new(remote_config).unwrap();
+    remote_app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent {
+            path: Path::new("2023/07/remote").to_path_buf(),
+            tags: vec![],
+            content: "Remote".to_owned()
+        },
+        Command::EditNoteSetContent {
+            path: Path::new("2023/07/local").to_path_buf(),
+            clear_tags: false,
+            add_tags: vec![],
+            content: "Changed remotely".to_owned(),
+            force: false
+        }
+    ]).unwrap();
+
+    repository.remote("origin", temp_remote_dir.path().to_str().unwrap()).unwrap();
+    let branch = repository.head().unwrap().shorthand().unwrap().to_owned();
+
+    let changes = app.pull("origin", &branch, false).unwrap();
+    assert_eq!(
+        vec![
+            (NoteChange::Modified, Path::new("2023/07/local").to_path_buf()),
+            (NoteChange::Added, Path::new("2023/07/remote").to_path_buf())
+        ],
+        changes
+    );
+    assert_eq!("Remote", app.note_metadata_storage().unwrap().get_content(Path::new("2023/07/remote")).unwrap());
+
+    assert_eq!(Vec::<(NoteChange, PathBuf)>::new(), app.pull("origin", &branch, false).unwrap());
+}
+
+#[test]
+fn test_pull_conflicts() {
+    use tempfile::TempDir;
+
+    for abort_on_conflict in [false, true] {
+        let temp_repository_dir = TempDir::new().unwrap();
+        let config = create_test_config(&temp_repository_dir);
+        let repository = git2::Repository::init(&config.repository).unwrap();
+
+        let mut app = App::new(config).unwrap();
+        app.create_and_execute_commands(vec![
+            Command::AddNoteWithContent { path: Path::new("2023/07/conflict").to_path_buf(), tags: vec![], content: "Original".to_owned() },
+            Command::AddNoteWithContent { path: Path::new("2023/07/other").to_path_buf(), tags: vec![], content: "Other".to_owned() }
+        ]).unwrap();
+
+        let temp_remote_dir = TempDir::new().unwrap();
+        let remote_config = create_test_config(&temp_remote_dir);
+        git2::Repository::clone(temp_repository_dir.path().to_str().unwrap(), &remote_config.repository).unwrap();
+
+        let mut remote_app = App::new(remote_config).unwrap();
+        remote_app.create_and_execute_commands(vec![
+            Command::EditNoteSetContent { path: Path::new("2023/07/conflict").to_path_buf(), clear_tags: false, add_tags: vec![], content: "Changed remotely".to_owned(), force: false },
+            Command::EditNoteSetContent { path: Path::new("2023/07/other").to_path_buf(), clear_tags: false, add_tags: vec![], content: "Other remotely".to_owned(), force: false }
+        ]).unwrap();
+
+        app.create_and_execute_commands(vec![
+            Command::EditNoteSetContent { path: Path::new("2023/07/conflict").to_path_buf(), clear_tags: false, add_tags: vec![], content: "Changed locally".to_owned(), force: false }
+        ]).unwrap();
+
+        repository.remote("origin", temp_remote_dir.path().to_str().unwrap()).unwrap();
+        let branch = repository.head().unwrap().shorthand().unwrap().to_owned();
+        let conflict_id = app.note_metadata_storage().unwrap().get_id(Path::new("2023/07/conflict")).unwrap();
+
+        let err = app.pull("origin", &branch, abort_on_conflict).err().unwrap();
+        match err {
+            AppError::MergeConflicts(paths) => assert_eq!(vec![Path::new("2023/07/conflict").to_path_buf()], paths),
+            err => panic!("Unexpected error: {:?}", err)
+        }
+
+        let content = std::fs::read_to_string(NoteMetadataStorage::get_note_storage_path(temp_repository_dir.path(), &conflict_id).1).unwrap();
+        if abort_on_conflict {
+            assert_eq!("Changed locally", content);
+            assert_eq!("Other", app.note_metadata_storage().unwrap().get_content(Path::new("2023/07/other")).unwrap());
+            assert_eq!(git2::RepositoryState::Clean, repository.state());
+        } else {
+            assert!(content.contains("<<<<<<<"), "{}", content);
+            assert!(content.contains("Changed locally") && content.contains("Changed remotely"), "{}", content);
+        }
+    }
+}
+
+#[test]
+fn test_new_note_id() {
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    git2::Repository::init(&config.repository).unwrap();
+
+    let mut app = App::new(config).unwrap();
+
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent {
+            path: Path::new("2023/07/sample1").to_path_buf(),
+            tags: vec![],
+            content: "Test1".to_owned()
+        },
+        Command::AddNoteWithContent {
+            path: Path::new("2023/07/sample2").to_path_buf(),
+            tags: vec![],
+            content: "Test2".to_owned()
+        }
+    ]).unwrap();
+
+    let note_metadata_storage = app.note_metadata_storage().unwrap();
+    for _ in 0..100 {
+        let id = note_metadata_storage.new_note_id().unwrap().to_string();
+        assert_eq!(6, id.len());
+        assert!(id.chars().all(|c| c.is_ascii_digit()));
+        assert!(note_metadata_storage.get_by_id(&id.parse().unwrap()).is_none());
+    }
+
+    app.run(InputCommand::Id {}).unwrap();
+}
+
+#[test]
+fn test_new_note_id_collision() {
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    git2::Repository::init(&config.repository).unwrap();
+
+    let used_id: NoteId = "123456".parse().unwrap();
+    let free_id: NoteId = "654321".parse().unwrap();
+
+    App::new(config.clone()).unwrap();
+    let note_metadata_storage = NoteMetadataStorage::from_dir_with_config(&config).unwrap();
+
+    // Only the metadata file exists (such as a note added but not yet loaded)
+    let (_, abs_metadata_path) = NoteMetadataStorage::get_note_metadata_path(&config.repository, &used_id);
+    std::fs::create_dir_all(abs_metadata_path.parent().unwrap()).unwrap();
+    std::fs::write(&abs_metadata_path, "").unwrap();
+
+    let mut ids = vec![free_id, used_id];
+    assert_eq!(Some(free_id), note_metadata_storage.new_note_id_with(|| ids.pop().unwrap()));
+    assert_eq!(None, note_metadata_storage.new_note_id_with(|| used_id));
+}
+
+#[test]
+fn test_undo() {
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    let repository = git2::Repository::init(&config.repository).unwrap();
+
+    let note_path = Path::new("2023/07/sample");
+    let note_content1 = "Test1".to_owned();
+    let note_content2 = "Test2".to_owned();
+
+    let mut app = App::new(config).unwrap();
+
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent {
+            path: note_path.to_path_buf(),
+            tags: vec![],
+            content: note_content1.clone()
+        },
+    ]).unwrap();
+    assert_eq!(note_content1, app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
+    assert_eq!(1, repository.reflog("HEAD").unwrap().len());
+
+    app.create_and_execute_commands(vec![
+        Command::EditNoteSetContent {
+            path: note_path.to_path_buf(),
+            clear_tags: false,
+            add_tags: vec![],
+            content: note_content2.clone(),
+            force: false
+        },
+    ]).unwrap();
+    assert_eq!(note_content2, app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
+    assert_eq!(2, repository.reflog("HEAD").unwrap().len());
+    let commit_id = repository.reflog("HEAD").unwrap().get(0).unwrap().id_new();
+
+    app.run(InputCommand::Undo { commit: Some(commit_id.to_string()), last: None }).unwrap();
+    assert_eq!(note_content1, app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
+    assert_eq!(3, repository.reflog("HEAD").unwrap().len());
+}
+
+#[test]
+fn test_undo_last() {
+    use structopt::StructOpt;
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    let repository = git2::Repository::init(&config.repository).unwrap();
+
+    let note_path = Path::new("2023/07/sample");
+
+    let mut app = App::new(config).unwrap();
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent { path: note_path.to_path_buf(), tags: vec![], content: "Test1".to_owned() }
+    ]).unwrap();
+    for content in ["Test2", "Test3", "Test4"] {
+        app.create_and_execute_commands(vec![
+            Command::EditNoteSetContent { path: note_path.to_path_buf(), clear_tags: false, add_tags: vec![], content: content.to_owned(), force: false }
+        ]).unwrap();
+    }
+    assert_eq!(4, repository.reflog("HEAD").unwrap().len());
+
+    app.run(InputCommand::from_iter_safe(["gitnotes", "undo", "--last"]).unwrap()).unwrap();
+    assert_eq!("Test3", app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
+    assert_eq!(5, repository.reflog("HEAD").unwrap().len());
+
+    for content in ["Test5", "Test6"] {
+        app.create_and_execute_commands(vec![
+            Command::EditNoteSetContent { path: note_path.to_path_buf(), clear_tags: false, add_tags: vec![], content: content.to_owned(), force: false }
+        ]).unwrap();
+    }
+    app.run(InputCommand::from_iter_safe(["gitnotes", "undo", "--last", "2"]).unwrap()).unwrap();
+    assert_eq!("Test3", app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
+    assert_eq!(8, repository.reflog("HEAD").unwrap().len());
+    let head_commit = repository.head().unwrap().peel_to_commit().unwrap();
+    assert_eq!(2, head_commit.message().unwrap().lines().filter(|line| line.starts_with("Undo commit")).count());
+
+    // Reaches the initial commit
+    let err = app.run(InputCommand::Undo { commit: None, last: Some(Some(20)) }).err().unwrap();
+    assert!(matches!(err, AppError::Command(CommandError::FailedToUndo(_))), "{:?}", err);
+    assert_eq!(8, repository.reflog("HEAD").unwrap().len());
+
+    assert!(InputCommand::from_iter_safe(["gitnotes", "undo"]).is_err());
+    assert!(InputCommand::from_iter_safe(["gitnotes", "undo", "HEAD", "--last"]).is_err());
+}
+
+#[test]
+fn test_squash() {
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    let repository = git2::Repository::init(&config.repository).unwrap();
+
+    let base_note_path = Path::new("2023/07/base");
+    let note_path = Path::new("2023/07/sample");
+
+    let mut app = App::new(config).unwrap();
+
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent {
+            path: base_note_path.to_path_buf(),
+            tags: vec![],
+            content: "Base".to_owned()
+        },
+    ]).unwrap();
+    let base_commit_id = repository.head().unwrap().peel_to_commit().unwrap().id();
+
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent {
+            path: note_path.to_path_buf(),
+            tags: vec![],
+            content: "Test1".to_owned()
+        },
+    ]).unwrap();
+
+    for content in ["Test2", "Test3"] {
+        app.create_and_execute_commands(vec![
+            Command::EditNoteSetContent {
+                path: note_path.to_path_buf(),
+                clear_tags: false,
+                add_tags: vec![],
+                content: content.to_owned(),
+                force: false
+            },
+        ]).unwrap();
+    }
+    let last_tree_id = repository.head().unwrap().peel_to_commit().unwrap().tree_id();
+
+    app.run(InputCommand::Squash { range: format!("{}..HEAD", base_commit_id), force: false }).unwrap();
+
+    let head_commit = repository.head().unwrap().peel_to_commit().unwrap();
+    assert_eq!(1, head_commit.parent_count());
+    assert_eq!(base_commit_id, head_commit.parent_id(0).unwrap());
+    assert_eq!(last_tree_id, head_commit.tree_id());
+    assert!(head_commit.message().unwrap().contains("Added note '2023/07/sample'"));
+    assert!(head_commit.message().unwrap().contains("Updated note '2023/07/sample'."));
+    assert_eq!("Test3", app.note_metadata_storage().unwrap().get_content(note_path).unwrap());
+}
+
+#[test]
+fn test_read_bare_repository() {
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    git2::Repository::init(&config.repository).unwrap();
+
+    let note_path = Path::new("2023/07/sample");
+    let note_content = "Hello, World!\nSecond line".to_owned();
+
+    let mut app = App::new(config.clone()).unwrap();
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent {
+            path: note_path.to_path_buf(),
+            tags: vec!["python".to_owned()],
+            content: note_content.clone()
+        }
+    ]).unwrap();
+
+    let temp_bare_dir = TempDir::new().unwrap();
+    git2::build::RepoBuilder::new()
+        .bare(true)
+        .clone(config.repository.to_str().unwrap(), temp_bare_dir.path())
+        .unwrap();
+
+    let mut bare_config = config.clone();
+    bare_config.repository = temp_bare_dir.path().to_path_buf();
+    let mut bare_app = App::new(bare_config).unwrap();
+
+    let note_metadata_storage = bare_app.note_metadata_storage().unwrap();
+    assert_eq!(note_content, note_metadata_storage.get_content(note_path).unwrap());
+    assert_eq!(vec!["python".to_owned()], note_metadata_storage.get(note_path).unwrap().tags);
+    assert_eq!(
+        vec!["Hello, World!".to_owned(), "Second line".to_owned()],
+        note_metadata_storage.get_content_lines(note_path).unwrap().collect::<Result<Vec<_>, _>>().unwrap()
+    );
+    assert_eq!(false, temp_bare_dir.path().join(".notes").exists());
+
+    let result = bare_app.create_and_execute_commands(vec![
+        Command::RemoveNote { path: note_path.to_path_buf(), force: false }
+    ]);
+    assert!(matches!(result, Err(AppError::Command(CommandError::BareRepository))));
+}
+
+#[test]
+fn test_add_resource() {
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    let repository = git2::Repository::init(&config.repository).unwrap();
+    let resources_dir = config.resources_dir();
+
+    let mut app = App::new(config).unwrap();
+
+    app.run_until_completion(
+        InputCommand::Resource {
+            command: InputCommandResource::Add {
+                path: Path::new("testdata/resource1.txt").to_owned(),
+                destination: Path::new("resource1.txt").to_owned(),
+            },
+        }
+    ).unwrap();
+
+    assert_eq!(
+        std::fs::read_to_string(Path::new("testdata/resource1.txt").to_owned()).unwrap(),
+        std::fs::read_to_string(resources_dir.join("resource1.txt")).unwrap(),
+    );
+    assert_eq!(1, repository.reflog("HEAD").unwrap().len());
+}
+
+#[test]
+fn test_find_resources() {
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    git2::Repository::init(&config.repository).unwrap();
+    let resources_dir = config.resources_dir();
+
+    let mut app = App::new(config).unwrap();
+
+    std::fs::create_dir_all(resources_dir.join("images")).unwrap();
+    std::fs::write(resources_dir.join("data.txt"), "Hello").unwrap();
+    std::fs::write(resources_dir.join("images/image.png"), vec![0u8; 1500]).unwrap();
+
+    let results = find_resources(&resources_dir, None, ResourceSort::Name).unwrap();
+    assert_eq!(2, results.len());
+    assert_eq!(Path::new("data.txt"), results[0].relative_path);
+    assert_eq!(5, results[0].size);
+    assert_eq!("text/plain", results[0].resource_type());
+    assert_eq!(Path::new("images/image.png"), results[1].relative_path);
+    assert_eq!(1500, results[1].size);
+    assert_eq!("image/png", results[1].resource_type());
+
+    let results = find_resources(&resources_dir, None, ResourceSort::Size).unwrap();
+    assert_eq!(Path::new("images/image.png"), results[0].relative_path);
+    assert_eq!(Path::new("data.txt"), results[1].relative_path);
+
+    app.run(InputCommand::Resource {
+        command: InputCommandResource::List { query: None, print_absolute: false, long: true, sort: ResourceSort::Size }
+    }).unwrap();
+}
+
+#[test]
+fn test_find_resources_nested() {
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    let resources_dir = config.resources_dir();
+
+    std::fs::create_dir_all(resources_dir.join("a/b/c")).unwrap();
+    std::fs::create_dir_all(resources_dir.join("d")).unwrap();
+    std::fs::write(resources_dir.join("root.txt"), "1").unwrap();
+    std::fs::write(resources_dir.join("a/a.txt"), "2").unwrap();
+    std::fs::write(resources_dir.join("a/b/c/c.txt"), "3").unwrap();
+    std::fs::write(resources_dir.join("d/d.txt"), "4").unwrap();
+
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(resources_dir.join("a"), resources_dir.join("a/b/loop")).unwrap();
+        std::os::unix::fs::symlink(resources_dir.join("root.txt"), resources_dir.join("d/link.txt")).unwrap();
+    }
+
+    let results = find_resources(&resources_dir, None, ResourceSort::Name).unwrap()
+        .into_iter()
+        .map(|entry| entry.relative_path.to_str().unwrap().to_owned())
+        .collect::<Vec<_>>();
+
+    let mut expected = vec!["a/a.txt", "a/b/c/c.txt", "d/d.txt", "root.txt"];
+    if cfg!(unix) {
+        expected.insert(3, "d/link.txt");
+    }
+    assert_eq!(expected, results);
+
+    let results = find_resources(&resources_dir, Some(Path::new("a").to_path_buf()), ResourceSort::Name).unwrap();
+    assert_eq!(2, results.len());
+
+    assert!(find_resources(&resources_dir, Some(Path::new("missing").to_path_buf()), ResourceSort::Name).is_err());
+}
+
+#[test]
+fn test_resource_apply_all() {
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let temp_command_dir = TempDir::new().unwrap();
+    let log_path = temp_command_dir.path().join("log.txt");
+    let command_path = temp_command_dir.path().join("log-resource");
+    std::fs::write(&command_path, format!("#!/bin/sh\nbasename \"$1\" >> {}\n", log_path.to_str().unwrap())).unwrap();
+    std::fs::set_permissions(&command_path, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let config = create_test_config(&temp_repository_dir);
+    git2::Repository::init(&config.repository).unwrap();
+    let resources_dir = config.resources_dir();
+
+    std::fs::create_dir_all(resources_dir.join("images")).unwrap();
+    std::fs::write(resources_dir.join("data.txt"), "Hello").unwrap();
+    std::fs::write(resources_dir.join("images/image1.png"), "1").unwrap();
+    std::fs::write(resources_dir.join("images/image2.png"), "2").unwrap();
+
+    let mut app = App::new(config).unwrap();
+
+    app.run(InputCommand::Resource {
+        command: InputCommandResource::Apply {
+            command: command_path.to_str().unwrap().to_owned(),
+            resource: Some(Path::new("images/*.png").to_owned()),
+            all: true
+        }
+    }).unwrap();
+    assert_eq!("image1.png\nimage2.png\n", std::fs::read_to_string(&log_path).unwrap());
+
+    std::fs::remove_file(&log_path).unwrap();
+    assert_eq!(2, app.apply_resource_command(command_path.to_str().unwrap(), Some(Path::new("images").to_owned()), true).unwrap());
+    assert_eq!("image1.png\nimage2.png\n", std::fs::read_to_string(&log_path).unwrap());
+
+    std::fs::remove_file(&log_path).unwrap();
+    assert_eq!(1, app.apply_resource_command(command_path.to_str().unwrap(), Some(Path::new("data.txt").to_owned()), false).unwrap());
+    assert_eq!("data.txt\n", std::fs::read_to_string(&log_path).unwrap());
+}
+
+#[test]
+fn test_resource_tree() {
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    let resources_dir = config.resources_dir();
+
+    std::fs::create_dir_all(resources_dir.join("images/2023")).unwrap();
+    std::fs::write(resources_dir.join("data.txt"), "Hello").unwrap();
+    std::fs::write(resources_dir.join("images/logo.png"), "1").unwrap();
+    std::fs::write(resources_dir.join("images/2023/photo.jpg"), "12").unwrap();
+
+    let to_lines = |lines: Vec<ListTreeLine>| {
+        lines.into_iter().map(|line| format!("{}{}", line.prefix, line.content)).collect::<Vec<_>>()
+    };
+
+    let entries = find_resources(&resources_dir, None, ResourceSort::Name).unwrap();
+    assert_eq!(
+        vec![
+            "├── data.txt (size: 5 B)",
+            "└── images",
+            "    ├── 2023",
+            "    │   └── photo.jpg (size: 2 B)",
+            "    └── logo.png (size: 1 B)",
+        ],
+        to_lines(create_resource_tree_lines(&entries, Path::new("")))
+    );
+
+    let entries = find_resources(&resources_dir, Some(Path::new("images").to_path_buf()), ResourceSort::Name).unwrap();
+    assert_eq!(
+        vec![
+            "├── 2023",
+            "│   └── photo.jpg (size: 2 B)",
+            "└── logo.png (size: 1 B)",
+        ],
+        to_lines(create_resource_tree_lines(&entries, Path::new("images")))
+    );
+}
+
+#[test]
+fn test_remove_resource() {
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    let repository = git2::Repository::init(&config.repository).unwrap();
+    let resources_dir = config.resources_dir();
+
+    let mut app = App::new(config).unwrap();
+
+    app.run_until_completion(
+        InputCommand::Resource {
+            command: InputCommandResource::Add {
+                path: Path::new("testdata/resource1.txt").to_owned(),
+                destination: Path::new("resource1.txt").to_owned(),
+            },
+        }
+    ).unwrap();
+
+    app.run_until_completion(
+        InputCommand::Resource {
+            command: InputCommandResource::Remove {
+                path: Path::new("resource1.txt").to_owned(),
+            },
+        }
+    ).unwrap();
+
+    assert!(!resources_dir.join("resource1.txt").exists());
+    assert_eq!(2, repository.reflog("HEAD").unwrap().len());
+}
+
+#[test]
+fn test_search_replace() {
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    let repository = git2::Repository::init(&config.repository).unwrap();
+
+    let note_path1 = Path::new("2023/07/sample1");
+    let note_path2 = Path::new("2023/07/sample2");
+    let note_path3 = Path::new("2023/07/sample3");
+
+    let mut app = App::new(config).unwrap();
+
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent {
+            path: note_path1.to_path_buf(),
+            tags: vec![],
+            content: "Uses foo-1 here.\nAnd FOO-2 there.\n".to_owned()
+        },
+        Command::AddNoteWithContent {
+            path: note_path2.to_path_buf(),
+            tags: vec![],
+            content: "Only foo-3".to_owned()
+        },
+        Command::AddNoteWithContent {
+            path: note_path3.to_path_buf(),
+            tags: vec![],
+            content: "Nothing to see".to_owned()
+        }
+    ]).unwrap();
+    assert_eq!(1, repository.reflog("HEAD").unwrap().len());
+
+    let search_replace = |dry_run: bool| {
+        InputCommand::SearchContent {
+            query: r"foo-(\d)".to_owned(),
+            case_sensitive: false,
+            multiline: false,
+            tree: false,
+            include_resources: false,
+            history: vec![],
+            interactive: None,
+            replace: Some("bar-$1".to_owned()),
+            dry_run,
+            limit: None,
+            json: false,
+            after: None,
+            before: None,
+            context: None,
+            files_with_matches: false,
+            count: false
+        }
+    };
+
+    app.run(search_replace(true)).unwrap();
+    assert_eq!("Only foo-3", app.note_metadata_storage().unwrap().get_content(note_path2).unwrap());
+    assert_eq!(1, repository.reflog("HEAD").unwrap().len());
+
+    app.run(search_replace(false)).unwrap();
+    assert_eq!("Uses bar-1 here.\nAnd bar-2 there.\n", app.note_metadata_storage().unwrap().get_content(note_path1).unwrap());
+    assert_eq!("Only bar-3", app.note_metadata_storage().unwrap().get_content(note_path2).unwrap());
+    assert_eq!("Nothing to see", app.note_metadata_storage().unwrap().get_content(note_path3).unwrap());
+    assert_eq!(2, repository.reflog("HEAD").unwrap().len());
+}
+
+#[test]
+fn test_replace() {
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    let repository = git2::Repository::init(&config.repository).unwrap();
+
+    let note_path1 = Path::new("2023/07/sample1");
+    let note_path2 = Path::new("2023/07/sample2");
+    let note_path3 = Path::new("2023/08/sample3");
+
+    let mut app = App::new(config).unwrap();
+
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent { path: note_path1.to_path_buf(), tags: vec![], content: "Uses foo-1 here.\nAnd foo-2 there.\n".to_owned() },
+        Command::AddNoteWithContent { path: note_path2.to_path_buf(), tags: vec![], content: "Only foo-3".to_owned() },
+        Command::AddNoteWithContent { path: note_path3.to_path_buf(), tags: vec![], content: "Also foo-4".to_owned() }
+    ]).unwrap();
+    assert_eq!(1, repository.reflog("HEAD").unwrap().len());
+
+    let replace = |path: Option<&str>, dry_run: bool| {
+        InputCommand::Replace {
+            query: r"foo-(\d)".to_owned(),
+            replacement: "bar-$1".to_owned(),
+            path: path.map(|path| Path::new(path).to_path_buf()),
+            dry_run
+        }
+    };
+
+    assert_eq!(Some(2), app.run_until_completion(replace(Some("2023/07"), true)).unwrap());
+    assert_eq!("Only foo-3", app.note_metadata_storage().unwrap().get_content(note_path2).unwrap());
+    assert_eq!(1, repository.reflog("HEAD").unwrap().len());
+
+    assert_eq!(Some(2), app.run_until_completion(replace(Some("2023/07"), false)).unwrap());
+    assert_eq!("Uses bar-1 here.\nAnd bar-2 there.\n", app.note_metadata_storage().unwrap().get_content(note_path1).unwrap());
+    assert_eq!("Only bar-3", app.note_metadata_storage().unwrap().get_content(note_path2).unwrap());
+    assert_eq!("Also foo-4", app.note_metadata_storage().unwrap().get_content(note_path3).unwrap());
+    assert_eq!(2, repository.reflog("HEAD").unwrap().len());
+
+    assert_eq!(Some(1), app.run_until_completion(replace(Some("2023/08/sample3"), false)).unwrap());
+    assert_eq!("Also bar-4", app.note_metadata_storage().unwrap().get_content(note_path3).unwrap());
+    assert_eq!(3, repository.reflog("HEAD").unwrap().len());
+
+    assert_eq!(Some(0), app.run_until_completion(replace(None, false)).unwrap());
+    assert_eq!(3, repository.reflog("HEAD").unwrap().len());
+
+    assert!(app.run(replace(Some("2023/09"), false)).is_err());
+}
+
+#[test]
+fn test_search_multiline() {
+    use tempfile::TempDir;
+    use regex::Regex;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    git2::Repository::init(&config.repository).unwrap();
+
+    let mut app = App::new(config).unwrap();
+
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent {
+            path: Path::new("2023/07/sample1").to_path_buf(),
+            tags: vec![],
+            content: "First line ends with hello\nworld starts the second.\n".to_owned()
+        },
+        Command::AddNoteWithContent {
+            path: Path::new("2023/07/sample2").to_path_buf(),
+            tags: vec![],
+            content: "hello there\nand world".to_owned()
+        }
+    ]).unwrap();
+
+    let searcher = Searcher::new(app.note_metadata_storage().unwrap()).unwrap();
+    let query = Regex::new(r"(?m)hello\nworld").unwrap();
+
+    assert_eq!(0, searcher.search(&query).unwrap().len());
+
+    let matches = searcher.search_multiline(&query).unwrap();
+    assert_eq!(1, matches.len());
+    assert_eq!(Path::new("2023/07/sample1"), matches[0].path);
+
+    let matches = searcher.search_multiline(&Regex::new(r"(?m)^world").unwrap()).unwrap();
+    assert_eq!(1, matches.len());
+    assert_eq!(Path::new("2023/07/sample1"), matches[0].path);
+}
+
+#[test]
+fn test_search_resources() {
+    use structopt::StructOpt;
+    use tempfile::TempDir;
+    use regex::Regex;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    git2::Repository::init(&config.repository).unwrap();
+
+    let resources_dir = config.resources_dir();
+    std::fs::create_dir_all(resources_dir.join("docs")).unwrap();
+    std::fs::write(resources_dir.join("docs/readme.txt"), "first line\nhello world\n").unwrap();
+    std::fs::write(resources_dir.join("image.bin"), b"hello world\0\x01").unwrap();
+    std::fs::write(resources_dir.join("large.txt"), format!("{}\nhello world\n", "text ".repeat(2000))).unwrap();
+
+    let mut app = App::new(config).unwrap();
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent {
+            path: Path::new("2023/07/sample").to_path_buf(),
+            tags: vec![],
+            content: "nothing".to_owned()
+        }
+    ]).unwrap();
+
+    let searcher = Searcher::new(app.note_metadata_storage().unwrap()).unwrap();
+    let mut output = Vec::new();
+    let matches = searcher.search_resources_to(&Regex::new("world").unwrap(), &resources_dir, &mut output, false).unwrap();
+    assert_eq!(vec![Path::new("docs/readme.txt").to_owned(), Path::new("large.txt").to_owned()], matches);
+    assert_eq!("resource:docs/readme.txt: hello world\nresource:large.txt: hello world\n", String::from_utf8(output).unwrap());
+
+    assert!(InputCommand::from_iter_safe(["gitnotes", "grep", "--tree", "--include-resources", "world"]).is_err());
+}
+
+#[test]
+fn test_search_grouped() {
+    use tempfile::TempDir;
+    use regex::Regex;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    git2::Repository::init(&config.repository).unwrap();
+
+    let mut app = App::new(config).unwrap();
+
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent {
+            path: Path::new("2023/07/sample1").to_path_buf(),
+            tags: vec![],
+            content: "hello world\nnothing here\nworld again\n".to_owned()
+        },
+        Command::AddNoteWithContent {
+            path: Path::new("2023/07/sample2").to_path_buf(),
+            tags: vec![],
+            content: "the world".to_owned()
+        }
+    ]).unwrap();
+
+    let note_metadata_storage = app.note_metadata_storage().unwrap();
+    let info_text1 = note_metadata_storage.get(Path::new("2023/07/sample1")).unwrap().info_text();
+    let info_text2 = note_metadata_storage.get(Path::new("2023/07/sample2")).unwrap().info_text();
+    let (first, second) = if note_metadata_storage.notes().next().unwrap().path == Path::new("2023/07/sample1") {
+        (format!("{}\n  hello world\n  world again\n", info_text1), format!("{}\n  the world\n", info_text2))
+    } else {
+        (format!("{}\n  the world\n", info_text2), format!("{}\n  hello world\n  world again\n", info_text1))
+    };
+
+    let mut searcher = Searcher::new(note_metadata_storage).unwrap();
+    searcher.set_grouped(true);
+
+    let mut output = Vec::new();
+    let matches = searcher.search_to(&Regex::new("world").unwrap(), &mut output, false).unwrap();
+    assert_eq!(3, matches.len());
+    assert_eq!(format!("{}\n{}", first, second), String::from_utf8(output).unwrap());
+
+    searcher.set_limit(Some(2));
+    let mut output = Vec::new();
+    let matches = searcher.search_to(&Regex::new("world").unwrap(), &mut output, false).unwrap();
+    assert_eq!(2, matches.len());
+    assert_eq!(2, String::from_utf8(output).unwrap().lines().filter(|line| line.starts_with("  ")).count());
+}
+
+#[test]
+fn test_search_context() {
+    use tempfile::TempDir;
+    use regex::Regex;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    let repository = git2::Repository::init(&config.repository).unwrap();
+
+    let mut app = App::new(config).unwrap();
+
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent {
+            path: Path::new("2023/07/sample1").to_path_buf(),
+            tags: vec![],
+            content: "a\nb\nworld 1\nc\nd\ne\nf\nworld 2\ng\n".to_owned()
+        }
+    ]).unwrap();
+
+    let note_metadata_storage = app.note_metadata_storage().unwrap();
+    let info_text = note_metadata_storage.get(Path::new("2023/07/sample1")).unwrap().info_text();
+    let mut searcher = Searcher::new(note_metadata_storage).unwrap();
+
+    searcher.set_context(1, 1);
+    let mut output = Vec::new();
+    let matches = searcher.search_to(&Regex::new("world").unwrap(), &mut output, false).unwrap();
+    assert_eq!(2, matches.len());
+    assert_eq!(
+        format!("{0}- b\n{0}: world 1\n{0}- c\n--\n{0}- f\n{0}: world 2\n{0}- g\n", info_text),
+        String::from_utf8(output).unwrap()
+    );
+
+    // Overlapping context is merged into the same hunk
+    searcher.set_context(2, 2);
+    let mut output = Vec::new();
+    searcher.search_to(&Regex::new("world").unwrap(), &mut output, false).unwrap();
+    assert_eq!(
+        format!("{0}- a\n{0}- b\n{0}: world 1\n{0}- c\n{0}- d\n{0}- e\n{0}- f\n{0}: world 2\n{0}- g\n", info_text),
+        String::from_utf8(output).unwrap()
+    );
+
+    searcher.set_context(0, 1);
+    searcher.set_grouped(true);
+    let mut output = Vec::new();
+    searcher.search_to(&Regex::new("world").unwrap(), &mut output, false).unwrap();
+    assert_eq!(
+        format!("{}\n  world 1\n  c\n--\n  world 2\n  g\n", info_text),
+        String::from_utf8(output).unwrap()
+    );
+
+    searcher.set_grouped(false);
+    let mut output = Vec::new();
+    let matches = searcher.search_historic_to(&repository, &Regex::new("world 2").unwrap(), "HEAD", None, &mut output, false).unwrap();
+    assert_eq!(1, matches.len());
+    let output = String::from_utf8(output).unwrap();
+    assert_eq!(2, output.lines().count());
+    assert!(output.lines().next().unwrap().ends_with(&format!(" - {}: world 2", info_text)), "{}", output);
+    assert!(output.lines().nth(1).unwrap().ends_with(&format!(" - {}- g", info_text)), "{}", output);
+}
+
+#[test]
+fn test_search_historic() {
+    use tempfile::TempDir;
+    use regex::Regex;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    let repository = git2::Repository::init(&config.repository).unwrap();
+
+    let note_path = Path::new("2023/07/sample");
+
+    let mut app = App::new(config).unwrap();
+
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent {
+            path: note_path.to_path_buf(),
+            tags: vec![],
+            content: "Hello, World!".to_owned()
+        }
+    ]).unwrap();
+    let first_commit_id = repository.head().unwrap().peel_to_commit().unwrap().id();
+
+    app.create_and_execute_commands(vec![
+        Command::EditNoteSetContent {
+            path: note_path.to_path_buf(),
+            clear_tags: false,
+            add_tags: vec![],
+            content: "Hello, Earth!".to_owned(),
+            force: false
+        }
+    ]).unwrap();
+    let second_commit_id = repository.head().unwrap().peel_to_commit().unwrap().id();
+
+    let searcher = Searcher::new(app.note_metadata_storage().unwrap()).unwrap();
+
+    let matches = searcher.search_historic_to(&repository, &Regex::new("Hello").unwrap(), "HEAD", None, &mut Vec::new(), false).unwrap();
+    assert_eq!(
+        vec![(second_commit_id, note_path.to_path_buf()), (first_commit_id, note_path.to_path_buf())],
+        matches.into_iter().map(|(commit_id, note_metadata)| (commit_id, note_metadata.path)).collect::<Vec<_>>()
+    );
+
+    let matches = searcher.search_historic_to(&repository, &Regex::new("World").unwrap(), "HEAD", None, &mut Vec::new(), false).unwrap();
+    assert_eq!(1, matches.len());
+    assert_eq!(first_commit_id, matches[0].0);
+
+    let matches = searcher.search_historic_to(&repository, &Regex::new("World").unwrap(), "HEAD", Some("HEAD~1"), &mut Vec::new(), false).unwrap();
+    assert_eq!(0, matches.len());
+}
+
+#[test]
+fn test_search_matches_json() {
+    use structopt::StructOpt;
+    use tempfile::TempDir;
+    use regex::Regex;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    git2::Repository::init(&config.repository).unwrap();
+
+    let mut app = App::new(config).unwrap();
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent {
+            path: Path::new("2023/07/sample").to_path_buf(),
+            tags: vec![],
+            content: "nothing here\nhello world\n".to_owned()
+        },
+        Command::AddNoteWithContent {
+            path: Path::new("2023/07/other").to_path_buf(),
+            tags: vec![],
+            content: "nothing".to_owned()
+        }
+    ]).unwrap();
+
+    let note_metadata_storage = app.note_metadata_storage().unwrap();
+    let id = note_metadata_storage.get(Path::new("2023/07/sample")).unwrap().id;
+    let mut searcher = Searcher::new(note_metadata_storage).unwrap();
+    let matches = searcher.search_matches(&Regex::new("world").unwrap()).unwrap();
+
+    let json: serde_json::Value = serde_json::from_str(&search_matches_json(&matches)).unwrap();
+    assert_eq!(
+        serde_json::json!([{
+            "path": "2023/07/sample",
+            "id": id.to_string(),
+            "line_number": 2,
+            "line": "hello world",
+            "match_start": 6,
+            "match_end": 11
+        }]),
+        json
+    );
+
+    // The limit is the number of matching lines, which can contain multiple matches
+    searcher.set_limit(Some(1));
+    let matches = searcher.search_matches(&Regex::new("l").unwrap()).unwrap();
+    assert_eq!(3, matches.len());
+    assert!(matches.iter().all(|current_match| current_match.line_number == 2));
+
+    assert!(InputCommand::from_iter_safe(["gitnotes", "grep", "--json", "world"]).is_ok());
+    assert!(InputCommand::from_iter_safe(["gitnotes", "grep", "--json", "--include-resources", "world"]).is_err());
+}
+
+#[test]
+fn test_search_counts() {
+    use structopt::StructOpt;
+    use tempfile::TempDir;
+    use regex::Regex;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    git2::Repository::init(&config.repository).unwrap();
+
+    let mut app = App::new(config).unwrap();
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent {
+            path: Path::new("2023/07/sample").to_path_buf(),
+            tags: vec![],
+            content: "hello world\nnothing here\nworld again\n".to_owned()
+        },
+        Command::AddNoteWithContent {
+            path: Path::new("2023/07/another").to_path_buf(),
+            tags: vec![],
+            content: "the world".to_owned()
+        },
+        Command::AddNoteWithContent {
+            path: Path::new("2023/07/other").to_path_buf(),
+            tags: vec![],
+            content: "nothing".to_owned()
+        }
+    ]).unwrap();
+
+    let searcher = Searcher::new(app.note_metadata_storage().unwrap()).unwrap();
+    let to_paths = |results: Vec<(&NoteMetadata, usize)>| {
+        results
+            .into_iter()
+            .map(|(note_metadata, count)| (note_metadata.path.to_str().unwrap().to_owned(), count))
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        vec![("2023/07/another".to_owned(), 1), ("2023/07/sample".to_owned(), 2)],
+        to_paths(searcher.search_counts(&Regex::new("world").unwrap(), false).unwrap())
+    );
+    assert_eq!(
+        vec![("2023/07/another".to_owned(), 1), ("2023/07/sample".to_owned(), 1)],
+        to_paths(searcher.search_counts(&Regex::new("world").unwrap(), true).unwrap())
+    );
+
+    let grep = |args: &[&str]| InputCommand::from_iter_safe(["gitnotes", "grep"].iter().chain(args.iter())).unwrap();
+    assert_eq!(Some(2), app.run_until_completion(grep(&["--count", "world"])).unwrap());
+    assert_eq!(Some(2), app.run_until_completion(grep(&["-l", "world"])).unwrap());
+    assert!(InputCommand::from_iter_safe(["gitnotes", "grep", "-l", "--count", "world"]).is_err());
+    assert!(InputCommand::from_iter_safe(["gitnotes", "grep", "-l", "-C", "2", "world"]).is_err());
+}
+
+#[test]
+fn test_search_invalid_utf8() {
+    use tempfile::TempDir;
+    use regex::Regex;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    let repository_path = config.repository.clone();
+    git2::Repository::init(&config.repository).unwrap();
+
+    let mut app = App::new(config).unwrap();
+
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent {
+            path: Path::new("2023/07/binary").to_path_buf(),
+            tags: vec![],
+            content: String::new()
+        },
+        Command::AddNoteWithContent {
+            path: Path::new("2023/07/sample").to_path_buf(),
+            tags: vec![],
+            content: "hello world".to_owned()
+        }
+    ]).unwrap();
+
+    let binary_id = app.note_metadata_storage().unwrap().get_id(Path::new("2023/07/binary")).unwrap();
+    let binary_path = NoteMetadataStorage::get_note_storage_path(&repository_path, &binary_id).1;
+    std::fs::write(&binary_path, b"hello \xFF\xFE binary\n").unwrap();
+
+    let note_metadata_storage = app.note_metadata_storage().unwrap();
+    let searcher = Searcher::new(note_metadata_storage).unwrap();
+    let mut matches = searcher.search(&Regex::new("world|binary").unwrap()).unwrap();
+    matches.sort_by_key(|note_metadata| &note_metadata.path);
+    assert_eq!(vec![Path::new("2023/07/binary"), Path::new("2023/07/sample")], matches.iter().map(|note| note.path.as_path()).collect::<Vec<_>>());
+
+    assert_eq!(
+        ("hello \u{FFFD}\u{FFFD} binary\n".to_owned(), false),
+        note_metadata_storage.get_content_lossy(Path::new("2023/07/binary")).unwrap()
+    );
+}
+
+#[test]
+fn test_link_graph() {
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    git2::Repository::init(&config.repository).unwrap();
+
+    let mut app = App::new(config).unwrap();
+
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent {
+            path: Path::new("topics/a").to_path_buf(),
+            tags: vec![],
+            content: "See [B](b) and [C](/other/c).".to_owned()
+        },
+        Command::AddNoteWithContent {
+            path: Path::new("topics/b").to_path_buf(),
+            tags: vec![],
+            content: "Back to [A](a).".to_owned()
+        },
+        Command::AddNoteWithContent {
+            path: Path::new("other/c").to_path_buf(),
+            tags: vec![],
+            content: "Also [B](topics/b), [web](https://example.com) and [missing](d).".to_owned()
+        }
+    ]).unwrap();
+
+    let note_metadata_storage = app.note_metadata_storage().unwrap();
+    let link_graph = LinkGraph::new(note_metadata_storage).unwrap();
+    let paths = |notes: Vec<&NoteMetadata>| notes.iter().map(|note| note.path.to_str().unwrap().to_owned()).collect::<Vec<_>>();
+
+    let id_a = note_metadata_storage.get_id(Path::new("topics/a")).unwrap();
+    assert_eq!(vec!["other/c", "topics/b"], paths(link_graph.outbound(&id_a)));
+    assert_eq!(vec!["topics/b"], paths(link_graph.inbound(&id_a)));
+
+    let id_b = note_metadata_storage.get_id(Path::new("topics/b")).unwrap();
+    assert_eq!(vec!["topics/a"], paths(link_graph.outbound(&id_b)));
+    assert_eq!(vec!["other/c", "topics/a"], paths(link_graph.inbound(&id_b)));
+}
+
+#[test]
+fn test_duplicates() {
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    git2::Repository::init(&config.repository).unwrap();
+
+    let note_path1 = Path::new("2023/07/sample1");
+    let note_path2 = Path::new("2023/07/sample2");
+    let note_path3 = Path::new("2023/07/sample3");
+
+    let mut app = App::new(config).unwrap();
+
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent {
+            path: note_path1.to_path_buf(),
+            tags: vec![],
+            content: "Hello, World!".to_owned()
+        },
+        Command::AddNoteWithContent {
+            path: note_path2.to_path_buf(),
+            tags: vec![],
+            content: "Goodbye, World!".to_owned()
+        },
+        Command::AddNoteWithContent {
+            path: note_path3.to_path_buf(),
+            tags: vec![],
+            content: "Hello, World!".to_owned()
+        }
+    ]).unwrap();
+
+    app.run(InputCommand::Duplicates { exact: true, similar: false, threshold: 0.8 }).unwrap();
+
+    let result = app.run(InputCommand::Duplicates { exact: false, similar: true, threshold: 1.5 });
+    assert!(matches!(result, Err(AppError::Input(_))));
+    let result = app.run(InputCommand::Duplicates { exact: false, similar: true, threshold: -0.1 });
+    assert!(matches!(result, Err(AppError::Input(_))));
+
+    let duplicate_finder = DuplicateFinder::new(app.note_metadata_storage().unwrap()).unwrap();
+    let groups = duplicate_finder.find_exact().unwrap();
+    assert_eq!(1, groups.len());
+    assert_eq!(
+        vec![note_path1, note_path3],
+        groups[0].iter().map(|note_metadata| note_metadata.path.as_path()).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn test_list_directory_with_size() {
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    git2::Repository::init(&config.repository).unwrap();
+
+    let mut app = App::new(config).unwrap();
+
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent {
+            path: Path::new("2023/07/sample1").to_path_buf(),
+            tags: vec![],
+            content: "Hello, World!".to_owned()
+        },
+        Command::AddNoteWithContent {
+            path: Path::new("2023/07/sample2").to_path_buf(),
+            tags: vec![],
+            content: "Hello".to_owned()
+        }
+    ]).unwrap();
+
+    app.run(InputCommand::ListDirectory { query: Some(Path::new("2023").to_owned()), with_size: true, pinned_first: false, format: OutputFormat::Text }).unwrap();
+    app.run(InputCommand::ListDirectory { query: Some(Path::new("2023").to_owned()), with_size: false, pinned_first: false, format: OutputFormat::Json }).unwrap();
+
+    let list_directory = ListDirectory::new(app.note_metadata_storage().unwrap()).unwrap();
+    let results = list_directory.list(Path::new("2023/07"), true).unwrap();
+    assert_eq!(2, results.len());
+    assert_eq!("sample1", results[0].name);
+    assert_eq!(Some(13), results[0].size);
+    assert_eq!(Some(5), results[1].size);
+
+    let results = list_directory.list(Path::new("2023"), true).unwrap();
+    assert_eq!(1, results.len());
+    assert_eq!(Some(18), results[0].size);
+
+    let results = list_directory.list(Path::new("2023"), false).unwrap();
+    assert_eq!(None, results[0].size);
+}
+
+#[test]
+fn test_commit_hook() {
+    use std::cell::RefCell;
+    use std::path::PathBuf;
+    use std::rc::Rc;
+
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    let repository = git2::Repository::init(&config.repository).unwrap();
+
+    let note_path1 = Path::new("2023/07/sample1");
+    let note_path2 = Path::new("2023/07/sample2");
+
+    let mut app = App::new(config).unwrap();
+
+    let commits = Rc::new(RefCell::new(Vec::<(git2::Oid, Vec<PathBuf>)>::new()));
+    let commits_clone = commits.clone();
+    app.set_commit_hook(Some(Box::new(move |commit_id, changed_note_paths| {
+        commits_clone.borrow_mut().push((commit_id, changed_note_paths.to_vec()));
+    })));
+
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent {
+            path: note_path1.to_path_buf(),
+            tags: vec![],
+            content: "Test1".to_owned()
+        },
+        Command::AddNoteWithContent {
+            path: note_path2.to_path_buf(),
+            tags: vec![],
+            content: "Test2".to_owned()
+        }
+    ]).unwrap();
+
+    assert_eq!(1, commits.borrow().len());
+    assert_eq!(repository.head().unwrap().target().unwrap(), commits.borrow()[0].0);
+    assert_eq!(vec![note_path1.to_owned(), note_path2.to_owned()], commits.borrow()[0].1);
+
+    app.run(InputCommand::Remove { path: note_path1.to_owned(), recursive: false, dry_run: false, yes: false, force: false }).unwrap();
+    assert_eq!(2, commits.borrow().len());
+    assert_eq!(vec![note_path1.to_owned()], commits.borrow()[1].1);
+}
+
+#[test]
+fn test_timings() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let config = create_test_config(&temp_repository_dir);
+    git2::Repository::init(&config.repository).unwrap();
+
+    let mut app = App::new(config).unwrap();
+
+    let output = Rc::new(RefCell::new(Vec::<u8>::new()));
+    app.set_timings(Timings::with_writer(output.clone()));
+
+    app.create_and_execute_commands(vec![
+        Command::AddNoteWithContent {
+            path: Path::new("2023/07/sample").to_path_buf(),
+            tags: vec![],
+            content: "Test1".to_owned()
+        }
+    ]).unwrap();
+
+    let output = String::from_utf8(output.borrow().clone()).unwrap();
+    let lines = output.lines().collect::<Vec<_>>();
+    assert!(lines.iter().any(|line| line.starts_with("[timings] Metadata load: ")));
+    assert!(lines.iter().any(|line| line.starts_with("[timings] Commit: ")));
+    assert!(lines.iter().any(|line| line.starts_with("[timings] Command execution: ")));
+}
+
+fn create_test_config(temp_repository_dir: &tempfile::TempDir) -> Config {
     let mut config = Config::from_env(FileConfig::new(&temp_repository_dir.path().to_path_buf()));
     config.use_working_dir = false;
     config
-}
\ No newline at end of file
+}
+
@@ -0,0 +1,31 @@
+pub trait Clipboard {
+    fn set_text(&mut self, text: &str) -> Result<(), String>;
+}
+
+/// The clipboard of the system.
+/// On Linux (X11/Wayland) the content is owned by the process that set it, so it is served for a short while
+/// to let a clipboard manager take it over. Without a clipboard manager, the content is lost when gitnotes exits.
+pub struct SystemClipboard;
+
+#[cfg(target_os = "linux")]
+const LINUX_SERVE_DURATION: std::time::Duration = std::time::Duration::from_secs(2);
+
+impl Clipboard for SystemClipboard {
+    #[cfg(target_os = "linux")]
+    fn set_text(&mut self, text: &str) -> Result<(), String> {
+        use arboard::SetExtLinux;
+
+        let mut clipboard = arboard::Clipboard::new().map_err(|err| err.to_string())?;
+        clipboard
+            .set()
+            .wait_until(std::time::Instant::now() + LINUX_SERVE_DURATION)
+            .text(text.to_owned())
+            .map_err(|err| err.to_string())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn set_text(&mut self, text: &str) -> Result<(), String> {
+        let mut clipboard = arboard::Clipboard::new().map_err(|err| err.to_string())?;
+        clipboard.set_text(text).map_err(|err| err.to_string())
+    }
+}
@@ -1,21 +1,23 @@
 use std::ffi::{OsStr};
 use std::ops::Deref;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-use chrono::Local;
+use chrono::{DateTime, Local};
 use comrak::Arena;
 use thiserror::Error;
 
 use comrak::nodes::{AstNode, NodeValue};
 
 use crate::config::Config;
-use crate::model::{NOTE_CONTENT_EXT, NoteId, NoteMetadata, NoteMetadataStorage, NOTES_DIR, RESOURCES_DIR};
-use crate::{editor, markdown, tags};
+use crate::model::{AccessTimes, created_from_path, NoteAliases, NOTE_CONTENT_EXT, NoteId, NoteMetadata, NoteMetadataStorage, NOTES_DIR, RESOURCES_DIR, TRASH_DIR};
+use crate::{editor, git_helpers, helpers, markdown, tags};
 use crate::app::{RepositoryRef};
 use crate::editor::EditorOutput;
-use crate::helpers::{get_or_insert_with, OrderedSet};
+use crate::markdown::NoteDirectives;
+use crate::helpers::{get_or_insert_with, OrderedSet, Timings};
 use crate::querying::{GitContentFetcher};
-use crate::snippets::{SnippetError, SnippetRunnerManger};
+use crate::snippets::{SnippetContext, SnippetError, SnippetOutput, SnippetResult, SnippetRunnerManger};
 use crate::web_editor::AccessMode;
 
 #[derive(Debug)]
@@ -25,7 +27,8 @@ pub enum Command {
     },
     AddNote {
         path: PathBuf,
-        tags: Vec<String>
+        tags: Vec<String>,
+        allow_empty: bool
     },
     AddNoteWithContent {
         path: PathBuf,
@@ -36,25 +39,66 @@ pub enum Command {
         path: PathBuf,
         history: Option<String>,
         clear_tags: bool,
-        add_tags: Vec<String>
+        add_tags: Vec<String>,
+        force: bool
     },
     EditNoteSetContent {
         path: PathBuf,
         clear_tags: bool,
         add_tags: Vec<String>,
-        content: String
+        content: String,
+        force: bool
     },
     MoveNote {
         source: PathBuf,
         destination: PathBuf,
-        force: bool
+        force: bool,
+        merge: bool,
+        ignore_lock: bool
+    },
+    CopyNote {
+        source: PathBuf,
+        destination: PathBuf,
+        force: bool,
+        ignore_lock: bool
     },
     RemoveNote {
-        path: PathBuf
+        path: PathBuf,
+        force: bool
+    },
+    RestoreNote {
+        id: NoteId
+    },
+    ChangeNoteId {
+        path: PathBuf,
+        new_id: NoteId
+    },
+    SetNoteMetadata {
+        path: PathBuf,
+        tags: Vec<String>,
+        created: DateTime<Local>,
+        last_updated: DateTime<Local>
+    },
+    SetNotePinned {
+        path: PathBuf,
+        pinned: bool
+    },
+    SetNoteLocked {
+        path: PathBuf,
+        locked: bool
+    },
+    SetNoteTags {
+        path: PathBuf,
+        tags: Vec<String>
     },
     UndoCommit {
         commit: String
     },
+    SquashCommits {
+        from: String,
+        to: String,
+        force: bool
+    },
     RunSnippet {
         path: PathBuf,
         save_output: bool
@@ -70,10 +114,15 @@ pub enum Command {
 }
 
 pub type LaunchEditorFn = Box<dyn Fn(&Config, &Path, &Path) -> CommandResult<EditorOutput>>;
+/// Called after a commit has been created with the id of the commit and the paths of the changed notes.
+pub type CommitHookFn = Box<dyn Fn(git2::Oid, &[PathBuf])>;
+
 pub struct CommandInterpreter {
     config: Config,
 
     launch_editor: LaunchEditorFn,
+    commit_hook: Option<CommitHookFn>,
+    timings: Timings,
 
     repository: RepositoryRef,
 
@@ -82,7 +131,9 @@ pub struct CommandInterpreter {
 
     index: Option<git2::Index>,
     commit_message_lines: OrderedSet<String>,
-    changed_files: Vec<PathBuf>
+    changed_files: Vec<PathBuf>,
+    changed_note_paths: OrderedSet<PathBuf>,
+    changed_note_ids: Vec<(NoteId, NoteId)>
 }
 
 impl CommandInterpreter {
@@ -98,12 +149,15 @@ impl CommandInterpreter {
         let snippet_runner_manager = SnippetRunnerManger::from_config(
             config.snippet.as_ref()
         ).map_err(|err| CommandError::Snippet(err))?;
+        let timings = Timings::from_enabled(config.timings);
 
         Ok(
             CommandInterpreter {
                 config,
 
                 launch_editor,
+                commit_hook: None,
+                timings,
 
                 repository,
 
@@ -112,7 +166,9 @@ impl CommandInterpreter {
 
                 index: None,
                 commit_message_lines: OrderedSet::new(),
-                changed_files: Vec::new()
+                changed_files: Vec::new(),
+                changed_note_paths: OrderedSet::new(),
+                changed_note_ids: Vec::new()
             }
         )
     }
@@ -120,6 +176,10 @@ impl CommandInterpreter {
     pub fn execute(&mut self, commands: Vec<Command>) -> CommandResult<()> {
         use CommandError::*;
 
+        if self.repository.borrow().is_bare() {
+            return Err(BareRepository);
+        }
+
         for command in commands.into_iter() {
             match command {
                 Command::UpdateSymbolicLinks { } => {
@@ -132,10 +192,10 @@ impl CommandInterpreter {
                         create_note_symbolic_link(&self.config.repository, note)?;
                     }
                 }
-                Command::AddNote { path, tags } => {
+                Command::AddNote { path, tags, allow_empty } => {
                     self.check_if_note_exists(&path)?;
 
-                    let id = NoteId::new();
+                    let id = self.new_note_id()?;
                     let (relative_content_path, abs_content_path) = self.get_note_storage_path(&id);
 
                     if !abs_content_path.exists() {
@@ -148,21 +208,31 @@ impl CommandInterpreter {
                         &path
                     ).map_err(|err| FailedToAddNote(err.to_string()))?;
 
+                    if !allow_empty {
+                        let content = std::fs::read(&abs_content_path).unwrap_or_default();
+                        if String::from_utf8_lossy(&content).trim().is_empty() {
+                            let _ = std::fs::remove_file(&abs_content_path);
+                            return Err(EmptyNote);
+                        }
+                    }
+
                     self.add_note(id, &relative_content_path, path, tags)?;
                     self.add_resources_from_editor_output(output)?;
                 }
                 Command::AddNoteWithContent { path, tags, content } => {
                     self.check_if_note_exists(&path)?;
 
-                    let id = NoteId::new();
+                    let id = self.new_note_id()?;
                     let (relative_note_path, abs_note_path) = self.get_note_storage_path(&id);
 
+                    let content = self.normalize_content(content);
                     std::fs::write(&abs_note_path, content).map_err(|err| FailedToAddNote(err.to_string()))?;
 
                     self.add_note(id, &relative_note_path, path, tags)?;
                 }
-                Command::EditNoteContent { path, history, clear_tags, add_tags } => {
+                Command::EditNoteContent { path, history, clear_tags, add_tags, force } => {
                     let id = self.get_note_id(&path)?;
+                    self.check_if_note_locked(&id, force)?;
                     let note_path = self.get_note_path(&id)?.to_owned();
 
                     let (relative_content_path, abs_content_path) = self.get_note_storage_path(&id);
@@ -194,14 +264,17 @@ impl CommandInterpreter {
 
                     if changed {
                         self.commit_message_lines.insert(format!("Updated note '{}'.", real_path.to_str().unwrap()));
+                        self.changed_note_paths.insert(real_path);
                     }
 
                     self.add_resources_from_editor_output(output)?;
                 }
-                Command::EditNoteSetContent { path, clear_tags, add_tags, content } => {
+                Command::EditNoteSetContent { path, clear_tags, add_tags, content, force } => {
                     let id = self.get_note_id(&path)?;
+                    self.check_if_note_locked(&id, force)?;
                     let (relative_content_path, abs_content_path) = self.get_note_storage_path(&id);
 
+                    let content = self.normalize_content(content);
                     std::fs::write(&abs_content_path, content).map_err(|err| FailedToEditNote(err.to_string()))?;
 
                     self.edited_file(relative_content_path)?;
@@ -211,17 +284,30 @@ impl CommandInterpreter {
 
                     let real_path = self.get_note_path(&id)?.to_str().unwrap().to_owned();
                     self.commit_message_lines.insert(format!("Updated note '{}'.", real_path));
+                    self.changed_note_paths.insert(PathBuf::from(real_path));
                 }
-                Command::MoveNote { source, destination, force } => {
+                Command::MoveNote { source, destination, force, merge, ignore_lock } => {
                     let id = self.get_note_id(&source)?;
+                    self.check_if_note_locked(&id, ignore_lock)?;
                     let real_source_path = self.get_note_path(&id)?.to_str().unwrap().to_owned();
 
                     self.note_metadata_storage_mut()?;
                     let note_symbolic_link = get_note_symbolic_link(&self.config.repository, self.get_note_metadata(&id)?)?;
 
-                    let destination_exist = self.get_note_id(&destination).is_ok();
-                    if destination_exist {
-                        if force {
+                    let destination_id = self.get_note_id(&destination).ok();
+                    if let Some(destination_id) = destination_id {
+                        if destination_id == id {
+                            return Err(SameSourceAndDestination(PathBuf::from(real_source_path)));
+                        }
+
+                        if merge || force {
+                            self.check_if_note_locked(&destination_id, ignore_lock)?;
+                        }
+
+                        if merge {
+                            self.merge_note(&source, &destination)?;
+                            continue;
+                        } else if force {
                             self.remove_note(&destination)?;
                         } else {
                             return Err(NoteExistsAtDestination(destination))?;
@@ -241,10 +327,154 @@ impl CommandInterpreter {
                     self.commit_message_lines.insert(
                         format!("Moved note from '{}' to '{}'.", real_source_path, destination.to_str().unwrap())
                     );
+                    self.changed_note_paths.insert(PathBuf::from(real_source_path));
+                    self.changed_note_paths.insert(destination);
+
+                    // So that later commands in the same batch see the new path
+                    self.note_metadata_storage = None;
+                }
+                Command::CopyNote { source, destination, force, ignore_lock } => {
+                    let source_id = self.get_note_id(&source)?;
+                    let real_source_path = self.get_note_path(&source_id)?.to_str().unwrap().to_owned();
+
+                    if let Ok(destination_id) = self.get_note_id(&destination) {
+                        if force {
+                            self.check_if_note_locked(&destination_id, ignore_lock)?;
+                            self.remove_note(&destination)?;
+                        } else {
+                            return Err(NoteExistsAtDestination(destination))?;
+                        }
+                    }
+
+                    let tags = self.get_note_metadata(&source_id)?.tags.clone();
+                    let id = self.new_note_id()?;
+                    let (_, abs_source_content_path) = self.get_note_storage_path(&source_id);
+                    let (relative_content_path, abs_content_path) = self.get_note_storage_path(&id);
+                    std::fs::copy(&abs_source_content_path, &abs_content_path).map_err(|err| FailedToAddNote(err.to_string()))?;
+
+                    let no_tags = tags.is_empty();
+                    self.add_note(id, &relative_content_path, destination.clone(), tags)?;
+                    if no_tags {
+                        // Keep the copy without tags instead of the automatic ones
+                        self.change_note_tags(&id, true, Vec::new())?;
+                    }
+
+                    self.commit_message_lines.insert(
+                        format!("Copied note from '{}' to '{}'.", real_source_path, destination.to_str().unwrap())
+                    );
                 }
-                Command::RemoveNote { path } => {
+                Command::RemoveNote { path, force } => {
+                    let id = self.get_note_id(&path)?;
+                    self.check_if_note_locked(&id, force)?;
                     self.remove_note(&path)?;
                 }
+                Command::RestoreNote { id } => {
+                    self.restore_note(&id)?;
+                }
+                Command::ChangeNoteId { path, new_id } => {
+                    let id = self.get_note_id(&path)?;
+                    if id == new_id {
+                        continue;
+                    }
+
+                    if self.note_metadata_storage()?.get_by_id(&new_id).is_some() {
+                        return Err(NoteIdAlreadyUsed(new_id.to_string()));
+                    }
+
+                    let real_path = self.get_note_path(&id)?.to_owned();
+                    let note_symbolic_link = get_note_symbolic_link(&self.config.repository, self.get_note_metadata(&id)?)?;
+
+                    let (relative_content_path, abs_content_path) = self.get_note_storage_path(&id);
+                    let (relative_metadata_path, abs_metadata_path) = self.get_note_metadata_path(&id);
+                    let (new_relative_content_path, new_abs_content_path) = self.get_note_storage_path(&new_id);
+                    let (new_relative_metadata_path, new_abs_metadata_path) = self.get_note_metadata_path(&new_id);
+
+                    let mut note_metadata = self.get_note_metadata(&id)?.clone();
+                    note_metadata.id = new_id;
+                    note_metadata.save(&new_abs_metadata_path).map_err(|err| FailedToUpdateMetadata(err.to_string()))?;
+                    std::fs::rename(&abs_content_path, &new_abs_content_path)?;
+                    std::fs::remove_file(&abs_metadata_path)?;
+
+                    let index = self.index()?;
+                    index.remove_path(&relative_content_path)?;
+                    index.remove_path(&relative_metadata_path)?;
+                    index.add_path(&new_relative_content_path)?;
+                    index.add_path(&new_relative_metadata_path)?;
+                    index.write()?;
+
+                    let _ = std::fs::remove_file(&note_symbolic_link);
+                    create_note_symbolic_link(&self.config.repository, &note_metadata)?;
+
+                    self.note_metadata_storage = None;
+
+                    self.commit_message_lines.insert(format!("Changed id of note '{}' from {} to {}.", real_path.to_str().unwrap(), id, new_id));
+                    self.changed_files.push(relative_content_path);
+                    self.changed_files.push(relative_metadata_path);
+                    self.changed_files.push(new_relative_content_path);
+                    self.changed_files.push(new_relative_metadata_path);
+                    self.changed_note_paths.insert(real_path);
+                    self.changed_note_ids.push((id, new_id));
+                }
+                Command::SetNoteMetadata { path, tags, created, last_updated } => {
+                    let id = self.get_note_id(&path)?;
+                    let real_path = self.get_note_path(&id)?.to_owned();
+
+                    self.change_note_metadata(&id, |note_metadata| {
+                        let changed = note_metadata.tags != tags
+                            || note_metadata.created != created
+                            || note_metadata.last_updated != last_updated;
+
+                        note_metadata.tags = tags.clone();
+                        note_metadata.created = created;
+                        note_metadata.last_updated = last_updated;
+                        changed
+                    })?;
+
+                    self.commit_message_lines.insert(format!("Updated metadata of note '{}'.", real_path.to_str().unwrap()));
+                    self.changed_note_paths.insert(real_path);
+                }
+                Command::SetNotePinned { path, pinned } => {
+                    let id = self.get_note_id(&path)?;
+                    let real_path = self.get_note_path(&id)?.to_owned();
+
+                    self.change_note_metadata(&id, |note_metadata| {
+                        let changed = note_metadata.pinned != pinned;
+                        note_metadata.pinned = pinned;
+                        changed
+                    })?;
+
+                    let action = if pinned { "Pinned" } else { "Unpinned" };
+                    self.commit_message_lines.insert(format!("{} note '{}'.", action, real_path.to_str().unwrap()));
+                    self.changed_note_paths.insert(real_path);
+                }
+                Command::SetNoteLocked { path, locked } => {
+                    let id = self.get_note_id(&path)?;
+                    let real_path = self.get_note_path(&id)?.to_owned();
+
+                    self.change_note_metadata(&id, |note_metadata| {
+                        let changed = note_metadata.locked != locked;
+                        note_metadata.locked = locked;
+                        changed
+                    })?;
+
+                    let action = if locked { "Locked" } else { "Unlocked" };
+                    self.commit_message_lines.insert(format!("{} note '{}'.", action, real_path.to_str().unwrap()));
+                    self.changed_note_paths.insert(real_path);
+                }
+                Command::SetNoteTags { path, tags } => {
+                    let id = self.get_note_id(&path)?;
+                    let real_path = self.get_note_path(&id)?.to_owned();
+
+                    let tags_str = tags.join(", ");
+                    self.change_note_metadata(&id, |note_metadata| {
+                        let changed = note_metadata.tags != tags;
+                        note_metadata.tags = tags.clone();
+                        changed
+                    })?;
+
+                    self.commit_message_lines.insert(format!("Set tags of note '{}' to: {}.", real_path.to_str().unwrap(), tags_str));
+                    self.changed_note_paths.insert(real_path);
+                }
                 Command::UndoCommit { commit } => {
                     let git_commit_id = {
                         let repository = self.repository.borrow_mut();
@@ -252,14 +482,83 @@ impl CommandInterpreter {
                         let git_commit = git_commit.as_commit().ok_or_else(|| CommitNotFound(commit.clone()))?;
                         let git_commit_id = git_commit.as_object().short_id().unwrap().as_str().unwrap().to_owned();
 
-                        repository.revert(&git_commit, None).map_err(|err| FailedToUndo(err.to_string()))?;
-                        repository.cleanup_state()?;
+                        let parent_tree = match git_commit.parent_count() {
+                            0 => repository.find_tree(repository.treebuilder(None)?.write()?)?,
+                            1 => git_commit.parent(0)?.tree()?,
+                            _ => { return Err(FailedToUndo(format!("'{}' is a merge commit", git_commit_id))); }
+                        };
+
+                        // Reverts relative to the index (instead of HEAD), so that multiple commits can be undone in the same commit
+                        let index = CommandInterpreter::get_index(repository.deref(), &mut self.index)?;
+                        let current_tree = repository.find_tree(index.write_tree()?)?;
+                        let mut reverted_index = repository.merge_trees(&git_commit.tree()?, &current_tree, &parent_tree, None)?;
+                        if reverted_index.has_conflicts() {
+                            return Err(FailedToUndo(format!("the changes of '{}' conflict with later changes", git_commit_id)));
+                        }
+
+                        let reverted_tree = repository.find_tree(reverted_index.write_tree_to(repository.deref())?)?;
+                        repository.checkout_tree(
+                            reverted_tree.as_object(),
+                            Some(git2::build::CheckoutBuilder::new().force())
+                        )?;
+                        index.read_tree(&reverted_tree)?;
+                        index.write()?;
 
                         git_commit_id
                     };
 
+                    self.note_metadata_storage = None;
                     self.commit_message_lines.insert(format!("Undo commit '{}'.", git_commit_id));
                 },
+                Command::SquashCommits { from, to, force } => {
+                    let message_lines = {
+                        let repository = self.repository.borrow_mut();
+                        let base_commit = repository.revparse_single(&from)?.peel_to_commit().map_err(|_| CommitNotFound(from.clone()))?;
+                        let last_commit = repository.revparse_single(&to)?.peel_to_commit().map_err(|_| CommitNotFound(to.clone()))?;
+
+                        let head_commit = repository.head()?.peel_to_commit()?;
+                        if last_commit.id() != head_commit.id() {
+                            return Err(FailedToSquash("the end of the range must be the current HEAD".to_owned()));
+                        }
+
+                        if last_commit.id() == base_commit.id() {
+                            return Err(FailedToSquash("no commits in range".to_owned()));
+                        }
+
+                        if !repository.graph_descendant_of(last_commit.id(), base_commit.id())? {
+                            return Err(FailedToSquash(format!("'{}' is not an ancestor of '{}'", from, to)));
+                        }
+
+                        let mut rev_walk = repository.revwalk()?;
+                        rev_walk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::REVERSE)?;
+                        rev_walk.push(last_commit.id())?;
+                        rev_walk.hide(base_commit.id())?;
+
+                        let mut message_lines = Vec::new();
+                        for commit_id in rev_walk {
+                            let commit = repository.find_commit(commit_id?)?;
+                            if commit.parent_count() > 1 && !force {
+                                return Err(FailedToSquash(format!("range contains merge commit '{}', use --force to squash anyway", commit.id())));
+                            }
+
+                            for line in commit.message().unwrap_or("").lines() {
+                                if !line.trim().is_empty() {
+                                    message_lines.push(line.to_owned());
+                                }
+                            }
+                        }
+
+                        // Keeps the index (and working tree) at the last commit, so committing re-creates the accumulated tree
+                        repository.reset(base_commit.as_object(), git2::ResetType::Soft, None)?;
+                        message_lines
+                    };
+
+                    self.index = None;
+                    self.note_metadata_storage = None;
+                    for line in message_lines {
+                        self.commit_message_lines.insert(line);
+                    }
+                }
                 Command::RunSnippet { path, save_output } => {
                     let id = self.get_note_id(&path)?;
                     let (relative_note_path, abs_note_path) = self.get_note_storage_path(&id);
@@ -267,7 +566,9 @@ impl CommandInterpreter {
                     let content = std::fs::read_to_string(&abs_note_path)?;
 
                     let arena = markdown::storage();
-                    let root = run_snippet(&self.snippet_runner_manager, &arena, &content, |text| print!("{}", text))?;
+                    let note_path = self.get_note_path(&id)?.to_owned();
+                    let context = SnippetContext::new(&self.config.repository, &note_path);
+                    let root = run_snippet(&self.snippet_runner_manager, &arena, &content, Some(&context), |text| print!("{}", text))?;
 
                     if save_output {
                         std::fs::write(abs_note_path, markdown::ast_to_string(&root)?)?;
@@ -280,6 +581,7 @@ impl CommandInterpreter {
 
                         let real_path = self.get_note_path(&id)?.to_str().unwrap().to_owned();
                         self.commit_message_lines.insert(format!("Saved run output for note '{}'.", real_path));
+                        self.changed_note_paths.insert(PathBuf::from(real_path));
                     }
                 }
                 Command::AddResource { path, destination } => {
@@ -324,6 +626,8 @@ impl CommandInterpreter {
                     }
                 }
                 Command::Commit => {
+                    let commit_start = self.timings.start();
+
                     let new_tree = self.index()?.write_tree()?;
                     let repository = self.repository.borrow();
                     let new_tree = repository.find_tree(new_tree)?;
@@ -347,14 +651,13 @@ impl CommandInterpreter {
 
                         let signature = git2::Signature::now(&self.config.user_name_and_email.0, &self.config.user_name_and_email.1)?;
                         let commit_message = std::mem::take(&mut self.commit_message_lines).into_iter().collect::<Vec<_>>().join("\n");
-                        self.repository.borrow().commit(
-                            Some("HEAD"),
-                            &signature,
+                        let commit_id = self.create_commit(
+                            repository.deref(),
                             &signature,
                             &commit_message,
                             &new_tree,
                             &head_commit
-                        ).map_err(|err| FailedToCommit(err.to_string()))?;
+                        )?;
                         println!("Created commit with message:");
                         for line in commit_message.lines() {
                             println!("\t{}", line);
@@ -363,7 +666,28 @@ impl CommandInterpreter {
                         self.index = None;
                         self.note_metadata_storage = None;
                         self.changed_files.clear();
+
+                        // The access times and aliases refers to notes by id, but are not part of the commit
+                        let changed_note_ids = std::mem::take(&mut self.changed_note_ids);
+                        if !changed_note_ids.is_empty() {
+                            let mut access_times = AccessTimes::load(repository.deref())?;
+                            let mut note_aliases = NoteAliases::load(repository.deref())?;
+                            for (id, new_id) in &changed_note_ids {
+                                access_times.change_id(id, new_id);
+                                note_aliases.change_id(id, new_id);
+                            }
+
+                            access_times.save()?;
+                            note_aliases.save()?;
+                        }
+
+                        let changed_note_paths = std::mem::take(&mut self.changed_note_paths).into_iter().collect::<Vec<_>>();
+                        if let Some(commit_hook) = self.commit_hook.as_ref() {
+                            commit_hook(commit_id, &changed_note_paths);
+                        }
                     }
+
+                    self.timings.record("Commit", commit_start);
                 }
             }
         }
@@ -374,11 +698,30 @@ impl CommandInterpreter {
     pub fn new_commit(&mut self) -> CommandResult<()> {
         self.index = None;
         self.commit_message_lines.clear();
+        self.changed_note_paths.clear();
+        self.changed_note_ids.clear();
         Ok(())
     }
 
+    pub fn set_commit_hook(&mut self, commit_hook: Option<CommitHookFn>) {
+        self.commit_hook = commit_hook;
+    }
+
+    pub fn take_commit_hook(&mut self) -> Option<CommitHookFn> {
+        self.commit_hook.take()
+    }
+
+    pub fn set_timings(&mut self, timings: Timings) {
+        self.timings = timings;
+    }
+
     pub fn reset(&mut self) -> CommandResult<()> {
         let repository = self.repository.borrow_mut();
+        if repository.is_bare() {
+            // Nothing can have been changed
+            return Ok(());
+        }
+
         let head = repository.head()?;
         let head_commit = head.peel(git2::ObjectType::Commit)?;
 
@@ -395,6 +738,8 @@ impl CommandInterpreter {
 
         self.changed_files.clear();
         self.commit_message_lines.clear();
+        self.changed_note_paths.clear();
+        self.changed_note_ids.clear();
 
         Ok(())
     }
@@ -407,11 +752,19 @@ impl CommandInterpreter {
         if tags.is_empty() {
             let (_, abs_content_path) = self.get_note_storage_path(&id);
             let content = std::fs::read_to_string(abs_content_path)?;
-            tags = tags::automatic(&content);
+            if !NoteDirectives::parse(&content).no_auto_tag {
+                tags = tags::automatic(&content, &self.config.tags);
+            }
         }
 
         let (relative_metadata_path, abs_metadata_path) = self.get_note_metadata_path(&id);
-        let metadata = NoteMetadata::new(id, path.to_owned(), tags);
+        let mut metadata = NoteMetadata::new(id, path.to_owned(), tags);
+        if self.config.created_from_path {
+            if let Some(created) = created_from_path(&path) {
+                metadata.created = created;
+            }
+        }
+
         metadata.save(&abs_metadata_path).map_err(|err| FailedToAddNote(err.to_string()))?;
 
         let index = self.index()?;
@@ -421,6 +774,9 @@ impl CommandInterpreter {
 
         create_note_symbolic_link(&self.config.repository, &metadata)?;
 
+        // So that later commands in the same batch see the new note
+        self.note_metadata_storage = None;
+
         let tags_str = if !metadata.tags.is_empty() {
             format!(" using tags: {}", metadata.tags.join(", "))
         } else {
@@ -433,6 +789,7 @@ impl CommandInterpreter {
             id,
             tags_str
         ));
+        self.changed_note_paths.insert(path);
 
         Ok(())
     }
@@ -444,6 +801,10 @@ impl CommandInterpreter {
         let real_path = self.get_note_path(&id)?.to_str().unwrap().to_owned();
         let note_symbolic_link = get_note_symbolic_link(&self.config.repository, self.get_note_metadata(&id)?)?;
 
+        if self.config.soft_delete {
+            return self.move_note_to_trash(&id, &real_path, note_symbolic_link);
+        }
+
         let (relative_content_path, abs_content_path) = self.get_note_storage_path(&id);
         let (relative_metadata_path, abs_metadata_path) = self.get_note_metadata_path(&id);
 
@@ -459,10 +820,140 @@ impl CommandInterpreter {
 
         self.commit_message_lines.insert(format!("Deleted note '{}'.", real_path));
         self.changed_files.push(relative_metadata_path);
+        self.changed_note_paths.insert(PathBuf::from(real_path));
 
         Ok(())
     }
 
+    fn move_note_to_trash(&mut self, id: &NoteId, real_path: &str, note_symbolic_link: PathBuf) -> CommandResult<()> {
+        use CommandError::*;
+
+        let (relative_content_path, abs_content_path) = self.get_note_storage_path(id);
+        let (relative_metadata_path, abs_metadata_path) = self.get_note_metadata_path(id);
+        let (relative_trash_content_path, abs_trash_content_path) = NoteMetadataStorage::get_trash_storage_path(&self.config.repository, id);
+        let (relative_trash_metadata_path, abs_trash_metadata_path) = NoteMetadataStorage::get_trash_metadata_path(&self.config.repository, id);
+
+        if abs_trash_content_path.exists() || abs_trash_metadata_path.exists() {
+            return Err(FailedToRemoveNote(format!("a note with id {} already exists in the trash", id)));
+        }
+
+        std::fs::create_dir_all(self.config.repository.join(TRASH_DIR)).map_err(|err| FailedToRemoveNote(err.to_string()))?;
+        std::fs::rename(abs_content_path, abs_trash_content_path).map_err(|err| FailedToRemoveNote(err.to_string()))?;
+        std::fs::rename(abs_metadata_path, abs_trash_metadata_path).map_err(|err| FailedToRemoveNote(err.to_string()))?;
+
+        let index = self.index()?;
+        index.remove_path(&relative_content_path)?;
+        index.remove_path(&relative_metadata_path)?;
+        index.add_path(&relative_trash_content_path)?;
+        index.add_path(&relative_trash_metadata_path)?;
+        index.write()?;
+
+        let _ = std::fs::remove_file(note_symbolic_link);
+
+        self.commit_message_lines.insert(format!("Moved note '{}' to trash (id: {}).", real_path, id));
+        self.changed_files.push(relative_content_path);
+        self.changed_files.push(relative_metadata_path);
+        self.changed_files.push(relative_trash_content_path);
+        self.changed_files.push(relative_trash_metadata_path);
+        self.changed_note_paths.insert(PathBuf::from(real_path));
+
+        Ok(())
+    }
+
+    fn restore_note(&mut self, id: &NoteId) -> CommandResult<()> {
+        use CommandError::*;
+
+        let (relative_trash_content_path, abs_trash_content_path) = NoteMetadataStorage::get_trash_storage_path(&self.config.repository, id);
+        let (relative_trash_metadata_path, abs_trash_metadata_path) = NoteMetadataStorage::get_trash_metadata_path(&self.config.repository, id);
+        if !abs_trash_metadata_path.exists() {
+            return Err(NoteNotInTrash(id.to_string()));
+        }
+
+        let note_metadata = NoteMetadata::load(&abs_trash_metadata_path)?;
+        if self.note_metadata_storage()?.get_by_id(id).is_some() {
+            return Err(NoteIdAlreadyUsed(id.to_string()));
+        }
+        self.check_if_note_exists(&note_metadata.path)?;
+
+        let (relative_content_path, abs_content_path) = self.get_note_storage_path(id);
+        let (relative_metadata_path, abs_metadata_path) = self.get_note_metadata_path(id);
+        std::fs::rename(abs_trash_content_path, abs_content_path)?;
+        std::fs::rename(abs_trash_metadata_path, abs_metadata_path)?;
+
+        let index = self.index()?;
+        index.remove_path(&relative_trash_content_path)?;
+        index.remove_path(&relative_trash_metadata_path)?;
+        index.add_path(&relative_content_path)?;
+        index.add_path(&relative_metadata_path)?;
+        index.write()?;
+
+        create_note_symbolic_link(&self.config.repository, &note_metadata)?;
+
+        self.note_metadata_storage = None;
+
+        self.commit_message_lines.insert(format!("Restored note '{}' (id: {}) from trash.", note_metadata.path.to_str().unwrap(), id));
+        self.changed_files.push(relative_trash_content_path);
+        self.changed_files.push(relative_trash_metadata_path);
+        self.changed_files.push(relative_content_path);
+        self.changed_files.push(relative_metadata_path);
+        self.changed_note_paths.insert(note_metadata.path);
+
+        Ok(())
+    }
+
+    fn create_commit(&self,
+                     repository: &git2::Repository,
+                     signature: &git2::Signature,
+                     commit_message: &str,
+                     tree: &git2::Tree,
+                     parents: &[&git2::Commit]) -> CommandResult<git2::Oid> {
+        git_helpers::create_commit(repository, signature, commit_message, tree, parents, self.config.commit_signing())
+            .map_err(|err| CommandError::FailedToCommit(err.to_string()))
+    }
+
+    fn merge_note(&mut self, source: &Path, destination: &Path) -> CommandResult<()> {
+        use CommandError::*;
+
+        let source_id = self.get_note_id(source)?;
+        let destination_id = self.get_note_id(destination)?;
+        let real_source_path = self.get_note_path(&source_id)?.to_str().unwrap().to_owned();
+        if source_id == destination_id {
+            return Err(SameSourceAndDestination(PathBuf::from(real_source_path)));
+        }
+
+        let real_destination_path = self.get_note_path(&destination_id)?.to_str().unwrap().to_owned();
+
+        let (_, abs_source_content_path) = self.get_note_storage_path(&source_id);
+        let (relative_destination_content_path, abs_destination_content_path) = self.get_note_storage_path(&destination_id);
+
+        let source_content = std::fs::read_to_string(&abs_source_content_path).map_err(|err| FailedToEditNote(err.to_string()))?;
+        let destination_content = std::fs::read_to_string(&abs_destination_content_path).map_err(|err| FailedToEditNote(err.to_string()))?;
+
+        let mut content = destination_content.trim_end().to_owned();
+        if !content.is_empty() {
+            content += "\n\n";
+        }
+        content += &source_content;
+
+        std::fs::write(&abs_destination_content_path, content).map_err(|err| FailedToEditNote(err.to_string()))?;
+        self.edited_file(relative_destination_content_path)?;
+
+        let destination_tags = self.get_note_metadata(&destination_id)?.tags.clone();
+        let new_tags = self.get_note_metadata(&source_id)?.tags
+            .iter()
+            .filter(|tag| !destination_tags.contains(tag))
+            .cloned()
+            .collect::<Vec<_>>();
+        self.change_note_tags(&destination_id, false, new_tags)?;
+        self.try_change_last_updated(&destination_id)?;
+
+        self.remove_note(source)?;
+
+        self.commit_message_lines.insert(format!("Merged note '{}' into '{}'.", real_source_path, real_destination_path));
+        self.changed_note_paths.insert(PathBuf::from(real_destination_path));
+        Ok(())
+    }
+
     fn edited_file(&mut self, path: PathBuf) -> CommandResult<()> {
         let index = self.index()?;
         index.add_path(&path)?;
@@ -471,7 +962,7 @@ impl CommandInterpreter {
         Ok(())
     }
 
-    fn add_resources_from_editor_output(&mut self, output: EditorOutput) -> CommandResult<()> {
+    pub fn add_resources_from_editor_output(&mut self, output: EditorOutput) -> CommandResult<()> {
         for path in output.added_resources.iter() {
             self.index()?.add_path(&Path::new(RESOURCES_DIR).join(path))?;
 
@@ -525,6 +1016,20 @@ impl CommandInterpreter {
         Ok(())
     }
 
+    fn new_note_id(&mut self) -> CommandResult<NoteId> {
+        self.note_metadata_storage()?
+            .new_note_id()
+            .ok_or_else(|| CommandError::InternalError("No free note id found".to_owned()))
+    }
+
+    fn normalize_content(&self, content: String) -> String {
+        if self.config.normalize_content {
+            helpers::normalize_content(&content)
+        } else {
+            content
+        }
+    }
+
     fn change_note_metadata<F: FnMut(&mut NoteMetadata) -> bool>(&mut self, id: &NoteId, mut apply: F) -> CommandResult<()> {
         let mut internal = || -> CommandResult<()> {
             let (relative_metadata_path, abs_metadata_path) = self.get_note_metadata_path(&id);
@@ -612,6 +1117,16 @@ impl CommandInterpreter {
         }
     }
 
+    fn check_if_note_locked(&mut self, id: &NoteId, force: bool) -> CommandResult<()> {
+        self.note_metadata_storage()?;
+        let note_metadata = self.get_note_metadata(id)?;
+        if note_metadata.locked && !force {
+            Err(CommandError::NoteLocked(note_metadata.path.clone()))
+        } else {
+            Ok(())
+        }
+    }
+
     fn note_metadata_storage(&mut self) -> CommandResult<&NoteMetadataStorage> {
         self.note_metadata_storage_mut().map(|x| &*x)
     }
@@ -625,7 +1140,12 @@ impl CommandInterpreter {
     fn note_metadata_storage_mut(&mut self) -> CommandResult<&mut NoteMetadataStorage> {
         get_or_insert_with(
             &mut self.note_metadata_storage,
-            || Ok(NoteMetadataStorage::from_dir_with_config(&self.config)?)
+            || {
+                let start = self.timings.start();
+                let note_metadata_storage = NoteMetadataStorage::from_dir_with_config(&self.config)?;
+                self.timings.record("Metadata load", start);
+                Ok(note_metadata_storage)
+            }
         )
     }
 
@@ -653,6 +1173,8 @@ pub enum CommandError {
     FailedToCommit(String),
     #[error("Failed to undo commit: {0}")]
     FailedToUndo(String),
+    #[error("Failed to squash commits: {0}")]
+    FailedToSquash(String),
 
     #[error("Failed to update metadata: {0}")]
     FailedToUpdateMetadata(String),
@@ -660,8 +1182,18 @@ pub enum CommandError {
     NoteNotFound(String),
     #[error("Note '{0}' already exists")]
     NoteAlreadyExists(PathBuf),
+    #[error("The id {0} is already used by another note")]
+    NoteIdAlreadyUsed(String),
     #[error("Existing note at destination '{0}', use -f to delete that note before moving")]
     NoteExistsAtDestination(PathBuf),
+    #[error("The source and destination are the same note '{0}'")]
+    SameSourceAndDestination(PathBuf),
+    #[error("Note '{0}' is locked, use --force to change it anyway")]
+    NoteLocked(PathBuf),
+    #[error("Note with id {0} not found in the trash")]
+    NoteNotInTrash(String),
+    #[error("Note is empty, not adding it (use --allow-empty to add anyway)")]
+    EmptyNote,
 
     #[error("Commit {0} not found")]
     CommitNotFound(String),
@@ -669,12 +1201,21 @@ pub enum CommandError {
     #[error("Failed to run snippet: {0}")]
     Snippet(SnippetError),
 
+    #[error("The output of {0} snippet(s) did not match the saved output")]
+    SnippetOutputMismatch(usize),
+
     #[error("Resource not found: {0}")]
     ResourceNotFound(String),
 
     #[error("Branch '{0}' not found")]
     BranchNotFound(String),
 
+    #[error("No token available for the HTTPS remote '{0}' (set the {1} environment variable or configure a git credential helper)")]
+    MissingSyncToken(String, String),
+
+    #[error("The repository is bare, only reading notes is supported")]
+    BareRepository,
+
     #[error("Internal error: {0}")]
     InternalError(String),
 
@@ -702,23 +1243,34 @@ pub fn run_snippet<'a, F: FnMut(&str)>(
     snippet_runner_manager: &SnippetRunnerManger,
     arena: &'a Arena<AstNode<'a>>,
     content: &str,
+    context: Option<&SnippetContext>,
     mut do_print: F
 ) -> CommandResult<&'a AstNode<'a>> {
     let root = markdown::parse(&arena, content);
+    let directives = NoteDirectives::parse(content);
 
     markdown::visit_code_blocks::<CommandError, _>(
         &root,
         |current_node| {
             if let NodeValue::CodeBlock(ref block) = current_node.data.borrow().value {
-                let snippet_result = snippet_runner_manager.run(
+                let stdin = preceding_stdin(current_node);
+                let snippet_result = run_code_block(
+                    snippet_runner_manager,
                     &block.info,
-                    &block.literal
+                    &block.literal,
+                    stdin.as_deref(),
+                    directives.snippet_timeout,
+                    context
                 );
 
-                let output_stdout = match snippet_result {
-                    Ok(output_stdout) => {
-                        do_print(&output_stdout);
-                        output_stdout
+                let output = match snippet_result {
+                    Ok(output) => {
+                        do_print(&output.stdout);
+                        if let Some(stderr) = output.stderr.as_ref() {
+                            do_print(stderr);
+                        }
+
+                        output
                     }
                     Err(SnippetError::Execution { status, output }) => {
                         do_print(&output);
@@ -729,21 +1281,140 @@ pub fn run_snippet<'a, F: FnMut(&str)>(
                     }
                 };
 
-                let mut create_output_node = true;
-                if let Some(next_node) = current_node.next_sibling() {
-                    match next_node.data.borrow_mut().value {
-                        NodeValue::CodeBlock(ref mut output_block) => {
-                            if output_block.info == "output" {
-                                output_block.literal = output_stdout.clone();
-                                create_output_node = false;
-                            }
+                let output_node = set_or_create_sibling_code_block(&arena, current_node, "output", output.stdout);
+                match output.stderr {
+                    Some(stderr) if !stderr.is_empty() => {
+                        set_or_create_sibling_code_block(&arena, output_node, "stderr", stderr);
+                    }
+                    _ => {
+                        if let Some(stderr_node) = output_node.next_sibling().filter(|node| is_code_block_with_info(node, "stderr")) {
+                            stderr_node.detach();
                         }
-                        _ => {}
                     }
                 }
+            }
+
+            Ok(())
+        },
+        true,
+        false
+    )?;
+
+    Ok(root)
+}
+
+/// Updates the code block with the given info string directly after the node, or creates it if it does not exist.
+fn set_or_create_sibling_code_block<'a>(arena: &'a Arena<AstNode<'a>>,
+                                        node: &'a AstNode<'a>,
+                                        info: &str,
+                                        literal: String) -> &'a AstNode<'a> {
+    if let Some(next_node) = node.next_sibling().filter(|next_node| is_code_block_with_info(next_node, info)) {
+        if let NodeValue::CodeBlock(ref mut block) = next_node.data.borrow_mut().value {
+            block.literal = literal;
+        }
+
+        return next_node;
+    }
+
+    let new_node = markdown::create_code_block(arena, info, literal);
+    node.insert_after(new_node);
+    new_node
+}
+
+/// The content of a `stdin` block directly before the node, which is given as input to the snippet.
+fn preceding_stdin(node: &AstNode) -> Option<String> {
+    let previous_node = node.previous_sibling()?;
+    match &previous_node.data.borrow().value {
+        NodeValue::CodeBlock(block) if block.info == "stdin" => Some(block.literal.clone()),
+        _ => None
+    }
+}
+
+fn is_code_block_with_info(node: &AstNode, info: &str) -> bool {
+    match &node.data.borrow().value {
+        NodeValue::CodeBlock(block) => block.info == info,
+        _ => false
+    }
+}
+
+/// Runs a code block, where an info string of the form `run:<command>` uses the command instead of a named runner.
+/// Otherwise, the info string is the name of the runner (optionally with a variant, e.g. `python:python2`) followed by extra arguments for it (e.g. `rust -O` or `python -u`).
+fn run_code_block(snippet_runner_manager: &SnippetRunnerManger,
+                  info: &str,
+                  source_code: &str,
+                  stdin: Option<&str>,
+                  timeout: Option<Duration>,
+                  context: Option<&SnippetContext>) -> SnippetResult<SnippetOutput> {
+    match info.strip_prefix("run:") {
+        Some(command_line) => snippet_runner_manager.run_command(command_line.trim(), source_code, stdin, timeout, context),
+        None => {
+            let (name, args) = parse_info_string(info);
+            snippet_runner_manager.run(name, &args, source_code, stdin, timeout, context)
+        }
+    }
+}
+
+/// Splits the info string of a code block into the name of the runner and extra arguments.
+fn parse_info_string(info: &str) -> (&str, Vec<String>) {
+    let mut parts = info.split_whitespace();
+    let name = parts.next().unwrap_or("");
+    let args = parts.map(|argument| argument.to_owned()).collect();
+    (name, args)
+}
+
+/// The output of a snippet compared with the output block saved after it.
+pub struct SnippetCheckResult {
+    pub index: usize,
+    pub language: String,
+    pub expected: String,
+    pub actual: String
+}
+
+impl SnippetCheckResult {
+    pub fn is_match(&self) -> bool {
+        self.expected.trim_end() == self.actual.trim_end()
+    }
+}
+
+/// Runs the snippets that have a saved output block and compares the outputs (without changing the content).
+pub fn check_snippets(snippet_runner_manager: &SnippetRunnerManger,
+                      content: &str,
+                      context: Option<&SnippetContext>) -> CommandResult<Vec<SnippetCheckResult>> {
+    let arena = markdown::storage();
+    let root = markdown::parse(&arena, content);
+    let directives = NoteDirectives::parse(content);
+
+    let mut results = Vec::new();
+    let mut index = 0;
+    markdown::visit_code_blocks::<CommandError, _>(
+        &root,
+        |current_node| {
+            if let NodeValue::CodeBlock(ref block) = current_node.data.borrow().value {
+                index += 1;
+
+                let expected = current_node.next_sibling().and_then(|next_node| {
+                    match &next_node.data.borrow().value {
+                        NodeValue::CodeBlock(output_block) if output_block.info == "output" => Some(output_block.literal.clone()),
+                        _ => None
+                    }
+                });
+
+                if let Some(expected) = expected {
+                    let stdin = preceding_stdin(current_node);
+                    let actual = match run_code_block(snippet_runner_manager, &block.info, &block.literal, stdin.as_deref(), directives.snippet_timeout, context) {
+                        Ok(output) => output.stdout,
+                        Err(SnippetError::Execution { output, .. }) => output,
+                        Err(err) => {
+                            return Err(CommandError::Snippet(err));
+                        }
+                    };
 
-                if create_output_node {
-                    current_node.insert_after(markdown::create_output_code_block(&arena, output_stdout));
+                    results.push(SnippetCheckResult {
+                        index,
+                        language: block.info.clone(),
+                        expected,
+                        actual
+                    });
                 }
             }
 
@@ -753,7 +1424,7 @@ pub fn run_snippet<'a, F: FnMut(&str)>(
         false
     )?;
 
-    Ok(root)
+    Ok(results)
 }
 
 fn create_note_symbolic_link(repository: &Path, note: &NoteMetadata) -> CommandResult<()> {
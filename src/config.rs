@@ -1,16 +1,212 @@
+use std::io::{IsTerminal, stdout, Write};
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crossterm::style::Color;
+use fnv::FnvHashMap;
 use home::home_dir;
 
 use serde::{Serialize, Deserialize};
 
-use crate::helpers::{base_dir, io_error};
+use crate::git_helpers::CommitSigning;
+use crate::helpers::{base_dir, io_error, TablePrinter};
 use crate::model::RESOURCES_DIR;
-use crate::snippets::{BashSnippetRunnerConfig, JavaScriptSnippetRunnerConfig, PythonSnippetRunnerConfig, RustSnippetRunnerConfig, TypeScriptSnippetRunnerConfig};
+use crate::querying::DateFormat;
+use crate::tags::TagConfig;
+use crate::snippets::{BashSnippetRunnerConfig, CppSnippetRunnerConfig, JavaScriptSnippetRunnerConfig, PythonSnippetRunnerConfig, RustSnippetRunnerConfig, TypeScriptSnippetRunnerConfig};
 
 pub fn config_path() -> PathBuf {
     base_dir().join("config.toml")
 }
 
+/// A key that can be changed in the file config, together with the type of its value.
+pub struct FileConfigKey {
+    pub name: &'static str,
+    pub value_type: &'static str,
+    get: fn(&FileConfig) -> Option<String>,
+    set: fn(&mut FileConfig, &str) -> Result<(), String>
+}
+
+/// The keys that can be changed in the file config.
+pub const FILE_CONFIG_KEYS: &[FileConfigKey] = &[
+    FileConfigKey {
+        name: "repository",
+        value_type: "path",
+        get: |config| Some(path_to_string(&config.repository)),
+        set: |config, value| { config.repository = PathBuf::from(value); Ok(()) }
+    },
+    FileConfigKey {
+        name: "editor",
+        value_type: "string",
+        get: |config| config.editor.clone(),
+        set: |config, value| { config.editor = Some(value.to_owned()); Ok(()) }
+    },
+    FileConfigKey {
+        name: "base_dir",
+        value_type: "path",
+        get: |config| config.base_dir.as_ref().map(|path| path_to_string(path)),
+        set: |config, value| { config.base_dir = Some(PathBuf::from(value)); Ok(()) }
+    },
+    FileConfigKey {
+        name: "sync_default_branch",
+        value_type: "string",
+        get: |config| config.sync_default_branch.clone(),
+        set: |config, value| { config.sync_default_branch = Some(value.to_owned()); Ok(()) }
+    },
+    FileConfigKey {
+        name: "sync_default_remote",
+        value_type: "string",
+        get: |config| config.sync_default_remote.clone(),
+        set: |config, value| { config.sync_default_remote = Some(value.to_owned()); Ok(()) }
+    },
+    FileConfigKey {
+        name: "sync_username",
+        value_type: "string",
+        get: |config| config.sync_username.clone(),
+        set: |config, value| { config.sync_username = Some(value.to_owned()); Ok(()) }
+    },
+    FileConfigKey {
+        name: "sync_token_env",
+        value_type: "string",
+        get: |config| config.sync_token_env.clone(),
+        set: |config, value| { config.sync_token_env = Some(value.to_owned()); Ok(()) }
+    },
+    FileConfigKey {
+        name: "created_from_path",
+        value_type: "bool",
+        get: |config| config.created_from_path.map(|value| value.to_string()),
+        set: |config, value| { config.created_from_path = Some(parse_value(value, "bool")?); Ok(()) }
+    },
+    FileConfigKey {
+        name: "track_access",
+        value_type: "bool",
+        get: |config| config.track_access.map(|value| value.to_string()),
+        set: |config, value| { config.track_access = Some(parse_value(value, "bool")?); Ok(()) }
+    },
+    FileConfigKey {
+        name: "web_editor_theme",
+        value_type: "string",
+        get: |config| config.web_editor_theme.clone(),
+        set: |config, value| { config.web_editor_theme = Some(value.to_owned()); Ok(()) }
+    },
+    FileConfigKey {
+        name: "default_command",
+        value_type: "string",
+        get: |config| config.default_command.clone(),
+        set: |config, value| { config.default_command = Some(value.to_owned()); Ok(()) }
+    },
+    FileConfigKey {
+        name: "normalize_content",
+        value_type: "bool",
+        get: |config| config.normalize_content.map(|value| value.to_string()),
+        set: |config, value| { config.normalize_content = Some(parse_value(value, "bool")?); Ok(()) }
+    },
+    FileConfigKey {
+        name: "signing_key",
+        value_type: "string",
+        get: |config| config.signing_key.clone(),
+        set: |config, value| { config.signing_key = Some(value.to_owned()); Ok(()) }
+    },
+    FileConfigKey {
+        name: "gpg_program",
+        value_type: "string",
+        get: |config| config.gpg_program.clone(),
+        set: |config, value| { config.gpg_program = Some(value.to_owned()); Ok(()) }
+    },
+    FileConfigKey {
+        name: "soft_delete",
+        value_type: "bool",
+        get: |config| config.soft_delete.map(|value| value.to_string()),
+        set: |config, value| { config.soft_delete = Some(parse_value(value, "bool")?); Ok(()) }
+    },
+    FileConfigKey {
+        name: "snippet.allow_arbitrary_runners",
+        value_type: "bool",
+        get: |config| config.snippet.as_ref()?.allow_arbitrary_runners.map(|value| value.to_string()),
+        set: |config, value| {
+            config.snippet.get_or_insert_with(Default::default).allow_arbitrary_runners = Some(parse_value(value, "bool")?);
+            Ok(())
+        }
+    },
+    FileConfigKey {
+        name: "snippet.timeout_seconds",
+        value_type: "float",
+        get: |config| config.snippet.as_ref()?.timeout_seconds.map(|value| value.to_string()),
+        set: |config, value| {
+            config.snippet.get_or_insert_with(Default::default).timeout_seconds = Some(parse_value(value, "float")?);
+            Ok(())
+        }
+    },
+    FileConfigKey {
+        name: "tags.max_keyword_tags",
+        value_type: "integer",
+        get: |config| config.tags.as_ref().map(|tags| tags.max_keyword_tags.to_string()),
+        set: |config, value| {
+            config.tags.get_or_insert_with(Default::default).max_keyword_tags = parse_value(value, "integer")?;
+            Ok(())
+        }
+    },
+    FileConfigKey {
+        name: "tags.min_score",
+        value_type: "float",
+        get: |config| config.tags.as_ref().map(|tags| tags.min_score.to_string()),
+        set: |config, value| {
+            config.tags.get_or_insert_with(Default::default).min_score = parse_value(value, "float")?;
+            Ok(())
+        }
+    },
+    FileConfigKey {
+        name: "tags.snippet_tag",
+        value_type: "bool",
+        get: |config| config.tags.as_ref().map(|tags| tags.snippet_tag.to_string()),
+        set: |config, value| {
+            config.tags.get_or_insert_with(Default::default).snippet_tag = parse_value(value, "bool")?;
+            Ok(())
+        }
+    },
+    FileConfigKey {
+        name: "tags.language_aliases",
+        value_type: "list of alias=language",
+        get: |config| config.tags.as_ref().map(|tags| language_aliases_to_string(&tags.language_aliases)),
+        set: |config, value| {
+            let mut language_aliases = FnvHashMap::default();
+            for alias in value.split(',').map(|alias| alias.trim()).filter(|alias| !alias.is_empty()) {
+                let (alias, language) = alias
+                    .split_once('=')
+                    .ok_or_else(|| format!("Invalid language alias (format: alias=language): {}", alias))?;
+                language_aliases.insert(alias.trim().to_owned(), language.trim().to_owned());
+            }
+
+            config.tags.get_or_insert_with(Default::default).language_aliases = language_aliases;
+            Ok(())
+        }
+    }
+];
+
+fn path_to_string(path: &Path) -> String {
+    path.to_str().unwrap().to_owned()
+}
+
+fn language_aliases_to_string(language_aliases: &FnvHashMap<String, String>) -> String {
+    let mut language_aliases = language_aliases
+        .iter()
+        .map(|(alias, language)| format!("{}={}", alias, language))
+        .collect::<Vec<_>>();
+    language_aliases.sort();
+    language_aliases.join(", ")
+}
+
+fn parse_value<T: FromStr>(value: &str, value_type: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("Invalid {} value: {}", value_type, value))
+}
+
+fn find_file_config_key(key: &str) -> Result<&'static FileConfigKey, String> {
+    FILE_CONFIG_KEYS
+        .iter()
+        .find(|config_key| config_key.name == key)
+        .ok_or_else(|| format!("Undefined key: {}", key))
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct FileConfig {
@@ -19,7 +215,18 @@ pub struct FileConfig {
     pub snippet: Option<SnippetFileConfig>,
     pub base_dir: Option<PathBuf>,
     pub sync_default_branch: Option<String>,
-    pub sync_default_remote: Option<String>
+    pub sync_default_remote: Option<String>,
+    pub sync_username: Option<String>,
+    pub sync_token_env: Option<String>,
+    pub created_from_path: Option<bool>,
+    pub track_access: Option<bool>,
+    pub web_editor_theme: Option<String>,
+    pub default_command: Option<String>,
+    pub tags: Option<TagConfig>,
+    pub normalize_content: Option<bool>,
+    pub signing_key: Option<String>,
+    pub gpg_program: Option<String>,
+    pub soft_delete: Option<bool>
 }
 
 impl FileConfig {
@@ -30,7 +237,18 @@ impl FileConfig {
             snippet: None,
             base_dir: None,
             sync_default_branch: None,
-            sync_default_remote: None
+            sync_default_remote: None,
+            sync_username: None,
+            sync_token_env: None,
+            created_from_path: None,
+            track_access: None,
+            web_editor_theme: None,
+            default_command: None,
+            tags: None,
+            normalize_content: None,
+            signing_key: None,
+            gpg_program: None,
+            soft_delete: None
         }
     }
 
@@ -39,46 +257,49 @@ impl FileConfig {
         toml::from_str(&content).map_err(|err| io_error(err))
     }
 
+    /// Loads the config if it exists, otherwise a new config for the given repository is created.
+    pub fn load_or_new(path: &Path, repository: &Path) -> std::io::Result<FileConfig> {
+        if path.exists() {
+            FileConfig::load(path)
+        } else {
+            Ok(FileConfig::new(repository))
+        }
+    }
+
     pub fn save(&self, path: &Path) -> std::io::Result<()> {
         let toml = toml::to_string(self).map_err(|err| io_error(err))?;
         std::fs::write(path, toml)
     }
 
-    pub fn change(&mut self, key: &str, value: &str) -> Result<(), String> {
-        match key {
-            "repository" => {
-                self.repository = Path::new(value).to_path_buf();
-            }
-            "editor" => {
-                self.editor = Some(value.to_owned());
-            }
-            "base_dir" => {
-                self.base_dir = Some(Path::new(value).to_owned());
-            }
-            "sync_default_branch" => {
-                self.sync_default_branch = Some(value.to_owned());
-            }
-            "sync_default_remote" => {
-                self.sync_default_remote = Some(value.to_owned());
-            }
-            _ => {
-                return Err(format!("Undefined key: {}", key));
-            }
-        }
+    /// Returns the (key, type, current value) of each key that can be changed.
+    pub fn list_keys(&self) -> Vec<(&'static str, &'static str, Option<String>)> {
+        FILE_CONFIG_KEYS
+            .iter()
+            .map(|config_key| (config_key.name, config_key.value_type, self.get(config_key.name).unwrap_or(None)))
+            .collect()
+    }
 
-        Ok(())
+    pub fn get(&self, key: &str) -> Result<Option<String>, String> {
+        Ok((find_file_config_key(key)?.get)(self))
+    }
+
+    pub fn change(&mut self, key: &str, value: &str) -> Result<(), String> {
+        (find_file_config_key(key)?.set)(self, value)
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct SnippetFileConfig {
     pub python: Option<PythonSnippetRunnerConfig>,
     pub bash: Option<BashSnippetRunnerConfig>,
-    pub cpp: Option<RustSnippetRunnerConfig>,
+    pub cpp: Option<CppSnippetRunnerConfig>,
     pub rust: Option<RustSnippetRunnerConfig>,
     pub javascript: Option<JavaScriptSnippetRunnerConfig>,
-    pub typescript: Option<TypeScriptSnippetRunnerConfig>
+    pub typescript: Option<TypeScriptSnippetRunnerConfig>,
+    pub allow_arbitrary_runners: Option<bool>,
+    /// Aborts the execution of snippets that takes longer than this.
+    pub timeout_seconds: Option<f64>
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -91,7 +312,21 @@ pub struct Config {
     pub use_working_dir: bool,
     pub allow_stdin: bool,
     pub sync_default_branch: String,
-    pub sync_default_remote: String
+    pub sync_default_remote: String,
+    pub sync_username: Option<String>,
+    pub sync_token_env: String,
+    pub created_from_path: bool,
+    pub timings: bool,
+    pub date_format: DateFormat,
+    pub track_access: bool,
+    pub web_editor_theme: Option<String>,
+    pub default_command: Option<String>,
+    pub read_only: bool,
+    pub tags: TagConfig,
+    pub normalize_content: bool,
+    pub signing_key: Option<String>,
+    pub gpg_program: String,
+    pub soft_delete: bool
 }
 
 impl Config {
@@ -105,7 +340,21 @@ impl Config {
             use_working_dir: true,
             allow_stdin: true,
             sync_default_branch: file_config.sync_default_branch.unwrap_or("master".to_owned()),
-            sync_default_remote: file_config.sync_default_remote.unwrap_or("origin".to_owned())
+            sync_default_remote: file_config.sync_default_remote.unwrap_or("origin".to_owned()),
+            sync_username: file_config.sync_username,
+            sync_token_env: file_config.sync_token_env.unwrap_or("GITNOTES_SYNC_TOKEN".to_owned()),
+            created_from_path: file_config.created_from_path.unwrap_or(false),
+            timings: false,
+            date_format: DateFormat::Default,
+            track_access: file_config.track_access.unwrap_or(false),
+            web_editor_theme: file_config.web_editor_theme,
+            default_command: file_config.default_command,
+            read_only: false,
+            tags: file_config.tags.unwrap_or_default(),
+            normalize_content: file_config.normalize_content.unwrap_or(false),
+            signing_key: file_config.signing_key,
+            gpg_program: file_config.gpg_program.unwrap_or("gpg".to_owned()),
+            soft_delete: file_config.soft_delete.unwrap_or(false)
         }
     }
 
@@ -115,16 +364,70 @@ impl Config {
     }
 
     pub fn print(&self) {
-        println!("Repository: {}", self.repository.to_str().unwrap());
-        println!("User name: {}, email: {}", self.user_name_and_email.0, self.user_name_and_email.1);
-        println!("Editor: {}", self.editor);
-        println!("Snippet: {}", self.snippet.is_some());
-        println!("Base dir: {}", self.base_dir.as_ref().map(|x| x.to_str().unwrap()).unwrap_or("N/A"));
+        let _ = self.print_to(&mut stdout(), stdout().is_terminal());
+    }
+
+    /// Prints the config values as a table (with colored keys if is_terminal).
+    pub fn print_to(&self, out: &mut dyn Write, is_terminal: bool) -> std::io::Result<()> {
+        let mut table_printer = TablePrinter::new(vec!["Key".to_owned(), "Value".to_owned()]);
+        table_printer.set_column_color(0, Color::DarkCyan);
+
+        for (key, value) in self.values() {
+            table_printer.add_row(vec![key.to_owned(), value]);
+        }
+
+        table_printer.print_to(out, is_terminal)
+    }
+
+    /// Returns the (key, value) of each config value.
+    pub fn values(&self) -> Vec<(&'static str, String)> {
+        let path_to_string = |path: &Path| path.to_str().unwrap().to_owned();
+        let optional = |value: Option<String>| value.unwrap_or_else(|| "N/A".to_owned());
+
+        vec![
+            ("repository", path_to_string(&self.repository)),
+            ("user_name", self.user_name_and_email.0.clone()),
+            ("user_email", self.user_name_and_email.1.clone()),
+            ("editor", self.editor.clone()),
+            ("snippet", self.snippet.is_some().to_string()),
+            ("base_dir", optional(self.base_dir.as_ref().map(|path| path_to_string(path)))),
+            ("use_working_dir", self.use_working_dir.to_string()),
+            ("allow_stdin", self.allow_stdin.to_string()),
+            ("sync_default_branch", self.sync_default_branch.clone()),
+            ("sync_default_remote", self.sync_default_remote.clone()),
+            ("sync_username", optional(self.sync_username.clone())),
+            ("sync_token_env", self.sync_token_env.clone()),
+            ("created_from_path", self.created_from_path.to_string()),
+            ("timings", self.timings.to_string()),
+            ("date_format", format!("{:?}", self.date_format).to_lowercase()),
+            ("track_access", self.track_access.to_string()),
+            ("web_editor_theme", optional(self.web_editor_theme.clone())),
+            ("default_command", optional(self.default_command.clone())),
+            ("read_only", self.read_only.to_string()),
+            ("tags.max_keyword_tags", self.tags.max_keyword_tags.to_string()),
+            ("tags.min_score", self.tags.min_score.to_string()),
+            ("tags.snippet_tag", self.tags.snippet_tag.to_string()),
+            ("tags.language_aliases", language_aliases_to_string(&self.tags.language_aliases)),
+            ("normalize_content", self.normalize_content.to_string()),
+            ("signing_key", optional(self.signing_key.clone())),
+            ("gpg_program", self.gpg_program.clone()),
+            ("soft_delete", self.soft_delete.to_string())
+        ]
     }
 
     pub fn resources_dir(&self) -> PathBuf {
         self.repository.join(RESOURCES_DIR)
     }
+
+    /// The signing to use for commits, if a signing key is configured.
+    pub fn commit_signing(&self) -> Option<CommitSigning<'_>> {
+        self.signing_key.as_ref().map(|signing_key| {
+            CommitSigning {
+                signing_key,
+                gpg_program: &self.gpg_program
+            }
+        })
+    }
 }
 
 fn get_user_name_and_email() -> (String, String) {
@@ -136,4 +439,71 @@ fn get_user_name_and_email() -> (String, String) {
     }
 
     ("unknown".to_owned(), "unknown".to_owned())
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_list_keys() {
+    let mut file_config = FileConfig::new(Path::new("/notes"));
+    file_config.change("created_from_path", "true").unwrap();
+
+    let keys = file_config.list_keys();
+    assert_eq!(FILE_CONFIG_KEYS.len(), keys.len());
+    assert!(keys.contains(&("repository", "path", Some("/notes".to_owned()))));
+    assert!(keys.contains(&("editor", "string", None)));
+    assert!(keys.contains(&("created_from_path", "bool", Some("true".to_owned()))));
+    assert!(keys.contains(&("snippet.timeout_seconds", "float", None)));
+
+    for (key, _, value) in keys {
+        assert_eq!(value, file_config.get(key).unwrap());
+    }
+
+    assert!(file_config.get("unknown").is_err());
+    assert!(file_config.change("unknown", "value").is_err());
+
+    file_config.change("snippet.timeout_seconds", "2.5").unwrap();
+    assert_eq!(Some(2.5), file_config.snippet.as_ref().unwrap().timeout_seconds);
+    assert_eq!(Some("2.5".to_owned()), file_config.get("snippet.timeout_seconds").unwrap());
+    assert!(file_config.change("snippet.allow_arbitrary_runners", "yes").is_err());
+
+    file_config.change("normalize_content", "true").unwrap();
+    assert_eq!(Some(true), file_config.normalize_content);
+
+    file_config.change("tags.max_keyword_tags", "5").unwrap();
+    assert_eq!(5, file_config.tags.as_ref().unwrap().max_keyword_tags);
+    assert_eq!(3.0, file_config.tags.as_ref().unwrap().min_score);
+    assert!(file_config.change("tags.min_score", "high").is_err());
+
+    file_config.change("tags.language_aliases", "sh=bash, zsh=bash").unwrap();
+    assert_eq!(Some("bash"), file_config.tags.as_ref().unwrap().language_aliases.get("sh").map(|language| language.as_str()));
+    assert_eq!(Some("sh=bash, zsh=bash".to_owned()), file_config.get("tags.language_aliases").unwrap());
+    assert!(file_config.change("tags.language_aliases", "sh").is_err());
+
+    file_config.change("tags.snippet_tag", "false").unwrap();
+    assert!(!file_config.tags.as_ref().unwrap().snippet_tag);
+}
+
+#[test]
+fn test_print() {
+    let mut config = Config::from_env(FileConfig::new(Path::new("/notes")));
+    config.signing_key = Some("ABCDEF".to_owned());
+
+    let mut output = Vec::new();
+    config.print_to(&mut output, false).unwrap();
+    let output = String::from_utf8(output).unwrap();
+
+    let expected_keys = [
+        "repository", "user_name", "user_email", "editor", "snippet", "base_dir", "use_working_dir", "allow_stdin",
+        "sync_default_branch", "sync_default_remote", "sync_username", "sync_token_env", "created_from_path", "timings", "date_format", "track_access",
+        "web_editor_theme", "default_command", "read_only", "tags.max_keyword_tags", "tags.min_score", "tags.snippet_tag",
+        "tags.language_aliases", "normalize_content", "signing_key", "gpg_program", "soft_delete"
+    ];
+    assert_eq!(expected_keys.len(), config.values().len());
+    for key in expected_keys {
+        assert!(output.lines().any(|line| line.starts_with(&format!(" {} ", key))), "{}", key);
+    }
+
+    assert!(output.lines().any(|line| line.starts_with(" sync_default_branch ") && line.contains("│ master ")), "{}", output);
+    assert!(output.contains("ABCDEF"));
+    assert!(output.contains("GITNOTES_SYNC_TOKEN"));
+    assert!(!output.contains("\x1b["));
+}
@@ -34,6 +34,7 @@ pub fn launch(
             let mut web_config = WebEditorConfig::default();
             web_config.access_mode = access_mode;
             web_config.snippet_config = config.snippet.clone();
+            web_config.theme = config.web_editor_theme.clone();
 
             return Ok(
                 web_editor::launch_sync(
@@ -1,3 +1,7 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::Stdio;
+
 use git2::{BranchType, Cred, CredentialType, Repository};
 use crate::command::CommandError;
 
@@ -21,11 +25,174 @@ pub fn create_ssh_credentials() -> impl FnMut(&str, Option<&str>, CredentialType
     }
 }
 
+/// Credentials for both SSH (using the agent) and HTTPS (using the configured git credential helper).
+pub fn create_credentials() -> impl FnMut(&str, Option<&str>, CredentialType) -> Result<Cred, git2::Error> {
+    let mut ssh_credentials = create_ssh_credentials();
+    move |url, username_from_url, allowed_types| {
+        if allowed_types.contains(CredentialType::USER_PASS_PLAINTEXT) {
+            Cred::credential_helper(&git2::Config::open_default()?, url, username_from_url)
+        } else if allowed_types.contains(CredentialType::SSH_KEY) {
+            ssh_credentials(url, username_from_url, allowed_types)
+        } else {
+            Cred::default()
+        }
+    }
+}
+
+pub type CredentialsCallback = Box<dyn FnMut(&str, Option<&str>, CredentialType) -> Result<Cred, git2::Error>>;
+
+/// Credentials for syncing with the remote at the given URL, where HTTPS remotes use a token from the given environment variable
+/// (or the git credential helper if not set) and other remotes use the SSH agent or the git credential helper.
+/// The token is never sent to unencrypted (HTTP) remotes.
+pub fn create_sync_credentials(url: &str, token_env: &str, username: Option<String>) -> Result<CredentialsCallback, CommandError> {
+    if !is_https_url(url) {
+        return Ok(Box::new(create_credentials()));
+    }
+
+    let token = std::env::var(token_env).ok().filter(|token| !token.is_empty());
+    let has_credential_helper = git2::Config::open_default()
+        .map(|config| config.get_string("credential.helper").is_ok())
+        .unwrap_or(false);
+
+    create_https_credentials(url, token_env, token, username, has_credential_helper)
+}
+
+fn create_https_credentials(url: &str,
+                            token_env: &str,
+                            token: Option<String>,
+                            username: Option<String>,
+                            has_credential_helper: bool) -> Result<CredentialsCallback, CommandError> {
+    if token.is_none() && !has_credential_helper {
+        return Err(CommandError::MissingSyncToken(url.to_owned(), token_env.to_owned()));
+    }
+
+    let mut attempted = false;
+    Ok(Box::new(move |url, username_from_url, allowed_types| {
+        // Called again if the credentials are rejected, fail instead of retrying forever
+        if attempted || !allowed_types.contains(CredentialType::USER_PASS_PLAINTEXT) {
+            return Err(git2::Error::new(
+                git2::ErrorCode::Auth,
+                git2::ErrorClass::Http,
+                &format!("Authentication failed for '{}'", url)
+            ));
+        }
+        attempted = true;
+
+        let username = username.as_deref().or(username_from_url);
+        match token.as_ref() {
+            Some(token) => Cred::userpass_plaintext(username.unwrap_or("git"), token),
+            None => Cred::credential_helper(&git2::Config::open_default()?, url, username)
+        }
+    }))
+}
+
+fn is_https_url(url: &str) -> bool {
+    url.to_lowercase().starts_with("https://")
+}
+
+#[test]
+fn test_is_https_url() {
+    assert!(is_https_url("https://github.com/user/notes.git"));
+    assert!(is_https_url("HTTPS://example.com/notes.git"));
+    assert!(!is_https_url("http://example.com/notes.git"));
+    assert!(!is_https_url("git@github.com:user/notes.git"));
+    assert!(!is_https_url("ssh://git@github.com/user/notes.git"));
+}
+
+#[test]
+fn test_https_credentials() {
+    let url = "https://github.com/user/notes.git";
+
+    let err = create_https_credentials(url, "GITNOTES_SYNC_TOKEN", None, None, false).err().unwrap();
+    assert!(matches!(err, CommandError::MissingSyncToken(_, _)));
+
+    let mut credentials = create_https_credentials(url, "GITNOTES_SYNC_TOKEN", Some("secret".to_owned()), None, false).unwrap();
+    assert!(credentials(url, None, CredentialType::USER_PASS_PLAINTEXT).is_ok());
+    assert!(credentials(url, None, CredentialType::USER_PASS_PLAINTEXT).is_err());
+
+    let mut credentials = create_https_credentials(url, "GITNOTES_SYNC_TOKEN", Some("secret".to_owned()), None, false).unwrap();
+    assert!(credentials(url, None, CredentialType::SSH_KEY).is_err());
+}
+
+/// Creates a detached ASCII-armored signature of the content using the given GPG program and key.
+pub fn gpg_sign(gpg_program: &str, signing_key: &str, content: &str) -> Result<String, String> {
+    let mut child = std::process::Command::new(gpg_program)
+        .args(["--status-fd=2", "-bsau", signing_key])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| format!("failed to run '{}': {}", gpg_program, err))?;
+
+    child.stdin
+        .take()
+        .ok_or_else(|| "failed to open stdin".to_owned())?
+        .write_all(content.as_bytes())
+        .map_err(|err| err.to_string())?;
+
+    let output = child.wait_with_output().map_err(|err| err.to_string())?;
+    if !output.status.success() {
+        return Err(format!("'{}' failed: {}", gpg_program, String::from_utf8_lossy(&output.stderr).trim()));
+    }
+
+    String::from_utf8(output.stdout).map_err(|err| err.to_string())
+}
+
+/// The key and GPG program used to sign commits.
+pub struct CommitSigning<'a> {
+    pub signing_key: &'a str,
+    pub gpg_program: &'a str
+}
+
+/// Creates a commit and moves HEAD (or the branch it points to) to it, where the commit is signed if signing is given.
+/// The reference is only updated if it still points to the first parent (or doesn't exist for a commit without parents).
+pub fn create_commit(
+    repository: &Repository,
+    signature: &git2::Signature,
+    message: &str,
+    tree: &git2::Tree,
+    parents: &[&git2::Commit],
+    signing: Option<CommitSigning>
+) -> Result<git2::Oid, git2::Error> {
+    let signing = match signing {
+        Some(signing) => signing,
+        None => {
+            return repository.commit(Some("HEAD"), signature, signature, message, tree, parents);
+        }
+    };
+
+    let commit_buffer = repository.commit_create_buffer(signature, signature, message, tree, parents)?;
+    let commit_content = commit_buffer.as_str().ok_or_else(|| git2::Error::from_str("commit is not valid UTF-8"))?;
+
+    let gpg_signature = gpg_sign(signing.gpg_program, signing.signing_key, commit_content)
+        .map_err(|err| git2::Error::from_str(&format!("failed to sign commit with key '{}': {}", signing.signing_key, err)))?;
+    let commit_id = repository.commit_signed(commit_content, &gpg_signature, None)?;
+
+    // Signed commits are not attached to any branch, so move HEAD (or the branch it points to) manually
+    let reflog_message = format!("commit: {}", message.lines().next().unwrap_or(""));
+    let head = repository.find_reference("HEAD")?;
+    let reference_name = head.symbolic_target().unwrap_or("HEAD").to_owned();
+    match parents.first() {
+        Some(parent) => {
+            repository.reference_matching(&reference_name, commit_id, true, parent.id(), &reflog_message)?;
+        }
+        None => {
+            repository.reference(&reference_name, commit_id, false, &reflog_message)?;
+        }
+    }
+
+    Ok(commit_id)
+}
+
+/// Merges the fetched commit into the branch, returning the (repository relative) paths of the files with conflicts.
+/// If there are conflicts, the conflict markers are written to the working tree unless abort_on_conflict is set.
 pub fn merge<'a>(
     repository: &'a Repository,
     remote_branch: &str,
-    fetch_commit: git2::AnnotatedCommit<'a>
-) -> Result<(), git2::Error> {
+    fetch_commit: git2::AnnotatedCommit<'a>,
+    abort_on_conflict: bool,
+    signing: Option<CommitSigning>
+) -> Result<Vec<PathBuf>, git2::Error> {
     // 1. do a merge analysis
     let analysis = repository.merge_analysis(&[&fetch_commit])?;
 
@@ -59,10 +226,10 @@ pub fn merge<'a>(
     } else if analysis.0.is_normal() {
         // do a normal merge
         let head_commit = repository.reference_to_annotated_commit(&repository.head()?)?;
-        normal_merge(&repository, &head_commit, &fetch_commit)?;
+        return normal_merge(&repository, &head_commit, &fetch_commit, abort_on_conflict, signing);
     }
 
-    Ok(())
+    Ok(Vec::new())
 }
 
 fn fast_forward(
@@ -91,8 +258,10 @@ fn fast_forward(
 fn normal_merge(
     repository: &Repository,
     local: &git2::AnnotatedCommit,
-    remote: &git2::AnnotatedCommit
-) -> Result<(), git2::Error> {
+    remote: &git2::AnnotatedCommit,
+    abort_on_conflict: bool,
+    signing: Option<CommitSigning>
+) -> Result<Vec<PathBuf>, git2::Error> {
     let local_tree = repository.find_commit(local.id())?.tree()?;
     let remote_tree = repository.find_commit(remote.id())?.tree()?;
     let ancestor = repository
@@ -101,12 +270,33 @@ fn normal_merge(
     let mut idx = repository.merge_trees(&ancestor, &local_tree, &remote_tree, None)?;
 
     if idx.has_conflicts() {
-        println!("Merge conflicts detected...");
-        repository.checkout_index(Some(&mut idx), None)?;
-        return Ok(());
+        let mut conflicted_paths = Vec::new();
+        for conflict in idx.conflicts()? {
+            let conflict = conflict?;
+            let entry = conflict.our.as_ref().or(conflict.their.as_ref()).or(conflict.ancestor.as_ref());
+            if let Some(entry) = entry {
+                conflicted_paths.push(PathBuf::from(String::from_utf8_lossy(&entry.path).to_string()));
+            }
+        }
+
+        if !abort_on_conflict {
+            repository.checkout_index(
+                Some(&mut idx),
+                Some(
+                    git2::build::CheckoutBuilder::default()
+                        .allow_conflicts(true)
+                        .conflict_style_merge(true)
+                )
+            )?;
+        }
+
+        return Ok(conflicted_paths);
     }
 
     let result_tree = repository.find_tree(idx.write_tree_to(repository)?)?;
+    // Update the working tree while HEAD still points to the local commit, as that is what the checkout compares against.
+    repository.checkout_tree(result_tree.as_object(), None)?;
+
     // now create the merge commit
     let msg = format!("Merge: {} into {}", remote.id(), local.id());
     let sig = repository.signature()?;
@@ -114,16 +304,14 @@ fn normal_merge(
     let remote_commit = repository.find_commit(remote.id())?;
 
     // Do our merge commit and set current branch head to that commit.
-    let _merge_commit = repository.commit(
-        Some("HEAD"),
-        &sig,
+    create_commit(
+        repository,
         &sig,
         &msg,
         &result_tree,
         &[&local_commit, &remote_commit],
+        signing
     )?;
 
-    // Set working tree to match head.
-    repository.checkout_head(None)?;
-    Ok(())
+    Ok(Vec::new())
 }
\ No newline at end of file
@@ -1,10 +1,15 @@
+use std::cell::RefCell;
 use std::collections::{HashSet};
 use std::error;
 use std::hash::{Hash, Hasher};
-use std::io::{Read, Stdin};
+use std::io::{IsTerminal, Read, stdin, Stdin, stdout, Write};
 use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::Instant;
 
 use chrono::{DateTime, FixedOffset, NaiveDateTime, TimeZone};
+use crossterm::ExecutableCommand;
+use crossterm::style::{Color, Print, ResetColor, SetForegroundColor};
 
 use home::home_dir;
 
@@ -49,6 +54,16 @@ impl StdinExt for Stdin {
     }
 }
 
+/// Asks a yes/no question on stdin, where the default answer is no.
+pub fn confirm(question: &str) -> std::io::Result<bool> {
+    print!("{} [y/N] ", question);
+    stdout().flush()?;
+
+    let mut answer = String::new();
+    stdin().read_line(&mut answer)?;
+    Ok(answer.trim().eq_ignore_ascii_case("y"))
+}
+
 pub struct OrderedSet<T> where T: Eq + Hash {
     set: HashSet<PointerValueEquality<T>>,
     values: Vec<Box<T>>
@@ -167,23 +182,36 @@ fn test_ordered_set2() {
 
 pub struct TablePrinter {
     column_names: Vec<String>,
-    rows: Vec<Vec<String>>
+    rows: Vec<Vec<String>>,
+    column_colors: Vec<Option<Color>>
 }
 
 impl TablePrinter {
     pub fn new(column_names: Vec<String>) -> TablePrinter {
+        let column_colors = vec![None; column_names.len()];
         TablePrinter {
             column_names,
-            rows: Vec::new()
+            rows: Vec::new(),
+            column_colors
         }
     }
 
+    /// Sets the color of the values in the given column, used when printing to a terminal.
+    pub fn set_column_color(&mut self, column_index: usize, color: Color) {
+        self.column_colors[column_index] = Some(color);
+    }
+
     pub fn add_row(&mut self, row: Vec<String>) {
         assert_eq!(self.column_names.len(), row.len());
         self.rows.push(row);
     }
 
     pub fn print(&mut self) {
+        let _ = self.print_to(&mut stdout(), stdout().is_terminal());
+    }
+
+    /// Prints the table to the given output (with colors if is_terminal).
+    pub fn print_to(&mut self, out: &mut dyn Write, is_terminal: bool) -> std::io::Result<()> {
         let mut column_lengths = vec![0; self.column_names.len()];
         for (column_index, column) in self.column_names.iter().enumerate() {
             column_lengths[column_index] = column_lengths[column_index].max(column.len() + 2);
@@ -195,52 +223,188 @@ impl TablePrinter {
             }
         }
 
-        let print_chars = |c: char, n: usize| {
+        let print_chars = |out: &mut dyn Write, c: char, n: usize| -> std::io::Result<()> {
             for _ in 0..n {
-                print!("{}", c);
+                write!(out, "{}", c)?;
             }
+
+            Ok(())
         };
 
         for (column_index, column) in self.column_names.iter().enumerate() {
             let length = column_lengths[column_index] - (column.len() + 2);
 
-            print_chars(' ', length / 2);
-            print!(" {} ", column);
-            print_chars(' ', length / 2 + length % 2);
+            print_chars(out, ' ', length / 2)?;
+            write!(out, " {} ", column)?;
+            print_chars(out, ' ', length / 2 + length % 2)?;
 
             if column_index != self.column_names.len() - 1 {
-                print!("│");
+                write!(out, "│")?;
             }
         }
 
-        println!();
+        writeln!(out)?;
 
         let mut first = true;
         for length in &column_lengths {
             if !first {
-                print!("┼");
+                write!(out, "┼")?;
             } else {
                 first = false;
             }
 
-            print_chars('─', *length);
+            print_chars(out, '─', *length)?;
         }
-        println!();
+        writeln!(out)?;
 
         for row in &self.rows {
             for (column_index, column) in row.iter().enumerate() {
-                print!(" {} ", column);
+                match self.column_colors[column_index] {
+                    Some(color) if is_terminal => {
+                        write!(out, " ")?;
+                        out
+                            .execute(SetForegroundColor(color))?
+                            .execute(Print(column))?
+                            .execute(ResetColor)?;
+                        write!(out, " ")?;
+                    }
+                    _ => {
+                        write!(out, " {} ", column)?;
+                    }
+                }
 
-                print_chars(' ', column_lengths[column_index] - (column.len() + 2));
+                print_chars(out, ' ', column_lengths[column_index] - (column.len() + 2))?;
 
                 if column_index != self.column_names.len() - 1 {
-                    print!("│");
+                    write!(out, "│")?;
                 }
             }
 
-            println!();
+            writeln!(out)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Records the elapsed time of different phases. Does nothing if not enabled.
+#[derive(Clone)]
+pub struct Timings {
+    writer: Option<Rc<RefCell<dyn Write>>>
+}
+
+impl Timings {
+    pub fn disabled() -> Timings {
+        Timings {
+            writer: None
+        }
+    }
+
+    pub fn stderr() -> Timings {
+        Timings::with_writer(Rc::new(RefCell::new(std::io::stderr())))
+    }
+
+    pub fn with_writer(writer: Rc<RefCell<dyn Write>>) -> Timings {
+        Timings {
+            writer: Some(writer)
+        }
+    }
+
+    pub fn from_enabled(enabled: bool) -> Timings {
+        if enabled {
+            Timings::stderr()
+        } else {
+            Timings::disabled()
+        }
+    }
+
+    pub fn start(&self) -> Option<Instant> {
+        self.writer.as_ref().map(|_| Instant::now())
+    }
+
+    pub fn record(&self, name: &str, start: Option<Instant>) {
+        if let (Some(writer), Some(start)) = (self.writer.as_ref(), start) {
+            let elapsed = start.elapsed().as_secs_f64() * 1000.0;
+            let _ = writeln!(writer.borrow_mut(), "[timings] {}: {:.3} ms", name, elapsed);
+        }
+    }
+}
+
+pub fn human_readable_size(size: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+
+    if size < 1024 {
+        return format!("{} B", size);
+    }
+
+    let mut value = size as f64;
+    let mut unit_index = 0;
+    while value >= 1024.0 && unit_index < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit_index += 1;
+    }
+
+    format!("{:.1} {}", value, UNITS[unit_index])
+}
+
+#[test]
+fn test_human_readable_size1() {
+    assert_eq!("13 B", human_readable_size(13));
+    assert_eq!("1.0 KB", human_readable_size(1024));
+    assert_eq!("1.5 KB", human_readable_size(1536));
+    assert_eq!("2.0 MB", human_readable_size(2 * 1024 * 1024));
+}
+
+/// Removes a leading UTF-8 BOM and converts CRLF line endings to LF.
+pub fn normalize_content(content: &str) -> String {
+    content.strip_prefix('\u{feff}').unwrap_or(content).replace("\r\n", "\n")
+}
+
+#[test]
+fn test_normalize_content() {
+    assert_eq!("# Hello\nWorld\n", normalize_content("\u{feff}# Hello\r\nWorld\r\n"));
+    assert_eq!("Hello\rWorld\n", normalize_content("Hello\rWorld\n"));
+    assert_eq!("Hello \u{feff}", normalize_content("Hello \u{feff}"));
+}
+
+/// Resolves the path into an absolute canonical path, expanding a leading '~'. The part of the path that doesn't exist yet is appended as is.
+pub fn absolute_path(path: &Path) -> std::io::Result<PathBuf> {
+    let path = match path.strip_prefix("~") {
+        Ok(rest) => home_dir().ok_or_else(|| io_error("Unable to determine home folder."))?.join(rest),
+        Err(_) => path.to_owned()
+    };
+
+    let path = if path.is_absolute() { path } else { std::env::current_dir()?.join(path) };
+
+    let mut existing = path.as_path();
+    let mut remaining = Vec::new();
+    while !existing.exists() {
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                remaining.push(name.to_owned());
+                existing = parent;
+            }
+            _ => break
         }
     }
+
+    let mut absolute_path = existing.canonicalize()?;
+    for name in remaining.into_iter().rev() {
+        absolute_path.push(name);
+    }
+
+    Ok(absolute_path)
+}
+
+#[test]
+fn test_absolute_path() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let canonical_temp_dir = temp_dir.path().canonicalize().unwrap();
+
+    assert_eq!(canonical_temp_dir, absolute_path(temp_dir.path()).unwrap());
+    assert_eq!(canonical_temp_dir.join("notes/2023"), absolute_path(&temp_dir.path().join("notes/2023")).unwrap());
+    assert_eq!(std::env::current_dir().unwrap().canonicalize().unwrap().join("notes"), absolute_path(Path::new("notes")).unwrap());
+    assert_eq!(home_dir().unwrap().canonicalize().unwrap().join(".gitnotes-test"), absolute_path(Path::new("~/.gitnotes-test")).unwrap());
 }
 
 pub fn where_is_binary(binary: &Path) -> Option<PathBuf> {
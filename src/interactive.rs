@@ -20,6 +20,7 @@ use substring::Substring;
 
 use crate::app::{AppError, App, InputCommand, MainInputCommand};
 use crate::config::config_path;
+use crate::helpers::confirm;
 use crate::model::{NoteFileTree, NoteMetadata};
 
 pub fn run(main_input_command: MainInputCommand) -> Result<(), AppError> {
@@ -60,7 +61,7 @@ fn run_app(app: &mut App, history: &mut FileHistory, notes_version: &mut u64) ->
             helper.update(app);
         }
 
-        match input_command_interactive(&line) {
+        match input_command_interactive(&line, app.config().default_command.as_deref()) {
             Ok(input_command) => {
                 if let Err(err) = app.run_until_completion(input_command) {
                     println!("{}.", err);
@@ -80,6 +81,26 @@ fn run_app(app: &mut App, history: &mut FileHistory, notes_version: &mut u64) ->
     Ok(false)
 }
 
+/// Hotkeys that run a command on the selected match directly, instead of the command given to the selection.
+const SELECT_HOTKEYS: [(char, &str, bool); 3] = [
+    ('e', "edit", false),
+    ('c', "cat", false),
+    ('d', "rm", true)
+];
+
+#[derive(Debug, PartialEq)]
+pub struct Selection {
+    pub index: usize,
+    pub command_name: String,
+    pub confirm: bool
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SelectMove {
+    Up,
+    Down
+}
+
 pub fn select<F: Fn(&str, usize) -> String>(
     command_name: &str,
     num_matches: usize,
@@ -89,21 +110,59 @@ pub fn select<F: Fn(&str, usize) -> String>(
         return Ok(None);
     }
 
-    let mut current_index: Option<usize> = None;
     stdout().execute(SavePosition)?;
     enable_raw_mode()?;
 
+    let selection = select_with_events(
+        command_name,
+        num_matches,
+        || read(),
+        |select_move| {
+            match select_move {
+                SelectMove::Up => stdout().execute(MoveUp(1))?,
+                SelectMove::Down => stdout().execute(MoveDown(1))?
+            };
+
+            Ok(())
+        }
+    );
+
+    stdout().execute(RestorePosition)?;
+    disable_raw_mode()?;
+
+    if let Some(selection) = selection? {
+        let input_line = create_input_line(&selection.command_name, selection.index);
+        if selection.confirm && !confirm(&format!("Run '{}'?", input_line))? {
+            return Ok(None);
+        }
+
+        input_command_interactive(&input_line, None)
+            .map(|command| Some(command))
+            .map_err(|err| AppError::Input(err))
+    } else {
+        Ok(None)
+    }
+}
+
+pub fn select_with_events<E: FnMut() -> std::io::Result<Event>, M: FnMut(SelectMove) -> std::io::Result<()>>(
+    command_name: &str,
+    num_matches: usize,
+    mut next_event: E,
+    mut move_cursor: M
+) -> std::io::Result<Option<Selection>> {
+    let mut current_index: Option<usize> = None;
+
     loop {
-        let event = read()?;
+        let event = next_event()?;
         match event {
             Event::Key(KeyEvent { code: KeyCode::Up, .. }) => {
                 match current_index.as_mut() {
                     Some(current_index) if *current_index > 0 => {
-                        stdout().execute(MoveUp(1))?;
+                        move_cursor(SelectMove::Up)?;
                         *current_index -= 1;
                     }
                     None => {
-                        stdout().execute(MoveUp(1))?;
+                        move_cursor(SelectMove::Up)?;
                         current_index = Some(num_matches - 1);
                     }
                     _ => {}
@@ -112,32 +171,30 @@ pub fn select<F: Fn(&str, usize) -> String>(
             Event::Key(KeyEvent { code: KeyCode::Down, .. }) => {
                 match current_index.as_mut() {
                     Some(current_index) if *current_index < num_matches - 1 => {
-                        stdout().execute(MoveDown(1))?;
+                        move_cursor(SelectMove::Down)?;
                         *current_index += 1;
                     }
                     _ => {}
                 }
             }
             Event::Key(KeyEvent { code: KeyCode::Char('c'), modifiers: KeyModifiers::CONTROL, .. }) => {
-                current_index = None;
-                break;
+                return Ok(None);
             }
             Event::Key(KeyEvent { code: KeyCode::Enter, .. }) => {
-                break;
+                return Ok(
+                    current_index.map(|index| Selection { index, command_name: command_name.to_owned(), confirm: false })
+                );
+            }
+            Event::Key(KeyEvent { code: KeyCode::Char(key), modifiers: KeyModifiers::NONE, .. }) => {
+                if let Some(index) = current_index {
+                    if let Some((_, hotkey_command_name, confirm)) = SELECT_HOTKEYS.iter().find(|(hotkey, _, _)| *hotkey == key) {
+                        return Ok(Some(Selection { index, command_name: hotkey_command_name.to_string(), confirm: *confirm }));
+                    }
+                }
             }
             _ => {}
         }
     }
-    stdout().execute(RestorePosition)?;
-    disable_raw_mode()?;
-
-    if let Some(current_index) = current_index {
-        input_command_interactive(&create_input_line(command_name, current_index))
-            .map(|command| Some(command))
-            .map_err(|err| AppError::Input(err))
-    } else {
-        Ok(None)
-    }
 }
 
 pub fn select_with_note_metadata(command_name: &str, notes_metadata: &Vec<&NoteMetadata>) -> Result<Option<InputCommand>, AppError> {
@@ -150,15 +207,33 @@ pub fn select_with_note_metadata(command_name: &str, notes_metadata: &Vec<&NoteM
     )
 }
 
-fn input_command_interactive(line: &str) -> Result<InputCommand, String> {
-    let words = shellwords::split(line).map_err(|err| err.to_string())?;
-    Ok(
-        InputCommand::from_clap(
-            &InputCommand::clap()
-                .setting(clap::AppSettings::NoBinaryName)
-                .get_matches_from_safe(words).map_err(|err| err.to_string())?
-        )
-    )
+/// Parses a line in interactive mode. If the line doesn't start with a known command, the default command (if any) is used.
+fn input_command_interactive(line: &str, default_command: Option<&str>) -> Result<InputCommand, String> {
+    let mut words = shellwords::split(line).map_err(|err| err.to_string())?;
+    let parse = |words: &[String]| {
+        InputCommand::clap()
+            .setting(clap::AppSettings::NoBinaryName)
+            .get_matches_from_safe(words)
+    };
+
+    let matches = match (parse(&words), default_command) {
+        (Err(err), Some(default_command)) if is_unknown_command(&err, &words) => {
+            words.insert(0, default_command.to_owned());
+            parse(&words)
+        }
+        (result, _) => result
+    };
+
+    Ok(InputCommand::from_clap(&matches.map_err(|err| err.to_string())?))
+}
+
+/// Indicates if the first word is not a command, as opposed to e.g. an unknown argument of a known command.
+fn is_unknown_command(err: &clap::Error, words: &[String]) -> bool {
+    match err.kind {
+        clap::ErrorKind::UnrecognizedSubcommand => true,
+        clap::ErrorKind::UnknownArgument => err.info.as_ref().and_then(|info| info.first()) == words.first(),
+        _ => false
+    }
 }
 
 pub enum AutoCompletionCommand {
@@ -188,7 +263,8 @@ impl AutoCompletionCommand {
 struct AutoCompletion<'a> {
     commands: FnvHashMap<String, AutoCompletionCommand>,
     note_file_tree: NoteFileTree<'a>,
-    working_dir: Option<PathBuf>
+    working_dir: Option<PathBuf>,
+    aliases: Vec<String>
 }
 
 impl<'a> AutoCompletion<'a> {
@@ -196,12 +272,28 @@ impl<'a> AutoCompletion<'a> {
         let commands = vec![
             AutoCompletionCommand::Path { name: "add".to_owned() },
             AutoCompletionCommand::Path { name: "rm".to_owned() },
+            AutoCompletionCommand::Regular { name: "restore".to_owned() },
             AutoCompletionCommand::Path { name: "edit".to_owned() },
             AutoCompletionCommand::Path { name: "mv".to_owned() },
+            AutoCompletionCommand::Path { name: "cp".to_owned() },
+            AutoCompletionCommand::Path { name: "copy".to_owned() },
             AutoCompletionCommand::Path { name: "cat".to_owned() },
             AutoCompletionCommand::Path { name: "show".to_owned() },
+            AutoCompletionCommand::Path { name: "copy-to-clipboard".to_owned() },
+            AutoCompletionCommand::Path { name: "clip".to_owned() },
+            AutoCompletionCommand::Path { name: "touch".to_owned() },
             AutoCompletionCommand::Path { name: "convert".to_owned() },
+            AutoCompletionCommand::Regular { name: "convert-all".to_owned() },
+            AutoCompletionCommand::Regular { name: "replace".to_owned() },
             AutoCompletionCommand::Path { name: "info".to_owned() },
+            AutoCompletionCommand::Path { name: "rename-id".to_owned() },
+            AutoCompletionCommand::Path { name: "pin".to_owned() },
+            AutoCompletionCommand::Path { name: "history".to_owned() },
+            AutoCompletionCommand::Path { name: "diff".to_owned() },
+            AutoCompletionCommand::Path { name: "unpin".to_owned() },
+            AutoCompletionCommand::Path { name: "lock".to_owned() },
+            AutoCompletionCommand::Path { name: "unlock".to_owned() },
+            AutoCompletionCommand::Path { name: "set-tags".to_owned() },
             AutoCompletionCommand::Path { name: "tree".to_owned() },
             AutoCompletionCommand::Path { name: "cd".to_owned() },
             AutoCompletionCommand::Regular { name: "begin".to_owned() },
@@ -213,15 +305,31 @@ impl<'a> AutoCompletion<'a> {
                     "tag".to_string(),
                     "name".to_owned(),
                     "id".to_owned(),
+                    "content".to_owned(),
+                    "expr".to_owned(),
+                    "created".to_owned(),
+                    "updated".to_owned()
+                ]
+            },
+            AutoCompletionCommand::SubCommand {
+                name: "move-to-date".to_owned(),
+                sub_commands: vec![
+                    "tag".to_string(),
+                    "name".to_owned(),
+                    "id".to_owned(),
+                    "content".to_owned(),
+                    "expr".to_owned(),
                     "created".to_owned(),
                     "updated".to_owned()
                 ]
             },
             AutoCompletionCommand::Regular { name: "grep".to_owned() },
+            AutoCompletionCommand::Regular { name: "duplicates".to_owned() },
             AutoCompletionCommand::SubCommand {
                 name: "resource".to_owned(),
                 sub_commands: vec![
                     "list".to_owned(),
+                    "tree".to_owned(),
                     "add".to_string(),
                     "remove".to_string(),
                     "open".to_string(),
@@ -230,9 +338,26 @@ impl<'a> AutoCompletion<'a> {
             },
             AutoCompletionCommand::Regular { name: "help".to_owned() },
             AutoCompletionCommand::Regular { name: "log".to_owned() },
+            AutoCompletionCommand::Regular { name: "status".to_owned() },
+            AutoCompletionCommand::Regular { name: "size-report".to_owned() },
+            AutoCompletionCommand::Regular { name: "tags".to_owned() },
             AutoCompletionCommand::Regular { name: "switch".to_owned() },
             AutoCompletionCommand::Regular { name: "undo".to_owned() },
+            AutoCompletionCommand::Regular { name: "squash".to_owned() },
+            AutoCompletionCommand::Regular { name: "replay".to_owned() },
+            AutoCompletionCommand::Regular { name: "prune-empty".to_owned() },
+            AutoCompletionCommand::Regular { name: "id".to_owned() },
+            AutoCompletionCommand::Regular { name: "recent".to_owned() },
+            AutoCompletionCommand::Regular { name: "pinned".to_owned() },
             AutoCompletionCommand::Regular { name: "pwd".to_owned() },
+            AutoCompletionCommand::SubCommand {
+                name: "metadata".to_owned(),
+                sub_commands: vec!["dump".to_owned(), "load".to_owned()]
+            },
+            AutoCompletionCommand::SubCommand {
+                name: "alias".to_owned(),
+                sub_commands: vec!["list".to_owned(), "add".to_owned(), "remove".to_owned()]
+            },
             AutoCompletionCommand::SubCommand {
                 name: "remote".to_owned(),
                 sub_commands: vec!["list".to_owned(), "add".to_owned(), "remove".to_owned()]
@@ -245,12 +370,16 @@ impl<'a> AutoCompletion<'a> {
         AutoCompletion {
             commands: FnvHashMap::from_iter(commands.into_iter().map(|command| (command.name().to_owned(), command))),
             note_file_tree,
-            working_dir: None
+            working_dir: None,
+            aliases: Vec::new()
         }
     }
 
     pub fn update(&mut self, app: &mut App) {
         self.working_dir = app.working_dir().ok();
+        self.aliases = app.list_aliases()
+            .map(|aliases| aliases.into_iter().map(|(alias, _)| alias).collect())
+            .unwrap_or_default();
     }
 
     fn current_command<'b>(&'b self, line: &'b str) -> Option<&'b str> {
@@ -350,7 +479,7 @@ impl<'a> Completer for AutoCompletion<'a> {
                             current_completion = &current_path_segment;
                             current_completion_length = current_path_segment_length;
 
-                            self.get_note_tree(&current_word, path_segment_done)
+                            let paths = self.get_note_tree(&current_word, path_segment_done)
                                 .map(|note_file_tree| {
                                     note_file_tree.children().map(|children| {
                                         let iter: Box<dyn Iterator<Item=(&str, bool)>> = Box::new(
@@ -362,7 +491,13 @@ impl<'a> Completer for AutoCompletion<'a> {
                                     })
                                 })
                                 .flatten()
-                                .unwrap_or_else(|| Box::new(std::iter::empty()))
+                                .unwrap_or_else(|| Box::new(std::iter::empty()));
+
+                            if path_segment_done {
+                                paths
+                            } else {
+                                Box::new(paths.chain(self.aliases.iter().map(|alias| (alias.as_str(), false))))
+                            }
                         }
                         AutoCompletionCommand::SubCommand { sub_commands, .. } => {
                             Box::new(sub_commands.iter().map(|command| (command.as_str(), false)))
@@ -389,3 +524,90 @@ impl<'a> Completer for AutoCompletion<'a> {
         Ok((pos - current_completion_length, results))
     }
 }
+
+#[test]
+fn test_select_hotkeys() {
+    fn key(code: KeyCode) -> Event {
+        Event::Key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    let select = |events: Vec<Event>| {
+        let mut events = events.into_iter();
+        let mut moves = Vec::new();
+        let selection = select_with_events(
+            "show",
+            3,
+            || Ok(events.next().unwrap()),
+            |select_move| { moves.push(select_move); Ok(()) }
+        ).unwrap();
+        (selection, moves)
+    };
+
+    assert_eq!(
+        (Some(Selection { index: 1, command_name: "show".to_owned(), confirm: false }), vec![SelectMove::Up, SelectMove::Up]),
+        select(vec![key(KeyCode::Up), key(KeyCode::Up), key(KeyCode::Enter)])
+    );
+    assert_eq!(
+        Some(Selection { index: 2, command_name: "edit".to_owned(), confirm: false }),
+        select(vec![key(KeyCode::Up), key(KeyCode::Char('e'))]).0
+    );
+    assert_eq!(
+        Some(Selection { index: 2, command_name: "cat".to_owned(), confirm: false }),
+        select(vec![key(KeyCode::Up), key(KeyCode::Char('x')), key(KeyCode::Char('c'))]).0
+    );
+    assert_eq!(
+        Some(Selection { index: 1, command_name: "rm".to_owned(), confirm: true }),
+        select(vec![key(KeyCode::Up), key(KeyCode::Up), key(KeyCode::Char('d'))]).0
+    );
+    assert_eq!(
+        None,
+        select(vec![key(KeyCode::Char('e')), key(KeyCode::Up), Event::Key(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL))]).0
+    );
+}
+
+#[test]
+fn test_complete_aliases() {
+    use rustyline::history::DefaultHistory;
+
+    let notes = vec![
+        NoteMetadata::new(crate::model::NoteId::new(), Path::new("2023/sample").to_owned(), Vec::new()),
+        NoteMetadata::new(crate::model::NoteId::new(), Path::new("scratch").to_owned(), Vec::new())
+    ];
+
+    let mut auto_completion = AutoCompletion::new(NoteFileTree::from_iter(notes.iter()).unwrap());
+    auto_completion.aliases = vec!["sample".to_owned(), "todo".to_owned()];
+
+    let history = DefaultHistory::new();
+    let complete = |line: &str| {
+        let (_, pairs) = auto_completion.complete(line, line.len(), &Context::new(&history)).unwrap();
+        let mut completions = pairs.into_iter().map(|pair| pair.replacement).collect::<Vec<_>>();
+        completions.sort();
+        completions
+    };
+
+    assert_eq!(vec!["sample", "scratch"], complete("cat s"));
+    assert_eq!(vec!["2023/", "sample", "scratch", "todo"], complete("cat "));
+    assert_eq!(vec!["sample"], complete("cat 2023/s"));
+}
+
+#[test]
+fn test_input_command_default_command() {
+    match input_command_interactive("2023/07/sample", Some("cat")).unwrap() {
+        InputCommand::PrintContent { path, only_code, .. } => {
+            assert_eq!(Path::new("2023/07/sample"), path);
+            assert!(!only_code);
+        }
+        _ => panic!("Expected cat command")
+    }
+
+    match input_command_interactive("2023/07/sample --code", Some("cat")).unwrap() {
+        InputCommand::PrintContent { only_code, .. } => assert!(only_code),
+        _ => panic!("Expected cat command")
+    }
+
+    assert!(matches!(input_command_interactive("ls", Some("cat")).unwrap(), InputCommand::ListDirectory { .. }));
+    assert!(input_command_interactive("2023/07/sample", None).is_err());
+
+    let err = input_command_interactive("ls --bogus", Some("cat")).err().unwrap();
+    assert!(err.contains("--bogus") && err.contains("USAGE:\n    ls "), "{}", err);
+}
@@ -1,7 +1,6 @@
 use std::path::Path;
 
 use structopt::StructOpt;
-use structopt::clap::Shell;
 
 mod config;
 mod helpers;
@@ -16,61 +15,92 @@ mod tags;
 mod interactive;
 mod app;
 mod git_helpers;
+mod clipboard;
 
 #[cfg(test)]
 mod app_tests;
 
-use crate::app::{AppError, App, InputCommand, MainInputCommand};
+use crate::app::{AppError, App, exit_code, InputCommand, MainInputCommand};
 use crate::config::{Config, config_path, FileConfig};
-use crate::helpers::base_dir;
+use crate::helpers::{absolute_path, base_dir};
 use crate::web_editor::{AccessMode, WebEditorConfig, WebEditorInput};
 
 fn main() {
-    if generate_completions() {
-        return;
-    }
-
     let mut main_command = MainInputCommand::from_args();
     if let Some(input_command) = std::mem::take(&mut main_command.command) {
-        if let Err(err) = run(input_command, main_command) {
+        let result = run(&config_path(), input_command, main_command);
+        if let Err(err) = &result {
             println!("{}.", err.to_string());
-            std::process::exit(1);
         }
+
+        std::process::exit(exit_code(&result));
     } else  {
         if let Err(err) = interactive::run(main_command) {
             println!("{}.", err.to_string());
-            std::process::exit(1);
+            std::process::exit(2);
         }
     }
 }
 
-fn generate_completions() -> bool {
-    if std::env::args().skip(1).next() == Some("generate-completions".to_owned()) {
-        let output_dir = "completions";
-        std::fs::create_dir_all(output_dir).unwrap();
-        MainInputCommand::clap().gen_completions("gitnotes", Shell::Bash, output_dir);
-        true
-    } else {
-        false
-    }
-}
-
-fn run(input_command: InputCommand, main_input_command: MainInputCommand) -> Result<(), AppError> {
-    let config_path = config_path();
+fn run(config_path: &Path, input_command: InputCommand, main_input_command: MainInputCommand) -> Result<Option<usize>, AppError> {
     match input_command {
         InputCommand::Initialize { .. } => {
-            run_init(&config_path, input_command)
+            run_init(config_path, input_command)?;
+            Ok(None)
+        }
+        InputCommand::Clone { url, name } => {
+            let name = name
+                .or_else(|| app::clone_name(&url))
+                .ok_or_else(|| AppError::Input("Unable to determine the name of the repository, please specify it".to_owned()))?;
+
+            let repository_path = base_dir().join(name);
+            app::clone_repository(config_path, &url, &repository_path)?;
+            println!("Cloned repository into '{}'.", repository_path.to_str().unwrap());
+            Ok(None)
+        }
+        InputCommand::WebEditor { path, note, port, is_read_only, theme } => {
+            let mut web_config = WebEditorConfig::default();
+            web_config.port = port;
+            web_config.theme = theme;
+            web_config.access_mode = if is_read_only { AccessMode::Read } else { AccessMode::ReadWrite };
+            web_config.is_standalone = true;
+
+            if note {
+                let config = main_input_command.apply(load_config(config_path));
+                if config.read_only {
+                    web_config.access_mode = AccessMode::Read;
+                }
+
+                App::new(config)?.edit_with_web_editor(path, |input| web_editor::launch_sync(web_config, input))?;
+            } else {
+                web_editor::launch_sync(web_config, WebEditorInput::from_path(&path));
+            }
+
+            Ok(None)
+        }
+        InputCommand::Completions { shell, install } => {
+            let shell = shell
+                .or_else(|| std::env::var("SHELL").ok().and_then(|shell| app::detect_shell(&shell)))
+                .ok_or_else(|| AppError::Input("Unable to detect the shell, please specify it".to_owned()))?;
+
+            if install {
+                let home_dir = home::home_dir().ok_or_else(|| AppError::Input("Unable to determine home folder".to_owned()))?;
+                let path = app::install_completions(shell, &home_dir)?;
+                println!("Installed completions at '{}'.", path.to_str().unwrap());
+            } else {
+                MainInputCommand::clap().gen_completions_to("gitnotes", shell, &mut std::io::stdout());
+            }
+
+            Ok(None)
         }
-        InputCommand::WebEditor { path, port, is_read_only } => {
-            let mut config = WebEditorConfig::default();
-            config.port = port;
-            config.access_mode = if is_read_only { AccessMode::Read } else { AccessMode::ReadWrite };
-            config.is_standalone = true;
-            web_editor::launch_sync(config, WebEditorInput::from_path(&path));
-            Ok(())
+        InputCommand::Config { repository_path: true, set: None, list_keys: false, .. } => {
+            // Handled before opening the repository, as the path is also wanted when the repository does not exist (yet)
+            let config = main_input_command.apply(load_config(config_path));
+            println!("{}", absolute_path(&config.repository)?.to_str().unwrap());
+            Ok(None)
         }
         _ => {
-            let config = main_input_command.apply(load_config(&config_path));
+            let config = main_input_command.apply(load_config(config_path));
             App::new(config)?.run_until_completion(input_command)
         }
     }
@@ -84,7 +114,7 @@ fn run_init(config_path: &Path, input_command: InputCommand) -> Result<(), AppEr
             Path::new(&name).to_owned()
         };
 
-        let file_config = FileConfig::load(&config_path).unwrap_or_else(|_| FileConfig::new(&repository_path));
+        let file_config = FileConfig::load_or_new(&config_path, &repository_path)?;
 
         if !use_existing {
             std::fs::create_dir_all(&repository_path)?;
@@ -1,5 +1,6 @@
 use std::cell::RefCell;
 use std::path::Path;
+use std::time::Duration;
 
 use comrak::{Arena, ComrakOptions};
 use comrak::nodes::{Ast, AstNode, LineColumn, NodeCodeBlock, NodeValue};
@@ -28,7 +29,7 @@ pub fn visit_code_blocks<'a, E, F: FnMut(&'a AstNode<'a>) -> Result<(), E>>(
     for current_node in root.children() {
         match current_node.data.borrow().value {
             NodeValue::CodeBlock(ref block) => {
-                let is_code = block.info != "output";
+                let is_code = block.info != "output" && block.info != "stderr" && block.info != "stdin";
                 if (is_code && show_code) || (!is_code && show_output) {
                     apply(current_node)?;
                 }
@@ -40,6 +41,47 @@ pub fn visit_code_blocks<'a, E, F: FnMut(&'a AstNode<'a>) -> Result<(), E>>(
     Ok(())
 }
 
+/// Removes the saved output (output and stderr blocks) of snippets, keeping the rest of the content as is.
+pub fn strip_output_blocks(content: &str) -> String {
+    let arena = storage();
+    let root = parse(&arena, content);
+
+    let mut removed_lines = Vec::new();
+    visit_code_blocks::<(), _>(
+        root,
+        |current_node| {
+            let data = current_node.data.borrow();
+            if let NodeValue::CodeBlock(ref block) = data.value {
+                if block.info != "stdin" {
+                    removed_lines.push((data.sourcepos.start.line, data.sourcepos.end.line));
+                }
+            }
+
+            Ok(())
+        },
+        false,
+        true
+    ).unwrap();
+
+    let is_removed = |line: usize| removed_lines.iter().any(|&(start, end)| start <= line && line <= end);
+    let mut new_content = String::new();
+    for (index, line) in content.split_inclusive('\n').enumerate() {
+        let line_number = index + 1;
+        if is_removed(line_number) {
+            continue;
+        }
+
+        // The blank line separating the output block from the preceding content
+        if line.trim().is_empty() && is_removed(line_number + 1) {
+            continue;
+        }
+
+        new_content += line;
+    }
+
+    new_content
+}
+
 pub fn visit_non_code_blocks<'a, E, F: FnMut(&'a AstNode<'a>) -> Result<(), E>>(
     root: &'a AstNode<'a>,
     mut apply: F
@@ -57,17 +99,122 @@ pub fn visit_non_code_blocks<'a, E, F: FnMut(&'a AstNode<'a>) -> Result<(), E>>(
     Ok(())
 }
 
+/// Returns the URLs of all links in the given content.
+pub fn links(content: &str) -> Vec<String> {
+    let arena = storage();
+    let root = parse(&arena, content);
+
+    root.descendants()
+        .filter_map(|node| {
+            match &node.data.borrow().value {
+                NodeValue::Link(link) => Some(link.url.clone()),
+                _ => None
+            }
+        })
+        .collect()
+}
+
+/// Returns the text of the first heading in the given content.
+pub fn title(content: &str) -> Option<String> {
+    let arena = storage();
+    let root = parse(&arena, content);
+    first_heading(root)
+}
+
+/// Returns the text of the first heading (ATX or setext) in the document.
+pub fn first_heading<'a>(root: &'a AstNode<'a>) -> Option<String> {
+    headings(root).next().map(|(_, text)| text)
+}
+
+/// Returns the (level, text) of all headings in the document, in order.
+pub fn headings<'a>(root: &'a AstNode<'a>) -> impl Iterator<Item=(u32, String)> + 'a {
+    root.descendants()
+        .filter_map(|node| {
+            let level = match &node.data.borrow().value {
+                NodeValue::Heading(heading) => heading.level as u32,
+                _ => return None
+            };
+
+            Some((level, heading_text(node)))
+        })
+}
+
+fn heading_text<'a>(heading: &'a AstNode<'a>) -> String {
+    let mut text = String::new();
+    for node in heading.descendants() {
+        match &node.data.borrow().value {
+            NodeValue::Text(current) => text.push_str(current),
+            NodeValue::Code(code) => text.push_str(&code.literal),
+            _ => {}
+        }
+    }
+
+    text.trim().to_owned()
+}
+
+const DIRECTIVES_PREFIX: &str = "gitnotes:";
+
+/// Directives that a note can declare at the top of its content to override the global behavior for that note,
+/// e.g. `<!-- gitnotes: snippet-timeout=5, no-auto-tag -->`.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct NoteDirectives {
+    pub snippet_timeout: Option<Duration>,
+    pub no_auto_tag: bool
+}
+
+impl NoteDirectives {
+    pub fn parse(content: &str) -> NoteDirectives {
+        let mut directives = NoteDirectives::default();
+
+        // Only consecutive comments at the start of the note are considered
+        for line in content.lines().map(|line| line.trim()).skip_while(|line| line.is_empty()) {
+            let comment = match line.strip_prefix("<!--").and_then(|line| line.strip_suffix("-->")) {
+                Some(comment) => comment.trim(),
+                None => break
+            };
+
+            let entries = match comment.strip_prefix(DIRECTIVES_PREFIX) {
+                Some(entries) => entries,
+                None => continue
+            };
+
+            for entry in entries.split(",").map(|entry| entry.trim()).filter(|entry| !entry.is_empty()) {
+                let (key, value) = match entry.split_once("=") {
+                    Some((key, value)) => (key.trim(), Some(value.trim())),
+                    None => (entry, None)
+                };
+
+                match (key, value) {
+                    ("snippet-timeout", Some(value)) => {
+                        directives.snippet_timeout = value.parse::<f64>().ok()
+                            .filter(|seconds| *seconds > 0.0)
+                            .map(|seconds| Duration::from_secs_f64(seconds));
+                    }
+                    ("no-auto-tag", None) => {
+                        directives.no_auto_tag = true;
+                    }
+                    _ => {
+                        eprintln!("Unknown note directive: {}", entry);
+                    }
+                }
+            }
+        }
+
+        directives
+    }
+}
+
 pub fn ast_to_string<'a>(root: &'a AstNode<'a>) -> std::io::Result<String> {
     let mut output = Vec::new();
     comrak::format_commonmark(root, &ComrakOptions::default(), &mut output)?;
     Ok(String::from_utf8(output).unwrap())
 }
 
-pub fn create_output_code_block<'a>(arena: &'a Arena<AstNode<'a>>, output: String) -> &'a mut AstNode::<'a> {
-    let mut output_block = NodeCodeBlock::default();
-    output_block.info = "output".to_owned();
-    output_block.literal = output;
-    arena.alloc(AstNode::new(RefCell::new(Ast::new(NodeValue::CodeBlock(output_block), LineColumn::from((0, 0))))))
+pub fn create_code_block<'a>(arena: &'a Arena<AstNode<'a>>, info: &str, literal: String) -> &'a mut AstNode::<'a> {
+    let mut code_block = NodeCodeBlock::default();
+    code_block.info = info.to_owned();
+    code_block.literal = literal;
+    arena.alloc(AstNode::new(RefCell::new(Ast::new(NodeValue::CodeBlock(code_block), LineColumn::from((0, 0))))))
 }
 
 pub fn convert(source: &Path, destination: &Path) -> AppResult<()> {
@@ -77,12 +224,63 @@ pub fn convert(source: &Path, destination: &Path) -> AppResult<()> {
         ));
     }
 
-    std::process::Command::new("pandoc")
+    let status = std::process::Command::new("pandoc")
         .arg(source)
         .args(["--pdf-engine", "pdfroff"])
         .args(["-o", destination.to_str().unwrap()])
         .spawn().map_err(|err| AppError::FailedToConvert(err.to_string()))?
         .wait().map_err(|err| AppError::FailedToConvert(err.to_string()))?;
 
+    if !status.success() {
+        return Err(AppError::FailedToConvert(format!("pandoc exited with {}", status)));
+    }
+
     Ok(())
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_parse_note_directives() {
+    let directives = NoteDirectives::parse("\n<!-- gitnotes: snippet-timeout=2.5, no-auto-tag -->\n# Title\n<!-- gitnotes: snippet-timeout=1 -->");
+    assert_eq!(Some(Duration::from_millis(2500)), directives.snippet_timeout);
+    assert_eq!(true, directives.no_auto_tag);
+
+    assert_eq!(NoteDirectives::default(), NoteDirectives::parse("# Title\n<!-- gitnotes: no-auto-tag -->"));
+}
+
+#[test]
+fn test_strip_output_blocks() {
+    let content = "# Title\n\n```python\nprint(1)\n```\n\n```output\n1\n```\n\n```stderr\nwarning\n```\n\nText *here*\n\n```stdin\ninput\n```\n";
+    assert_eq!(
+        "# Title\n\n```python\nprint(1)\n```\n\nText *here*\n\n```stdin\ninput\n```\n",
+        strip_output_blocks(content)
+    );
+
+    assert_eq!("No output\n", strip_output_blocks("No output\n"));
+}
+
+#[test]
+fn test_title() {
+    assert_eq!(Some("Hello world".to_owned()), title("Some text\n\n## Hello `world`\n\n# Other"));
+    assert_eq!(None, title("No heading"));
+}
+
+#[test]
+fn test_headings() {
+    let arena = storage();
+    let root = parse(&arena, "Setext title\n============\n\nText\n\n## ATX `code` heading\n\nSub title\n---------\n\n```\n# Not a heading\n```\n### Last ###\n");
+    assert_eq!(Some("Setext title".to_owned()), first_heading(root));
+    assert_eq!(
+        vec![
+            (1, "Setext title".to_owned()),
+            (2, "ATX code heading".to_owned()),
+            (2, "Sub title".to_owned()),
+            (3, "Last".to_owned())
+        ],
+        headings(root).collect::<Vec<_>>()
+    );
+
+    let arena = storage();
+    let root = parse(&arena, "No heading\n\n    # Indented code\n");
+    assert_eq!(None, first_heading(root));
+    assert_eq!(Vec::<(u32, String)>::new(), headings(root).collect::<Vec<_>>());
+}
@@ -2,11 +2,11 @@ use std::collections::BTreeMap;
 use std::ffi::OsString;
 use std::fmt::{Display};
 use std::fs::File;
-use std::io::{BufRead, BufReader, Lines};
+use std::io::{BufRead, BufReader};
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
-use chrono::{Datelike, DateTime, Local, Timelike};
+use chrono::{Datelike, DateTime, Local, Timelike, TimeZone};
 
 use fnv::FnvHashMap;
 
@@ -19,9 +19,12 @@ use crate::config::Config;
 use crate::helpers::io_error;
 
 pub const NOTES_DIR: &str = ".notes";
+pub const TRASH_DIR: &str = ".trash";
 pub const NOTE_METADATA_EXT: &str = "metadata";
 pub const NOTE_CONTENT_EXT: &str = "md";
 pub const RESOURCES_DIR: &str = "resources";
+pub const ACCESS_TIMES_FILE: &str = "gitnotes_access_times.toml";
+pub const ALIASES_FILE: &str = "gitnotes_aliases.toml";
 
 const NOTE_ID_SIZE: usize = 6;
 
@@ -116,7 +119,17 @@ pub struct NoteMetadata {
     pub created: DateTime<Local>,
     pub last_updated: DateTime<Local>,
     pub path: PathBuf,
-    pub tags: Vec<String>
+    pub tags: Vec<String>,
+    /// Pinned notes are listed by the pinned command (only saved when set, to not change existing metadata files).
+    #[serde(default, skip_serializing_if="is_false")]
+    pub pinned: bool,
+    /// Locked notes cannot be edited, moved or removed without forcing (only saved when set).
+    #[serde(default, skip_serializing_if="is_false")]
+    pub locked: bool
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
 }
 
 impl NoteMetadata {
@@ -127,7 +140,9 @@ impl NoteMetadata {
             created: now,
             last_updated: now,
             path,
-            tags
+            tags,
+            pinned: false,
+            locked: false
         }
     }
 
@@ -166,7 +181,9 @@ pub struct NoteMetadataStorage {
     root_dir: PathBuf,
     base_dir: Option<PathBuf>,
     id_to_notes: FnvHashMap<NoteId, NoteMetadata>,
-    path_to_id: FnvHashMap<PathBuf, NoteId>
+    path_to_id: FnvHashMap<PathBuf, NoteId>,
+    /// The content of the notes when loaded from a git tree (i.e. there is no working tree to read from)
+    tree_contents: Option<FnvHashMap<NoteId, String>>
 }
 
 impl NoteMetadataStorage {
@@ -184,7 +201,59 @@ impl NoteMetadataStorage {
                 root_dir: root_dir.to_path_buf(),
                 base_dir: base_dir.map(|x| x.to_owned()),
                 path_to_id,
-                id_to_notes
+                id_to_notes,
+                tree_contents: None
+            }
+        )
+    }
+
+    /// Loads the notes from the HEAD tree of the repository instead of the working tree, used for bare repositories.
+    pub fn from_git_tree(repository: &git2::Repository, base_dir: Option<&Path>) -> std::io::Result<NoteMetadataStorage> {
+        let mut path_to_id = FnvHashMap::default();
+        let mut id_to_notes = FnvHashMap::default();
+        let mut tree_contents = FnvHashMap::default();
+
+        let head_tree = repository.head().and_then(|head| head.peel_to_tree());
+        if let Ok(head_tree) = head_tree {
+            if let Ok(notes_entry) = head_tree.get_path(Path::new(NOTES_DIR)) {
+                let notes_tree = notes_entry.to_object(repository).map_err(io_error)?.peel_to_tree().map_err(io_error)?;
+
+                for entry in notes_tree.iter() {
+                    let name = match entry.name() {
+                        Some(name) => Path::new(name).to_owned(),
+                        None => continue
+                    };
+
+                    let blob = match entry.to_object(repository).map_err(io_error)?.into_blob() {
+                        Ok(blob) => blob,
+                        Err(_) => continue
+                    };
+                    let content = String::from_utf8_lossy(blob.content()).to_string();
+
+                    match name.extension().map(|ext| ext.to_str()).flatten() {
+                        Some(NOTE_METADATA_EXT) => {
+                            let note_metadata = NoteMetadata::parse(&content)?;
+                            path_to_id.insert(note_metadata.path.clone(), note_metadata.id);
+                            id_to_notes.insert(note_metadata.id, note_metadata);
+                        }
+                        Some(NOTE_CONTENT_EXT) => {
+                            if let Some(Ok(id)) = name.file_stem().map(|stem| stem.to_str()).flatten().map(NoteId::from_str) {
+                                tree_contents.insert(id, content);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        Ok(
+            NoteMetadataStorage {
+                root_dir: repository.path().to_path_buf(),
+                base_dir: base_dir.map(|x| x.to_owned()),
+                path_to_id,
+                id_to_notes,
+                tree_contents: Some(tree_contents)
             }
         )
     }
@@ -243,6 +312,40 @@ impl NoteMetadataStorage {
         None
     }
 
+    /// Returns an id that is not used by any note, or None if no free id was found.
+    pub fn new_note_id(&self) -> Option<NoteId> {
+        self.new_note_id_with(NoteId::new)
+    }
+
+    pub fn new_note_id_with<F: FnMut() -> NoteId>(&self, mut generate: F) -> Option<NoteId> {
+        const MAX_ATTEMPTS: usize = 1000;
+
+        for _ in 0..MAX_ATTEMPTS {
+            let id = generate();
+            if self.id_to_notes.contains_key(&id) {
+                continue;
+            }
+
+            // Notes added but not yet loaded (e.g. within the same commit) and trashed notes only exist on disk
+            if self.tree_contents.is_none() {
+                let (_, abs_content_path) = NoteMetadataStorage::get_note_storage_path(&self.root_dir, &id);
+                let (_, abs_metadata_path) = NoteMetadataStorage::get_note_metadata_path(&self.root_dir, &id);
+                let (_, abs_trash_content_path) = NoteMetadataStorage::get_trash_storage_path(&self.root_dir, &id);
+                let (_, abs_trash_metadata_path) = NoteMetadataStorage::get_trash_metadata_path(&self.root_dir, &id);
+                let is_used = [abs_content_path, abs_metadata_path, abs_trash_content_path, abs_trash_metadata_path]
+                    .iter()
+                    .any(|path| path.exists());
+                if is_used {
+                    continue;
+                }
+            }
+
+            return Some(id);
+        }
+
+        None
+    }
+
     pub fn contains_path(&self, path: &Path) -> bool {
         self.path_to_id.contains_key(path)
     }
@@ -253,14 +356,67 @@ impl NoteMetadataStorage {
 
     pub fn get_content(&self, path: &Path) -> std::io::Result<String> {
         let id = self.get_id_result(path)?;
+        if let Some(tree_contents) = self.tree_contents.as_ref() {
+            return Ok(tree_contents.get(&id).cloned().unwrap_or_default());
+        }
+
         let (_, abs_note_path) = NoteMetadataStorage::get_note_storage_path(&self.root_dir, &id);
         std::fs::read_to_string(abs_note_path)
     }
 
-    pub fn get_content_lines(&self, path: &Path) -> std::io::Result<Lines<BufReader<File>>> {
+    /// Like get_content, but replaces invalid UTF-8 instead of failing. The second value indicates if the content was valid.
+    pub fn get_content_lossy(&self, path: &Path) -> std::io::Result<(String, bool)> {
         let id = self.get_id_result(path)?;
+        if let Some(tree_contents) = self.tree_contents.as_ref() {
+            return Ok((tree_contents.get(&id).cloned().unwrap_or_default(), true));
+        }
+
+        let (_, abs_note_path) = NoteMetadataStorage::get_note_storage_path(&self.root_dir, &id);
+        let content = std::fs::read(abs_note_path)?;
+        match String::from_utf8(content) {
+            Ok(content) => Ok((content, true)),
+            Err(err) => Ok((String::from_utf8_lossy(err.as_bytes()).into_owned(), false))
+        }
+    }
+
+    pub fn get_content_size(&self, id: &NoteId) -> std::io::Result<u64> {
+        if let Some(tree_contents) = self.tree_contents.as_ref() {
+            return Ok(tree_contents.get(id).map(|content| content.len() as u64).unwrap_or(0));
+        }
+
+        let (_, abs_note_path) = NoteMetadataStorage::get_note_storage_path(&self.root_dir, id);
+        Ok(std::fs::metadata(abs_note_path)?.len())
+    }
+
+    pub fn get_content_lines(&self, path: &Path) -> std::io::Result<Box<dyn Iterator<Item=std::io::Result<String>> + '_>> {
+        let id = self.get_id_result(path)?;
+        if let Some(tree_contents) = self.tree_contents.as_ref() {
+            let content = tree_contents.get(&id).map(|content| content.as_str()).unwrap_or("");
+            return Ok(Box::new(content.lines().map(|line| Ok(line.to_owned()))));
+        }
+
         let (_, abs_note_path) = NoteMetadataStorage::get_note_storage_path(&self.root_dir, &id);
-        Ok(BufReader::new(File::open(abs_note_path)?).lines())
+        let mut reader = BufReader::new(File::open(abs_note_path)?);
+
+        // Invalid UTF-8 is replaced instead of failing, so that one bad note doesn't abort reading all notes
+        let mut line = Vec::new();
+        Ok(Box::new(std::iter::from_fn(move || {
+            line.clear();
+            match reader.read_until(b'\n', &mut line) {
+                Ok(0) => None,
+                Ok(_) => {
+                    if line.ends_with(b"\n") {
+                        line.pop();
+                        if line.ends_with(b"\r") {
+                            line.pop();
+                        }
+                    }
+
+                    Some(Ok(String::from_utf8_lossy(&line).into_owned()))
+                }
+                Err(err) => Some(Err(err))
+            }
+        })))
     }
 
     pub fn get_note_storage_path(root_dir: &Path, id: &NoteId) -> (PathBuf, PathBuf) {
@@ -274,6 +430,146 @@ impl NoteMetadataStorage {
         let abs_path = root_dir.join(NOTES_DIR).join(&relative_path);
         (Path::new(NOTES_DIR).join(relative_path), abs_path)
     }
+
+    pub fn get_trash_storage_path(root_dir: &Path, id: &NoteId) -> (PathBuf, PathBuf) {
+        let relative_path = Path::new(&(id.to_string() + "." + NOTE_CONTENT_EXT)).to_path_buf();
+        let abs_path = root_dir.join(TRASH_DIR).join(&relative_path);
+        (Path::new(TRASH_DIR).join(relative_path), abs_path)
+    }
+
+    pub fn get_trash_metadata_path(root_dir: &Path, id: &NoteId) -> (PathBuf, PathBuf) {
+        let relative_path = Path::new(&(id.to_string() + "." + NOTE_METADATA_EXT)).to_path_buf();
+        let abs_path = root_dir.join(TRASH_DIR).join(&relative_path);
+        (Path::new(TRASH_DIR).join(relative_path), abs_path)
+    }
+
+    /// Returns the notes that have been moved to the trash (by soft deletion), sorted by path.
+    pub fn trashed_notes(root_dir: &Path) -> std::io::Result<Vec<NoteMetadata>> {
+        let mut notes = Vec::new();
+        let trash_dir = root_dir.join(TRASH_DIR);
+        if trash_dir.exists() {
+            NoteMetadata::load_all(&trash_dir, |note_metadata| notes.push(note_metadata))?;
+        }
+
+        notes.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(notes)
+    }
+}
+
+/// When notes were last read. Kept in the git directory so that recording an access never changes the repository.
+pub struct AccessTimes {
+    path: PathBuf,
+    times: BTreeMap<String, DateTime<Local>>
+}
+
+impl AccessTimes {
+    pub fn load(repository: &git2::Repository) -> std::io::Result<AccessTimes> {
+        let path = repository.path().join(ACCESS_TIMES_FILE);
+        let times = if path.exists() {
+            toml::from_str(&std::fs::read_to_string(&path)?).map_err(|err| io_error(err))?
+        } else {
+            BTreeMap::new()
+        };
+
+        Ok(
+            AccessTimes {
+                path,
+                times
+            }
+        )
+    }
+
+    pub fn get(&self, id: &NoteId) -> Option<DateTime<Local>> {
+        self.times.get(&id.to_string()).cloned()
+    }
+
+    pub fn record(&mut self, id: &NoteId, time: DateTime<Local>) {
+        self.times.insert(id.to_string(), time);
+    }
+
+    pub fn change_id(&mut self, id: &NoteId, new_id: &NoteId) {
+        if let Some(time) = self.times.remove(&id.to_string()) {
+            self.times.insert(new_id.to_string(), time);
+        }
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let toml = toml::to_string(&self.times).map_err(|err| io_error(err))?;
+        std::fs::write(&self.path, toml)
+    }
+}
+
+/// Short names that can be used instead of the path of a note. Kept in the git directory (like access times) and refers to notes by id.
+pub struct NoteAliases {
+    path: PathBuf,
+    aliases: BTreeMap<String, String>
+}
+
+impl NoteAliases {
+    pub fn load(repository: &git2::Repository) -> std::io::Result<NoteAliases> {
+        let path = repository.path().join(ALIASES_FILE);
+        let aliases = if path.exists() {
+            toml::from_str(&std::fs::read_to_string(&path)?).map_err(|err| io_error(err))?
+        } else {
+            BTreeMap::new()
+        };
+
+        Ok(
+            NoteAliases {
+                path,
+                aliases
+            }
+        )
+    }
+
+    pub fn get(&self, alias: &str) -> Option<NoteId> {
+        self.aliases.get(alias).map(|id| NoteId::from_str(id).ok()).flatten()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item=(&str, NoteId)> {
+        self.aliases.keys().filter_map(|alias| Some((alias.as_str(), self.get(alias)?)))
+    }
+
+    pub fn add(&mut self, alias: &str, id: &NoteId) {
+        self.aliases.insert(alias.to_owned(), id.to_string());
+    }
+
+    pub fn remove(&mut self, alias: &str) -> bool {
+        self.aliases.remove(alias).is_some()
+    }
+
+    pub fn change_id(&mut self, id: &NoteId, new_id: &NoteId) {
+        for alias_id in self.aliases.values_mut() {
+            if *alias_id == id.to_string() {
+                *alias_id = new_id.to_string();
+            }
+        }
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let toml = toml::to_string(&self.aliases).map_err(|err| io_error(err))?;
+        std::fs::write(&self.path, toml)
+    }
+}
+
+pub fn created_from_path(path: &Path) -> Option<DateTime<Local>> {
+    let parts = path.iter().map(|part| part.to_str()).collect::<Option<Vec<_>>>()?;
+    if parts.len() < 4 {
+        return None;
+    }
+
+    let parse_part = |part: &str, length: usize| -> Option<u32> {
+        if part.len() == length && part.chars().all(|c| c.is_ascii_digit()) {
+            part.parse().ok()
+        } else {
+            None
+        }
+    };
+
+    let year = parse_part(parts[0], 4)?;
+    let month = parse_part(parts[1], 2)?;
+    let day = parse_part(parts[2], 2)?;
+    Local.with_ymd_and_hms(year as i32, month, day, 0, 0, 0).single()
 }
 
 fn resolve_absolute_path(base_dir: Option<&PathBuf>, path: PathBuf) -> PathBuf {
@@ -418,35 +714,46 @@ impl<'a> NoteFileTree<'a> {
             apply: &mut F,
             level: usize,
             parent: &Path,
-            is_last_stack: &mut Vec<bool>,
             tree: &'a NoteFileTree
         ) {
             if let Some(children) = tree.children() {
-                let num_children = children.len();
                 for (child_index, (name, child)) in children.iter().enumerate() {
                     let is_first = child_index == 0;
-                    let is_last = child_index == (num_children - 1);
                     match child {
                         NoteFileTree::Note(_) => {
-                            if !apply(level, parent, name, child, NoteFileTreeWalkStack { is_first, is_last, is_last_stack }) {
+                            if !apply(level, parent, name, child, NoteFileTreeWalkStack { is_first }) {
                                 continue;
                             }
                         }
                         NoteFileTree::Tree { .. } => {
-                            if !apply(level, parent, name, child, NoteFileTreeWalkStack { is_first, is_last, is_last_stack }) {
+                            if !apply(level, parent, name, child, NoteFileTreeWalkStack { is_first }) {
                                 continue;
                             }
 
-                            is_last_stack.push(is_last);
-                            do_walk(apply, level + 1, &parent.join(name), is_last_stack, child);
-                            is_last_stack.pop();
+                            do_walk(apply, level + 1, &parent.join(name), child);
                         }
                     }
                 }
             }
         }
 
-        do_walk(&mut apply, 0, Path::new(""), &mut Vec::new(), self);
+        do_walk(&mut apply, 0, Path::new(""), self);
+    }
+
+    pub fn size(&self, note_metadata_storage: &NoteMetadataStorage) -> std::io::Result<u64> {
+        match self {
+            NoteFileTree::Note(note_metadata) => {
+                note_metadata_storage.get_content_size(&note_metadata.id)
+            }
+            NoteFileTree::Tree { children, .. } => {
+                let mut size = 0;
+                for child in children.values() {
+                    size += child.size(note_metadata_storage)?;
+                }
+
+                Ok(size)
+            }
+        }
     }
 
     pub fn children(&self) -> Option<&BTreeMap<OsString, NoteFileTree>> {
@@ -472,10 +779,8 @@ impl<'a> NoteFileTree<'a> {
     }
 }
 
-pub struct NoteFileTreeWalkStack<'a> {
-    pub is_first: bool,
-    pub is_last: bool,
-    pub is_last_stack: &'a mut Vec<bool>
+pub struct NoteFileTreeWalkStack {
+    pub is_first: bool
 }
 
 #[cfg(test)]
@@ -498,7 +803,7 @@ macro_rules! assert_tree_eq {
 
 #[test]
 fn test_create_tree1() {
-    use crate::querying::ListTree;
+    use crate::querying::{ListTree, ListTreeFilter};
 
     let note_metadata = vec![
         NoteMetadata::new(NoteId::new(), Path::new("00.md").to_path_buf(), Vec::new()),
@@ -511,7 +816,7 @@ fn test_create_tree1() {
     ];
 
     let tree = NoteFileTree::from_iter(note_metadata.iter()).unwrap();
-    ListTree::print_tree(&tree, "");
+    ListTree::print_tree(&tree, "", ListTreeFilter::All, None).unwrap();
 
     assert_tree_eq!(
         vec!["00.md", "2023", "01", "01", "03.md", "04.md", "02", "05.md", "01.md", "02", "01", "06.md", "02.md"],
@@ -521,7 +826,7 @@ fn test_create_tree1() {
 
 #[test]
 fn test_find_tree1() {
-    use crate::querying::ListTree;
+    use crate::querying::{ListTree, ListTreeFilter};
 
     let note_metadata = vec![
         NoteMetadata::new(NoteId::new(), Path::new("00.md").to_path_buf(), Vec::new()),
@@ -537,10 +842,42 @@ fn test_find_tree1() {
     let tree = NoteFileTree::from_iter(note_metadata.iter()).unwrap();
 
     let found = tree.find(Path::new("2023/01")).unwrap();
-    ListTree::print_tree(&found, "");
+    ListTree::print_tree(&found, "", ListTreeFilter::All, None).unwrap();
 
     assert_tree_eq!(
         vec!["00.md", "2023", "01", "01", "03.md", "04.md", "02", "05.md", "06.md", "01.md", "02", "01", "07.md", "02.md"],
         tree
     );
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_list_tree_filter1() {
+    use crate::querying::{ListTree, ListTreeFilter};
+
+    let note_metadata = vec![
+        NoteMetadata::new(NoteId::new(), Path::new("00.md").to_path_buf(), Vec::new()),
+        NoteMetadata::new(NoteId::new(), Path::new("2023/01.md").to_path_buf(), Vec::new()),
+        NoteMetadata::new(NoteId::new(), Path::new("2023/01/01/03.md").to_path_buf(), Vec::new()),
+        NoteMetadata::new(NoteId::new(), Path::new("2023/02/01/04.md").to_path_buf(), Vec::new()),
+    ];
+
+    let tree = NoteFileTree::from_iter(note_metadata.iter()).unwrap();
+
+    let lines = ListTree::create_lines(&tree, ListTreeFilter::All, None).unwrap();
+    assert_eq!(
+        vec!["├── 00.md", "└── 2023", "    ├── 01", "    │   └── 01", "    │       └── 03.md", "    ├── 01.md", "    └── 02", "        └── 01", "            └── 04.md"],
+        lines.iter().map(|line| format!("{}{}", line.prefix, line.content.split(" (").next().unwrap())).collect::<Vec<_>>()
+    );
+
+    let lines = ListTree::create_lines(&tree, ListTreeFilter::OnlyFiles, None).unwrap();
+    assert_eq!(
+        vec!["00.md", "2023/01/01/03.md", "2023/01.md", "2023/02/01/04.md"],
+        lines.iter().map(|line| format!("{}{}", line.prefix, line.content.split(" (").next().unwrap())).collect::<Vec<_>>()
+    );
+
+    let lines = ListTree::create_lines(&tree, ListTreeFilter::OnlyDirs, None).unwrap();
+    assert_eq!(
+        vec!["└── 2023", "    ├── 01", "    │   └── 01", "    └── 02", "        └── 01"],
+        lines.iter().map(|line| format!("{}{}", line.prefix, line.content)).collect::<Vec<_>>()
+    );
+}
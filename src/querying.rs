@@ -1,24 +1,84 @@
-use std::collections::BTreeMap;
-use std::io::{IsTerminal, stdout};
+use std::collections::{BTreeMap, BTreeSet, HashSet, VecDeque};
+use std::fmt::Display;
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::io::{IsTerminal, Read, stdout, Write};
+use std::ops::Range;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
-use chrono::{Datelike, DateTime, Local, Timelike};
+use chrono::{Datelike, DateTime, FixedOffset, Local, Timelike, TimeZone, Utc};
 use regex::{Regex};
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+use fnv::{FnvHashMap, FnvHasher};
+
 use comrak::nodes::NodeValue;
 
 use crossterm::ExecutableCommand;
 use crossterm::style::{Color, Print, ResetColor, SetAttribute, SetForegroundColor};
 use crossterm::style::Attribute::Bold;
 
-use crate::helpers::{TablePrinter, ToChronoDateTime};
+use crate::helpers::{human_readable_size, TablePrinter, ToChronoDateTime};
 use crate::markdown;
-use crate::model::{NOTE_CONTENT_EXT, NOTE_METADATA_EXT, NoteFileTree, NoteFileTreeCreateConfig, NoteMetadata, NoteMetadataStorage, NOTES_DIR};
+use crate::model::{AccessTimes, NOTE_CONTENT_EXT, NOTE_METADATA_EXT, NoteFileTree, NoteFileTreeCreateConfig, NoteId, NoteMetadata, NoteMetadataStorage, NOTES_DIR, RESOURCES_DIR};
 
 pub const DATETIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
 
+/// How dates are shown in listings.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all="lowercase")]
+pub enum DateFormat {
+    Default,
+    Rfc3339,
+    Unix,
+    Relative
+}
+
+impl FromStr for DateFormat {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "default" => Ok(DateFormat::Default),
+            "rfc3339" => Ok(DateFormat::Rfc3339),
+            "unix" => Ok(DateFormat::Unix),
+            "relative" => Ok(DateFormat::Relative),
+            _ => Err(format!("Invalid date format '{}', expected default, rfc3339, unix or relative", text))
+        }
+    }
+}
+
+pub fn format_datetime<Tz: TimeZone>(datetime: &DateTime<Tz>, date_format: DateFormat) -> String where Tz::Offset: Display {
+    format_datetime_relative_to(datetime, date_format, &Utc::now())
+}
+
+pub fn format_datetime_relative_to<Tz: TimeZone>(datetime: &DateTime<Tz>, date_format: DateFormat, now: &DateTime<Utc>) -> String where Tz::Offset: Display {
+    match date_format {
+        DateFormat::Default => datetime.format(DATETIME_FORMAT).to_string(),
+        DateFormat::Rfc3339 => datetime.to_rfc3339(),
+        DateFormat::Unix => datetime.timestamp().to_string(),
+        DateFormat::Relative => {
+            let seconds = now.timestamp() - datetime.timestamp();
+            if seconds < 0 {
+                return "in the future".to_owned();
+            }
+
+            let (amount, unit) = match seconds {
+                0..=59 => return "just now".to_owned(),
+                60..=3599 => (seconds / 60, "minute"),
+                3600..=86399 => (seconds / 3600, "hour"),
+                86400..=2591999 => (seconds / 86400, "day"),
+                2592000..=31535999 => (seconds / 2592000, "month"),
+                _ => (seconds / 31536000, "year")
+            };
+
+            format!("{} {}{} ago", amount, unit, if amount == 1 { "" } else { "s" })
+        }
+    }
+}
+
 pub type QueryingResult<T> = Result<T, QueryingError>;
 
 #[derive(Error, Debug)]
@@ -72,7 +132,22 @@ impl<'a> Finder<'a> {
         let mut results = Vec::new();
 
         for note_metadata in self.note_metadata_storage.notes() {
-            if query.is_match(note_metadata) {
+            if query.is_match(note_metadata, self.note_metadata_storage)? {
+                results.push(note_metadata);
+            }
+        }
+
+        results.sort_by_key(|note_metadata| &note_metadata.path);
+        Ok(results)
+    }
+
+    /// Finds the notes whose content is empty or only whitespace.
+    pub fn find_empty(&self) -> QueryingResult<Vec<&NoteMetadata>> {
+        let mut results = Vec::new();
+
+        for note_metadata in self.note_metadata_storage.notes() {
+            let (content, _) = self.note_metadata_storage.get_content_lossy(&note_metadata.path)?;
+            if content.trim().is_empty() {
                 results.push(note_metadata);
             }
         }
@@ -82,7 +157,7 @@ impl<'a> Finder<'a> {
     }
 }
 
-pub fn print_note_metadata_results(results: &Vec<&NoteMetadata>) {
+pub fn print_note_metadata_results(results: &Vec<&NoteMetadata>, date_format: DateFormat) {
     let mut table_printer = TablePrinter::new(vec![
         "path".to_owned(),
         "id".to_owned(),
@@ -96,466 +171,2093 @@ pub fn print_note_metadata_results(results: &Vec<&NoteMetadata>) {
             note_metadata.path.to_str().unwrap().to_owned(),
             note_metadata.id.to_string(),
             note_metadata.tags.join(" "),
-            note_metadata.created.format(DATETIME_FORMAT).to_string(),
-            note_metadata.last_updated.format(DATETIME_FORMAT).to_string()
+            format_datetime(&note_metadata.created, date_format),
+            format_datetime(&note_metadata.last_updated, date_format)
         ]);
     }
 
     table_printer.print();
 }
 
-pub struct Searcher<'a> {
+/// Creates a JSON array of the results (dates are formatted using DATETIME_FORMAT).
+pub fn note_metadata_results_json(results: &Vec<&NoteMetadata>) -> String {
+    #[derive(Serialize)]
+    struct Entry<'a> {
+        path: &'a Path,
+        id: String,
+        tags: &'a Vec<String>,
+        created: String,
+        last_updated: String
+    }
+
+    let entries = results
+        .iter()
+        .map(|note_metadata| {
+            Entry {
+                path: &note_metadata.path,
+                id: note_metadata.id.to_string(),
+                tags: &note_metadata.tags,
+                created: note_metadata.created.format(DATETIME_FORMAT).to_string(),
+                last_updated: note_metadata.last_updated.format(DATETIME_FORMAT).to_string()
+            }
+        })
+        .collect::<Vec<_>>();
+
+    serde_json::to_string_pretty(&entries).unwrap()
+}
+
+/// How listings are printed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    Text,
+    Json
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            _ => Err(format!("Invalid output format '{}', expected text or json", text))
+        }
+    }
+}
+
+/// The key used to order note listings.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortBy {
+    Path,
+    Created,
+    Updated,
+    Title,
+    Id
+}
+
+impl FromStr for SortBy {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "path" => Ok(SortBy::Path),
+            "created" => Ok(SortBy::Created),
+            "updated" => Ok(SortBy::Updated),
+            "title" => Ok(SortBy::Title),
+            "id" => Ok(SortBy::Id),
+            _ => Err(format!("Invalid sort key '{}', expected path, created, updated, title or id", text))
+        }
+    }
+}
+
+/// Sorts the results by the given key, using the path to break ties.
+pub fn sort_note_metadata_results(note_metadata_storage: &NoteMetadataStorage,
+                                  results: &mut Vec<&NoteMetadata>,
+                                  sort_by: SortBy,
+                                  reverse: bool) {
+    match sort_by {
+        SortBy::Path => {
+            results.sort_by(|a, b| a.path.cmp(&b.path));
+        }
+        SortBy::Created => {
+            results.sort_by(|a, b| a.created.cmp(&b.created).then_with(|| a.path.cmp(&b.path)));
+        }
+        SortBy::Updated => {
+            results.sort_by(|a, b| a.last_updated.cmp(&b.last_updated).then_with(|| a.path.cmp(&b.path)));
+        }
+        SortBy::Title => {
+            let titles = FnvHashMap::from_iter(
+                results.iter().map(|note_metadata| (note_metadata.id, note_title(note_metadata_storage, note_metadata).to_lowercase()))
+            );
+
+            results.sort_by(|a, b| titles[&a.id].cmp(&titles[&b.id]).then_with(|| a.path.cmp(&b.path)));
+        }
+        SortBy::Id => {
+            results.sort_by(|a, b| a.id.cmp(&b.id));
+        }
+    }
+
+    if reverse {
+        results.reverse();
+    }
+}
+
+/// Returns the first heading of the note, or the file name if it has none.
+pub fn note_title(note_metadata_storage: &NoteMetadataStorage, note_metadata: &NoteMetadata) -> String {
+    note_metadata_storage.get_content_lossy(&note_metadata.path)
+        .ok()
+        .and_then(|(content, _)| markdown::title(&content))
+        .unwrap_or_else(|| note_metadata.path.file_name().map(|name| name.to_str().unwrap().to_owned()).unwrap_or_default())
+}
+
+/// Returns the most recently updated notes, newest first.
+pub fn recently_updated(note_metadata_storage: &NoteMetadataStorage, count: usize) -> Vec<&NoteMetadata> {
+    let mut results = note_metadata_storage.notes().collect::<Vec<_>>();
+    results.sort_by(|a, b| b.last_updated.cmp(&a.last_updated));
+    results.truncate(count);
+    results
+}
+
+/// Returns the most recently accessed notes together with their access time, newest first.
+pub fn recently_accessed<'a>(note_metadata_storage: &'a NoteMetadataStorage,
+                             access_times: &AccessTimes,
+                             count: usize) -> Vec<(&'a NoteMetadata, DateTime<Local>)> {
+    let mut results = note_metadata_storage
+        .notes()
+        .flat_map(|note_metadata| access_times.get(&note_metadata.id).map(|time| (note_metadata, time)))
+        .collect::<Vec<_>>();
+    results.sort_by(|a, b| b.1.cmp(&a.1));
+    results.truncate(count);
+    results
+}
+
+pub fn print_recently_accessed_results(results: &Vec<(&NoteMetadata, DateTime<Local>)>, date_format: DateFormat) {
+    let mut table_printer = TablePrinter::new(vec![
+        "path".to_owned(),
+        "id".to_owned(),
+        "tags".to_owned(),
+        "last accessed".to_owned(),
+    ]);
+
+    for (note_metadata, last_accessed) in results {
+        table_printer.add_row(vec![
+            note_metadata.path.to_str().unwrap().to_owned(),
+            note_metadata.id.to_string(),
+            note_metadata.tags.join(" "),
+            format_datetime(last_accessed, date_format)
+        ]);
+    }
+
+    table_printer.print();
+}
+
+/// Returns the notes together with the size of their content, largest first (at most top if given).
+pub fn note_sizes(note_metadata_storage: &NoteMetadataStorage, top: Option<usize>) -> QueryingResult<Vec<(&NoteMetadata, u64)>> {
+    let mut results = Vec::new();
+    for note_metadata in note_metadata_storage.notes() {
+        results.push((note_metadata, note_metadata_storage.get_content_size(&note_metadata.id)?));
+    }
+
+    results.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.path.cmp(&b.0.path)));
+    if let Some(top) = top {
+        results.truncate(top);
+    }
+
+    Ok(results)
+}
+
+pub fn print_note_sizes(results: &Vec<(&NoteMetadata, u64)>) {
+    let mut table_printer = TablePrinter::new(vec![
+        "path".to_owned(),
+        "id".to_owned(),
+        "size".to_owned()
+    ]);
+
+    for (note_metadata, size) in results {
+        table_printer.add_row(vec![
+            note_metadata.path.to_str().unwrap().to_owned(),
+            note_metadata.id.to_string(),
+            human_readable_size(*size)
+        ]);
+    }
+
+    table_printer.print();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TagSort {
+    Name,
+    Count
+}
+
+impl FromStr for TagSort {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "name" => Ok(TagSort::Name),
+            "count" => Ok(TagSort::Count),
+            _ => Err(format!("Invalid sort key '{}', expected name or count", text))
+        }
+    }
+}
+
+/// Returns each tag together with the number of notes that have it.
+pub fn tag_counts(note_metadata_storage: &NoteMetadataStorage, sort: TagSort) -> Vec<(String, usize)> {
+    let mut counts = BTreeMap::new();
+    for note_metadata in note_metadata_storage.notes() {
+        for tag in &note_metadata.tags {
+            *counts.entry(tag.clone()).or_insert(0) += 1;
+        }
+    }
+
+    let mut results = counts.into_iter().collect::<Vec<_>>();
+    if sort == TagSort::Count {
+        results.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    }
+
+    results
+}
+
+pub fn print_tag_counts(results: &Vec<(String, usize)>) {
+    let mut table_printer = TablePrinter::new(vec![
+        "tag".to_owned(),
+        "count".to_owned()
+    ]);
+
+    for (tag, count) in results {
+        table_printer.add_row(vec![tag.clone(), count.to_string()]);
+    }
+
+    table_printer.print();
+}
+
+pub fn tag_counts_json(results: &Vec<(String, usize)>) -> String {
+    #[derive(Serialize)]
+    struct Entry<'a> {
+        tag: &'a str,
+        count: usize
+    }
+
+    let entries = results
+        .iter()
+        .map(|(tag, count)| Entry { tag, count: *count })
+        .collect::<Vec<_>>();
+
+    serde_json::to_string_pretty(&entries).unwrap()
+}
+
+pub struct DuplicateFinder<'a> {
     note_metadata_storage: &'a NoteMetadataStorage
 }
 
-impl<'a> Searcher<'a> {
-    pub fn new(note_metadata_storage: &'a NoteMetadataStorage) -> QueryingResult<Searcher<'a>> {
+impl<'a> DuplicateFinder<'a> {
+    pub fn new(note_metadata_storage: &'a NoteMetadataStorage) -> QueryingResult<DuplicateFinder<'a>> {
         Ok(
-            Searcher {
+            DuplicateFinder {
                 note_metadata_storage
             }
         )
     }
 
-    pub fn search(&self, query: &Regex) -> QueryingResult<Vec<&'a NoteMetadata>> {
-        let is_terminal = stdout().is_terminal();
+    pub fn find_exact(&self) -> QueryingResult<Vec<Vec<&'a NoteMetadata>>> {
+        let mut notes_by_hash = FnvHashMap::default();
+        for note_metadata in self.note_metadata_storage.notes() {
+            let content = self.note_metadata_storage.get_content(&note_metadata.path)?;
 
-        let mut matches = Vec::new();
+            let mut hasher = FnvHasher::default();
+            content.hash(&mut hasher);
+            notes_by_hash.entry(hasher.finish()).or_insert_with(|| Vec::new()).push(note_metadata);
+        }
+
+        Ok(DuplicateFinder::create_groups(notes_by_hash.into_values()))
+    }
+
+    pub fn find_similar(&self, threshold: f64) -> QueryingResult<Vec<Vec<&'a NoteMetadata>>> {
+        let mut notes = Vec::new();
         for note_metadata in self.note_metadata_storage.notes() {
-            for line in self.note_metadata_storage.get_content_lines(&note_metadata.path)? {
-                let line = line?;
+            let content = self.note_metadata_storage.get_content(&note_metadata.path)?;
+            let words = content.split_whitespace().map(|word| word.to_owned()).collect::<HashSet<_>>();
+            notes.push((note_metadata, words));
+        }
 
-                self.find_matches(
-                    query,
-                    &line,
-                    is_terminal,
-                    |is_terminal| {
-                        let info_text = note_metadata.info_text();
-                        if is_terminal {
-                            stdout()
-                                .execute(SetForegroundColor(Color::DarkMagenta))?
-                                .execute(Print(format!("{}: ", info_text)))?
-                                .execute(ResetColor)?;
-                        } else {
-                            print!("{}: ", info_text);
-                        }
+        // Groups are formed transitively, i.e. if A is similar to B and B to C, then all three are in the same group.
+        let mut group_index = (0..notes.len()).collect::<Vec<_>>();
+        fn find_root(group_index: &mut Vec<usize>, mut index: usize) -> usize {
+            while group_index[index] != index {
+                group_index[index] = group_index[group_index[index]];
+                index = group_index[index];
+            }
 
-                        matches.push(note_metadata);
-                        Ok(())
-                    }
-                )?;
+            index
+        }
+
+        for i in 0..notes.len() {
+            for j in (i + 1)..notes.len() {
+                if jaccard_similarity(&notes[i].1, &notes[j].1) >= threshold {
+                    let root_i = find_root(&mut group_index, i);
+                    let root_j = find_root(&mut group_index, j);
+                    group_index[root_j] = root_i;
+                }
             }
         }
 
-        Ok(matches)
+        let mut groups = FnvHashMap::default();
+        for index in 0..notes.len() {
+            let root = find_root(&mut group_index, index);
+            groups.entry(root).or_insert_with(|| Vec::new()).push(notes[index].0);
+        }
+
+        Ok(DuplicateFinder::create_groups(groups.into_values()))
     }
 
-    pub fn search_historic(
-        &self,
-        repository: &git2::Repository,
-        query: &Regex,
-        git_start: &str, git_end: Option<&str>
-    ) -> QueryingResult<Vec<(git2::Oid, NoteMetadata)>> {
-        let is_terminal = stdout().is_terminal();
+    fn create_groups(groups: impl Iterator<Item=Vec<&'a NoteMetadata>>) -> Vec<Vec<&'a NoteMetadata>> {
+        let mut groups = groups
+            .filter(|group| group.len() > 1)
+            .map(|mut group| {
+                group.sort_by_key(|note_metadata| &note_metadata.path);
+                group
+            })
+            .collect::<Vec<_>>();
+
+        groups.sort_by(|a, b| a[0].path.cmp(&b[0].path));
+        groups
+    }
+}
 
-        let mut rev_walk = repository.revwalk()?;
-        rev_walk.push(repository.revparse_single(git_start)?.id())?;
+fn jaccard_similarity(a: &HashSet<String>, b: &HashSet<String>) -> f64 {
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
 
-        if let Some(git_end) = git_end {
-            rev_walk.hide(repository.revparse_single(git_end)?.id())?;
+    let intersection = a.intersection(b).count();
+    let union = a.union(b).count();
+    intersection as f64 / union as f64
+}
+
+pub fn print_duplicate_results(groups: &Vec<Vec<&NoteMetadata>>) {
+    if groups.is_empty() {
+        println!("No duplicates found.");
+        return;
+    }
+
+    for (group_index, group) in groups.iter().enumerate() {
+        println!("Group {}:", group_index + 1);
+        for note_metadata in group {
+            println!("\t{}", note_metadata.info_text());
         }
+    }
+}
 
-        let mut matches = Vec::new();
-        for commit_id in rev_walk {
-            let commit_id = commit_id?;
-            let commit = repository.find_commit(commit_id)?;
-            let tree = commit.tree()?;
+/// The links between notes, where a link is a markdown link whose URL is the path (or id) of another note.
+pub struct LinkGraph<'a> {
+    note_metadata_storage: &'a NoteMetadataStorage,
+    outbound: FnvHashMap<NoteId, Vec<NoteId>>,
+    inbound: FnvHashMap<NoteId, Vec<NoteId>>
+}
 
-            let mut notes = BTreeMap::new();
-            for file_entry in tree.iter() {
-                let file_path = Path::new(file_entry.name().unwrap());
-                let note_id = file_path.file_stem().unwrap().to_os_string();
+impl<'a> LinkGraph<'a> {
+    pub fn new(note_metadata_storage: &'a NoteMetadataStorage) -> QueryingResult<LinkGraph<'a>> {
+        let mut outbound = FnvHashMap::default();
+        let mut inbound = FnvHashMap::default();
 
-                let note_entry = notes.entry(note_id).or_insert_with(|| (None, None));
-                match file_path.extension().map(|x| x.to_str().unwrap()) {
-                    Some(entry) if entry == NOTE_METADATA_EXT => {
-                        note_entry.0 = Some(file_entry);
-                    }
-                    Some(entry) if entry == NOTE_CONTENT_EXT => {
-                        note_entry.1 = Some(file_entry);
-                    }
-                    _ => {}
-                }
-            }
-
-            for note_entry in notes.values() {
-                if let (Some(metadata_entry), Some(content_entry)) = note_entry {
-                    let metadata_entry = metadata_entry.to_object(&repository)?;
-                    let metadata_content = metadata_entry
-                        .as_blob()
-                        .map(|blob| std::str::from_utf8(blob.content()).ok())
-                        .flatten();
-
-                    let content_entry = content_entry.to_object(&repository)?;
-                    let content = content_entry
-                        .as_blob()
-                        .map(|blob| std::str::from_utf8(blob.content()).ok())
-                        .flatten();
-
-                    if let (Some(metadata_content), Some(content)) = (metadata_content, content) {
-                        let note_metadata = NoteMetadata::parse(metadata_content)?;
-
-                        for line in content.lines() {
-                            self.find_matches(
-                                query,
-                                line,
-                                is_terminal,
-                                |is_terminal| {
-                                    matches.push((commit_id, note_metadata.clone()));
-
-                                    let info_text = note_metadata.info_text();
-                                    let short_commit_id = commit.as_object().short_id()?.as_str().unwrap().to_owned();
-
-                                    if is_terminal {
-                                        stdout()
-                                            .execute(SetForegroundColor(Color::Yellow))?
-                                            .execute(Print(format!("{}", short_commit_id)))?
-                                            .execute(ResetColor)?
-
-                                            .execute(Print(format!(" - ")))?
-
-                                            .execute(SetForegroundColor(Color::DarkMagenta))?
-                                            .execute(Print(format!("{}: ", info_text)))?
-                                            .execute(ResetColor)?;
-                                    } else {
-                                        print!("{} - {}: ", short_commit_id, info_text);
-                                    }
-
-                                    Ok(())
-                                }
-                            )?;
-                        }
+        for note_metadata in note_metadata_storage.notes() {
+            let content = note_metadata_storage.get_content(&note_metadata.path)?;
+
+            let mut links = Vec::new();
+            for url in markdown::links(&content) {
+                if let Some(target) = LinkGraph::resolve_link(note_metadata_storage, note_metadata, &url) {
+                    if target.id != note_metadata.id && !links.contains(&target.id) {
+                        links.push(target.id);
+                        inbound.entry(target.id).or_insert_with(|| Vec::new()).push(note_metadata.id);
                     }
                 }
             }
+
+            outbound.insert(note_metadata.id, links);
         }
 
-        Ok(matches)
+        Ok(
+            LinkGraph {
+                note_metadata_storage,
+                outbound,
+                inbound
+            }
+        )
     }
 
-    fn find_matches<FnFirst: FnMut(bool) -> QueryingResult<()>>(
-        &self,
-        query: &Regex, line: &str,
-        is_terminal: bool,
-        mut before_first: FnFirst,
-    ) -> QueryingResult<()> {
-        let mut remaining_line_start = 0;
-        let mut found_match = false;
-        for current_match in query.find_iter(&line) {
-            if !found_match {
-                before_first(is_terminal)?;
-                found_match = true;
-            }
+    /// The notes that the given note links to.
+    pub fn outbound(&self, id: &NoteId) -> Vec<&'a NoteMetadata> {
+        self.resolve_ids(self.outbound.get(id))
+    }
 
-            let before = &line[remaining_line_start..current_match.start()];
-            let during = &line[current_match.range()];
-            remaining_line_start = current_match.end();
+    /// The notes that link to the given note.
+    pub fn inbound(&self, id: &NoteId) -> Vec<&'a NoteMetadata> {
+        self.resolve_ids(self.inbound.get(id))
+    }
 
-            if is_terminal {
-                stdout()
-                    .execute(Print(before))?
+    fn resolve_ids(&self, ids: Option<&Vec<NoteId>>) -> Vec<&'a NoteMetadata> {
+        let mut notes = ids
+            .map(|ids| ids.iter().flat_map(|id| self.note_metadata_storage.get_by_id(id)).collect::<Vec<_>>())
+            .unwrap_or_else(|| Vec::new());
+        notes.sort_by_key(|note_metadata| &note_metadata.path);
+        notes
+    }
 
-                    .execute(SetAttribute(Bold))?
-                    .execute(SetForegroundColor(Color::Red))?
-                    .execute(Print(during))?
-                    .execute(ResetColor)?;
-            } else {
-                print!("{}{}", before, during);
-            }
+    fn resolve_link(note_metadata_storage: &'a NoteMetadataStorage, source: &NoteMetadata, url: &str) -> Option<&'a NoteMetadata> {
+        if url.contains("://") {
+            return None;
         }
 
-        if found_match {
-            if is_terminal {
-                stdout()
-                    .execute(Print(&line[remaining_line_start..]))?
-                    .execute(Print("\n"))?;
-            } else {
-                println!("{}", &line[remaining_line_start..]);
-            }
+        if let Some(url) = url.strip_prefix("/") {
+            return note_metadata_storage.get(Path::new(url));
         }
 
-        Ok(())
+        // Relative to the linking note first, then relative to the root
+        let relative_path = source.path.parent().map(|parent| parent.join(url));
+        relative_path
+            .and_then(|path| note_metadata_storage.get(&path))
+            .or_else(|| note_metadata_storage.get(Path::new(url)))
     }
 }
 
-pub struct ListDirectoryEntry<'a> {
-    pub name: String,
-    pub last_updated: Option<DateTime<Local>>,
-    pub note_metadata: Option<&'a NoteMetadata>
+/// Reads the file as text, or None if it looks binary (a null byte within the first 8 KB, like git) or is not valid UTF-8.
+fn read_text_file(path: &Path) -> std::io::Result<Option<String>> {
+    const SNIFF_SIZE: u64 = 8000;
+
+    let mut file = File::open(path)?;
+    let mut content = Vec::new();
+    (&mut file).take(SNIFF_SIZE).read_to_end(&mut content)?;
+    if content.contains(&0) {
+        return Ok(None);
+    }
+
+    file.read_to_end(&mut content)?;
+    Ok(String::from_utf8(content).ok())
 }
 
-pub struct ListDirectory<'a> {
-    root: NoteFileTree<'a>
+/// The state of a line based search that is kept between the searched notes (or resources).
+#[derive(Default)]
+struct SearchLinesState {
+    num_matches: usize,
+    printed_any: bool
 }
 
-impl<'a> ListDirectory<'a> {
-    pub fn new(note_metadata_storage: &'a NoteMetadataStorage) -> QueryingResult<ListDirectory<'a>> {
+pub struct Searcher<'a> {
+    note_metadata_storage: &'a NoteMetadataStorage,
+    grouped: bool,
+    limit: Option<usize>,
+    context_before: usize,
+    context_after: usize
+}
+
+impl<'a> Searcher<'a> {
+    pub fn new(note_metadata_storage: &'a NoteMetadataStorage) -> QueryingResult<Searcher<'a>> {
         Ok(
-            ListDirectory {
-                root: NoteFileTree::from_iter(note_metadata_storage.notes())
-                    .ok_or_else(|| QueryingError::FailedToCreateNoteFileTree)?
+            Searcher {
+                note_metadata_storage,
+                grouped: false,
+                limit: None,
+                context_before: 0,
+                context_after: 0
             }
         )
     }
 
-    pub fn list(&'a self, query: &Path) -> QueryingResult<Vec<ListDirectoryEntry<'a>>> {
-        let mut results = Vec::new();
+    /// Groups the matches of line based searches by note instead of prefixing each line with the note.
+    pub fn set_grouped(&mut self, grouped: bool) {
+        self.grouped = grouped;
+    }
 
-        let found_tree = if query == Path::new("") {
-            Some(&self.root)
-        } else {
-            self.root.find(query)
-        };
+    /// Stops searching after the given number of matching lines (regions for multiline searches).
+    pub fn set_limit(&mut self, limit: Option<usize>) {
+        self.limit = limit;
+    }
+
+    /// Also prints the given number of lines before and after each matching line (for line based searches), like grep.
+    pub fn set_context(&mut self, before: usize, after: usize) {
+        self.context_before = before;
+        self.context_after = after;
+    }
+
+    fn is_limit_reached(&self, count: usize) -> bool {
+        self.limit.map(|limit| count >= limit).unwrap_or(false)
+    }
+
+    fn has_context(&self) -> bool {
+        self.context_before > 0 || self.context_after > 0
+    }
+
+    pub fn search(&self, query: &Regex) -> QueryingResult<Vec<&'a NoteMetadata>> {
+        self.search_to(query, &mut stdout(), stdout().is_terminal())
+    }
+
+    /// Searches line by line, printing the matches to the given output (with colors if is_terminal).
+    pub fn search_to(&self, query: &Regex, out: &mut dyn Write, is_terminal: bool) -> QueryingResult<Vec<&'a NoteMetadata>> {
+        let mut matches = Vec::new();
+        let mut state = SearchLinesState::default();
+        for note_metadata in self.note_metadata_storage.notes() {
+            if self.is_limit_reached(matches.len()) {
+                break;
+            }
+
+            let is_first_note = matches.is_empty();
+            let mut printed_header = false;
+            let num_matches = self.search_lines(
+                out,
+                query,
+                self.note_metadata_storage.get_content_lines(&note_metadata.path)?,
+                is_terminal,
+                &mut state,
+                |out, is_terminal, is_match| {
+                    let info_text = note_metadata.info_text();
+                    if self.grouped {
+                        // Like ripgrep: a header line for each note with the matching lines indented below
+                        if !printed_header {
+                            if !is_first_note {
+                                writeln!(out)?;
+                            }
+
+                            if is_terminal {
+                                out
+                                    .execute(SetForegroundColor(Color::DarkMagenta))?
+                                    .execute(Print(format!("{}\n", info_text)))?
+                                    .execute(ResetColor)?;
+                            } else {
+                                writeln!(out, "{}", info_text)?;
+                            }
+
+                            printed_header = true;
+                        }
+
+                        write!(out, "  ")?;
+                    } else {
+                        let separator = if is_match { ':' } else { '-' };
+                        if is_terminal {
+                            out
+                                .execute(SetForegroundColor(Color::DarkMagenta))?
+                                .execute(Print(format!("{}{} ", info_text, separator)))?
+                                .execute(ResetColor)?;
+                        } else {
+                            write!(out, "{}{} ", info_text, separator)?;
+                        }
+                    }
+
+                    Ok(())
+                }
+            )?;
+
+            for _ in 0..num_matches {
+                matches.push(note_metadata);
+            }
+        }
+
+        Ok(matches)
+    }
+
+    /// Counts the matching lines of each note (only whether there is a match if only_first), sorted by path.
+    /// Notes without matches are not included.
+    pub fn search_counts(&self, query: &Regex, only_first: bool) -> QueryingResult<Vec<(&'a NoteMetadata, usize)>> {
+        let mut results = Vec::new();
+        for note_metadata in self.note_metadata_storage.notes() {
+            let mut count = 0;
+            for line in self.note_metadata_storage.get_content_lines(&note_metadata.path)? {
+                if query.is_match(&line?) {
+                    count += 1;
+                    if only_first {
+                        break;
+                    }
+                }
+            }
+
+            if count > 0 {
+                results.push((note_metadata, count));
+            }
+        }
+
+        results.sort_by_key(|(note_metadata, _)| &note_metadata.path);
+        Ok(results)
+    }
+
+    /// Searches line by line, returning each match instead of printing it. The limit applies to the matching lines.
+    pub fn search_matches(&self, query: &Regex) -> QueryingResult<Vec<SearchMatch<'a>>> {
+        let mut matches = Vec::new();
+        self.for_each_matching_line(query, |note_metadata, line_number, line| {
+            for current_match in query.find_iter(&line) {
+                matches.push(SearchMatch {
+                    note_metadata,
+                    line_number,
+                    line: line.clone(),
+                    range: current_match.range()
+                });
+            }
+        })?;
+
+        Ok(matches)
+    }
+
+    /// Calls on_match with the note, line number and content of each matching line, until the limit is reached.
+    fn for_each_matching_line<F: FnMut(&'a NoteMetadata, usize, String)>(&self, query: &Regex, mut on_match: F) -> QueryingResult<()> {
+        let mut num_matches = 0;
+        for note_metadata in self.note_metadata_storage.notes() {
+            for (line_index, line) in self.note_metadata_storage.get_content_lines(&note_metadata.path)?.enumerate() {
+                if self.is_limit_reached(num_matches) {
+                    return Ok(());
+                }
+
+                let line = line?;
+                if query.is_match(&line) {
+                    on_match(note_metadata, line_index + 1, line);
+                    num_matches += 1;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn search_resources(&self, query: &Regex, resources_dir: &Path) -> QueryingResult<Vec<PathBuf>> {
+        self.search_resources_to(query, resources_dir, &mut stdout(), stdout().is_terminal())
+    }
+
+    /// Searches the resources line by line, where files that look binary are skipped. Matches are prefixed with 'resource:' and the relative path.
+    pub fn search_resources_to(&self,
+                               query: &Regex,
+                               resources_dir: &Path,
+                               out: &mut dyn Write,
+                               is_terminal: bool) -> QueryingResult<Vec<PathBuf>> {
+        let mut matches = Vec::new();
+        let mut state = SearchLinesState::default();
+        for resource in find_resources(resources_dir, None, ResourceSort::Name)? {
+            let content = match read_text_file(&resource.path)? {
+                Some(content) => content,
+                None => continue
+            };
+
+            if self.is_limit_reached(matches.len()) {
+                break;
+            }
+
+            let num_matches = self.search_lines(
+                out,
+                query,
+                content.lines().map(|line| Ok(line.to_owned())),
+                is_terminal,
+                &mut state,
+                |out, is_terminal, is_match| {
+                    let info_text = format!("resource:{}", resource.relative_path.to_str().unwrap());
+                    let separator = if is_match { ':' } else { '-' };
+                    if is_terminal {
+                        out
+                            .execute(SetForegroundColor(Color::DarkMagenta))?
+                            .execute(Print(format!("{}{} ", info_text, separator)))?
+                            .execute(ResetColor)?;
+                    } else {
+                        write!(out, "{}{} ", info_text, separator)?;
+                    }
+
+                    Ok(())
+                }
+            )?;
+
+            for _ in 0..num_matches {
+                matches.push(resource.relative_path.clone());
+            }
+        }
+
+        Ok(matches)
+    }
+
+    /// Searches the whole content of each note instead of line by line, which allows matches to span lines.
+    pub fn search_multiline(&self, query: &Regex) -> QueryingResult<Vec<&'a NoteMetadata>> {
+        let is_terminal = stdout().is_terminal();
+
+        let mut matches = Vec::new();
+        let mut printed_regions = 0;
+        for note_metadata in self.note_metadata_storage.notes() {
+            if self.is_limit_reached(printed_regions) {
+                break;
+            }
+
+            let (content, _) = self.note_metadata_storage.get_content_lossy(&note_metadata.path)?;
+
+            // The lines that contain a match, where overlapping lines are merged into the same region
+            let mut regions: Vec<(Range<usize>, Vec<Range<usize>>)> = Vec::new();
+            for current_match in query.find_iter(&content) {
+                let start = content[..current_match.start()].rfind('\n').map(|index| index + 1).unwrap_or(0);
+                let end = content[current_match.end()..].find('\n').map(|index| index + current_match.end()).unwrap_or(content.len());
+
+                match regions.last_mut() {
+                    Some((region, region_matches)) if start < region.end => {
+                        region.end = region.end.max(end);
+                        region_matches.push(current_match.range());
+                    }
+                    _ => {
+                        regions.push((start..end, vec![current_match.range()]));
+                    }
+                }
+            }
+
+            for (region, region_matches) in regions {
+                if self.is_limit_reached(printed_regions) {
+                    break;
+                }
+
+                printed_regions += 1;
+                self.print_matches(
+                    &mut stdout(),
+                    &content[region.clone()],
+                    region_matches.into_iter().map(|range| (range.start - region.start)..(range.end - region.start)),
+                    is_terminal,
+                    |out, is_terminal| {
+                        let info_text = note_metadata.info_text();
+                        if is_terminal {
+                            out
+                                .execute(SetForegroundColor(Color::DarkMagenta))?
+                                .execute(Print(format!("{}: ", info_text)))?
+                                .execute(ResetColor)?;
+                        } else {
+                            write!(out, "{}: ", info_text)?;
+                        }
+
+                        Ok(())
+                    }
+                )?;
+            }
+
+            if query.is_match(&content) {
+                matches.push(note_metadata);
+            }
+        }
+
+        Ok(matches)
+    }
+
+    /// Replaces the matches of the query on each line with the replacement (supporting $1 style references).
+    /// Only the notes where the content changed are returned.
+    pub fn replace(&self, query: &Regex, replacement: &str) -> QueryingResult<Vec<ReplaceResult<'a>>> {
+        self.replace_in(self.note_metadata_storage.notes(), query, replacement)
+    }
+
+    /// Like replace, but only for the given notes.
+    pub fn replace_in(
+        &self,
+        notes: impl Iterator<Item=&'a NoteMetadata>,
+        query: &Regex,
+        replacement: &str
+    ) -> QueryingResult<Vec<ReplaceResult<'a>>> {
+        let mut results = Vec::new();
+        for note_metadata in notes {
+            let content = self.note_metadata_storage.get_content(&note_metadata.path)?;
+
+            let mut new_content = String::new();
+            let mut changed_lines = Vec::new();
+            for line in content.split_inclusive('\n') {
+                let (line, line_ending) = match line.strip_suffix('\n') {
+                    Some(line) => (line, "\n"),
+                    None => (line, "")
+                };
+
+                let new_line = query.replace_all(line, replacement);
+                if new_line != line {
+                    changed_lines.push((line.to_owned(), new_line.to_string()));
+                }
+
+                new_content += &new_line;
+                new_content += line_ending;
+            }
+
+            if !changed_lines.is_empty() {
+                results.push(ReplaceResult { note_metadata, content: new_content, changed_lines });
+            }
+        }
+
+        results.sort_by_key(|result| &result.note_metadata.path);
+        Ok(results)
+    }
+
+    pub fn search_historic(
+        &self,
+        repository: &git2::Repository,
+        query: &Regex,
+        git_start: &str, git_end: Option<&str>
+    ) -> QueryingResult<Vec<(git2::Oid, NoteMetadata)>> {
+        self.search_historic_to(repository, query, git_start, git_end, &mut stdout(), stdout().is_terminal())
+    }
+
+    /// Searches the notes of each commit between the given references, printing the matches to the given output (with colors if is_terminal).
+    pub fn search_historic_to(
+        &self,
+        repository: &git2::Repository,
+        query: &Regex,
+        git_start: &str, git_end: Option<&str>,
+        out: &mut dyn Write,
+        is_terminal: bool
+    ) -> QueryingResult<Vec<(git2::Oid, NoteMetadata)>> {
+
+        let mut rev_walk = repository.revwalk()?;
+        rev_walk.push(repository.revparse_single(git_start)?.id())?;
+
+        if let Some(git_end) = git_end {
+            rev_walk.hide(repository.revparse_single(git_end)?.id())?;
+        }
+
+        let mut matches = Vec::new();
+        let mut state = SearchLinesState::default();
+        for commit_id in rev_walk {
+            let commit_id = commit_id?;
+            let commit = repository.find_commit(commit_id)?;
+            let tree = commit.tree()?;
+
+            for historic_note in historic_notes(repository, &tree)? {
+                let note_metadata = historic_note.metadata;
+                let content = historic_note.content;
+
+                if self.is_limit_reached(matches.len()) {
+                    return Ok(matches);
+                }
+
+                let num_matches = self.search_lines(
+                    out,
+                    query,
+                    content.lines().map(|line| Ok(line.to_owned())),
+                    is_terminal,
+                    &mut state,
+                    |out, is_terminal, is_match| {
+                        let info_text = note_metadata.info_text();
+                        let short_commit_id = commit.as_object().short_id()?.as_str().unwrap().to_owned();
+                        let separator = if is_match { ':' } else { '-' };
+
+                        if is_terminal {
+                            out
+                                .execute(SetForegroundColor(Color::Yellow))?
+                                .execute(Print(&short_commit_id))?
+                                .execute(ResetColor)?
+
+                                .execute(Print(" - "))?
+
+                                .execute(SetForegroundColor(Color::DarkMagenta))?
+                                .execute(Print(format!("{}{} ", info_text, separator)))?
+                                .execute(ResetColor)?;
+                        } else {
+                            write!(out, "{} - {}{} ", short_commit_id, info_text, separator)?;
+                        }
+
+                        Ok(())
+                    }
+                )?;
+
+                for _ in 0..num_matches {
+                    matches.push((commit_id, note_metadata.clone()));
+                }
+            }
+        }
+
+        Ok(matches)
+    }
+
+    /// Prints the lines of a note (or resource) that match the query together with the context lines around them, where hunks
+    /// that are not adjacent are separated by '--'. The prefix of each printed line is told if the line is a match.
+    /// Returns the number of matching lines, which is also added to the state.
+    fn search_lines<FnPrefix: FnMut(&mut dyn Write, bool, bool) -> QueryingResult<()>>(
+        &self,
+        out: &mut dyn Write,
+        query: &Regex,
+        lines: impl Iterator<Item=std::io::Result<String>>,
+        is_terminal: bool,
+        state: &mut SearchLinesState,
+        mut print_prefix: FnPrefix
+    ) -> QueryingResult<usize> {
+        let mut num_matches = 0;
+        let mut before_lines = VecDeque::new();
+        let mut remaining_after = 0;
+        let mut last_printed: Option<usize> = None;
+
+        for (line_index, line) in lines.enumerate() {
+            if self.is_limit_reached(state.num_matches + num_matches) {
+                break;
+            }
+
+            let line = line?;
+            if query.is_match(&line) {
+                let first_index = before_lines.front().map(|(index, _)| *index).unwrap_or(line_index);
+                let new_hunk = match last_printed {
+                    Some(last_printed) => first_index > last_printed + 1,
+                    // In grouped mode, the notes are already separated by their headers
+                    None => !self.grouped
+                };
+
+                if self.has_context() && state.printed_any && new_hunk {
+                    writeln!(out, "--")?;
+                }
+
+                for (_, before_line) in before_lines.drain(..) {
+                    print_prefix(out, is_terminal, false)?;
+                    writeln!(out, "{}", before_line)?;
+                }
+
+                self.find_matches(out, query, &line, is_terminal, |out, is_terminal| print_prefix(out, is_terminal, true))?;
+
+                num_matches += 1;
+                remaining_after = self.context_after;
+                last_printed = Some(line_index);
+                state.printed_any = true;
+            } else if remaining_after > 0 {
+                print_prefix(out, is_terminal, false)?;
+                writeln!(out, "{}", line)?;
+
+                remaining_after -= 1;
+                last_printed = Some(line_index);
+            } else if self.context_before > 0 {
+                if before_lines.len() == self.context_before {
+                    before_lines.pop_front();
+                }
+
+                before_lines.push_back((line_index, line));
+            }
+        }
+
+        state.num_matches += num_matches;
+        Ok(num_matches)
+    }
+
+    fn find_matches<FnFirst: FnMut(&mut dyn Write, bool) -> QueryingResult<()>>(
+        &self,
+        out: &mut dyn Write,
+        query: &Regex, line: &str,
+        is_terminal: bool,
+        before_first: FnFirst,
+    ) -> QueryingResult<()> {
+        self.print_matches(
+            out,
+            line,
+            query.find_iter(&line).map(|current_match| current_match.range()),
+            is_terminal,
+            before_first
+        )
+    }
+
+    fn print_matches<FnFirst: FnMut(&mut dyn Write, bool) -> QueryingResult<()>>(
+        &self,
+        out: &mut dyn Write,
+        line: &str,
+        matches: impl Iterator<Item=Range<usize>>,
+        is_terminal: bool,
+        mut before_first: FnFirst,
+    ) -> QueryingResult<()> {
+        let mut remaining_line_start = 0;
+        let mut found_match = false;
+        for current_match in matches {
+            if !found_match {
+                before_first(out, is_terminal)?;
+                found_match = true;
+            }
+
+            let before = &line[remaining_line_start..current_match.start];
+            let during = &line[current_match.clone()];
+            remaining_line_start = current_match.end;
+
+            if is_terminal {
+                out
+                    .execute(Print(before))?
+
+                    .execute(SetAttribute(Bold))?
+                    .execute(SetForegroundColor(Color::Red))?
+                    .execute(Print(during))?
+                    .execute(ResetColor)?;
+            } else {
+                write!(out, "{}{}", before, during)?;
+            }
+        }
+
+        if found_match {
+            if is_terminal {
+                out
+                    .execute(Print(&line[remaining_line_start..]))?
+                    .execute(Print("\n"))?;
+            } else {
+                writeln!(out, "{}", &line[remaining_line_start..])?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub struct SearchMatch<'a> {
+    pub note_metadata: &'a NoteMetadata,
+    /// Starts at 1
+    pub line_number: usize,
+    pub line: String,
+    /// The byte range of the match within the line
+    pub range: Range<usize>
+}
+
+/// Creates a JSON array with an object for each match.
+pub fn search_matches_json(matches: &Vec<SearchMatch>) -> String {
+    #[derive(Serialize)]
+    struct Entry<'a> {
+        path: &'a Path,
+        id: String,
+        line_number: usize,
+        line: &'a str,
+        match_start: usize,
+        match_end: usize
+    }
+
+    let entries = matches
+        .iter()
+        .map(|current_match| {
+            Entry {
+                path: &current_match.note_metadata.path,
+                id: current_match.note_metadata.id.to_string(),
+                line_number: current_match.line_number,
+                line: &current_match.line,
+                match_start: current_match.range.start,
+                match_end: current_match.range.end
+            }
+        })
+        .collect::<Vec<_>>();
+
+    serde_json::to_string_pretty(&entries).unwrap()
+}
+
+pub struct ReplaceResult<'a> {
+    pub note_metadata: &'a NoteMetadata,
+    pub content: String,
+    /// The (old, new) content of the changed lines
+    pub changed_lines: Vec<(String, String)>
+}
+
+/// Prints the path of each note, followed by the number of matching lines if with_count.
+pub fn print_search_counts(results: &Vec<(&NoteMetadata, usize)>, with_count: bool) {
+    for (note_metadata, count) in results {
+        if with_count {
+            println!("{}: {}", note_metadata.path.to_str().unwrap(), count);
+        } else {
+            println!("{}", note_metadata.path.to_str().unwrap());
+        }
+    }
+}
+
+pub fn print_replace_results(results: &Vec<ReplaceResult>) {
+    if results.is_empty() {
+        println!("No matches found.");
+        return;
+    }
+
+    for result in results {
+        println!("{}:", result.note_metadata.info_text());
+        for (old_line, new_line) in &result.changed_lines {
+            println!("\t- {}", old_line);
+            println!("\t+ {}", new_line);
+        }
+    }
+}
+
+pub struct ListDirectoryEntry<'a> {
+    pub name: String,
+    pub last_updated: Option<DateTime<Local>>,
+    pub note_metadata: Option<&'a NoteMetadata>,
+    pub size: Option<u64>
+}
+
+pub struct ListDirectory<'a> {
+    note_metadata_storage: &'a NoteMetadataStorage,
+    root: NoteFileTree<'a>
+}
+
+impl<'a> ListDirectory<'a> {
+    pub fn new(note_metadata_storage: &'a NoteMetadataStorage) -> QueryingResult<ListDirectory<'a>> {
+        Ok(
+            ListDirectory {
+                note_metadata_storage,
+                root: NoteFileTree::from_iter(note_metadata_storage.notes())
+                    .ok_or_else(|| QueryingError::FailedToCreateNoteFileTree)?
+            }
+        )
+    }
+
+    pub fn list(&'a self, query: &Path, with_size: bool) -> QueryingResult<Vec<ListDirectoryEntry<'a>>> {
+        let mut results = Vec::new();
+
+        let found_tree = if query == Path::new("") {
+            Some(&self.root)
+        } else {
+            self.root.find(query)
+        };
+
+        if let Some(found_tree) = found_tree {
+            if found_tree.is_leaf() {
+                return Err(QueryingError::TreeNotDirectory);
+            }
+
+            let mut trees = Vec::new();
+            found_tree.walk(|level, _, name, tree, _| {
+                if level != 0 {
+                    return false;
+                }
+
+                results.push(
+                    match tree {
+                        NoteFileTree::Note(metadata) => {
+                            ListDirectoryEntry::<'a> {
+                                name: name.to_str().unwrap().to_owned(),
+                                last_updated: Some(metadata.last_updated),
+                                note_metadata: Some(*metadata),
+                                size: None
+                            }
+                        }
+                        NoteFileTree::Tree { last_updated, .. } => {
+                            ListDirectoryEntry::<'a> {
+                                name: name.to_str().unwrap().to_owned(),
+                                last_updated: *last_updated,
+                                note_metadata: None,
+                                size: None
+                            }
+                        }
+                    }
+                );
+                trees.push(tree);
+
+                true
+            });
+
+            if with_size {
+                for (entry, tree) in results.iter_mut().zip(trees.into_iter()) {
+                    entry.size = Some(tree.size(self.note_metadata_storage)?);
+                }
+            }
+        } else {
+            return Err(QueryingError::NoteNotFound(query.to_str().unwrap_or("").to_owned()));
+        }
+
+        Ok(results)
+    }
+}
+
+pub fn print_list_directory_results(results: &Vec<ListDirectoryEntry>, date_format: DateFormat) -> QueryingResult<()> {
+    let is_terminal = stdout().is_terminal();
+
+    for entry in results {
+        let last_updated = entry.last_updated.unwrap();
+
+        let last_updated = if date_format == DateFormat::Default {
+            format!(
+                "{}-{:0>2}-{:0>2} {:0>2}:{:0>2}",
+                last_updated.year(),
+                last_updated.month(),
+                last_updated.day(),
+                last_updated.hour(),
+                last_updated.minute()
+            )
+        } else {
+            format_datetime(&last_updated, date_format)
+        };
+
+        let mut date_part = format!(
+            "{}\t{}\t",
+            last_updated,
+            entry.note_metadata.map(|_| "note").unwrap_or("dir"),
+        );
+
+        if let Some(size) = entry.size {
+            date_part += &format!("{}\t", human_readable_size(size));
+        }
+
+        let name_part = format!(
+            "{}{}",
+            entry.name,
+            entry.note_metadata.map(|metadata| format!(" (id: {})", metadata.id)).unwrap_or_else(|| String::new())
+        );
+
+        if is_terminal {
+            stdout()
+                .execute(Print(date_part))?
+                .execute(SetForegroundColor(if entry.note_metadata.is_some() { Color::Green } else { Color::Blue }))?
+                .execute(Print(name_part))?
+                .execute(ResetColor)?
+                .execute(Print("\n"))?;
+        } else {
+            println!("{}{}", date_part, name_part);
+        }
+    }
 
-        if let Some(found_tree) = found_tree {
-            if found_tree.is_leaf() {
-                return Err(QueryingError::TreeNotDirectory);
+    Ok(())
+}
+
+/// Creates a JSON array of the results (dates are formatted using DATETIME_FORMAT).
+pub fn list_directory_results_json(results: &Vec<ListDirectoryEntry>) -> String {
+    #[derive(Serialize)]
+    struct Entry<'a> {
+        name: &'a str,
+        #[serde(rename="type")]
+        entry_type: &'static str,
+        last_updated: Option<String>
+    }
+
+    let entries = results
+        .iter()
+        .map(|entry| {
+            Entry {
+                name: &entry.name,
+                entry_type: entry.note_metadata.map(|_| "note").unwrap_or("dir"),
+                last_updated: entry.last_updated.map(|last_updated| last_updated.format(DATETIME_FORMAT).to_string())
             }
+        })
+        .collect::<Vec<_>>();
 
-            found_tree.walk(|level, _, name, tree, _| {
-                if level != 0 {
-                    return false;
-                }
+    serde_json::to_string_pretty(&entries).unwrap()
+}
 
-                results.push(
-                    match tree {
-                        NoteFileTree::Note(metadata) => {
-                            ListDirectoryEntry::<'a> {
-                                name: name.to_str().unwrap().to_owned(),
-                                last_updated: Some(metadata.last_updated),
-                                note_metadata: Some(*metadata)
-                            }
-                        }
-                        NoteFileTree::Tree { last_updated, .. } => {
-                            ListDirectoryEntry::<'a> {
-                                name: name.to_str().unwrap().to_owned(),
-                                last_updated: *last_updated,
-                                note_metadata: None
-                            }
+pub struct ListTree<'a> {
+    note_metadata_storage: &'a NoteMetadataStorage,
+    root: NoteFileTree<'a>
+}
+
+impl<'a> ListTree<'a> {
+    pub fn new(
+        note_metadata_storage: &'a NoteMetadataStorage,
+        config: NoteFileTreeCreateConfig
+    ) -> QueryingResult<ListTree<'a>> {
+        Ok(
+            ListTree {
+                note_metadata_storage,
+                root: NoteFileTree::from_iter_with_config(
+                    note_metadata_storage.notes(),
+                    config
+                ).ok_or_else(|| QueryingError::FailedToCreateNoteFileTree)?
+            }
+        )
+    }
+
+    pub fn list(&self, prefix: &Path, filter: ListTreeFilter, with_size: bool) -> QueryingResult<()> {
+        let note_metadata_storage = if with_size { Some(self.note_metadata_storage) } else { None };
+
+        if prefix == Path::new("") {
+            ListTree::print_tree(&self.root, ".", filter, note_metadata_storage)?;
+        } else {
+            if let Some(tree) = self.root.find(prefix) {
+                ListTree::print_tree(&tree, prefix.to_str().unwrap(), filter, note_metadata_storage)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn print_tree(
+        tree: &NoteFileTree,
+        dir: &str,
+        filter: ListTreeFilter,
+        size_storage: Option<&NoteMetadataStorage>
+    ) -> QueryingResult<()> {
+        print_tree_lines(dir, ListTree::create_lines(tree, filter, size_storage)?);
+        Ok(())
+    }
+
+    /// Creates the lines of the tree. If a storage is given, the size of each entry is also included.
+    pub fn create_lines(
+        tree: &NoteFileTree,
+        filter: ListTreeFilter,
+        size_storage: Option<&NoteMetadataStorage>
+    ) -> std::io::Result<Vec<ListTreeLine>> {
+        fn note_content(name: &str, note_metadata: &NoteMetadata, size: Option<u64>) -> String {
+            let date_part = format!(
+                "{}-{:0>2}-{:0>2} {:0>2}:{:0>2}",
+                note_metadata.last_updated.year(),
+                note_metadata.last_updated.month(),
+                note_metadata.last_updated.day(),
+                note_metadata.last_updated.hour(),
+                note_metadata.last_updated.minute()
+            );
+
+            if let Some(size) = size {
+                format!("{} (id: {}, updated: {}, size: {})", name, note_metadata.id, date_part, human_readable_size(size))
+            } else {
+                format!("{} (id: {}, updated: {})", name, note_metadata.id, date_part)
+            }
+        }
+
+        fn get_size(tree: &NoteFileTree, size_storage: Option<&NoteMetadataStorage>) -> std::io::Result<Option<u64>> {
+            size_storage.map(|size_storage| tree.size(size_storage)).transpose()
+        }
+
+        fn do_create_lines(
+            tree: &NoteFileTree,
+            filter: ListTreeFilter,
+            size_storage: Option<&NoteMetadataStorage>,
+            prefix: &str,
+            lines: &mut Vec<ListTreeLine>
+        ) -> std::io::Result<()> {
+            let children = tree.children()
+                .map(|children| {
+                    children
+                        .iter()
+                        .filter(|(_, child)| filter != ListTreeFilter::OnlyDirs || child.is_tree())
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_else(|| Vec::new());
+
+            let num_children = children.len();
+            for (child_index, (name, child)) in children.into_iter().enumerate() {
+                let is_last = child_index == num_children - 1;
+                let size = get_size(child, size_storage)?;
+                let (content, color) = match child {
+                    NoteFileTree::Note(note_metadata) => {
+                        (note_content(name.to_str().unwrap(), note_metadata, size), Color::Green)
+                    }
+                    NoteFileTree::Tree { .. } => {
+                        if let Some(size) = size {
+                            (format!("{} (size: {})", name.to_str().unwrap(), human_readable_size(size)), Color::Blue)
+                        } else {
+                            (name.to_str().unwrap().to_owned(), Color::Blue)
                         }
                     }
-                );
+                };
+
+                lines.push(ListTreeLine {
+                    prefix: format!("{}{}── ", prefix, if is_last {"└"} else {"├"}),
+                    content,
+                    color
+                });
+
+                if child.is_tree() {
+                    let child_prefix = format!("{}{}", prefix, if is_last {"    "} else {"│   "});
+                    do_create_lines(child, filter, size_storage, &child_prefix, lines)?;
+                }
+            }
+
+            Ok(())
+        }
+
+        let mut lines = Vec::new();
+        if filter == ListTreeFilter::OnlyFiles {
+            // Directories are still traversed, but only the paths of the notes (relative to the listed directory) are shown
+            let mut notes = Vec::new();
+            tree.walk(|_, parent, name, tree, _| {
+                if let NoteFileTree::Note(note_metadata) = tree {
+                    notes.push((parent.join(name), *note_metadata, tree));
+                }
 
                 true
             });
+
+            for (path, note_metadata, tree) in notes {
+                lines.push(ListTreeLine {
+                    prefix: String::new(),
+                    content: note_content(path.to_str().unwrap(), note_metadata, get_size(tree, size_storage)?),
+                    color: Color::Green
+                });
+            }
         } else {
-            return Err(QueryingError::NoteNotFound(query.to_str().unwrap_or("").to_owned()));
+            do_create_lines(tree, filter, size_storage, "", &mut lines)?;
         }
 
-        Ok(results)
+        Ok(lines)
     }
 }
 
-pub fn print_list_directory_results(results: &Vec<ListDirectoryEntry>) -> QueryingResult<()> {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListTreeFilter {
+    All,
+    OnlyFiles,
+    OnlyDirs
+}
+
+pub struct ListTreeLine {
+    pub prefix: String,
+    pub content: String,
+    pub color: Color
+}
+
+/// Prints the lines of a tree below the given root directory.
+pub fn print_tree_lines(dir: &str, lines: Vec<ListTreeLine>) {
     let is_terminal = stdout().is_terminal();
 
-    for entry in results {
-        let last_updated = entry.last_updated.unwrap();
+    if !dir.is_empty() {
+        if is_terminal {
+            stdout()
+                .execute(SetForegroundColor(Color::Blue)).unwrap()
+                .execute(Print(dir)).unwrap()
+                .execute(ResetColor).unwrap()
+                .execute(Print("\n")).unwrap();
+        } else {
+            println!("{}", dir);
+        }
+    }
+
+    for line in lines {
+        if is_terminal {
+            stdout()
+                .execute(Print(line.prefix)).unwrap()
+                .execute(SetForegroundColor(line.color)).unwrap()
+                .execute(Print(line.content)).unwrap()
+                .execute(ResetColor).unwrap()
+                .execute(Print("\n")).unwrap();
+        } else {
+            println!("{}{}", line.prefix, line.content);
+        }
+    }
+}
+
+pub struct ResourceEntry {
+    pub path: PathBuf,
+    pub relative_path: PathBuf,
+    pub size: u64
+}
+
+impl ResourceEntry {
+    /// Guesses the MIME type based on the extension.
+    pub fn resource_type(&self) -> &'static str {
+        let extension = self.path.extension().and_then(|extension| extension.to_str()).unwrap_or("").to_lowercase();
+        match extension.as_str() {
+            "png" => "image/png",
+            "jpg" | "jpeg" => "image/jpeg",
+            "gif" => "image/gif",
+            "svg" => "image/svg+xml",
+            "webp" => "image/webp",
+            "pdf" => "application/pdf",
+            "json" => "application/json",
+            "zip" => "application/zip",
+            "txt" => "text/plain",
+            "md" => "text/markdown",
+            "csv" => "text/csv",
+            "html" | "htm" => "text/html",
+            _ => "application/octet-stream"
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ResourceSort {
+    Name,
+    Size
+}
+
+impl FromStr for ResourceSort {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "name" => Ok(ResourceSort::Name),
+            "size" => Ok(ResourceSort::Size),
+            _ => Err(format!("Invalid sort key '{}', expected name or size", text))
+        }
+    }
+}
+
+pub fn find_resources(base_dir: &Path, query: Option<PathBuf>, sort: ResourceSort) -> QueryingResult<Vec<ResourceEntry>> {
+    let mut results = Vec::new();
+    if !base_dir.exists() {
+        return Ok(results);
+    }
+
+    let mut initial_search_dir = base_dir.to_owned();
+    if let Some(query) = query {
+        initial_search_dir = base_dir.join(query);
+    }
+
+    let mut stack = vec![initial_search_dir];
+    while let Some(top) = stack.pop() {
+        for entry in std::fs::read_dir(&top)? {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(err) => {
+                    eprintln!("Warning: skipping unreadable entry in '{}': {}", top.to_str().unwrap(), err);
+                    continue;
+                }
+            };
+
+            let path = entry.path();
+            // The file type does not follow symbolic links, so linked directories (which might create cycles) are skipped
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                stack.push(path);
+            } else if path.is_file() {
+                results.push(ResourceEntry {
+                    relative_path: path.strip_prefix(&base_dir).unwrap().to_owned(),
+                    size: std::fs::metadata(&path)?.len(),
+                    path
+                });
+            }
+        }
+    }
+
+    match sort {
+        ResourceSort::Name => results.sort_by(|a, b| a.relative_path.cmp(&b.relative_path)),
+        ResourceSort::Size => results.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.relative_path.cmp(&b.relative_path)))
+    }
+
+    Ok(results)
+}
+
+/// Finds the resources whose relative path either matches the given glob pattern or is within the given prefix.
+pub fn find_matching_resources(base_dir: &Path, pattern: Option<&str>) -> QueryingResult<Vec<ResourceEntry>> {
+    let results = find_resources(base_dir, None, ResourceSort::Name)?;
+
+    let pattern = match pattern {
+        Some(pattern) => pattern,
+        None => { return Ok(results); }
+    };
+
+    let glob = globset::Glob::new(pattern).ok().map(|glob| glob.compile_matcher());
+    Ok(
+        results
+            .into_iter()
+            .filter(|entry| {
+                entry.relative_path.starts_with(pattern)
+                || glob.as_ref().map(|glob| glob.is_match(&entry.relative_path)).unwrap_or(false)
+            })
+            .collect()
+    )
+}
+
+enum ResourceTree {
+    File(u64),
+    Dir(BTreeMap<String, ResourceTree>)
+}
+
+/// Creates the lines of a tree of the given resources, with paths relative to the given prefix.
+pub fn create_resource_tree_lines(entries: &Vec<ResourceEntry>, prefix: &Path) -> Vec<ListTreeLine> {
+    fn do_create_lines(children: &BTreeMap<String, ResourceTree>, prefix: &str, lines: &mut Vec<ListTreeLine>) {
+        let num_children = children.len();
+        for (child_index, (name, child)) in children.iter().enumerate() {
+            let is_last = child_index == num_children - 1;
+            let line_prefix = format!("{}{}── ", prefix, if is_last {"└"} else {"├"});
+
+            match child {
+                ResourceTree::File(size) => {
+                    lines.push(ListTreeLine {
+                        prefix: line_prefix,
+                        content: format!("{} (size: {})", name, human_readable_size(*size)),
+                        color: Color::Green
+                    });
+                }
+                ResourceTree::Dir(children) => {
+                    lines.push(ListTreeLine {
+                        prefix: line_prefix,
+                        content: name.clone(),
+                        color: Color::Blue
+                    });
+
+                    let child_prefix = format!("{}{}", prefix, if is_last {"    "} else {"│   "});
+                    do_create_lines(children, &child_prefix, lines);
+                }
+            }
+        }
+    }
+
+    let mut root = BTreeMap::new();
+    for entry in entries {
+        let relative_path = entry.relative_path.strip_prefix(prefix).unwrap_or(&entry.relative_path);
+        let parts = relative_path.iter().map(|part| part.to_str().unwrap().to_owned()).collect::<Vec<_>>();
+
+        let mut children = &mut root;
+        for (part_index, part) in parts.iter().enumerate() {
+            if part_index == parts.len() - 1 {
+                children.insert(part.clone(), ResourceTree::File(entry.size));
+            } else {
+                let child = children.entry(part.clone()).or_insert_with(|| ResourceTree::Dir(BTreeMap::new()));
+                children = match child {
+                    ResourceTree::Dir(children) => children,
+                    ResourceTree::File(_) => break
+                };
+            }
+        }
+    }
+
+    let mut lines = Vec::new();
+    do_create_lines(&root, "", &mut lines);
+    lines
+}
+
+pub fn list_resources_tree(base_dir: &Path, prefix: Option<PathBuf>) -> QueryingResult<()> {
+    let prefix = prefix.unwrap_or_else(|| PathBuf::new());
+    let entries = find_resources(base_dir, Some(prefix.clone()), ResourceSort::Name)?;
+    let dir = if prefix == Path::new("") { "." } else { prefix.to_str().unwrap() };
+    print_tree_lines(dir, create_resource_tree_lines(&entries, &prefix));
+    Ok(())
+}
+
+pub fn list_resources(
+    base_dir: &Path,
+    query: Option<PathBuf>,
+    print_absolute: bool,
+    long: bool,
+    sort: ResourceSort
+) -> QueryingResult<()> {
+    println!("Resources:");
+
+    let results = find_resources(base_dir, query, sort)?;
+    let path_to_use = |entry: &ResourceEntry| {
+        let path = if print_absolute { &entry.path } else { &entry.relative_path };
+        path.to_str().unwrap().to_owned()
+    };
+
+    if long {
+        let mut table_printer = TablePrinter::new(vec![
+            "path".to_owned(),
+            "size".to_owned(),
+            "type".to_owned()
+        ]);
+
+        for entry in &results {
+            table_printer.add_row(vec![
+                path_to_use(entry),
+                human_readable_size(entry.size),
+                entry.resource_type().to_owned()
+            ]);
+        }
+
+        table_printer.print();
+    } else {
+        for entry in &results {
+            println!("{}", path_to_use(entry));
+        }
+    }
+
+    Ok(())
+}
+
+pub struct GitLogFilter {
+    pub author: Option<String>,
+    pub grep: Option<Regex>
+}
+
+impl GitLogFilter {
+    pub fn is_match(&self, commit: &git2::Commit) -> bool {
+        if let Some(author) = self.author.as_ref() {
+            let signature = commit.author();
+            let name_match = signature.name().map(|name| name.contains(author)).unwrap_or(false);
+            let email_match = signature.email().map(|email| email.contains(author)).unwrap_or(false);
+            if !name_match && !email_match {
+                return false;
+            }
+        }
+
+        if let Some(grep) = self.grep.as_ref() {
+            if !grep.is_match(commit.message().unwrap_or("")) {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// A commit that changed the content or metadata of a note.
+pub struct NoteHistoryEntry {
+    pub short_commit_hash: String,
+    pub time: DateTime<FixedOffset>,
+    pub subject: String
+}
+
+/// Returns the commits (reachable from HEAD) that changed the given note, newest first.
+pub fn note_history(repository: &git2::Repository, id: &NoteId) -> QueryingResult<Vec<NoteHistoryEntry>> {
+    let note_paths = [
+        PathBuf::from(format!("{}/{}.{}", NOTES_DIR, id, NOTE_CONTENT_EXT)),
+        PathBuf::from(format!("{}/{}.{}", NOTES_DIR, id, NOTE_METADATA_EXT))
+    ];
+
+    let entry_ids = |tree: &git2::Tree| -> Vec<Option<git2::Oid>> {
+        note_paths.iter().map(|path| tree.get_path(path).ok().map(|entry| entry.id())).collect()
+    };
+
+    let mut rev_walk = repository.revwalk()?;
+    rev_walk.push_head()?;
+    rev_walk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)?;
+
+    let mut entries = Vec::new();
+    for commit_id in rev_walk {
+        let commit = repository.find_commit(commit_id?)?;
+        let current_ids = entry_ids(&commit.tree()?);
+        let parent_ids = match commit.parent(0) {
+            Ok(parent) => entry_ids(&parent.tree()?),
+            Err(_) => vec![None; note_paths.len()]
+        };
+
+        if current_ids != parent_ids {
+            entries.push(NoteHistoryEntry {
+                short_commit_hash: commit.as_object().short_id()?.as_str().unwrap().to_owned(),
+                time: commit.time().to_date_time().unwrap(),
+                subject: commit.summary().unwrap_or("").to_owned()
+            });
+        }
+    }
+
+    Ok(entries)
+}
+
+pub fn print_note_history(entries: &[NoteHistoryEntry], date_format: DateFormat) {
+    for entry in entries {
+        println!("{} ({}): {}", entry.short_commit_hash, format_datetime(&entry.time, date_format), entry.subject);
+    }
+}
+
+pub struct GitLog<'a> {
+    repository: &'a git2::Repository,
+    count: isize,
+    filter: GitLogFilter
+}
+
+impl<'a> GitLog<'a> {
+    pub fn new(repository: &'a git2::Repository, count: isize, filter: GitLogFilter) -> QueryingResult<GitLog<'a>> {
+        Ok(
+            GitLog {
+                repository,
+                count,
+                filter
+            }
+        )
+    }
+
+    pub fn print(&self, date_format: DateFormat, graph: bool) -> QueryingResult<()> {
+        for line in self.lines(date_format, graph)? {
+            println!("{}", line);
+        }
+
+        Ok(())
+    }
+
+    pub fn lines(&self, date_format: DateFormat, graph: bool) -> QueryingResult<Vec<String>> {
+        let mut rev_walk = self.repository.revwalk()?;
+        rev_walk.push_head()?;
+        if graph {
+            rev_walk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)?;
+        }
+
+        let max_count = if self.count >= 0 { self.count as usize } else { usize::MAX };
+        let mut graph_columns = GraphColumns::new();
+        let mut lines = Vec::new();
+        let mut shown = 0;
+
+        for commit_id in rev_walk {
+            if shown >= max_count {
+                break;
+            }
+
+            let commit_id = commit_id?;
+            let commit = self.repository.find_commit(commit_id)?;
+            let show = self.filter.is_match(&commit);
+
+            let short_commit_hash = commit.as_object().short_id()?.as_str().unwrap().to_owned();
+            let commit_time = commit.time().to_date_time().unwrap();
+            let commit_line = format!(
+                "{} ({}): {}",
+                short_commit_hash,
+                format_datetime(&commit_time, date_format),
+                commit.message().unwrap_or("").trim().replace("\n", " ")
+            );
+
+            if graph {
+                let graph_lines = graph_columns.add(commit_id, commit.parent_ids().collect(), &commit_line);
+                if show {
+                    lines.extend(graph_lines);
+                }
+            } else if show {
+                lines.push(commit_line);
+            }
+
+            if show {
+                shown += 1;
+            }
+        }
+
+        Ok(lines)
+    }
+}
+
+/// Tracks the commits expected in each column of the graph. Commits must be added in topological order.
+struct GraphColumns {
+    columns: Vec<git2::Oid>
+}
+
+impl GraphColumns {
+    fn new() -> GraphColumns {
+        GraphColumns {
+            columns: Vec::new()
+        }
+    }
+
+    /// Adds the given commit, returning the commit row (ending with the given text) together with any edge rows.
+    fn add(&mut self, commit_id: git2::Oid, parent_ids: Vec<git2::Oid>, text: &str) -> Vec<String> {
+        let mut lines = Vec::new();
+
+        let matching = self.columns
+            .iter()
+            .enumerate()
+            .filter(|(_, column)| **column == commit_id)
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+
+        // Other columns waiting for this commit branched off from it
+        let branched = matching.iter().skip(1).cloned().collect::<Vec<_>>();
+        if !branched.is_empty() {
+            lines.push(self.render(|column| if branched.contains(&column) { "/" } else { "|" }));
+            for column in branched.iter().rev() {
+                self.columns.remove(*column);
+            }
+        }
+
+        let index = match matching.first() {
+            Some(index) => *index,
+            None => {
+                self.columns.push(commit_id);
+                self.columns.len() - 1
+            }
+        };
+
+        lines.push(format!("{} {}", self.render(|column| if column == index { "*" } else { "|" }), text));
 
-        let date_part = format!(
-            "{}-{:0>2}-{:0>2} {:0>2}:{:0>2}\t{}\t",
-            last_updated.year(),
-            last_updated.month(),
-            last_updated.day(),
-            last_updated.hour(),
-            last_updated.minute(),
-            entry.note_metadata.map(|_| "note").unwrap_or("dir"),
-        );
+        match parent_ids.split_first() {
+            Some((first_parent, merged_parents)) => {
+                self.columns[index] = *first_parent;
 
-        let name_part = format!(
-            "{}{}",
-            entry.name,
-            entry.note_metadata.map(|metadata| format!(" (id: {})", metadata.id)).unwrap_or_else(|| String::new())
-        );
+                let mut merged_columns = Vec::new();
+                for merged_parent in merged_parents {
+                    if !self.columns.contains(merged_parent) {
+                        let column = index + 1 + merged_columns.len();
+                        self.columns.insert(column, *merged_parent);
+                        merged_columns.push(column);
+                    }
+                }
 
-        if is_terminal {
-            stdout()
-                .execute(Print(date_part))?
-                .execute(SetForegroundColor(if entry.note_metadata.is_some() { Color::Green } else { Color::Blue }))?
-                .execute(Print(name_part))?
-                .execute(ResetColor)?
-                .execute(Print("\n"))?;
-        } else {
-            println!("{}{}", date_part, name_part);
+                if !merged_columns.is_empty() {
+                    lines.push(self.render(|column| if merged_columns.contains(&column) { "\\" } else { "|" }));
+                }
+            }
+            None => {
+                self.columns.remove(index);
+            }
         }
+
+        lines
     }
 
-    Ok(())
+    fn render<F: Fn(usize) -> &'static str>(&self, symbol: F) -> String {
+        (0..self.columns.len()).map(symbol).collect::<Vec<_>>().join(" ")
+    }
 }
 
-pub struct ListTree<'a> {
-    root: NoteFileTree<'a>
+/// A note as stored in a git tree.
+pub struct HistoricNote {
+    pub metadata: NoteMetadata,
+    pub content_id: git2::Oid,
+    pub content: String
 }
 
-impl<'a> ListTree<'a> {
-    pub fn new(
-        note_metadata_storage: &'a NoteMetadataStorage,
-        config: NoteFileTreeCreateConfig
-    ) -> QueryingResult<ListTree<'a>> {
-        Ok(
-            ListTree {
-                root: NoteFileTree::from_iter_with_config(
-                    note_metadata_storage.notes(),
-                    config
-                ).ok_or_else(|| QueryingError::FailedToCreateNoteFileTree)?
+/// Returns the notes stored in the given git tree (such as the tree of a commit).
+pub fn historic_notes(repository: &git2::Repository, tree: &git2::Tree) -> QueryingResult<Vec<HistoricNote>> {
+    let notes_tree = match tree.get_path(Path::new(NOTES_DIR)) {
+        Ok(entry) => entry.to_object(repository)?.peel_to_tree()?,
+        Err(_) => { return Ok(Vec::new()); }
+    };
+
+    let mut notes = BTreeMap::new();
+    for file_entry in notes_tree.iter() {
+        let file_path = Path::new(file_entry.name().unwrap());
+        let note_id = file_path.file_stem().unwrap().to_os_string();
+
+        let note_entry = notes.entry(note_id).or_insert_with(|| (None, None));
+        match file_path.extension().map(|x| x.to_str().unwrap()) {
+            Some(entry) if entry == NOTE_METADATA_EXT => {
+                note_entry.0 = Some(file_entry);
             }
-        )
+            Some(entry) if entry == NOTE_CONTENT_EXT => {
+                note_entry.1 = Some(file_entry);
+            }
+            _ => {}
+        }
     }
 
-    pub fn list(&self, prefix: &Path) {
-        if prefix == Path::new("") {
-            ListTree::print_tree(&self.root, ".")
-        } else {
-            if let Some(tree) = self.root.find(prefix) {
-                ListTree::print_tree(&tree, prefix.to_str().unwrap());
+    let mut historic_notes = Vec::new();
+    for note_entry in notes.values() {
+        if let (Some(metadata_entry), Some(content_entry)) = note_entry {
+            let metadata_entry = metadata_entry.to_object(&repository)?;
+            let metadata_content = metadata_entry
+                .as_blob()
+                .map(|blob| std::str::from_utf8(blob.content()).ok())
+                .flatten();
+
+            let content_entry = content_entry.to_object(&repository)?;
+            let content = content_entry
+                .as_blob()
+                .map(|blob| std::str::from_utf8(blob.content()).ok())
+                .flatten();
+
+            if let (Some(metadata_content), Some(content)) = (metadata_content, content) {
+                historic_notes.push(HistoricNote {
+                    metadata: NoteMetadata::parse(metadata_content)?,
+                    content_id: content_entry.id(),
+                    content: content.to_owned()
+                });
             }
         }
     }
 
-    pub fn print_tree(tree: &NoteFileTree, dir: &str) {
-        let is_terminal = stdout().is_terminal();
+    Ok(historic_notes)
+}
 
-        if !dir.is_empty() {
-            if is_terminal {
-                stdout()
-                    .execute(SetForegroundColor(Color::Blue)).unwrap()
-                    .execute(Print(dir)).unwrap()
-                    .execute(ResetColor).unwrap()
-                    .execute(Print("\n")).unwrap();
-            } else {
-                println!("{}", dir);
-            }
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoteChange {
+    Added,
+    Modified,
+    Removed
+}
+
+impl std::fmt::Display for NoteChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NoteChange::Added => write!(f, "added"),
+            NoteChange::Modified => write!(f, "modified"),
+            NoteChange::Removed => write!(f, "removed")
         }
+    }
+}
 
-        tree.walk(
-            |_, _, name, tree, stack| {
-                for current in stack.is_last_stack.iter() {
-                    if !current {
-                        print!("│   ");
-                    } else {
-                        print!("    ");
-                    }
+/// Returns the notes that differ between the trees (such as before and after a merge), sorted by path.
+pub fn note_changes(repository: &git2::Repository,
+                    old_tree: Option<&git2::Tree>,
+                    new_tree: &git2::Tree) -> QueryingResult<Vec<(NoteChange, PathBuf)>> {
+    let diff = repository.diff_tree_to_tree(old_tree, Some(new_tree), None)?;
+
+    let mut changed_ids = BTreeSet::new();
+    for delta in diff.deltas() {
+        for file_path in [delta.old_file().path(), delta.new_file().path()].into_iter().flatten() {
+            if file_path.parent() == Some(Path::new(NOTES_DIR)) {
+                if let Some(note_id) = file_path.file_stem().and_then(|stem| stem.to_str()) {
+                    changed_ids.insert(note_id.to_owned());
                 }
+            }
+        }
+    }
 
-                print!("{}── ", if stack.is_last {"└"} else {"├"});
-                let (content, color) = match tree {
-                    NoteFileTree::Note(note_metadata) => {
-                        let date_part = format!(
-                            "{}-{:0>2}-{:0>2} {:0>2}:{:0>2}",
-                            note_metadata.last_updated.year(),
-                            note_metadata.last_updated.month(),
-                            note_metadata.last_updated.day(),
-                            note_metadata.last_updated.hour(),
-                            note_metadata.last_updated.minute()
-                        );
-
-                        (format!("{} (id: {}, updated: {})", name.to_str().unwrap(), note_metadata.id, date_part), Color::Green)
-                    }
-                    NoteFileTree::Tree { .. } => {
-                        (format!("{}", name.to_str().unwrap().to_owned()), Color::Blue)
-                    }
-                };
+    let notes_by_id = |tree: Option<&git2::Tree>| -> QueryingResult<BTreeMap<String, PathBuf>> {
+        let notes = match tree {
+            Some(tree) => historic_notes(repository, tree)?,
+            None => Vec::new()
+        };
 
-                if is_terminal {
-                    stdout()
-                        .execute(SetForegroundColor(color)).unwrap()
-                        .execute(Print(content)).unwrap()
-                        .execute(ResetColor).unwrap()
-                        .execute(Print("\n")).unwrap();
-                } else {
-                    println!("{}", content);
-                }
+        Ok(notes.into_iter().map(|note| (note.metadata.id.to_string(), note.metadata.path)).collect())
+    };
 
-                true
-            }
-        );
+    let old_notes = notes_by_id(old_tree)?;
+    let new_notes = notes_by_id(Some(new_tree))?;
+
+    let mut changes = Vec::new();
+    for note_id in changed_ids {
+        match (old_notes.get(&note_id), new_notes.get(&note_id)) {
+            (None, Some(path)) => changes.push((NoteChange::Added, path.clone())),
+            (Some(path), None) => changes.push((NoteChange::Removed, path.clone())),
+            (Some(_), Some(path)) => changes.push((NoteChange::Modified, path.clone())),
+            (None, None) => {}
+        }
     }
+
+    changes.sort_by(|a, b| a.1.cmp(&b.1));
+    Ok(changes)
 }
 
-pub fn list_resources(
-    base_dir: &Path,
-    query: Option<PathBuf>,
-    print_absolute: bool
-) -> QueryingResult<()> {
-    println!("Resources:");
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum StatusKind {
+    Note,
+    Resource,
+    Other
+}
 
-    if !base_dir.exists() {
-        return Ok(());
-    }
+/// An uncommitted change in the repository.
+#[derive(Debug, PartialEq)]
+pub struct StatusEntry {
+    pub staged: bool,
+    pub change: NoteChange,
+    pub kind: StatusKind,
+    /// The path of the note, or the path relative to the resources directory for resources
+    pub path: PathBuf
+}
 
-    let mut initial_search_dir = base_dir.to_owned();
-    if let Some(query) = query {
-        initial_search_dir = base_dir.join(query);
+/// Maps a file in the repository (relative path) to the path of the note it belongs to, or '<id>' if the note is not known.
+pub fn note_path_for_file(file_path: &Path, note_metadata_storage: &NoteMetadataStorage) -> Option<PathBuf> {
+    if file_path.parent() != Some(Path::new(NOTES_DIR)) {
+        return None;
     }
 
-    let mut stack = vec![initial_search_dir];
-    'outer:
-    while let Some(top) = stack.pop() {
-        for entry in std::fs::read_dir(top)? {
-            if let Ok(entry) = entry {
-                let path = entry.path();
-                if path.is_file() {
-                    let relative_path = path.strip_prefix(&base_dir).unwrap();
-                    let path_to_use = if print_absolute { &path } else { relative_path };
+    let stem = file_path.file_stem()?.to_str()?;
+    let path = stem.parse::<NoteId>().ok()
+        .and_then(|id| note_metadata_storage.get_by_id(&id))
+        .map(|note_metadata| note_metadata.path.clone())
+        .unwrap_or_else(|| PathBuf::from(format!("<{}>", stem)));
+    Some(path)
+}
 
-                    println!("{}", path_to_use.to_str().unwrap());
-                } else {
-                    stack.push(entry.path());
-                }
+/// Returns the staged and unstaged changes (including untracked files), where changes to the content and metadata of a note are merged.
+pub fn working_tree_status(repository: &git2::Repository,
+                           note_metadata_storage: &NoteMetadataStorage) -> QueryingResult<Vec<StatusEntry>> {
+    let mut status_options = git2::StatusOptions::new();
+    status_options.include_untracked(true);
+    status_options.recurse_untracked_dirs(true);
+
+    let mut entries: Vec<StatusEntry> = Vec::new();
+    for status_entry in repository.statuses(Some(&mut status_options))?.iter() {
+        let file_path = match status_entry.path() {
+            Some(file_path) => PathBuf::from(file_path),
+            None => continue
+        };
+
+        let (kind, path) = if let Some(path) = note_path_for_file(&file_path, note_metadata_storage) {
+            (StatusKind::Note, path)
+        } else if let Ok(path) = file_path.strip_prefix(RESOURCES_DIR) {
+            (StatusKind::Resource, path.to_owned())
+        } else {
+            (StatusKind::Other, file_path)
+        };
+
+        let status = status_entry.status();
+        if kind == StatusKind::Other && status.is_wt_new() {
+            // Skip the symbolic links to the notes in the working dir
+            let is_symbolic_link = repository.workdir()
+                .and_then(|workdir| workdir.join(&path).symlink_metadata().ok())
+                .map(|metadata| metadata.file_type().is_symlink())
+                .unwrap_or(false);
+
+            if is_symbolic_link {
+                continue;
+            }
+        }
+
+        let changes = [
+            (true, status.is_index_new(), status.is_index_deleted(), status.is_index_modified() || status.is_index_renamed() || status.is_index_typechange()),
+            (false, status.is_wt_new(), status.is_wt_deleted(), status.is_wt_modified() || status.is_wt_renamed() || status.is_wt_typechange())
+        ];
+
+        for (staged, added, removed, modified) in changes {
+            let change = if added {
+                NoteChange::Added
+            } else if removed {
+                NoteChange::Removed
+            } else if modified {
+                NoteChange::Modified
             } else {
-                break 'outer;
+                continue;
+            };
+
+            let existing = entries.iter_mut().find(|entry| entry.staged == staged && entry.kind == kind && entry.path == path);
+            match existing {
+                Some(entry) => {
+                    if entry.change == NoteChange::Modified {
+                        entry.change = change;
+                    }
+                }
+                None => entries.push(StatusEntry { staged, change, kind, path: path.clone() })
             }
         }
     }
 
-    Ok(())
-}
-
-pub struct GitLog<'a> {
-    repository: &'a git2::Repository,
-    count: isize
+    entries.sort_by(|a, b| b.staged.cmp(&a.staged).then_with(|| a.kind.cmp(&b.kind)).then_with(|| a.path.cmp(&b.path)));
+    Ok(entries)
 }
 
-impl<'a> GitLog<'a> {
-    pub fn new(repository: &'a git2::Repository, count: isize) -> QueryingResult<GitLog<'a>> {
-        Ok(
-            GitLog {
-                repository,
-                count
-            }
-        )
+pub fn print_status(entries: &Vec<StatusEntry>) {
+    if entries.is_empty() {
+        println!("No uncommitted changes.");
+        return;
     }
 
-    pub fn print(&self) -> QueryingResult<()> {
-        let mut rev_walk = self.repository.revwalk()?;
-        rev_walk.push_head()?;
-
-        for commit_id in rev_walk.into_iter().take(if self.count >= 0 { self.count as usize } else { usize::MAX }) {
-            let commit_id = commit_id?;
-            let commit = self.repository.find_commit(commit_id)?;
-
-            let short_commit_hash = commit.as_object().short_id()?.as_str().unwrap().to_owned();
-            let commit_time = commit.time().to_date_time().unwrap();
-
-            println!(
-                "{} ({}): {}",
-                short_commit_hash,
-                commit_time.format(DATETIME_FORMAT),
-                commit.message().unwrap_or("").trim().replace("\n", " ")
-            );
+    for staged in [true, false] {
+        let current_entries = entries.iter().filter(|entry| entry.staged == staged).collect::<Vec<_>>();
+        if current_entries.is_empty() {
+            continue;
         }
 
-        Ok(())
+        println!("{}", if staged { "Staged changes:" } else { "Unstaged changes:" });
+        for entry in current_entries {
+            let untracked = !staged && entry.change == NoteChange::Added;
+            let change = if untracked { "untracked".to_owned() } else { entry.change.to_string() };
+            match entry.kind {
+                StatusKind::Note => println!("\t{} note: {}", change, entry.path.to_str().unwrap()),
+                StatusKind::Resource => println!("\t{} resource: {}", change, entry.path.to_str().unwrap()),
+                StatusKind::Other => println!("\t{} file: {}", change, entry.path.to_str().unwrap())
+            }
+        }
     }
 }
 
@@ -573,7 +2275,12 @@ pub fn get_note_content(
             Err(QueryingError::NoteNotFoundAtGitReference(git_reference))
         }
     } else {
-        Ok(note_metadata_storage.get_content(&path)?)
+        let (content, valid) = note_metadata_storage.get_content_lossy(&path)?;
+        if !valid {
+            eprintln!("Warning: note '{}' contains invalid UTF-8, showing lossy content.", path.to_str().unwrap());
+        }
+
+        Ok(content)
     }
 }
 
@@ -607,6 +2314,51 @@ impl<'a> GitContentFetcher<'a> {
     }
 }
 
+/// Creates a unified diff between two versions of the content of a note.
+pub fn create_note_diff(path: &Path, old_content: &str, new_content: &str) -> QueryingResult<String> {
+    let mut patch = git2::Patch::from_buffers(
+        old_content.as_bytes(),
+        Some(path),
+        new_content.as_bytes(),
+        Some(path),
+        None
+    )?;
+
+    Ok(String::from_utf8_lossy(&patch.to_buf()?).to_string())
+}
+
+/// Prints the diff, with colors if is_terminal.
+pub fn print_diff(diff: &str, out: &mut dyn Write, is_terminal: bool) -> QueryingResult<()> {
+    for line in diff.lines() {
+        let color = if line.starts_with("+++") || line.starts_with("---") || line.starts_with("diff ") {
+            None
+        } else if line.starts_with("@@") {
+            Some(Color::Cyan)
+        } else if line.starts_with('+') {
+            Some(Color::Green)
+        } else if line.starts_with('-') {
+            Some(Color::Red)
+        } else {
+            None
+        };
+
+        match color {
+            Some(color) if is_terminal => {
+                out
+                    .execute(SetForegroundColor(color))?
+                    .execute(Print(line))?
+                    .execute(ResetColor)?;
+                writeln!(out)?;
+            }
+            _ => {
+                writeln!(out, "{}", line)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
 pub fn extract_content(content: String, only_code: bool, only_output: bool) -> QueryingResult<String> {
     if only_code || only_output {
         let arena = markdown::storage();
@@ -679,38 +2431,255 @@ impl FromStr for RegexMatcher {
     }
 }
 
+#[derive(Debug)]
+pub struct GlobMatcher(globset::GlobMatcher);
+
+impl Matcher for GlobMatcher {
+    fn is_match(&self, text: &str) -> bool {
+        self.0.is_match(text)
+    }
+}
+
+impl FromStr for GlobMatcher {
+    type Err = globset::Error;
+
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        Ok(GlobMatcher(globset::Glob::new(str)?.compile_matcher()))
+    }
+}
+
+/// Matches tags exactly, unless the input contains wildcard characters in which case it is matched as a glob.
+#[derive(Debug)]
+pub enum TagMatcher {
+    Exact(StringMatcher),
+    Glob(GlobMatcher)
+}
+
+impl Matcher for TagMatcher {
+    fn is_match(&self, text: &str) -> bool {
+        match self {
+            TagMatcher::Exact(matcher) => matcher.is_match(text),
+            TagMatcher::Glob(matcher) => matcher.is_match(text)
+        }
+    }
+}
+
+impl FromStr for TagMatcher {
+    type Err = globset::Error;
+
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        if str.contains(|c| c == '*' || c == '?' || c == '[') {
+            Ok(TagMatcher::Glob(GlobMatcher::from_str(str)?))
+        } else {
+            Ok(TagMatcher::Exact(StringMatcher::new(str)))
+        }
+    }
+}
+
 pub enum FindQuery {
-    Tags(Vec<StringMatcher>),
+    Tags(Vec<TagMatcher>),
     Path(RegexMatcher),
     Id(RegexMatcher),
     Created(Vec<i32>),
-    LastUpdated(Vec<i32>)
+    LastUpdated(Vec<i32>),
+    Content(RegexMatcher),
+    And(Vec<FindQuery>),
+    Or(Vec<FindQuery>),
+    Not(Box<FindQuery>)
 }
 
 impl FindQuery {
-    pub fn is_match(&self, note_metadata: &NoteMetadata) -> bool {
+    pub fn is_match(&self, note_metadata: &NoteMetadata, note_metadata_storage: &NoteMetadataStorage) -> QueryingResult<bool> {
         match self {
             FindQuery::Tags(tags) => {
                 for tag in tags {
                     if !note_metadata.tags.iter().any(|current_tag| tag.is_match(current_tag)) {
-                        return false;
+                        return Ok(false);
                     }
                 }
 
-                true
+                Ok(true)
             }
             FindQuery::Path(path) => {
-                path.is_match(note_metadata.path.to_str().unwrap())
+                Ok(path.is_match(note_metadata.path.to_str().unwrap()))
             }
             FindQuery::Id(id) => {
-                id.is_match(&note_metadata.id.to_string())
+                Ok(id.is_match(&note_metadata.id.to_string()))
             }
             FindQuery::Created(parts) => {
-                is_datetime_match(&note_metadata.created, parts)
+                Ok(is_datetime_match(&note_metadata.created, parts))
             }
             FindQuery::LastUpdated(parts) => {
-                is_datetime_match(&note_metadata.last_updated, parts)
+                Ok(is_datetime_match(&note_metadata.last_updated, parts))
+            }
+            FindQuery::Content(pattern) => {
+                let content = note_metadata_storage.get_content(&note_metadata.path)?;
+                Ok(pattern.is_match(&content))
+            }
+            FindQuery::And(queries) => {
+                for query in queries {
+                    if !query.is_match(note_metadata, note_metadata_storage)? {
+                        return Ok(false);
+                    }
+                }
+
+                Ok(true)
+            }
+            FindQuery::Or(queries) => {
+                for query in queries {
+                    if query.is_match(note_metadata, note_metadata_storage)? {
+                        return Ok(true);
+                    }
+                }
+
+                Ok(false)
+            }
+            FindQuery::Not(query) => {
+                Ok(!query.is_match(note_metadata, note_metadata_storage)?)
+            }
+        }
+    }
+}
+
+/// Parses expressions such as 'tag:rust AND NOT (tag:draft OR name:"^old/")'.
+/// NOT binds tighter than AND, which binds tighter than OR.
+impl FromStr for FindQuery {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let tokens = tokenize_find_expression(text)?;
+        let mut parser = FindExpressionParser { tokens, index: 0 };
+        let query = parser.parse_or()?;
+
+        if let Some(token) = parser.next() {
+            return Err(format!("Unexpected '{}' in find expression", token));
+        }
+
+        Ok(query)
+    }
+}
+
+fn tokenize_find_expression(text: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut chars = text.chars();
+
+    while let Some(char) = chars.next() {
+        match char {
+            '"' => {
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(char) => current.push(char),
+                        None => return Err("Unterminated quote in find expression".to_owned())
+                    }
+                }
+            }
+            '(' | ')' => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+
+                tokens.push(char.to_string());
+            }
+            char if char.is_whitespace() => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
             }
+            char => current.push(char)
+        }
+    }
+
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+
+    Ok(tokens)
+}
+
+struct FindExpressionParser {
+    tokens: Vec<String>,
+    index: usize
+}
+
+impl FindExpressionParser {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.index).map(|token| token.as_str())
+    }
+
+    fn next(&mut self) -> Option<String> {
+        let token = self.tokens.get(self.index).cloned();
+        self.index += 1;
+        token
+    }
+
+    fn next_if_operator(&mut self, operator: &str) -> bool {
+        if self.peek().map(|token| token.eq_ignore_ascii_case(operator)).unwrap_or(false) {
+            self.index += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<FindQuery, String> {
+        let mut queries = vec![self.parse_and()?];
+        while self.next_if_operator("or") {
+            queries.push(self.parse_and()?);
+        }
+
+        Ok(if queries.len() == 1 { queries.remove(0) } else { FindQuery::Or(queries) })
+    }
+
+    fn parse_and(&mut self) -> Result<FindQuery, String> {
+        let mut queries = vec![self.parse_not()?];
+        while self.next_if_operator("and") {
+            queries.push(self.parse_not()?);
+        }
+
+        Ok(if queries.len() == 1 { queries.remove(0) } else { FindQuery::And(queries) })
+    }
+
+    fn parse_not(&mut self) -> Result<FindQuery, String> {
+        if self.next_if_operator("not") {
+            Ok(FindQuery::Not(Box::new(self.parse_not()?)))
+        } else {
+            self.parse_primary()
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<FindQuery, String> {
+        let token = self.next().ok_or_else(|| "Unexpected end of find expression".to_owned())?;
+        if token == "(" {
+            let query = self.parse_or()?;
+            return match self.next() {
+                Some(token) if token == ")" => Ok(query),
+                _ => Err("Expected ')' in find expression".to_owned())
+            };
+        }
+
+        let (name, value) = token
+            .split_once(':')
+            .ok_or_else(|| format!("Expected a search such as 'tag:<tag>' but got '{}'", token))?;
+
+        let parse_regex = |value: &str| RegexMatcher::from_str(value).map_err(|err| err.to_string());
+        let parse_date_parts = |value: &str| {
+            value
+                .split(|c| c == '-' || c == '/')
+                .filter(|part| !part.is_empty())
+                .map(|part| part.parse::<i32>().map_err(|_| format!("Invalid date part '{}'", part)))
+                .collect::<Result<Vec<_>, _>>()
+        };
+
+        match name {
+            "tag" => Ok(FindQuery::Tags(vec![TagMatcher::from_str(value).map_err(|err| err.to_string())?])),
+            "name" => Ok(FindQuery::Path(parse_regex(value)?)),
+            "id" => Ok(FindQuery::Id(parse_regex(value)?)),
+            "content" => Ok(FindQuery::Content(parse_regex(value)?)),
+            "created" => Ok(FindQuery::Created(parse_date_parts(value)?)),
+            "updated" => Ok(FindQuery::LastUpdated(parse_date_parts(value)?)),
+            _ => Err(format!("Unknown search '{}', expected tag, name, id, content, created or updated", name))
         }
     }
 }
@@ -730,4 +2699,133 @@ fn is_datetime_match(datetime: &DateTime<Local>, parts: &Vec<i32>) -> bool {
     && is_part_match(datetime.hour() as i32, parts.get(3))
     && is_part_match(datetime.minute() as i32, parts.get(4))
     && is_part_match(datetime.second() as i32, parts.get(5))
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_format_datetime() {
+    let datetime = Utc.with_ymd_and_hms(2024, 3, 5, 14, 30, 15).unwrap();
+    let now = Utc.with_ymd_and_hms(2024, 3, 5, 17, 0, 0).unwrap();
+
+    assert_eq!("2024-03-05 14:30:15", format_datetime_relative_to(&datetime, DateFormat::Default, &now));
+    assert_eq!("2024-03-05T14:30:15+00:00", format_datetime_relative_to(&datetime, DateFormat::Rfc3339, &now));
+    assert_eq!("1709649015", format_datetime_relative_to(&datetime, DateFormat::Unix, &now));
+    assert_eq!("2 hours ago", format_datetime_relative_to(&datetime, DateFormat::Relative, &now));
+}
+
+#[test]
+fn test_format_datetime_relative() {
+    let datetime = Utc.with_ymd_and_hms(2024, 3, 5, 14, 30, 15).unwrap();
+    let format = |now: DateTime<Utc>| format_datetime_relative_to(&datetime, DateFormat::Relative, &now);
+
+    assert_eq!("just now", format(datetime + chrono::Duration::seconds(30)));
+    assert_eq!("1 minute ago", format(datetime + chrono::Duration::seconds(90)));
+    assert_eq!("3 days ago", format(datetime + chrono::Duration::days(3)));
+    assert_eq!("2 months ago", format(datetime + chrono::Duration::days(65)));
+    assert_eq!("1 year ago", format(datetime + chrono::Duration::days(400)));
+    assert_eq!("in the future", format(datetime - chrono::Duration::days(1)));
+    assert_eq!(Ok(DateFormat::Relative), DateFormat::from_str("relative"));
+    assert!(DateFormat::from_str("iso").is_err());
+}
+
+#[test]
+fn test_git_log_graph() {
+    use tempfile::TempDir;
+
+    let temp_repository_dir = TempDir::new().unwrap();
+    let repository = git2::Repository::init(temp_repository_dir.path()).unwrap();
+    let signature = git2::Signature::now("Test", "test@test.com").unwrap();
+    let other_signature = git2::Signature::now("Other", "other@test.com").unwrap();
+    let tree = repository.find_tree(repository.index().unwrap().write_tree().unwrap()).unwrap();
+
+    let base_id = repository.commit(Some("HEAD"), &signature, &signature, "Base", &tree, &[]).unwrap();
+    let base = repository.find_commit(base_id).unwrap();
+    let main_id = repository.commit(Some("HEAD"), &signature, &signature, "Main", &tree, &[&base]).unwrap();
+    let branch_id = repository.commit(None, &other_signature, &other_signature, "Branch", &tree, &[&base]).unwrap();
+    let main = repository.find_commit(main_id).unwrap();
+    let branch = repository.find_commit(branch_id).unwrap();
+    repository.commit(Some("HEAD"), &signature, &signature, "Merge", &tree, &[&main, &branch]).unwrap();
+
+    let git_log = GitLog::new(&repository, -1, GitLogFilter { author: None, grep: None }).unwrap();
+    let lines = git_log.lines(DateFormat::Default, true).unwrap();
+    assert_eq!(6, lines.len());
+    assert!(lines[0].starts_with("* ") && lines[0].ends_with("Merge"));
+    assert_eq!("| \\", lines[1]);
+    assert!(lines[2].starts_with("* | ") || lines[2].starts_with("| * "));
+    assert!(lines[3].starts_with("* | ") || lines[3].starts_with("| * "));
+    assert_eq!("| /", lines[4]);
+    assert!(lines[5].starts_with("* ") && lines[5].ends_with("Base"));
+
+    let git_log = GitLog::new(&repository, -1, GitLogFilter { author: Some("other@".to_owned()), grep: None }).unwrap();
+    let lines = git_log.lines(DateFormat::Default, false).unwrap();
+    assert_eq!(1, lines.len());
+    assert!(lines[0].ends_with("Branch"));
+
+    let git_log = GitLog::new(&repository, -1, GitLogFilter { author: None, grep: Some(Regex::new("^(Base|Main)$").unwrap()) }).unwrap();
+    let lines = git_log.lines(DateFormat::Default, false).unwrap();
+    assert_eq!(2, lines.len());
+}
+
+#[test]
+fn test_parse_find_expression() {
+    let query = FindQuery::from_str("tag:rust AND NOT tag:draft").unwrap();
+    match query {
+        FindQuery::And(queries) => {
+            assert_eq!(2, queries.len());
+            assert!(matches!(queries[0], FindQuery::Tags(_)));
+            assert!(matches!(&queries[1], FindQuery::Not(query) if matches!(**query, FindQuery::Tags(_))));
+        }
+        _ => panic!("Expected AND")
+    }
+
+    let query = FindQuery::from_str("tag:a or tag:b and (name:\"x y\" or created:2023-07)").unwrap();
+    match query {
+        FindQuery::Or(queries) => {
+            assert_eq!(2, queries.len());
+            assert!(matches!(&queries[1], FindQuery::And(queries) if matches!(queries[1], FindQuery::Or(_))));
+        }
+        _ => panic!("Expected OR")
+    }
+
+    assert!(matches!(FindQuery::from_str("created:2023/07/05"), Ok(FindQuery::Created(parts)) if parts == vec![2023, 7, 5]));
+
+    assert!(FindQuery::from_str("").is_err());
+    assert!(FindQuery::from_str("tag:rust AND").is_err());
+    assert!(FindQuery::from_str("(tag:rust").is_err());
+    assert!(FindQuery::from_str("tag:rust tag:go").is_err());
+    assert!(FindQuery::from_str("rust").is_err());
+    assert!(FindQuery::from_str("size:10").is_err());
+    assert!(FindQuery::from_str("name:\"x").is_err());
+}
+
+#[test]
+fn test_results_json() {
+    let mut note_metadata = NoteMetadata::new(NoteId::new(), PathBuf::from("2023/07/sample"), vec!["python".to_owned()]);
+    note_metadata.created = Local.with_ymd_and_hms(2023, 7, 5, 14, 30, 15).unwrap();
+    note_metadata.last_updated = Local.with_ymd_and_hms(2023, 7, 6, 8, 0, 0).unwrap();
+
+    let json: serde_json::Value = serde_json::from_str(&note_metadata_results_json(&vec![&note_metadata])).unwrap();
+    assert_eq!(
+        serde_json::json!([{
+            "path": "2023/07/sample",
+            "id": note_metadata.id.to_string(),
+            "tags": ["python"],
+            "created": "2023-07-05 14:30:15",
+            "last_updated": "2023-07-06 08:00:00"
+        }]),
+        json
+    );
+
+    let results = vec![
+        ListDirectoryEntry { name: "07".to_owned(), last_updated: Some(note_metadata.last_updated), note_metadata: None, size: None },
+        ListDirectoryEntry { name: "sample".to_owned(), last_updated: Some(note_metadata.last_updated), note_metadata: Some(&note_metadata), size: None }
+    ];
+
+    let json: serde_json::Value = serde_json::from_str(&list_directory_results_json(&results)).unwrap();
+    assert_eq!(
+        serde_json::json!([
+            { "name": "07", "type": "dir", "last_updated": "2023-07-06 08:00:00" },
+            { "name": "sample", "type": "note", "last_updated": "2023-07-06 08:00:00" }
+        ]),
+        json
+    );
+}
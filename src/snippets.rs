@@ -1,8 +1,9 @@
 use std::any::Any;
-use std::io::{Write};
-use std::os::unix::process::CommandExt;
-use std::path::{Path, PathBuf};
-use std::process::{Command, ExitStatus};
+use std::io::{Read, Write};
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::path::{Component, Path, PathBuf};
+use std::process::{Command, ExitStatus, Output, Stdio};
+use std::time::{Duration, Instant};
 
 use serde::{Serialize, Deserialize};
 use fnv::FnvHashMap;
@@ -10,6 +11,7 @@ use thiserror::Error;
 
 use crate::config::SnippetFileConfig;
 use crate::helpers::where_is_binary;
+use crate::model::RESOURCES_DIR;
 
 pub type SnippetResult<T> = Result<T, SnippetError>;
 
@@ -24,6 +26,9 @@ pub enum SnippetError {
     #[error("Compiler '{0}' not found")]
     CompilerNotFound(String),
 
+    #[error("The runner has no variant '{0}'")]
+    InvalidVariant(String),
+
     #[error("The configuration type is not valid for this runner")]
     InvalidConfigType,
 
@@ -33,7 +38,15 @@ pub enum SnippetError {
     #[error("Failed to compile (see console output)")]
     Compiler,
 
-    #[error("Execution error: {status}")]
+    #[error("Arbitrary runners are not allowed (see the allow_arbitrary_runners config key)")]
+    ArbitraryRunnersNotAllowed,
+
+    #[error("Execution timed out after {seconds} seconds")]
+    Timeout {
+        seconds: f64
+    },
+
+    #[error("Execution error: {}", exit_status_text(.status))]
     Execution {
         status: ExitStatus,
         output: String
@@ -43,20 +56,83 @@ pub enum SnippetError {
     IO(std::io::Error)
 }
 
+/// Describes how the snippet exited, such as "exited with code 2" or "killed by signal SIGKILL".
+fn exit_status_text(status: &ExitStatus) -> String {
+    if let Some(code) = status.code() {
+        return format!("exited with code {}", code);
+    }
+
+    match status.signal() {
+        Some(signal) => format!("killed by signal {}", signal_name(signal)),
+        None => status.to_string()
+    }
+}
+
+fn signal_name(signal: i32) -> String {
+    let name = match signal {
+        libc::SIGHUP => "SIGHUP",
+        libc::SIGINT => "SIGINT",
+        libc::SIGQUIT => "SIGQUIT",
+        libc::SIGILL => "SIGILL",
+        libc::SIGTRAP => "SIGTRAP",
+        libc::SIGABRT => "SIGABRT",
+        libc::SIGBUS => "SIGBUS",
+        libc::SIGFPE => "SIGFPE",
+        libc::SIGKILL => "SIGKILL",
+        libc::SIGUSR1 => "SIGUSR1",
+        libc::SIGSEGV => "SIGSEGV",
+        libc::SIGUSR2 => "SIGUSR2",
+        libc::SIGPIPE => "SIGPIPE",
+        libc::SIGALRM => "SIGALRM",
+        libc::SIGTERM => "SIGTERM",
+        _ => return format!("{}", signal)
+    };
+
+    name.to_owned()
+}
+
 impl From<std::io::Error> for SnippetError {
     fn from(err: std::io::Error) -> Self {
         SnippetError::IO(err)
     }
 }
 
+/// Information about the note that the snippet is part of, exposed to the snippet as environment variables.
+pub struct SnippetContext {
+    pub repository: PathBuf,
+    pub note_path: PathBuf
+}
+
+impl SnippetContext {
+    pub fn new(repository: &Path, note_path: &Path) -> SnippetContext {
+        SnippetContext {
+            repository: repository.to_owned(),
+            note_path: note_path.to_owned()
+        }
+    }
+
+    fn apply(&self, command: &mut Command) {
+        command
+            .env("GITNOTES_REPOSITORY", &self.repository)
+            .env("GITNOTES_RESOURCES_DIR", self.repository.join(RESOURCES_DIR))
+            .env("GITNOTES_NOTE_PATH", &self.note_path);
+    }
+}
+
 pub struct SnippetRunnerManger {
-    runners: FnvHashMap<String, Box<dyn SnippetRunner + Send + Sync>>
+    runners: FnvHashMap<String, Box<dyn SnippetRunner + Send + Sync>>,
+    runner_aliases: FnvHashMap<String, String>,
+    allow_arbitrary_runners: bool,
+    default_timeout: Option<Duration>
 }
 
 impl SnippetRunnerManger {
     pub fn new() -> SnippetRunnerManger {
         SnippetRunnerManger {
-            runners: FnvHashMap::default()
+            runners: FnvHashMap::default(),
+            runner_aliases: FnvHashMap::default(),
+            allow_arbitrary_runners: false,
+            default_timeout: None
         }
     }
 
@@ -73,12 +149,80 @@ impl SnippetRunnerManger {
         self.runners.insert(name.to_owned(), runner);
     }
 
-    pub fn run(&self, name: &str, source_code: &str) -> SnippetResult<String> {
-        let runner = self.runners.get(name).ok_or_else(|| SnippetError::RunnerNotFound(name.to_owned()))?;
-        runner.run(source_code)
+    /// Makes code blocks with the alias (such as js) use the runner with the given name (such as javascript).
+    pub fn add_runner_alias(&mut self, alias: &str, name: &str) {
+        self.runner_aliases.insert(alias.to_owned(), name.to_owned());
+    }
+
+    fn resolve_runner_name<'a>(&'a self, name: &'a str) -> &'a str {
+        self.runner_aliases.get(name).map(|name| name.as_str()).unwrap_or(name)
+    }
+
+    /// Runs the snippet, where the given timeout (from the note) takes precedence over the timeout of the runner and then the global default.
+    /// The arguments are passed to the runner (such as compiler flags given in the info string of the code block).
+    /// A name of the form `<runner>:<variant>` (such as `python:python2`) runs the given variant of the runner.
+    pub fn run(&self,
+               name: &str,
+               args: &[String],
+               source_code: &str,
+               stdin: Option<&str>,
+               timeout: Option<Duration>,
+               context: Option<&SnippetContext>) -> SnippetResult<SnippetOutput> {
+        let (runner_name, variant) = match name.split_once(':') {
+            Some((runner_name, variant)) => (runner_name, Some(variant)),
+            None => (name, None)
+        };
+
+        let runner = self.runners.get(self.resolve_runner_name(runner_name)).ok_or_else(|| SnippetError::RunnerNotFound(runner_name.to_owned()))?;
+        let timeout = timeout.or_else(|| runner.timeout()).or(self.default_timeout);
+        match variant {
+            Some(variant) => runner.run_variant(variant, source_code, args, stdin, timeout, context),
+            None => runner.run_with_args(source_code, args, stdin, timeout, context)
+        }
+    }
+
+    /// Runs the snippet using the given command line instead of a named runner.
+    /// The snippet is saved as the file named by the last argument if it is a plain file name (otherwise 'snippet' is appended as an argument),
+    /// and the command is executed inside a temporary directory.
+    pub fn run_command(&self,
+                       command_line: &str,
+                       source_code: &str,
+                       stdin: Option<&str>,
+                       timeout: Option<Duration>,
+                       context: Option<&SnippetContext>) -> SnippetResult<SnippetOutput> {
+        if !self.allow_arbitrary_runners {
+            return Err(SnippetError::ArbitraryRunnersNotAllowed);
+        }
+
+        let mut parts = command_line.split_whitespace();
+        let executable = parts.next().ok_or_else(|| SnippetError::ExecutableNotFound(command_line.to_owned()))?;
+        let mut arguments = parts.map(|part| part.to_owned()).collect::<Vec<_>>();
+        assert_executable_exists(Path::new(executable))?;
+
+        let working_dir = tempfile::tempdir()?;
+        let source_code_file_name = match arguments.last() {
+            Some(argument) if is_plain_file_name(argument) => argument.clone(),
+            _ => {
+                arguments.push("snippet".to_owned());
+                "snippet".to_owned()
+            }
+        };
+        std::fs::write(working_dir.path().join(source_code_file_name), source_code)?;
+
+        run_and_capture(
+            Command::new(executable)
+                .args(&arguments)
+                .current_dir(working_dir.path()),
+            stdin,
+            timeout.or(self.default_timeout),
+            context,
+            false
+        )
     }
 
     pub fn apply_config(&mut self, file_config: &SnippetFileConfig) -> SnippetResult<()> {
+        self.allow_arbitrary_runners = file_config.allow_arbitrary_runners.unwrap_or(false);
+        self.default_timeout = file_config.timeout_seconds.map(Duration::from_secs_f64);
         self.change_config_opt("python", file_config.python.as_ref())?;
         self.change_config_opt("bash", file_config.bash.as_ref())?;
         self.change_config_opt("cpp", file_config.cpp.as_ref())?;
@@ -97,12 +241,19 @@ impl SnippetRunnerManger {
     }
 
     pub fn change_config(&mut self, name: &str, config: &dyn Any) -> SnippetResult<()> {
-        let runner = self.runners.get_mut(name).ok_or_else(|| SnippetError::RunnerNotFound(name.to_owned()))?;
+        let runner_name = self.resolve_runner_name(name).to_owned();
+        let runner = self.runners.get_mut(&runner_name).ok_or_else(|| SnippetError::RunnerNotFound(name.to_owned()))?;
         runner.change_config(config)?;
         Ok(())
     }
 }
 
+/// Checks that the argument is a single file name (not a path or a flag), so that it stays inside the working directory.
+fn is_plain_file_name(argument: &str) -> bool {
+    let mut components = Path::new(argument).components();
+    matches!(components.next(), Some(Component::Normal(_))) && components.next().is_none() && !argument.starts_with('-')
+}
+
 impl Default for SnippetRunnerManger {
     fn default() -> Self {
         let mut manager = SnippetRunnerManger::new();
@@ -111,13 +262,49 @@ impl Default for SnippetRunnerManger {
         manager.add_runner("cpp", Box::new(CppSnippetRunner::default()));
         manager.add_runner("rust", Box::new(RustSnippetRunner::default()));
         manager.add_runner("javascript", Box::new(JavaScriptSnippetRunner::default()));
+        manager.add_runner_alias("js", "javascript");
         manager.add_runner("typescript", Box::new(TypeScriptSnippetRunner::default()));
         manager
     }
 }
 
+/// The output of a snippet, where the standard error is only captured separately if enabled for the runner.
+#[derive(Debug, PartialEq)]
+pub struct SnippetOutput {
+    pub stdout: String,
+    pub stderr: Option<String>
+}
+
 pub trait SnippetRunner {
-    fn run(&self, source_code: &str) -> SnippetResult<String>;
+    /// Runs the snippet, where the execution (but not the compilation) is aborted if it takes longer than the timeout.
+    fn run(&self, source_code: &str, stdin: Option<&str>, timeout: Option<Duration>, context: Option<&SnippetContext>) -> SnippetResult<SnippetOutput>;
+
+    /// Runs the snippet with extra arguments, which are ignored unless the runner supports them.
+    fn run_with_args(&self,
+                     source_code: &str,
+                     _args: &[String],
+                     stdin: Option<&str>,
+                     timeout: Option<Duration>,
+                     context: Option<&SnippetContext>) -> SnippetResult<SnippetOutput> {
+        self.run(source_code, stdin, timeout, context)
+    }
+
+    /// Runs the snippet using a variant of the runner (such as another interpreter), which is an error unless the runner supports it.
+    fn run_variant(&self,
+                   variant: &str,
+                   _source_code: &str,
+                   _args: &[String],
+                   _stdin: Option<&str>,
+                   _timeout: Option<Duration>,
+                   _context: Option<&SnippetContext>) -> SnippetResult<SnippetOutput> {
+        Err(SnippetError::InvalidVariant(variant.to_owned()))
+    }
+
+    /// The timeout from the config of the runner (if any).
+    fn timeout(&self) -> Option<Duration>;
+
+    /// Indicates if the standard error should be kept separate from the standard output.
+    fn separate_stderr(&self) -> bool;
 
     fn change_config(&mut self, config: &dyn Any) -> SnippetResult<()>;
 }
@@ -125,7 +312,11 @@ pub trait SnippetRunner {
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct PythonSnippetRunnerConfig {
-    pub executable: PathBuf
+    pub executable: PathBuf,
+    /// Aborts the execution if it takes longer than this (overrides the global default).
+    pub timeout_seconds: Option<f64>,
+    /// Keeps the standard error separate from the standard output instead of interleaving them.
+    pub separate_stderr: Option<bool>
 }
 
 pub struct PythonSnippetRunner {
@@ -138,6 +329,29 @@ impl PythonSnippetRunner {
             config
         }
     }
+
+    fn run_executable(&self,
+                      executable: &Path,
+                      source_code: &str,
+                      args: &[String],
+                      stdin: Option<&str>,
+                      timeout: Option<Duration>,
+                      context: Option<&SnippetContext>) -> SnippetResult<SnippetOutput> {
+        assert_executable_exists(executable)?;
+
+        let mut source_code_file = tempfile::Builder::new()
+            .suffix(".py")
+            .tempfile()?;
+        source_code_file.write_all(source_code.as_bytes())?;
+
+        run_and_capture(
+            Command::new(executable).args(args).arg(source_code_file.path()),
+            stdin,
+            timeout,
+            context,
+            self.separate_stderr()
+        )
+    }
 }
 
 impl Default for PythonSnippetRunner {
@@ -145,21 +359,55 @@ impl Default for PythonSnippetRunner {
         PythonSnippetRunner::new(
             PythonSnippetRunnerConfig {
                 executable: Path::new("python3").to_owned(),
+                timeout_seconds: None,
+                separate_stderr: None
             }
         )
     }
 }
 
 impl SnippetRunner for PythonSnippetRunner {
-    fn run(&self, source_code: &str) -> SnippetResult<String> {
-        assert_executable_exists(&self.config.executable)?;
+    fn run(&self, source_code: &str, stdin: Option<&str>, timeout: Option<Duration>, context: Option<&SnippetContext>) -> SnippetResult<SnippetOutput> {
+        self.run_with_args(source_code, &[], stdin, timeout, context)
+    }
 
-        let mut source_code_file = tempfile::Builder::new()
-            .suffix(".py")
-            .tempfile()?;
-        source_code_file.write_all(source_code.as_bytes())?;
+    /// The arguments are passed to the interpreter before the source code file.
+    fn run_with_args(&self,
+                     source_code: &str,
+                     args: &[String],
+                     stdin: Option<&str>,
+                     timeout: Option<Duration>,
+                     context: Option<&SnippetContext>) -> SnippetResult<SnippetOutput> {
+        self.run_executable(&self.config.executable, source_code, args, stdin, timeout, context)
+    }
 
-        run_and_capture(Command::new(&self.config.executable).arg(source_code_file.path()))
+    /// The variant is the Python interpreter to use instead of the configured one (such as `python2`), which must be named python*.
+    fn run_variant(&self,
+                   variant: &str,
+                   source_code: &str,
+                   args: &[String],
+                   stdin: Option<&str>,
+                   timeout: Option<Duration>,
+                   context: Option<&SnippetContext>) -> SnippetResult<SnippetOutput> {
+        let executable = Path::new(variant);
+        let is_python = executable.file_name()
+            .and_then(|file_name| file_name.to_str())
+            .map(|file_name| file_name.starts_with("python"))
+            .unwrap_or(false);
+
+        if !is_python {
+            return Err(SnippetError::InvalidVariant(variant.to_owned()));
+        }
+
+        self.run_executable(executable, source_code, args, stdin, timeout, context)
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.config.timeout_seconds.map(Duration::from_secs_f64)
+    }
+
+    fn separate_stderr(&self) -> bool {
+        self.config.separate_stderr.unwrap_or(false)
     }
 
     fn change_config(&mut self, config: &dyn Any) -> SnippetResult<()> {
@@ -175,7 +423,11 @@ impl SnippetRunner for PythonSnippetRunner {
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct BashSnippetRunnerConfig {
-    pub executable: PathBuf
+    pub executable: PathBuf,
+    /// Aborts the execution if it takes longer than this (overrides the global default).
+    pub timeout_seconds: Option<f64>,
+    /// Keeps the standard error separate from the standard output instead of interleaving them.
+    pub separate_stderr: Option<bool>
 }
 
 pub struct BashSnippetRunner {
@@ -195,13 +447,15 @@ impl Default for BashSnippetRunner {
         BashSnippetRunner::new(
             BashSnippetRunnerConfig {
                 executable: Path::new("bash").to_owned(),
+                timeout_seconds: None,
+                separate_stderr: None
             }
         )
     }
 }
 
 impl SnippetRunner for BashSnippetRunner {
-    fn run(&self, source_code: &str) -> SnippetResult<String> {
+    fn run(&self, source_code: &str, stdin: Option<&str>, timeout: Option<Duration>, context: Option<&SnippetContext>) -> SnippetResult<SnippetOutput> {
         assert_executable_exists(&self.config.executable)?;
 
         let mut source_code_file = tempfile::Builder::new()
@@ -211,10 +465,22 @@ impl SnippetRunner for BashSnippetRunner {
 
         run_and_capture(
             Command::new(&self.config.executable)
-                .arg(source_code_file.path())
+                .arg(source_code_file.path()),
+            stdin,
+            timeout,
+            context,
+            self.separate_stderr()
         )
     }
 
+    fn timeout(&self) -> Option<Duration> {
+        self.config.timeout_seconds.map(Duration::from_secs_f64)
+    }
+
+    fn separate_stderr(&self) -> bool {
+        self.config.separate_stderr.unwrap_or(false)
+    }
+
     fn change_config(&mut self, config: &dyn Any) -> SnippetResult<()> {
         if let Some(config) = config.downcast_ref::<BashSnippetRunnerConfig>() {
             self.config = config.clone();
@@ -229,7 +495,11 @@ impl SnippetRunner for BashSnippetRunner {
 #[serde(deny_unknown_fields)]
 pub struct CppSnippetRunnerConfig {
     pub compiler_executable: PathBuf,
-    pub compiler_flags: Vec<String>
+    pub compiler_flags: Vec<String>,
+    /// Aborts the execution if it takes longer than this (overrides the global default).
+    pub timeout_seconds: Option<f64>,
+    /// Keeps the standard error separate from the standard output instead of interleaving them.
+    pub separate_stderr: Option<bool>
 }
 
 pub struct CppSnippetRunner {
@@ -250,13 +520,25 @@ impl Default for CppSnippetRunner {
             CppSnippetRunnerConfig {
                 compiler_executable: Path::new("c++").to_owned(),
                 compiler_flags: vec!["-std=c++14".to_owned()],
+                timeout_seconds: None,
+                separate_stderr: None
             }
         )
     }
 }
 
 impl SnippetRunner for CppSnippetRunner {
-    fn run(&self, source_code: &str) -> SnippetResult<String> {
+    fn run(&self, source_code: &str, stdin: Option<&str>, timeout: Option<Duration>, context: Option<&SnippetContext>) -> SnippetResult<SnippetOutput> {
+        self.run_with_args(source_code, &[], stdin, timeout, context)
+    }
+
+    /// The arguments are appended to the compiler flags.
+    fn run_with_args(&self,
+                     source_code: &str,
+                     args: &[String],
+                     stdin: Option<&str>,
+                     timeout: Option<Duration>,
+                     context: Option<&SnippetContext>) -> SnippetResult<SnippetOutput> {
         assert_compiler_exists(&self.config.compiler_executable)?;
 
         let mut source_code_file = tempfile::Builder::new()
@@ -274,6 +556,7 @@ impl SnippetRunner for CppSnippetRunner {
 
         let output = Command::new(&self.config.compiler_executable)
             .args(self.config.compiler_flags.iter())
+            .args(args)
             .arg(source_code_file.path())
             .arg("-o")
             .arg(&compiled_executable)
@@ -284,7 +567,15 @@ impl SnippetRunner for CppSnippetRunner {
             return Err(SnippetError::Compiler);
         }
 
-        run_and_capture(&mut Command::new(&compiled_executable))
+        run_and_capture(&mut Command::new(&compiled_executable), stdin, timeout, context, self.separate_stderr())
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.config.timeout_seconds.map(Duration::from_secs_f64)
+    }
+
+    fn separate_stderr(&self) -> bool {
+        self.config.separate_stderr.unwrap_or(false)
     }
 
     fn change_config(&mut self, config: &dyn Any) -> SnippetResult<()> {
@@ -301,7 +592,11 @@ impl SnippetRunner for CppSnippetRunner {
 #[serde(deny_unknown_fields)]
 pub struct RustSnippetRunnerConfig {
     pub compiler_executable: PathBuf,
-    pub compiler_flags: Vec<String>
+    pub compiler_flags: Vec<String>,
+    /// Aborts the execution if it takes longer than this (overrides the global default).
+    pub timeout_seconds: Option<f64>,
+    /// Keeps the standard error separate from the standard output instead of interleaving them.
+    pub separate_stderr: Option<bool>
 }
 
 pub struct RustSnippetRunner {
@@ -323,14 +618,26 @@ impl Default for RustSnippetRunner {
                 compiler_executable: Path::new("rustc").to_owned(),
                 compiler_flags: vec![
                     "--edition".to_owned(), "2021".to_owned()
-                ]
+                ],
+                timeout_seconds: None,
+                separate_stderr: None
             }
         )
     }
 }
 
 impl SnippetRunner for RustSnippetRunner {
-    fn run(&self, source_code: &str) -> SnippetResult<String> {
+    fn run(&self, source_code: &str, stdin: Option<&str>, timeout: Option<Duration>, context: Option<&SnippetContext>) -> SnippetResult<SnippetOutput> {
+        self.run_with_args(source_code, &[], stdin, timeout, context)
+    }
+
+    /// The arguments are appended to the compiler flags.
+    fn run_with_args(&self,
+                     source_code: &str,
+                     args: &[String],
+                     stdin: Option<&str>,
+                     timeout: Option<Duration>,
+                     context: Option<&SnippetContext>) -> SnippetResult<SnippetOutput> {
         assert_compiler_exists(&self.config.compiler_executable)?;
 
         let mut source_code_file = tempfile::Builder::new()
@@ -348,6 +655,7 @@ impl SnippetRunner for RustSnippetRunner {
 
         let output = Command::new(&self.config.compiler_executable)
             .args(self.config.compiler_flags.iter())
+            .args(args)
             .arg(source_code_file.path())
             .args(["--crate-name", "snippet"])
             .arg("-o")
@@ -359,7 +667,15 @@ impl SnippetRunner for RustSnippetRunner {
             return Err(SnippetError::Compiler);
         }
 
-        run_and_capture(&mut Command::new(&compiled_executable))
+        run_and_capture(&mut Command::new(&compiled_executable), stdin, timeout, context, self.separate_stderr())
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.config.timeout_seconds.map(Duration::from_secs_f64)
+    }
+
+    fn separate_stderr(&self) -> bool {
+        self.config.separate_stderr.unwrap_or(false)
     }
 
     fn change_config(&mut self, config: &dyn Any) -> SnippetResult<()> {
@@ -376,7 +692,11 @@ impl SnippetRunner for RustSnippetRunner {
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct JavaScriptSnippetRunnerConfig {
-    pub executable: PathBuf
+    pub executable: PathBuf,
+    /// Aborts the execution if it takes longer than this (overrides the global default).
+    pub timeout_seconds: Option<f64>,
+    /// Keeps the standard error separate from the standard output instead of interleaving them.
+    pub separate_stderr: Option<bool>
 }
 
 pub struct JavaScriptSnippetRunner {
@@ -396,13 +716,15 @@ impl Default for JavaScriptSnippetRunner {
         JavaScriptSnippetRunner::new(
             JavaScriptSnippetRunnerConfig {
                 executable: Path::new("node").to_owned(),
+                timeout_seconds: None,
+                separate_stderr: None
             }
         )
     }
 }
 
 impl SnippetRunner for JavaScriptSnippetRunner {
-    fn run(&self, source_code: &str) -> SnippetResult<String> {
+    fn run(&self, source_code: &str, stdin: Option<&str>, timeout: Option<Duration>, context: Option<&SnippetContext>) -> SnippetResult<SnippetOutput> {
         assert_executable_exists(&self.config.executable)?;
 
         let mut source_code_file = tempfile::Builder::new()
@@ -410,7 +732,15 @@ impl SnippetRunner for JavaScriptSnippetRunner {
             .tempfile()?;
         source_code_file.write_all(source_code.as_bytes())?;
 
-        run_and_capture(Command::new(&self.config.executable).arg(source_code_file.path()))
+        run_and_capture(Command::new(&self.config.executable).arg(source_code_file.path()), stdin, timeout, context, self.separate_stderr())
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.config.timeout_seconds.map(Duration::from_secs_f64)
+    }
+
+    fn separate_stderr(&self) -> bool {
+        self.config.separate_stderr.unwrap_or(false)
     }
 
     fn change_config(&mut self, config: &dyn Any) -> SnippetResult<()> {
@@ -428,6 +758,10 @@ impl SnippetRunner for JavaScriptSnippetRunner {
 pub struct TypeScriptSnippetRunnerConfig {
     pub compiler_executable: PathBuf,
     pub node_executable: PathBuf,
+    /// Aborts the execution if it takes longer than this (overrides the global default).
+    pub timeout_seconds: Option<f64>,
+    /// Keeps the standard error separate from the standard output instead of interleaving them.
+    pub separate_stderr: Option<bool>
 }
 
 pub struct TypeScriptSnippetRunner {
@@ -447,14 +781,16 @@ impl Default for TypeScriptSnippetRunner {
         TypeScriptSnippetRunner::new(
             TypeScriptSnippetRunnerConfig {
                 compiler_executable: Path::new("tsc").to_owned(),
-                node_executable: Path::new("node").to_owned()
+                node_executable: Path::new("node").to_owned(),
+                timeout_seconds: None,
+                separate_stderr: None
             }
         )
     }
 }
 
 impl SnippetRunner for TypeScriptSnippetRunner {
-    fn run(&self, source_code: &str) -> SnippetResult<String> {
+    fn run(&self, source_code: &str, stdin: Option<&str>, timeout: Option<Duration>, context: Option<&SnippetContext>) -> SnippetResult<SnippetOutput> {
         assert_compiler_exists(&self.config.compiler_executable)?;
         assert_executable_exists(&self.config.node_executable)?;
 
@@ -482,7 +818,15 @@ impl SnippetRunner for TypeScriptSnippetRunner {
             return Err(SnippetError::Compiler);
         }
 
-        run_and_capture(&mut Command::new(&self.config.node_executable).arg(compiled_javascript))
+        run_and_capture(&mut Command::new(&self.config.node_executable).arg(compiled_javascript), stdin, timeout, context, self.separate_stderr())
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.config.timeout_seconds.map(Duration::from_secs_f64)
+    }
+
+    fn separate_stderr(&self) -> bool {
+        self.config.separate_stderr.unwrap_or(false)
     }
 
     fn change_config(&mut self, config: &dyn Any) -> SnippetResult<()> {
@@ -495,28 +839,104 @@ impl SnippetRunner for TypeScriptSnippetRunner {
     }
 }
 
-fn run_and_capture(command: &mut Command) -> SnippetResult<String> {
-    let output = unsafe {
-        command
-            .pre_exec(|| { libc::dup2(1, 2); Ok(()) })
-            .output()
-            .map_err(|err| SnippetError::RunCommand(err))
-    }?;
+fn run_and_capture(command: &mut Command,
+                   stdin: Option<&str>,
+                   timeout: Option<Duration>,
+                   context: Option<&SnippetContext>,
+                   separate_stderr: bool) -> SnippetResult<SnippetOutput> {
+    if let Some(context) = context {
+        context.apply(command);
+    }
+
+    if !separate_stderr {
+        unsafe {
+            command.pre_exec(|| { libc::dup2(1, 2); Ok(()) });
+        }
+    }
+
+    let output = match (stdin, timeout) {
+        (None, None) => command.output().map_err(|err| SnippetError::RunCommand(err))?,
+        _ => spawn_and_wait(command, stdin, timeout)?
+    };
 
     let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = if separate_stderr {
+        Some(String::from_utf8(output.stderr).unwrap())
+    } else {
+        None
+    };
 
     if output.status.success() {
-        Ok(stdout)
+        Ok(SnippetOutput { stdout, stderr })
     } else {
         Err(
             SnippetError::Execution {
                 status: output.status,
-                output: stdout
+                output: stdout + stderr.as_deref().unwrap_or("")
             }
         )
     }
 }
 
+fn spawn_and_wait(command: &mut Command, stdin: Option<&str>, timeout: Option<Duration>) -> SnippetResult<Output> {
+    // In a new process group so that any processes started by the snippet are also killed on timeout
+    let mut child = command
+        .process_group(0)
+        .stdin(if stdin.is_some() { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| SnippetError::RunCommand(err))?;
+
+    // Write and read on separate threads to not block if a pipe becomes full
+    if let (Some(stdin), Some(mut child_stdin)) = (stdin, child.stdin.take()) {
+        let stdin = stdin.to_owned();
+        std::thread::spawn(move || {
+            // The snippet might exit without reading all input
+            let _ = child_stdin.write_all(stdin.as_bytes());
+        });
+    }
+
+    let stdout_reader = read_to_end_in_background(child.stdout.take().unwrap());
+    let stderr_reader = read_to_end_in_background(child.stderr.take().unwrap());
+
+    let start = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+
+        let timeout = match timeout {
+            Some(timeout) => timeout,
+            None => break child.wait()?
+        };
+
+        if start.elapsed() >= timeout {
+            unsafe { libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL); }
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(SnippetError::Timeout { seconds: timeout.as_secs_f64() });
+        }
+
+        std::thread::sleep(Duration::from_millis(10));
+    };
+
+    let join_reader = |reader: std::thread::JoinHandle<std::io::Result<Vec<u8>>>| {
+        reader.join().map_err(|_| SnippetError::IO(std::io::Error::new(std::io::ErrorKind::Other, "Failed to read output")))?.map_err(SnippetError::IO)
+    };
+
+    let stdout = join_reader(stdout_reader)?;
+    let stderr = join_reader(stderr_reader)?;
+    Ok(Output { status, stdout, stderr })
+}
+
+fn read_to_end_in_background<R: Read + Send + 'static>(mut reader: R) -> std::thread::JoinHandle<std::io::Result<Vec<u8>>> {
+    std::thread::spawn(move || {
+        let mut buffer = Vec::new();
+        reader.read_to_end(&mut buffer).map(|_| buffer)
+    })
+}
+
 struct DeleteFileGuard {
     path: PathBuf
 }
@@ -554,37 +974,54 @@ fn assert_executable_exists(executable: &Path) -> SnippetResult<()> {
 #[test]
 fn test_manager_success1() {
     let manager = SnippetRunnerManger::default();
-    let result = manager.run("python", r#"
+    let result = manager.run("python", &[], r#"
 xs = list(range(0, 10))
 print([x * x for x in xs])
-    "#);
+    "#, None, None, None);
 
-    assert_eq!("[0, 1, 4, 9, 16, 25, 36, 49, 64, 81]\n".to_owned(), result.unwrap());
+    assert_eq!("[0, 1, 4, 9, 16, 25, 36, 49, 64, 81]\n".to_owned(), result.unwrap().stdout);
 }
 
 #[test]
 fn test_manager_success2() {
     let manager = SnippetRunnerManger::default();
-    let result = manager.run("cpp", r#"
+    let result = manager.run("cpp", &[], r#"
 #include <iostream>
 int main() {
     std::cout << "Hello, World!" << std::endl;
 }
-    "#);
+    "#, None, None, None);
 
-    assert_eq!("Hello, World!\n".to_owned(), result.unwrap());
+    assert_eq!("Hello, World!\n".to_owned(), result.unwrap().stdout);
 }
 
 #[test]
 fn test_manager_success3() {
     let manager = SnippetRunnerManger::default();
-    let result = manager.run("rust", r#"
+    let result = manager.run("rust", &[], r#"
 fn main() {
     println!("Hello, World!");
 }
-    "#);
+    "#, None, None, None);
 
-    assert_eq!("Hello, World!\n".to_owned(), result.unwrap());
+    assert_eq!("Hello, World!\n".to_owned(), result.unwrap().stdout);
+}
+
+#[test]
+fn test_manager_rust_args() {
+    let manager = SnippetRunnerManger::default();
+    let source_code = r#"
+fn main() {
+    #[cfg(snippet_flag)]
+    println!("Enabled");
+    #[cfg(not(snippet_flag))]
+    println!("Disabled");
+}
+    "#;
+
+    assert_eq!("Disabled\n", manager.run("rust", &[], source_code, None, None, None).unwrap().stdout);
+    let args = vec!["--cfg".to_owned(), "snippet_flag".to_owned()];
+    assert_eq!("Enabled\n", manager.run("rust", &args, source_code, None, None, None).unwrap().stdout);
 }
 
 #[test]
@@ -593,9 +1030,47 @@ fn test_python_success1() {
     let result = runner.run(r#"
 xs = list(range(0, 10))
 print([x * x for x in xs])
-    "#);
+    "#, None, None, None);
+
+    assert_eq!("[0, 1, 4, 9, 16, 25, 36, 49, 64, 81]\n".to_owned(), result.unwrap().stdout);
+}
+
+#[test]
+fn test_python_stdin() {
+    let runner = PythonSnippetRunner::default();
+    let source_code = "name = input()\nprint(f'Hello, {name}!')";
+
+    let result = runner.run(source_code, Some("World\n"), None, None);
+    assert_eq!("Hello, World!\n".to_owned(), result.unwrap().stdout);
 
-    assert_eq!("[0, 1, 4, 9, 16, 25, 36, 49, 64, 81]\n".to_owned(), result.unwrap());
+    let result = runner.run(source_code, Some("World\n"), Some(Duration::from_secs(5)), None);
+    assert_eq!("Hello, World!\n".to_owned(), result.unwrap().stdout);
+
+    let result = runner.run(source_code, None, None, None);
+    assert!(matches!(result, Err(SnippetError::Execution { ref output, .. }) if output.contains("EOFError")), "{:?}", result);
+}
+
+#[test]
+fn test_python_args() {
+    let runner = PythonSnippetRunner::default();
+    let result = runner.run_with_args("import sys\nprint(sys.flags.dont_write_bytecode)", &["-B".to_owned()], None, None, None);
+    assert_eq!("1\n".to_owned(), result.unwrap().stdout);
+
+    let result = runner.run_with_args("echo Hello", &["/bin/sh".to_owned()], None, None, None);
+    assert!(result.is_err(), "{:?}", result);
+
+    let result = runner.run_variant("python3", "import sys\nprint(sys.version_info.major)", &[], None, None, None);
+    assert_eq!("3\n".to_owned(), result.unwrap().stdout);
+
+    let result = runner.run_variant("/bin/sh", "echo Hello", &[], None, None, None);
+    assert!(matches!(result, Err(SnippetError::InvalidVariant(_))), "{:?}", result);
+
+    let manager = SnippetRunnerManger::default();
+    let result = manager.run("python:python3", &["-B".to_owned()], "import sys\nprint(sys.flags.dont_write_bytecode)", None, None, None);
+    assert_eq!("1\n".to_owned(), result.unwrap().stdout);
+
+    let result = manager.run("bash:sh", &[], "echo Hello", None, None, None);
+    assert!(matches!(result, Err(SnippetError::InvalidVariant(_))), "{:?}", result);
 }
 
 #[test]
@@ -605,7 +1080,7 @@ fn test_python_fail1() {
 import wololo
 xs = list(range(0, 10))
 print([x * x for x in xs])
-    "#);
+    "#, None, None, None);
 
     assert_eq!(false, result.is_ok());
 
@@ -622,6 +1097,8 @@ fn test_python_change_config1() {
     let mut runner = PythonSnippetRunner::default();
     runner.change_config(&PythonSnippetRunnerConfig {
         executable: Path::new("python2").to_path_buf(),
+        timeout_seconds: None,
+        separate_stderr: None
     }).unwrap();
 
     assert_eq!(Path::new("python2"), runner.config.executable);
@@ -632,9 +1109,9 @@ fn test_bash_success1() {
     let runner = BashSnippetRunner::default();
     let result = runner.run(r#"
 echo "Hello, World!"
-    "#);
+    "#, None, None, None);
 
-    assert_eq!("Hello, World!\n".to_owned(), result.unwrap());
+    assert_eq!("Hello, World!\n".to_owned(), result.unwrap().stdout);
 }
 
 #[test]
@@ -645,9 +1122,9 @@ fn test_cpp_success1() {
 int main() {
     std::cout << "Hello, World!" << std::endl;
 }
-    "#);
+    "#, None, None, None);
 
-    assert_eq!("Hello, World!\n".to_owned(), result.unwrap());
+    assert_eq!("Hello, World!\n".to_owned(), result.unwrap().stdout);
 }
 
 #[test]
@@ -657,7 +1134,7 @@ fn test_cpp_fail1() {
 int main() {
     std::cout << "Hello, World!" << std::endl;
 }
-    "#);
+    "#, None, None, None);
 
     if let SnippetError::Compiler = result.err().unwrap() {
         assert!(true);
@@ -673,9 +1150,9 @@ fn test_rust_success1() {
 fn main() {
     println!("Hello, World!");
 }
-    "#);
+    "#, None, None, None);
 
-    assert_eq!("Hello, World!\n".to_owned(), result.unwrap());
+    assert_eq!("Hello, World!\n".to_owned(), result.unwrap().stdout);
 }
 
 #[test]
@@ -686,9 +1163,9 @@ fn test_rust_success2() {
 fn main() {
     println!("Hello, World!");
 }
-    "#);
+    "#, None, None, None);
 
-    assert_eq!("Hello, World!\n".to_owned(), result.unwrap());
+    assert_eq!("Hello, World!\n".to_owned(), result.unwrap().stdout);
 }
 
 #[test]
@@ -696,9 +1173,75 @@ fn test_javascript_success() {
     let runner = JavaScriptSnippetRunner::default();
     let result = runner.run(r#"
 console.log("Hello, World!");
-    "#);
+    "#, None, None, None);
+
+    assert_eq!("Hello, World!\n".to_owned(), result.unwrap().stdout);
+}
+
+#[test]
+fn test_manager_javascript_alias() {
+    let manager = SnippetRunnerManger::default();
+    let result = manager.run("js", &[], r#"
+console.log([1, 2, 3].map(x => x * x));
+    "#, None, None, None);
+
+    assert_eq!("[ 1, 4, 9 ]\n".to_owned(), result.unwrap().stdout);
+}
+
+#[test]
+fn test_manager_run_command_file_name() {
+    let mut manager = SnippetRunnerManger::new();
+    manager.allow_arbitrary_runners = true;
+
+    assert_eq!("Hello\n", manager.run_command("cat input.txt", "Hello\n", None, None, None).unwrap().stdout);
+    assert_eq!("Hello\n", manager.run_command("cat -v", "Hello\n", None, None, None).unwrap().stdout);
+
+    let outside_dir = tempfile::tempdir().unwrap();
+    let outside_path = outside_dir.path().join("existing.txt");
+    std::fs::write(&outside_path, "Original\n").unwrap();
+    let output = manager.run_command(&format!("cat {}", outside_path.to_str().unwrap()), "Hello\n", None, None, None).unwrap();
+    assert_eq!("Original\nHello\n", output.stdout);
+    assert_eq!("Original\n", std::fs::read_to_string(&outside_path).unwrap());
+
+    let parent_file_name = format!("gitnotes-run-command-{}", std::process::id());
+    let result = manager.run_command(&format!("cat ../{}", parent_file_name), "Hello\n", None, None, None);
+    assert!(result.is_err());
+    assert!(!std::env::temp_dir().join(&parent_file_name).exists());
+}
+
+#[test]
+fn test_manager_cpp_config() {
+    let manager = SnippetRunnerManger::from_config(Some(&SnippetFileConfig {
+        cpp: Some(CppSnippetRunnerConfig {
+            compiler_executable: Path::new("custom-cpp-compiler").to_owned(),
+            compiler_flags: vec![],
+            timeout_seconds: None,
+            separate_stderr: None
+        }),
+        ..Default::default()
+    })).unwrap();
+
+    let result = manager.run("cpp", &[], "int main() {}", None, None, None);
+    assert!(matches!(result, Err(SnippetError::CompilerNotFound(ref executable)) if executable == "custom-cpp-compiler"), "{:?}", result);
+}
 
-    assert_eq!("Hello, World!\n".to_owned(), result.unwrap());
+#[test]
+fn test_manager_timeout_config() {
+    let mut manager = SnippetRunnerManger::from_config(Some(&SnippetFileConfig {
+        timeout_seconds: Some(0.2),
+        ..Default::default()
+    })).unwrap();
+
+    let result = manager.run("bash", &[], "while true; do sleep 0.05; done", None, None, None);
+    assert!(matches!(result, Err(SnippetError::Timeout { .. })), "{:?}", result);
+    assert_eq!("done\n", manager.run("bash", &[], "sleep 0.5; echo done", None, Some(Duration::from_secs(5)), None).unwrap().stdout);
+
+    manager.change_config("bash", &BashSnippetRunnerConfig {
+        executable: Path::new("bash").to_owned(),
+        timeout_seconds: Some(5.0),
+        separate_stderr: None
+    }).unwrap();
+    assert_eq!("done\n", manager.run("bash", &[], "sleep 0.5; echo done", None, None, None).unwrap().stdout);
 }
 
 #[test]
@@ -710,7 +1253,49 @@ function printMessage(msg: string) {
 }
 
 printMessage("Hello, World!");
-    "#);
+    "#, None, None, None);
 
-    assert_eq!("Hello, World!\n".to_owned(), result.unwrap());
-}
\ No newline at end of file
+    assert_eq!("Hello, World!\n".to_owned(), result.unwrap().stdout);
+}
+
+#[test]
+fn test_bash_separate_stderr() {
+    let mut runner = BashSnippetRunner::default();
+    let result = runner.run("echo out; echo err >&2", None, None, None);
+    assert_eq!(SnippetOutput { stdout: "out\nerr\n".to_owned(), stderr: None }, result.unwrap());
+
+    runner.change_config(&BashSnippetRunnerConfig {
+        executable: Path::new("bash").to_owned(),
+        timeout_seconds: None,
+        separate_stderr: Some(true)
+    }).unwrap();
+    let result = runner.run("echo out; echo err >&2", None, None, None);
+    assert_eq!(SnippetOutput { stdout: "out\n".to_owned(), stderr: Some("err\n".to_owned()) }, result.unwrap());
+
+    let result = runner.run("echo out; echo err >&2", None, Some(Duration::from_secs(5)), None);
+    assert_eq!(SnippetOutput { stdout: "out\n".to_owned(), stderr: Some("err\n".to_owned()) }, result.unwrap());
+}
+
+#[test]
+fn test_execution_error_message() {
+    let runner = BashSnippetRunner::default();
+
+    let result = runner.run("exit 2", None, None, None);
+    assert_eq!("Execution error: exited with code 2", result.unwrap_err().to_string());
+
+    let result = runner.run("kill -KILL $$", None, None, None);
+    assert_eq!("Execution error: killed by signal SIGKILL", result.unwrap_err().to_string());
+
+    let result = runner.run("kill -USR1 $$", None, Some(Duration::from_secs(5)), None);
+    assert_eq!("Execution error: killed by signal SIGUSR1", result.unwrap_err().to_string());
+}
+
+#[test]
+fn test_bash_timeout() {
+    let runner = BashSnippetRunner::default();
+    let result = runner.run("echo start; sleep 5", None, Some(Duration::from_millis(200)), None);
+    assert!(matches!(result, Err(SnippetError::Timeout { .. })), "{:?}", result);
+
+    let result = runner.run("echo done", None, Some(Duration::from_secs(5)), None);
+    assert_eq!("done\n", result.unwrap().stdout);
+}
@@ -6,14 +6,69 @@ use lazy_static::lazy_static;
 
 use comrak::nodes::NodeValue;
 use rake::{KeywordScore, Rake, StopWords};
+use serde::{Deserialize, Serialize};
 
 use crate::helpers::OrderedSet;
 use crate::markdown;
 
-pub fn automatic(content: &str) -> Vec<String> {
+/// Controls the keyword tags that are added (in addition to the language of code blocks).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TagConfig {
+    /// The maximum number of keyword tags.
+    pub max_keyword_tags: usize,
+    /// The minimum score of a keyword for it to be used as a tag.
+    pub min_score: f64,
+    /// Adds the 'snippet' tag to notes with code blocks.
+    pub snippet_tag: bool,
+    /// Maps the language of code blocks to the tag to use (in addition to the built-in aliases).
+    pub language_aliases: FnvHashMap<String, String>
+}
+
+impl TagConfig {
+    pub fn language_tag<'a>(&'a self, language: &'a str) -> &'a str {
+        if let Some(tag) = self.language_aliases.get(language) {
+            return tag;
+        }
+
+        match language {
+            "py" => "python",
+            "rs" => "rust",
+            "js" => "javascript",
+            "c++" => "cpp",
+            _ => language
+        }
+    }
+}
+
+impl Default for TagConfig {
+    fn default() -> Self {
+        TagConfig {
+            max_keyword_tags: 3,
+            min_score: 3.0,
+            snippet_tag: true,
+            language_aliases: FnvHashMap::default()
+        }
+    }
+}
+
+pub fn automatic(content: &str, config: &TagConfig) -> Vec<String> {
     let mut tags = OrderedSet::new();
     let mut added_snippet_tag = false;
 
+    let content = match split_front_matter(content) {
+        Some((delimiter, front_matter, content)) => {
+            for tag in front_matter_tags(delimiter, front_matter) {
+                if !tags.contains(&tag) {
+                    tags.insert(tag);
+                }
+            }
+
+            content
+        }
+        None => content
+    };
+
     let arena = markdown::storage();
     let root = markdown::parse(&arena, &content);
 
@@ -22,14 +77,14 @@ pub fn automatic(content: &str) -> Vec<String> {
         |current_node| {
             if let NodeValue::CodeBlock(ref block) = current_node.data.borrow().value {
                 if !block.info.is_empty() {
-                    if !added_snippet_tag {
+                    if config.snippet_tag && !added_snippet_tag {
                         tags.insert("snippet".to_owned());
                         added_snippet_tag = true;
                     }
 
-                    let tag = &block.info;
-                    if !tags.contains(tag) {
-                        tags.insert(tag.clone());
+                    let tag = config.language_tag(&block.info).to_owned();
+                    if !tags.contains(&tag) {
+                        tags.insert(tag);
                     }
                 }
             }
@@ -69,8 +124,8 @@ pub fn automatic(content: &str) -> Vec<String> {
 
     let mut word_scores = Vec::from_iter(word_frequency.into_iter());
     word_scores.sort_by_key(|(_, score)| FloatOrd(-*score));
-    for (word, score) in word_scores.into_iter().take(3) {
-        if score >= 3.0 {
+    for (word, score) in word_scores.into_iter().take(config.max_keyword_tags) {
+        if score >= config.min_score {
             let tag = word.to_owned();
             if !tags.contains(&tag) {
                 tags.insert(tag);
@@ -81,6 +136,59 @@ pub fn automatic(content: &str) -> Vec<String> {
     tags.into_iter().collect()
 }
 
+fn split_front_matter(content: &str) -> Option<(&str, &str, &str)> {
+    let mut lines = content.split_inclusive('\n');
+    let delimiter = lines.next()?.trim_end();
+    if delimiter != "---" && delimiter != "+++" {
+        return None;
+    }
+
+    let start = content.find('\n')? + 1;
+    let mut end = start;
+    for line in lines {
+        if line.trim_end() == delimiter {
+            return Some((delimiter, &content[start..end], &content[end + line.len()..]));
+        }
+
+        end += line.len();
+    }
+
+    None
+}
+
+fn front_matter_tags(delimiter: &str, front_matter: &str) -> Vec<String> {
+    if delimiter == "+++" {
+        let table = front_matter.parse::<toml::Table>().ok();
+        return table
+            .as_ref()
+            .and_then(|table| table.get("tags"))
+            .and_then(|tags| tags.as_array())
+            .map(|tags| tags.iter().flat_map(|tag| tag.as_str()).map(|tag| tag.to_owned()).collect())
+            .unwrap_or_default();
+    }
+
+    let unquote = |tag: &str| tag.trim().trim_matches(|c| c == '"' || c == '\'').to_owned();
+
+    let mut lines = front_matter.lines();
+    while let Some(line) = lines.next() {
+        let Some(value) = line.strip_prefix("tags:") else { continue; };
+        let value = value.trim();
+
+        if value.is_empty() {
+            return lines
+                .map_while(|line| line.trim_start().strip_prefix("-"))
+                .map(unquote)
+                .filter(|tag| !tag.is_empty())
+                .collect();
+        }
+
+        let value = value.strip_prefix('[').and_then(|value| value.strip_suffix(']')).unwrap_or(value);
+        return value.split(',').map(unquote).filter(|tag| !tag.is_empty()).collect();
+    }
+
+    Vec::new()
+}
+
 lazy_static! {
     static ref STOP_LIST: HashSet<String> = {
         let content = include_str!("../data/stop_list.txt");
@@ -104,7 +212,7 @@ int main() {
 ```
 
 End of world.
-"#);
+"#, &TagConfig::default());
 
     assert_eq!(
         vec!["snippet".to_owned(), "python".to_owned(), "cpp".to_owned()],
@@ -263,10 +371,98 @@ Placed after the column type and adds additional constraints/transforms when ext
 * `CONVERT`: Tries to convert a string value into the value type.
 * `DEFAULT <value>`: Use this as default value instead of NULL.
 * `MICROSECONDS`: The decimal second part is in microseconds, not milliseconds.
-"#);
+"#, &TagConfig::default());
 
     assert_eq!(
         vec!["snippet".to_owned(), "python".to_owned(), "cpp".to_owned(), "type".to_owned(), "sql".to_owned(), "supported".to_owned()],
         tags
     );
 }
+
+#[test]
+fn test_automatic_no_keyword_tags() {
+    let config = TagConfig { max_keyword_tags: 0, ..TagConfig::default() };
+    let tags = automatic(r#"# sqlgrep
+Combines SQL with regular expressions to provide a new way to filter and process text files.
+
+``` python
+print("Hello, World!")
+```
+"#, &config);
+
+    assert_eq!(vec!["snippet".to_owned(), "python".to_owned()], tags);
+}
+
+#[test]
+fn test_automatic_language_alias() {
+    let content = r#"
+``` py
+print("Hello, World!")
+```
+
+``` python
+print("Hello, World!")
+```
+"#;
+
+    let tags = automatic(content, &TagConfig { max_keyword_tags: 0, ..TagConfig::default() });
+    assert_eq!(vec!["snippet".to_owned(), "python".to_owned()], tags);
+
+    let mut config = TagConfig { max_keyword_tags: 0, ..TagConfig::default() };
+    config.language_aliases.insert("py".to_owned(), "python3".to_owned());
+    let tags = automatic(content, &config);
+    assert_eq!(vec!["snippet".to_owned(), "python3".to_owned(), "python".to_owned()], tags);
+}
+
+#[test]
+fn test_automatic_front_matter() {
+    let config = TagConfig { max_keyword_tags: 0, ..TagConfig::default() };
+
+    let tags = automatic(r#"---
+title: Sample
+tags: [rust, async]
+---
+``` python
+print("Hello, World!")
+```
+"#, &config);
+    assert_eq!(vec!["rust".to_owned(), "async".to_owned(), "snippet".to_owned(), "python".to_owned()], tags);
+
+    let tags = automatic(r#"---
+tags:
+  - rust
+  - "async"
+title: Sample
+---
+Hello, World!
+"#, &config);
+    assert_eq!(vec!["rust".to_owned(), "async".to_owned()], tags);
+
+    let tags = automatic(r#"+++
+tags = ["rust", "python"]
++++
+``` python
+print("Hello, World!")
+```
+"#, &config);
+    assert_eq!(vec!["rust".to_owned(), "python".to_owned(), "snippet".to_owned()], tags);
+}
+
+#[test]
+fn test_automatic_no_front_matter() {
+    let config = TagConfig { max_keyword_tags: 0, ..TagConfig::default() };
+    assert_eq!(Vec::<String>::new(), automatic("Hello\n\n---\ntags: [rust]\n---\n", &config));
+    assert_eq!(Vec::<String>::new(), automatic("---\ntags: [rust]\n", &config));
+}
+
+#[test]
+fn test_automatic_no_snippet_tag() {
+    let config = TagConfig { max_keyword_tags: 0, snippet_tag: false, ..TagConfig::default() };
+    let tags = automatic(r#"
+``` python
+print("Hello, World!")
+```
+"#, &config);
+
+    assert_eq!(vec!["python".to_owned()], tags);
+}
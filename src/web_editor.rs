@@ -30,7 +30,7 @@ use crate::config::SnippetFileConfig;
 use crate::{command, markdown};
 use crate::editor::EditorOutput;
 use crate::model::RESOURCES_DIR;
-use crate::snippets::SnippetRunnerManger;
+use crate::snippets::{SnippetContext, SnippetRunnerManger};
 
 #[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
 pub enum AccessMode {
@@ -49,7 +49,8 @@ pub struct WebEditorConfig {
     pub port: u16,
     pub access_mode: AccessMode,
     pub is_standalone: bool,
-    pub snippet_config: Option<SnippetFileConfig>
+    pub snippet_config: Option<SnippetFileConfig>,
+    pub theme: Option<String>
 }
 
 impl Default for WebEditorConfig {
@@ -58,7 +59,8 @@ impl Default for WebEditorConfig {
             port: 9000,
             access_mode: AccessMode::default(),
             is_standalone: false,
-            snippet_config: None
+            snippet_config: None,
+            theme: None
         }
     }
 }
@@ -91,7 +93,8 @@ pub async fn launch(config: WebEditorConfig, input: WebEditorInput) -> EditorOut
         config.access_mode,
         config.is_standalone,
         input.repository_path.clone(),
-        SnippetRunnerManger::from_config(config.snippet_config.as_ref()).unwrap()
+        SnippetRunnerManger::from_config(config.snippet_config.as_ref()).unwrap(),
+        config.theme.clone()
     ));
 
     let app = Router::new()
@@ -101,6 +104,7 @@ pub async fn launch(config: WebEditorConfig, input: WebEditorInput) -> EditorOut
         .route("/api/content", get(get_content))
         .route("/api/content", put(save_content))
         .route("/api/run-snippet", post(run_snippet))
+        .route("/api/save-and-run", post(save_and_run))
         .route("/api/add-resource", post(add_resource))
         .route("/local/{*path}", get(get_local_file))
         .route("/resource/{*path}", get(get_resource_file))
@@ -146,7 +150,8 @@ struct WebServerState {
     is_standalone: bool,
     repository_path: Option<PathBuf>,
     snippet_runner_manager: SnippetRunnerManger,
-    added_resources: Mutex<Vec<PathBuf>>
+    added_resources: Mutex<Vec<PathBuf>>,
+    theme: Option<String>
 }
 
 impl WebServerState {
@@ -156,7 +161,8 @@ impl WebServerState {
         access_mode: AccessMode,
         is_standalone: bool,
         repository_path: Option<PathBuf>,
-        snippet_runner_manager: SnippetRunnerManger
+        snippet_runner_manager: SnippetRunnerManger,
+        theme: Option<String>
     ) -> WebServerState {
         WebServerState {
             path,
@@ -166,7 +172,8 @@ impl WebServerState {
             is_standalone,
             repository_path,
             snippet_runner_manager,
-            added_resources: Mutex::new(Vec::new())
+            added_resources: Mutex::new(Vec::new()),
+            theme
         }
     }
 }
@@ -229,7 +236,9 @@ struct AppTemplate {
     file_path: String,
     display_file_path: String,
     is_read_only: bool,
-    is_standalone: bool
+    is_standalone: bool,
+    /// The code highlight theme, empty for the default one
+    theme: String
 }
 
 async fn index(State(state): State<Arc<WebServerState>>) -> Response {
@@ -238,7 +247,8 @@ async fn index(State(state): State<Arc<WebServerState>>) -> Response {
         file_path: state.path.to_str().unwrap().to_owned(),
         display_file_path: state.display_path.to_str().unwrap().to_owned(),
         is_read_only: state.access_mode == AccessMode::Read,
-        is_standalone: state.is_standalone
+        is_standalone: state.is_standalone,
+        theme: state.theme.clone().unwrap_or_default()
     };
 
     Html(template.render().unwrap()).into_response()
@@ -294,13 +304,49 @@ async fn run_snippet(
     State(state): State<Arc<WebServerState>>,
     Json(input): Json<RunSnippet>
 ) -> WebServerResult<Response> {
+    let (snippet_output, new_content) = run_snippet_with_output(&state, &input.content);
+
+    Ok(
+        Json(
+            json!({
+                "output": snippet_output,
+                "newContent": new_content
+            })
+        ).into_response()
+    )
+}
+
+/// Saves the content (unless read only) and then runs the snippets in it.
+async fn save_and_run(
+    State(state): State<Arc<WebServerState>>,
+    Json(input): Json<SaveContent>
+) -> WebServerResult<Response> {
+    let content = input.content.clone();
+    let saved = save_content(State(state.clone()), Json(input)).await?.status().is_success();
+
+    let (snippet_output, new_content) = run_snippet_with_output(&state, &content);
+
+    Ok(
+        Json(
+            json!({
+                "saved": saved,
+                "output": snippet_output,
+                "newContent": new_content
+            })
+        ).into_response()
+    )
+}
+
+fn run_snippet_with_output(state: &WebServerState, content: &str) -> (String, Option<String>) {
     let arena = markdown::storage();
+    let context = state.repository_path.as_ref().map(|repository_path| SnippetContext::new(repository_path, &state.display_path));
 
     let mut snippet_output = String::new();
     let result = command::run_snippet(
         &state.snippet_runner_manager,
         &arena,
-        &input.content,
+        content,
+        context.as_ref(),
         |text| { snippet_output += text }
     );
 
@@ -315,14 +361,7 @@ async fn run_snippet(
         }
     }
 
-    Ok(
-        Json(
-            json!({
-                "output": snippet_output,
-                "newContent": new_content
-            })
-        ).into_response()
-    )
+    (snippet_output, new_content)
 }
 
 async fn add_resource(
@@ -400,3 +439,55 @@ fn with_response_code(mut response: Response, code: StatusCode) -> Response {
     *response.status_mut() = code;
     response
 }
+
+#[test]
+fn test_template_theme() {
+    let render = |theme: &str| {
+        AppTemplate {
+            time: 0,
+            file_path: "/tmp/note.md".to_owned(),
+            display_file_path: "note".to_owned(),
+            is_read_only: false,
+            is_standalone: false,
+            theme: theme.to_owned()
+        }.render().unwrap()
+    };
+
+    assert!(render("monokai").contains(r#"<input type="hidden" id="theme" value="monokai" />"#));
+    assert!(render("").contains(r#"<input type="hidden" id="theme" value="" />"#));
+}
+
+#[test]
+fn test_save_and_run_read_only() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let path = temp_dir.path().join("note.md");
+    std::fs::write(&path, "Original").unwrap();
+
+    let state = Arc::new(WebServerState::new(
+        path.clone(),
+        path.clone(),
+        AccessMode::Read,
+        false,
+        None,
+        SnippetRunnerManger::from_config(None).unwrap(),
+        None
+    ));
+
+    let input = SaveContent {
+        path: path.clone(),
+        content: "``` python\nprint(sum(range(0, 10)))\n```\n".to_owned()
+    };
+
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let body = runtime.block_on(async move {
+        let response = save_and_run(State(state), Json(input)).await.unwrap();
+        assert_eq!(StatusCode::OK, response.status());
+        axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap()
+    });
+
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(false, body["saved"]);
+    assert_eq!("45\n", body["output"]);
+    assert!(body["newContent"].as_str().unwrap().contains("``` output\n45\n```"));
+    assert_eq!("Original", std::fs::read_to_string(&path).unwrap());
+}